@@ -14,12 +14,53 @@ fn name() -> String {
     {
         "windows".to_string()
     }
-    #[cfg(all(unix, feature = "pidfd"))]
+    #[cfg(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd"
+    ))]
+    {
+        "unix_kqueue".to_string()
+    }
+    #[cfg(any(target_os = "illumos", target_os = "solaris"))]
+    {
+        "illumos_event_ports".to_string()
+    }
+    #[cfg(target_os = "fuchsia")]
+    {
+        "fuchsia".to_string()
+    }
+    #[cfg(all(
+        unix,
+        not(any(
+            target_vendor = "apple",
+            target_os = "freebsd",
+            target_os = "dragonfly",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "fuchsia"
+        )),
+        feature = "pidfd"
+    ))]
     {
         "unix_pidfd".to_string()
     }
     #[cfg(all(
         unix,
+        not(any(
+            target_vendor = "apple",
+            target_os = "freebsd",
+            target_os = "dragonfly",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "fuchsia"
+        )),
         any(
             all(feature = "thread", not(feature = "pidfd")),
             all(
@@ -34,6 +75,16 @@ fn name() -> String {
     }
     #[cfg(all(
         unix,
+        not(any(
+            target_vendor = "apple",
+            target_os = "freebsd",
+            target_os = "dragonfly",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "fuchsia"
+        )),
         feature = "signal",
         not(feature = "thread"),
         not(feature = "pidfd")
@@ -51,8 +102,7 @@ fn benchmark_methods_instant(c: &mut Criterion) {
                 let mut child = utilities::sleep_child("0");
                 child.wait_timeout(Duration::from_secs(10)).unwrap();
             }
-            let total_duration = start.elapsed();
-            total_duration
+            start.elapsed()
         })
     });
 
@@ -63,8 +113,7 @@ fn benchmark_methods_instant(c: &mut Criterion) {
                 let mut child = utilities::sleep_child("0");
                 child.wait().unwrap();
             }
-            let total_duration = start.elapsed();
-            total_duration
+            start.elapsed()
         })
     });
 }
@@ -77,8 +126,7 @@ fn benchmark_methods_short(c: &mut Criterion) {
                 let mut child = utilities::sleep_child("1");
                 child.wait_timeout(Duration::from_secs(10)).unwrap();
             }
-            let total_duration = start.elapsed();
-            total_duration
+            start.elapsed()
         })
     });
 
@@ -89,8 +137,7 @@ fn benchmark_methods_short(c: &mut Criterion) {
                 let mut child = utilities::sleep_child("1");
                 child.wait().unwrap();
             }
-            let total_duration = start.elapsed();
-            total_duration
+            start.elapsed()
         })
     });
 }
@@ -102,9 +149,10 @@ fn benchmark_methods_timeout(c: &mut Criterion) {
             for _ in 0..iters {
                 let mut child = utilities::sleep_child("100000");
                 let _ = child.wait_timeout(Duration::from_secs(1));
+                let _ = child.kill();
+                let _ = child.wait();
             }
-            let total_duration = start.elapsed();
-            total_duration
+            start.elapsed()
         })
     });
 
@@ -112,11 +160,12 @@ fn benchmark_methods_timeout(c: &mut Criterion) {
         b.iter_custom(|iters| {
             let start = std::time::Instant::now();
             for _ in 0..iters {
-                utilities::sleep_child("100000");
+                let mut child = utilities::sleep_child("100000");
                 thread::sleep(Duration::from_secs(1));
+                let _ = child.kill();
+                let _ = child.wait();
             }
-            let total_duration = start.elapsed();
-            total_duration
+            start.elapsed()
         })
     });
 }
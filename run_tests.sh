@@ -7,6 +7,13 @@ if [ $status1 -ne 0 ]; then
     exit $status1
 fi
 
+cargo test --features "uring"
+status1=$?
+if [ $status1 -ne 0 ]; then
+    echo "Tests with uring failed. Exiting."
+    exit $status1
+fi
+
 cargo test --features "thread"
 status1=$?
 if [ $status1 -ne 0 ]; then
@@ -20,3 +27,10 @@ if [ $status1 -ne 0 ]; then
     echo "Tests with signal failed. Exiting."
     exit $status1
 fi
+
+cargo test --features "signalfd" -- --test-threads=1
+status1=$?
+if [ $status1 -ne 0 ]; then
+    echo "Tests with signalfd failed. Exiting."
+    exit $status1
+fi
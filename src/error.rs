@@ -1,15 +1,162 @@
+use std::error::Error;
+use std::fmt;
 use std::io;
 
-pub(crate) fn _generate_default_error() -> io::Result<()> {
-    Err(io::Error::new(
-        io::ErrorKind::Other,
-        "an unspecified error occurred",
-    ))
+/// The reasons a wait can fail, for callers which need to match them precisely.
+///
+/// The functions of this crate return [`io::Error`] so that they compose with the standard
+/// library. A `WaitError` converts into an [`io::Error`] of the matching kind, and an
+/// [`io::Error`] returned by this crate converts back into a `WaitError` with [`From`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{ChildWT, WaitError};
+///
+/// let mut child = Command::new("sleep").arg("10").spawn()?;
+/// match child.wait_timeout(Duration::from_millis(100)).map_err(WaitError::from) {
+///     Ok(status) => println!("exited with {}", status),
+///     Err(WaitError::Timeout) => child.kill()?,
+///     Err(e) => return Err(e.into()),
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WaitError {
+    /// The timeout expired before the process exited.
+    Timeout,
+    /// The process does not exist or is not a child of the current process.
+    NoSuchProcess,
+    /// The wait was interrupted by a signal.
+    Interrupted,
+    /// The operation is not supported on this system.
+    Unsupported,
+    /// The resident memory of the process exceeded the limit, in bytes, and the process was killed.
+    MemoryLimitExceeded {
+        /// The limit set on the resident memory.
+        limit: u64,
+        /// The resident memory sampled above the limit.
+        resident: u64,
+    },
+    /// A system call of the backend failed.
+    Backend(io::Error),
 }
 
-pub(crate) fn _generate_timeout_error() -> io::Result<()> {
-    Err(io::Error::new(
-        io::ErrorKind::TimedOut,
-        "operation timed out",
-    ))
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitError::Timeout => f.write_str("operation timed out"),
+            WaitError::NoSuchProcess => f.write_str("no such process"),
+            WaitError::Interrupted => f.write_str("operation interrupted"),
+            WaitError::Unsupported => f.write_str("operation not supported on this system"),
+            WaitError::MemoryLimitExceeded { limit, resident } => write!(
+                f,
+                "memory limit of {} bytes exceeded with {} bytes resident",
+                limit, resident
+            ),
+            WaitError::Backend(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for WaitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WaitError::Backend(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<WaitError> for io::Error {
+    fn from(error: WaitError) -> Self {
+        let kind = match error {
+            WaitError::Timeout => io::ErrorKind::TimedOut,
+            WaitError::NoSuchProcess => io::ErrorKind::NotFound,
+            WaitError::Interrupted => io::ErrorKind::Interrupted,
+            WaitError::Unsupported => io::ErrorKind::Unsupported,
+            WaitError::MemoryLimitExceeded { .. } => io::ErrorKind::Other,
+            // The error of the backend is returned as is, keeping its OS error code
+            WaitError::Backend(e) => return e,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+impl From<io::Error> for WaitError {
+    fn from(error: io::Error) -> Self {
+        // Errors built from a `WaitError` are unwrapped
+        if error.get_ref().is_some_and(|inner| inner.is::<WaitError>()) {
+            match error
+                .into_inner()
+                .map(|inner| inner.downcast::<WaitError>())
+            {
+                Some(Ok(inner)) => return *inner,
+                _ => unreachable!("the inner error is a WaitError"),
+            }
+        }
+
+        #[cfg(unix)]
+        if matches!(error.raw_os_error(), Some(libc::ESRCH) | Some(libc::ECHILD)) {
+            return WaitError::NoSuchProcess;
+        }
+
+        match error.kind() {
+            io::ErrorKind::TimedOut => WaitError::Timeout,
+            io::ErrorKind::Interrupted => WaitError::Interrupted,
+            io::ErrorKind::Unsupported => WaitError::Unsupported,
+            _ => WaitError::Backend(error),
+        }
+    }
+}
+
+pub(crate) fn _generate_default_error<T>() -> io::Result<T> {
+    Err(WaitError::Backend(io::Error::other("an unspecified error occurred")).into())
+}
+
+// Returns the error of the last failed system call, keeping its OS error code
+pub(crate) fn _generate_os_error<T>() -> io::Result<T> {
+    Err(WaitError::Backend(io::Error::last_os_error()).into())
+}
+
+// Returns the error with the given OS error code, for calls returning it instead of setting errno
+pub(crate) fn _generate_raw_os_error<T>(code: i32) -> io::Result<T> {
+    Err(WaitError::Backend(io::Error::from_raw_os_error(code)).into())
+}
+
+pub(crate) fn _generate_timeout_error<T>() -> io::Result<T> {
+    Err(WaitError::Timeout.into())
+}
+
+#[cfg(any(
+    windows,
+    all(any(target_os = "linux", target_os = "android"), feature = "pidfd")
+))]
+pub(crate) fn _generate_no_such_process_error<T>() -> io::Result<T> {
+    Err(WaitError::NoSuchProcess.into())
+}
+
+pub(crate) fn _generate_unsupported_error<T>() -> io::Result<T> {
+    Err(WaitError::Unsupported.into())
+}
+
+pub(crate) fn _generate_memory_limit_error<T>(limit: u64, resident: u64) -> io::Result<T> {
+    Err(WaitError::MemoryLimitExceeded { limit, resident }.into())
+}
+
+pub(crate) fn _generate_interrupted_error<T>() -> io::Result<T> {
+    Err(WaitError::Interrupted.into())
+}
+
+// Returned by the waits without timeout on a mock child which would never exit
+#[cfg(feature = "mock")]
+pub(crate) fn _generate_never_exits_error<T>() -> io::Result<T> {
+    Err(WaitError::Backend(io::Error::other("the mock child has no scheduled exit")).into())
 }
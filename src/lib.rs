@@ -7,7 +7,10 @@
 //! - **Cross-Platform Support:** Works seamlessly on both Windows and Unix systems.
 //! - **Multiple Implementation Methods:**
 //!   - **Windows:** Uses `WaitForSingleObject`.
-//!   - **Unix:** Uses a method based on `thread_cancel` by default, see the wait_timeout documentation for more detail.
+//!   - **macOS and BSDs:** Uses `kqueue` with `EVFILT_PROC`.
+//!   - **illumos and Solaris:** Uses event ports on the `/proc` files of the children.
+//!   - **Fuchsia:** Uses `zx_object_wait_one` on the process handles.
+//!   - **Unix:** Uses a method based on a watcher thread by default, see the wait_timeout documentation for more detail.
 //! - **Performance Benchmarks:** Minimal performance overhead, with detailed benchmark results showing negligible differences between methods.
 //! - **Error Handling:** Provides clear error messages, including timeout errors.
 //!
@@ -40,5 +43,8 @@
 //! With this crate, managing child process termination with timeouts becomes straightforward, efficient and scalable, making it an essential tool when dealing with process management.
 //!
 mod error;
+pub use error::WaitError;
 mod platform;
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub use platform::async_child::AsyncChildWT;
 pub use platform::*;
@@ -0,0 +1,192 @@
+use std::future::Future;
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::thread;
+use std::time::Duration;
+
+use super::exit_oneshot::{exit_oneshot, ExitOneshot};
+use crate::error::_generate_timeout_error;
+
+// The Tokio runtime takes precedence when both features are enabled
+#[cfg(feature = "tokio")]
+#[path = "async_tokio.rs"]
+mod runtime;
+
+#[cfg(all(feature = "async-io", not(feature = "tokio")))]
+#[path = "async_io.rs"]
+mod runtime;
+
+/// Asynchronous waits on a child process.
+///
+/// The waits do not block the executor: on Linux, the pidfd of the child is registered with the reactor of the
+/// runtime. Elsewhere, or when `pidfd_open` is unavailable, the child is waited for from a blocking thread pool.
+///
+/// With the `tokio` feature, the waits use the reactor, timers and `spawn_blocking` of the current Tokio runtime,
+/// which must have its IO and time drivers enabled. With the `async-io` feature, they use the global reactor of
+/// `async-io` and the thread pool of `blocking`, and can be awaited from any executor such as `smol` or
+/// `async-std`. If both features are enabled, Tokio is used.
+///
+/// The method shares its name with [`ChildWT::wait_timeout`](crate::ChildWT::wait_timeout), only one of the traits
+/// should be imported in a module.
+///
+/// This trait is only available with the `tokio` or `async-io` feature.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::AsyncChildWT;
+///
+/// # #[cfg(feature = "tokio")]
+/// let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+/// # #[cfg(feature = "tokio")]
+/// runtime.block_on(async {
+///     let mut child = Command::new("sleep").arg("1").spawn()?;
+///     let status = child.wait_timeout(Duration::from_secs(2)).await?;
+///     println!("Process exited with status: {:?}", status);
+///     Ok::<_, Box<dyn Error>>(())
+/// })?;
+/// #     Ok(())
+/// # }
+/// ```
+pub trait AsyncChildWT {
+    /// Waits for the child process to exit or until the timeout expires, without blocking the executor.
+    ///
+    /// # Returns
+    /// - The exit status of the child process, which has been reaped.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process exits.
+    fn wait_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<ExitStatus>> + Send;
+
+    /// Hands the child process over to a task reaping it, and returns a future resolving to its exit status.
+    ///
+    /// Unlike [`AsyncChildWT::wait_timeout`], the returned [`ExitOneshot`] does not borrow the child, so the
+    /// completion can be raced in `select!` against other branches and polled again afterwards, without
+    /// holding a `&mut` reference across the await. The child is still reaped if the future is dropped.
+    ///
+    /// A [`Child`] is reaped by a thread of its own. A `tokio::process::Child` is reaped by a task spawned
+    /// on the current Tokio runtime, this method must then be called from within the runtime.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::AsyncChildWT;
+    ///
+    /// # #[cfg(feature = "tokio")]
+    /// let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    /// # #[cfg(feature = "tokio")]
+    /// runtime.block_on(async {
+    ///     let mut exit = Command::new("sleep").arg("1").spawn()?.exit_oneshot();
+    ///     let mut ticks = 0;
+    ///     let status = loop {
+    ///         tokio::select! {
+    ///             status = &mut exit => break status?,
+    ///             _ = tokio::time::sleep(Duration::from_millis(100)) => ticks += 1,
+    ///         }
+    ///     };
+    ///     println!("exited with {} after {} ticks", status, ticks);
+    ///     Ok::<_, Box<dyn Error>>(())
+    /// })?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn exit_oneshot(self) -> ExitOneshot
+    where
+        Self: Sized;
+}
+
+impl AsyncChildWT for Child {
+    async fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        if let Some(status) = self.try_wait()? {
+            return Ok(status);
+        }
+        if timeout.is_zero() {
+            return _generate_timeout_error();
+        }
+
+        runtime::timeout(timeout, exited(self, timeout)).await?;
+        // The child is a zombie, reaping it does not block
+        self.wait()
+    }
+
+    fn exit_oneshot(mut self) -> ExitOneshot {
+        let (sender, receiver) = exit_oneshot();
+        let spawned = thread::Builder::new()
+            .name("child_wait_timeout".to_string())
+            .spawn(move || sender.send(self.wait()));
+        match spawned {
+            Ok(_) => receiver,
+            Err(e) => ExitOneshot::ready(Err(e)),
+        }
+    }
+}
+
+// Completes once the pidfd of the child is readable, or from the blocking thread pool if
+// `pidfd_open` is unavailable
+#[cfg(any(target_os = "linux", target_os = "android"))]
+async fn exited(child: &Child, timeout: Duration) -> io::Result<()> {
+    use std::os::unix::io::{FromRawFd, OwnedFd};
+
+    use super::unix::pidfd_open;
+
+    match pidfd_open(child.id() as libc::pid_t, 0)? {
+        Some(pidfd) => runtime::readable(unsafe { OwnedFd::from_raw_fd(pidfd) }).await,
+        None => blocking_exited(child, timeout).await,
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+async fn exited(child: &Child, timeout: Duration) -> io::Result<()> {
+    blocking_exited(child, timeout).await
+}
+
+// Waits without reaping the child from the blocking thread pool. A thread blocked in `waitid`
+// cannot be cancelled, it is only released once the child exits.
+#[cfg(unix)]
+async fn blocking_exited(child: &Child, _timeout: Duration) -> io::Result<()> {
+    runtime::unblock(super::exit_future::exited(child.id())).await
+}
+
+// Waits on a duplicate of the process handle from the blocking thread pool, which stays valid
+// if the child is dropped in the meantime. The wait is bounded by the timeout so that the thread
+// is released even if the child keeps running.
+#[cfg(windows)]
+async fn exited(child: &Child, timeout: Duration) -> io::Result<()> {
+    use std::os::windows::io::{AsHandle, AsRawHandle};
+
+    use super::win32::{WaitForSingleObject, HANDLE, INFINITE, WAIT_OBJECT_0, WAIT_TIMEOUT};
+
+    use crate::error::_generate_os_error;
+
+    let process = child.as_handle().try_clone_to_owned()?;
+    // Rounded up so that the wait does not end before the timeout
+    let timeout_ms = timeout
+        .as_nanos()
+        .div_ceil(1_000_000)
+        .min(INFINITE as u128 - 1) as u32;
+    runtime::unblock(move || {
+        let result = unsafe { WaitForSingleObject(process.as_raw_handle() as HANDLE, timeout_ms) };
+        if result == WAIT_OBJECT_0 {
+            Ok(())
+        } else if result == WAIT_TIMEOUT {
+            _generate_timeout_error()
+        } else {
+            _generate_os_error()
+        }
+    })
+    .await
+}
@@ -0,0 +1,38 @@
+// Runtime primitives of the asynchronous waits, provided by `async-io` and `blocking`
+
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+use async_io::Timer;
+
+use crate::error::_generate_timeout_error;
+
+// Runs the blocking function on the thread pool of `blocking`
+pub(super) async fn unblock<T, F>(f: F) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    blocking::unblock(f).await
+}
+
+// Fails with `ErrorKind::TimedOut` if the future does not complete before the timeout
+pub(super) async fn timeout<F>(timeout: Duration, future: F) -> io::Result<()>
+where
+    F: Future<Output = io::Result<()>>,
+{
+    futures_lite::future::or(future, async {
+        Timer::after(timeout).await;
+        _generate_timeout_error()
+    })
+    .await
+}
+
+// Completes once the file descriptor is readable
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(super) async fn readable(fd: std::os::unix::io::OwnedFd) -> io::Result<()> {
+    // The pidfd is never read, it is registered without setting `O_NONBLOCK`
+    let fd = async_io::Async::new_nonblocking(fd)?;
+    fd.readable().await
+}
@@ -0,0 +1,37 @@
+// Runtime primitives of the asynchronous waits, provided by Tokio
+
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+use crate::error::_generate_timeout_error;
+
+// Runs the blocking function on the blocking thread pool of the runtime
+pub(super) async fn unblock<T, F>(f: F) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await?
+}
+
+// Fails with `ErrorKind::TimedOut` if the future does not complete before the timeout
+pub(super) async fn timeout<F>(timeout: Duration, future: F) -> io::Result<()>
+where
+    F: Future<Output = io::Result<()>>,
+{
+    tokio::time::timeout(timeout, future)
+        .await
+        .unwrap_or_else(|_| _generate_timeout_error())
+}
+
+// Completes once the file descriptor is readable
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(super) async fn readable(fd: std::os::unix::io::OwnedFd) -> io::Result<()> {
+    use tokio::io::unix::AsyncFd;
+    use tokio::io::Interest;
+
+    let fd = AsyncFd::with_interest(fd, Interest::READABLE)?;
+    let _ready = fd.readable().await?;
+    Ok(())
+}
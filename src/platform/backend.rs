@@ -0,0 +1,161 @@
+use std::fmt;
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+use std::io;
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+use std::time::Duration;
+
+use super::imp;
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+use crate::error::_generate_unsupported_error;
+
+/// A mechanism used to wait for the exit of a child process, see [`active_backend`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Backend {
+    /// `WaitForSingleObject` on the process handle, on Windows.
+    WindowsWaitObject,
+    /// `kqueue` with an `EVFILT_PROC`/`NOTE_EXIT` filter, on macOS, other Apple targets and the BSDs.
+    Kqueue,
+    /// Event ports notified when the `/proc` file of the process hangs up, on illumos and Solaris.
+    EventPorts,
+    /// `ppoll` on a pidfd opened with `pidfd_open`, with the `pidfd` feature.
+    Pidfd,
+    /// An io_uring `POLL_ADD` operation on a pidfd, with the `uring` feature.
+    Uring,
+    /// A watcher thread blocked in `waitid`, the default on the other Unix systems.
+    Thread,
+    /// `sigtimedwait` on `SIGCHLD`, with the `signal` feature.
+    Signal,
+    /// A `signalfd` receiving `SIGCHLD`, with the `signalfd` feature.
+    Signalfd,
+    /// `zx_object_wait_one` waiting for `ZX_TASK_TERMINATED` on the process handle, on Fuchsia.
+    FuchsiaWaitOne,
+    /// A loop checking the child with sleeps in between, with the `polling` feature. It is the default on
+    /// the targets without any of the other backends.
+    Polling,
+}
+
+impl Backend {
+    /// Returns the name of the backend, as used by the feature selecting it on Unix.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::WindowsWaitObject => "windows_wait_object",
+            Backend::Kqueue => "kqueue",
+            Backend::EventPorts => "event_ports",
+            Backend::Pidfd => "pidfd",
+            Backend::Uring => "uring",
+            Backend::Thread => "thread",
+            Backend::Signal => "signal",
+            Backend::Signalfd => "signalfd",
+            Backend::FuchsiaWaitOne => "fuchsia_wait_one",
+            Backend::Polling => "polling",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Returns the backend used by [`ChildWT::wait_timeout`](super::ChildWT::wait_timeout) in this process.
+///
+/// The backend is chosen at compile time from the target and the features, see
+/// [`ChildWT::wait_timeout`](super::ChildWT::wait_timeout). Some of them are also checked at runtime:
+/// with the `pidfd` feature, [`Backend::Thread`] is returned if `pidfd_open` is not available, and with
+/// the `uring` feature, [`Backend::Pidfd`] is returned if io_uring is not available. This is meant to be
+/// logged by applications, so that behavior differences between systems can be diagnosed.
+///
+/// # Example
+/// ```rust
+/// use child_wait_timeout::active_backend;
+///
+/// println!("waiting for children with {}", active_backend());
+/// ```
+pub fn active_backend() -> Backend {
+    imp::_active_backend()
+}
+
+// Waits for the child with the given pid to exit with the backend, without reaping it nor falling
+// back to another backend
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+))]
+pub(crate) fn wait_pid_with(backend: Backend, pid: u32, timeout: Duration) -> io::Result<()> {
+    match backend {
+        #[cfg(any(
+            target_vendor = "apple",
+            target_os = "freebsd",
+            target_os = "dragonfly",
+            target_os = "openbsd",
+            target_os = "netbsd"
+        ))]
+        Backend::Kqueue => imp::_wait_pid_untraced(pid, timeout),
+        #[cfg(any(target_os = "illumos", target_os = "solaris"))]
+        Backend::EventPorts => imp::_wait_pid_untraced(pid, timeout),
+        #[cfg(all(any(target_os = "linux", target_os = "android"), feature = "pidfd"))]
+        Backend::Pidfd => super::unix_pidfd::_wait_pid_with(pid, timeout, false),
+        #[cfg(all(target_os = "linux", feature = "uring"))]
+        Backend::Uring => super::unix_pidfd::_wait_pid_with(pid, timeout, true),
+        #[cfg(not(any(
+            target_vendor = "apple",
+            target_os = "freebsd",
+            target_os = "dragonfly",
+            target_os = "openbsd",
+            target_os = "netbsd"
+        )))]
+        Backend::Thread => super::unix_thread::_wait_pid_untraced(pid, timeout),
+        // Only one of `signal` and `signalfd` is compiled
+        #[cfg(all(
+            not(any(
+                target_vendor = "apple",
+                target_os = "freebsd",
+                target_os = "dragonfly",
+                target_os = "openbsd",
+                target_os = "netbsd"
+            )),
+            feature = "signal"
+        ))]
+        backend if backend == super::unix_signal::_active_backend() => {
+            super::unix_signal::_wait_pid_untraced(pid, timeout)
+        }
+        #[cfg(feature = "polling")]
+        Backend::Polling => super::polling::_wait_pid_untraced(pid, timeout),
+        _ => _generate_unsupported_error(),
+    }
+}
+
+// Waits for the process to exit with the backend, the handle must have the `SYNCHRONIZE` access right
+#[cfg(windows)]
+pub(crate) fn wait_handle_with(
+    backend: Backend,
+    handle: super::win32::HANDLE,
+    timeout: Duration,
+) -> io::Result<()> {
+    match backend {
+        Backend::WindowsWaitObject => imp::_wait_handle_untraced(handle, timeout),
+        #[cfg(feature = "polling")]
+        Backend::Polling => super::polling::_wait_handle_untraced(handle, timeout),
+        _ => _generate_unsupported_error(),
+    }
+}
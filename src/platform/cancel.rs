@@ -0,0 +1,263 @@
+use std::future::Future;
+use std::io;
+use std::pin::pin;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use super::{deadline_after, remaining_until, ChildExitFuture};
+use crate::error::{_generate_interrupted_error, _generate_timeout_error};
+
+/// A token aborting the waits of [`ChildWT::wait_timeout_cancellable`](super::ChildWT::wait_timeout_cancellable)
+/// from another thread.
+///
+/// Clones of the token share its state: once [`CancelToken::cancel`] is called, the waits in progress
+/// and all the following ones fail with `ErrorKind::Interrupted`. The cancellation cannot be undone.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::io;
+/// use std::process::Command;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::{CancelToken, ChildWT};
+///
+/// let token = CancelToken::new();
+/// let canceller = token.clone();
+/// thread::spawn(move || {
+///     thread::sleep(Duration::from_millis(100));
+///     canceller.cancel();
+/// });
+///
+/// let mut child = Command::new("sleep").arg("10").spawn()?;
+/// let result = child.wait_timeout_cancellable(Duration::from_secs(5), &token);
+/// assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+/// child.kill()?;
+/// child.wait()?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    waiters: Waiters,
+}
+
+/// A handle interrupting the waits of
+/// [`ChildWT::wait_timeout_interruptible`](super::ChildWT::wait_timeout_interruptible) from another thread.
+///
+/// Unlike a [`CancelToken`], an interruption only affects the waits in progress: they fail with
+/// `ErrorKind::Interrupted`, and the handle can be used again for the following waits. Clones of the
+/// handle interrupt the same waits, and [`Interrupter::waker`] returns a [`Waker`] interrupting them
+/// when it is woken.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::io;
+/// use std::process::Command;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::{ChildWT, Interrupter};
+///
+/// let interrupter = Interrupter::new();
+/// let handle = interrupter.clone();
+/// thread::spawn(move || {
+///     thread::sleep(Duration::from_millis(100));
+///     handle.interrupt();
+/// });
+///
+/// let mut child = Command::new("sleep").arg("1").spawn()?;
+/// let result = child.wait_timeout_interruptible(Duration::from_secs(5), &interrupter);
+/// assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+///
+/// // The handle is not consumed by the interruption
+/// let status = child.wait_timeout_interruptible(Duration::from_secs(5), &interrupter)?;
+/// assert!(status.success());
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Interrupter {
+    inner: Arc<InterrupterInner>,
+}
+
+#[derive(Debug, Default)]
+struct InterrupterInner {
+    // Incremented by each interruption, a wait is interrupted once it differs from its value
+    // when the wait started
+    generation: AtomicU64,
+    waiters: Waiters,
+}
+
+// The threads currently waiting with a token or an interrupter
+#[derive(Debug, Default)]
+struct Waiters(Mutex<Vec<Thread>>);
+
+impl CancelToken {
+    /// Creates a token which is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token, waking the waits in progress.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.waiters.wake();
+    }
+
+    /// Returns `true` if the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+impl Interrupter {
+    /// Creates a handle without any wait to interrupt.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interrupts the waits in progress. The waits started afterwards are not affected.
+    pub fn interrupt(&self) {
+        self.inner.generation.fetch_add(1, Ordering::SeqCst);
+        self.inner.waiters.wake();
+    }
+
+    /// Returns a [`Waker`] interrupting the waits in progress when it is woken.
+    pub fn waker(&self) -> Waker {
+        Waker::from(self.inner.clone())
+    }
+}
+
+impl Wake for InterrupterInner {
+    fn wake(self: Arc<Self>) {
+        Interrupter { inner: self }.interrupt();
+    }
+}
+
+impl Waiters {
+    fn lock(&self) -> MutexGuard<'_, Vec<Thread>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Unparks the waiting threads
+    fn wake(&self) {
+        for waiter in self.lock().iter() {
+            waiter.unpark();
+        }
+    }
+}
+
+// Registers the current thread as a waiter while it is alive
+struct Registration<'a>(&'a Waiters);
+
+impl<'a> Registration<'a> {
+    fn new(waiters: &'a Waiters) -> Self {
+        waiters.lock().push(thread::current());
+        Registration(waiters)
+    }
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        let id = thread::current().id();
+        let mut waiters = self.0.lock();
+        if let Some(index) = waiters.iter().position(|waiter| waiter.id() == id) {
+            waiters.swap_remove(index);
+        }
+    }
+}
+
+// Wakes the waiting thread once the child has exited
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// Waits for the child to exit, the timeout to expire or the token to be cancelled
+pub(crate) fn wait_cancellable(
+    future: ChildExitFuture<'_>,
+    timeout: Duration,
+    token: &CancelToken,
+) -> io::Result<ExitStatus> {
+    wait_until_woken(future, timeout, &token.inner.waiters, || {
+        token.is_cancelled()
+    })
+}
+
+// Waits for the child to exit, the timeout to expire or the interrupter to be poked
+pub(crate) fn wait_interruptible(
+    future: ChildExitFuture<'_>,
+    timeout: Duration,
+    interrupter: &Interrupter,
+) -> io::Result<ExitStatus> {
+    let generation = &interrupter.inner.generation;
+    let start = generation.load(Ordering::SeqCst);
+    wait_until_woken(future, timeout, &interrupter.inner.waiters, || {
+        generation.load(Ordering::SeqCst) != start
+    })
+}
+
+// Waits for the child to exit, the timeout to expire or `interrupted` to return `true`. The exit is
+// awaited with the future of the child, the thread is parked until it or one of the waiters wakes it.
+fn wait_until_woken(
+    mut future: ChildExitFuture<'_>,
+    timeout: Duration,
+    waiters: &Waiters,
+    interrupted: impl Fn() -> bool,
+) -> io::Result<ExitStatus> {
+    if interrupted() {
+        return _generate_interrupted_error();
+    }
+    // A zero timeout only checks the child once, without spawning the watcher thread
+    if timeout.is_zero() {
+        return match future.try_wait()? {
+            Some(status) => Ok(status),
+            None => _generate_timeout_error(),
+        };
+    }
+
+    let deadline = deadline_after(timeout);
+    let _registration = Registration::new(waiters);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(result) = future.as_mut().poll(&mut context) {
+            return result;
+        }
+        // Checked after registering, an interruption in between unparks the thread
+        if interrupted() {
+            return _generate_interrupted_error();
+        }
+        match deadline {
+            Some(_) => {
+                let remaining = remaining_until(deadline);
+                if remaining.is_zero() {
+                    return _generate_timeout_error();
+                }
+                thread::park_timeout(remaining);
+            }
+            None => thread::park(),
+        }
+    }
+}
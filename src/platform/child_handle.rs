@@ -0,0 +1,259 @@
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::unix::io::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsHandle, AsRawHandle, OwnedHandle};
+
+use super::imp;
+use crate::error::{_generate_os_error, _generate_timeout_error};
+
+/// A cheap handle to a child process, which can be cloned and sent to other threads to wait for the
+/// child or kill it.
+///
+/// The handle is decoupled from the [`Child`] it was created from: the `Child` keeps its standard
+/// input and output pipes and stays responsible for reaping the child process, while the clones of the
+/// handle only observe its exit. The exit status is read without reaping the child, and is kept by the
+/// handle once observed.
+///
+/// The handle refers to the process itself rather than to its pid where the system allows it, so that
+/// it cannot reach another process reusing the pid once the child has been reaped:
+/// - On Linux and Android, through a pidfd. If `pidfd_open` is not available at runtime, the pid is used
+///   as on the other Unix systems.
+/// - On the other Unix systems, through the pid. Before signaling it, the handle checks that it still
+///   belongs to an unreaped child of the current process.
+/// - On Windows, through a duplicate of the process handle.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::process::Command;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::ChildHandle;
+///
+/// let mut child = Command::new("sleep").arg("10").spawn()?;
+/// let handle = ChildHandle::new(&child)?;
+/// let waiter = {
+///     let handle = handle.clone();
+///     thread::spawn(move || handle.wait_timeout(Duration::from_secs(5)))
+/// };
+/// handle.kill()?;
+/// let status = waiter.join().unwrap()?;
+/// assert_eq!(child.wait()?, status);
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChildHandle {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    id: u32,
+    // `None` if `pidfd_open` is not available
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pidfd: Option<OwnedFd>,
+    #[cfg(windows)]
+    handle: OwnedHandle,
+    // The exit status once observed, the child may be reaped by its owner afterwards
+    status: Mutex<Option<ExitStatus>>,
+}
+
+impl ChildHandle {
+    /// Creates a handle to the child process.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the pidfd or the process handle could not be opened, for instance when the
+    /// file descriptor or handle limit is reached.
+    pub fn new(child: &Child) -> io::Result<Self> {
+        let inner = Inner {
+            id: child.id(),
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            pidfd: super::unix::pidfd_open(child.id() as libc::pid_t, 0)?
+                .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }),
+            #[cfg(windows)]
+            handle: child.as_handle().try_clone_to_owned()?,
+            status: Mutex::new(None),
+        };
+        Ok(ChildHandle {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Returns the OS-assigned process identifier of the child process.
+    pub fn id(&self) -> u32 {
+        self.inner.id
+    }
+
+    /// Waits for the child process to exit or until the timeout expires, without reaping it.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process
+    /// exits. On Unix, it fails with the `ECHILD` OS error if the child has been reaped by its owner before
+    /// any handle observed its exit, as its exit status is lost.
+    pub fn wait_timeout(&self, timeout: Duration) -> io::Result<ExitStatus> {
+        if let Some(status) = self.try_wait()? {
+            return Ok(status);
+        }
+        if timeout.is_zero() {
+            return _generate_timeout_error();
+        }
+
+        self.inner.wait_untraced(timeout)?;
+        match self.try_wait()? {
+            Some(status) => Ok(status),
+            None => _generate_timeout_error(),
+        }
+    }
+
+    /// Returns the exit status if the child process has exited, without blocking nor reaping it.
+    ///
+    /// # Errors
+    ///
+    /// On Unix, this function fails with the `ECHILD` OS error if the child has been reaped by its owner
+    /// before any handle observed its exit.
+    pub fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        let mut status = self.inner.status.lock().unwrap_or_else(|e| e.into_inner());
+        if status.is_none() {
+            *status = self.inner.peek_status()?;
+        }
+        Ok(*status)
+    }
+
+    /// Kills the child process. Nothing is done if it has already exited.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the child process could not be killed.
+    pub fn kill(&self) -> io::Result<()> {
+        if self.try_wait().is_ok_and(|status| status.is_some()) {
+            return Ok(());
+        }
+        self.inner.kill()
+    }
+}
+
+#[cfg(unix)]
+impl Inner {
+    // Waits for the child to exit with the backend, without reaping it
+    fn wait_untraced(&self, timeout: Duration) -> io::Result<()> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(pidfd) = &self.pidfd {
+            return super::wait_fd_timeout(pidfd.as_fd(), timeout);
+        }
+        imp::_wait_pid_untraced(self.id, timeout)
+    }
+
+    // Reads the exit status with `WNOWAIT`, leaving the zombie for the owner of the child
+    fn peek_status(&self) -> io::Result<Option<ExitStatus>> {
+        use std::mem;
+
+        use libc::{id_t, siginfo_t, waitid, P_PID, WEXITED, WNOHANG, WNOWAIT};
+
+        use super::unix::exit_status;
+
+        let (idtype, id) = (P_PID, self.id as id_t);
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let (idtype, id) = match &self.pidfd {
+            Some(pidfd) => (libc::P_PIDFD, pidfd.as_raw_fd() as id_t),
+            None => (idtype, id),
+        };
+
+        let mut info: siginfo_t = unsafe { mem::zeroed() };
+        if unsafe { waitid(idtype, id, &mut info, WEXITED | WNOWAIT | WNOHANG) } == -1 {
+            return _generate_os_error();
+        }
+        // With WNOHANG, the pid is left to zero if the process is still running
+        if unsafe { info.si_pid() } == 0 {
+            return Ok(None);
+        }
+        Ok(exit_status(&info))
+    }
+
+    fn kill(&self) -> io::Result<()> {
+        use libc::{ESRCH, SIGKILL};
+
+        // The pidfd keeps referring to the process once reaped, the signal is then not delivered
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(pidfd) = &self.pidfd {
+            let result = unsafe {
+                libc::syscall(
+                    libc::SYS_pidfd_send_signal,
+                    pidfd.as_raw_fd(),
+                    SIGKILL,
+                    std::ptr::null::<libc::siginfo_t>(),
+                    0,
+                )
+            };
+            return match result {
+                -1 if io::Error::last_os_error().raw_os_error() != Some(ESRCH) => {
+                    _generate_os_error()
+                }
+                _ => Ok(()),
+            };
+        }
+
+        // Without a pidfd, the pid is only signaled while it is an unreaped child
+        if self.peek_status().is_err() {
+            return Ok(());
+        }
+        if unsafe { libc::kill(self.id as libc::pid_t, SIGKILL) } == -1
+            && io::Error::last_os_error().raw_os_error() != Some(ESRCH)
+        {
+            return _generate_os_error();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Inner {
+    fn wait_untraced(&self, timeout: Duration) -> io::Result<()> {
+        use super::win32::HANDLE;
+
+        imp::_wait_handle_untraced(self.handle.as_raw_handle() as HANDLE, timeout)
+    }
+
+    fn peek_status(&self) -> io::Result<Option<ExitStatus>> {
+        use std::os::windows::process::ExitStatusExt;
+
+        use super::win32::{
+            GetExitCodeProcess, WaitForSingleObject, DWORD, HANDLE, STILL_ACTIVE, WAIT_OBJECT_0,
+        };
+
+        let handle = self.handle.as_raw_handle() as HANDLE;
+        // A process may exit with the `STILL_ACTIVE` code, so its handle is checked first
+        if unsafe { WaitForSingleObject(handle, 0) } != WAIT_OBJECT_0 {
+            return Ok(None);
+        }
+        let mut code: DWORD = STILL_ACTIVE;
+        if unsafe { GetExitCodeProcess(handle, &mut code) } == 0 {
+            return _generate_os_error();
+        }
+        Ok(Some(ExitStatus::from_raw(code)))
+    }
+
+    fn kill(&self) -> io::Result<()> {
+        use super::win32::{TerminateProcess, HANDLE};
+
+        if unsafe { TerminateProcess(self.handle.as_raw_handle() as HANDLE, 1) } == 0 {
+            // The process may have exited in the meantime
+            let error = io::Error::last_os_error();
+            return match self.peek_status() {
+                Ok(Some(_)) => Ok(()),
+                _ => Err(error),
+            };
+        }
+        Ok(())
+    }
+}
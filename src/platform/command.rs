@@ -0,0 +1,446 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{deadline_after, remaining_until, trace, ChildWT};
+use crate::error::{_generate_default_error, _generate_timeout_error};
+
+/// Extension trait running a [`Command`] to completion with a timeout.
+///
+/// The command is spawned, waited for with [`ChildWT::wait_or_kill`], and its process tree is killed and
+/// reaped if the timeout expires, so that it is never left running.
+pub trait CommandWT {
+    /// Runs the command and returns its exit status, killing it if the timeout expires.
+    ///
+    /// As with [`Command::status`], the standard streams are inherited unless configured otherwise.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the process exits,
+    /// in which case its process tree has been killed and reaped.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::io;
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::CommandWT;
+    ///
+    /// let status = Command::new("sleep").arg("10").status_timeout(Duration::from_millis(100));
+    /// assert_eq!(status.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn status_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus>;
+
+    /// Runs the command and collects its output, killing it if the timeout expires.
+    ///
+    /// The standard output and error are captured, and read on their own threads while the process runs
+    /// so that it is not blocked by full pipes. Unlike [`Command::output`], the standard input is inherited
+    /// unless configured otherwise.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the process exits,
+    /// in which case its process tree has been killed and reaped, or before its output pipes are closed
+    /// by the processes it spawned. The output collected until then is held by an [`OutputTimeout`].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::CommandWT;
+    ///
+    /// let output = Command::new("echo").arg("hello").output_timeout(Duration::from_secs(5))?;
+    /// assert!(output.status.success());
+    /// assert_eq!(output.stdout, b"hello\n");
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn output_timeout(&mut self, timeout: Duration) -> io::Result<Output>;
+}
+
+impl CommandWT for Command {
+    fn status_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        let mut child = self.spawn()?;
+        trace::spawned(child.id(), self);
+        child.wait_or_kill(timeout)
+    }
+
+    fn output_timeout(&mut self, timeout: Duration) -> io::Result<Output> {
+        let deadline = deadline_after(timeout);
+        let child = self.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+        trace::spawned(child.id(), self);
+        child.wait_with_output_timeout(remaining_until(deadline))
+    }
+}
+
+/// The error of [`ChildWT::wait_with_output_timeout`] and [`CommandWT::output_timeout`] when the timeout
+/// expires, holding the output collected until then.
+///
+/// It is wrapped in an [`io::Error`] of kind `ErrorKind::TimedOut`, from which it can be retrieved with
+/// [`io::Error::get_ref`] or [`io::Error::into_inner`] and a downcast. The status of the output is the one
+/// of the killed process.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{CommandWT, OutputTimeout};
+///
+/// let error = Command::new("sh")
+///     .args(["-c", "echo started; sleep 10"])
+///     .output_timeout(Duration::from_millis(500))
+///     .unwrap_err();
+/// let partial = error.get_ref().and_then(|e| e.downcast_ref::<OutputTimeout>());
+/// assert_eq!(partial.unwrap().output().stdout, b"started\n");
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct OutputTimeout {
+    output: Output,
+}
+
+impl OutputTimeout {
+    /// Returns the output collected before the timeout expired.
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Returns the output collected before the timeout expired, consuming the error.
+    pub fn into_output(self) -> Output {
+        self.output
+    }
+}
+
+impl fmt::Display for OutputTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("operation timed out")
+    }
+}
+
+impl Error for OutputTimeout {}
+
+/// Spawns the command, collects its output and kills it if the timeout expires.
+///
+/// This is [`CommandWT::output_timeout`] for the callers which rather handle the expiry of the timeout
+/// apart from the other errors: the standard output and error are captured, the process tree is killed
+/// and reaped once the timeout expires, and the output collected until then is returned in the error.
+///
+/// # Errors
+///
+/// This function fails with [`RunTimeoutError::TimedOut`] if the timeout expires before the process exits
+/// or before its output pipes are closed, and with [`RunTimeoutError::Io`] if the command could not be
+/// spawned, see [`Command::spawn`], or waited for.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{run_with_timeout, RunTimeoutError};
+///
+/// let mut command = Command::new("sh");
+/// command.args(["-c", "echo started; sleep 10"]);
+/// match run_with_timeout(command, Duration::from_millis(500)) {
+///     Err(RunTimeoutError::TimedOut(partial)) => assert_eq!(partial.stdout, b"started\n"),
+///     result => panic!("unexpected {:?}", result),
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+pub fn run_with_timeout(
+    mut command: Command,
+    timeout: Duration,
+) -> Result<Output, RunTimeoutError> {
+    command.output_timeout(timeout).map_err(|e| {
+        if e.kind() != io::ErrorKind::TimedOut
+            || !e.get_ref().is_some_and(|inner| inner.is::<OutputTimeout>())
+        {
+            return RunTimeoutError::Io(e);
+        }
+        let inner = e.into_inner().expect("the error holds the output");
+        let timeout = inner
+            .downcast::<OutputTimeout>()
+            .expect("the error is an OutputTimeout");
+        RunTimeoutError::TimedOut(timeout.into_output())
+    })
+}
+
+/// The error of [`run_with_timeout`].
+///
+/// It converts into an [`io::Error`], of kind `ErrorKind::TimedOut` holding an [`OutputTimeout`] when
+/// the timeout expired, so that it can be propagated with `?` from functions returning [`io::Result`].
+#[derive(Debug)]
+pub enum RunTimeoutError {
+    /// The timeout expired, the process tree has been killed and reaped. The output holds the data
+    /// collected until then and the exit status of the killed process.
+    TimedOut(Output),
+    /// The command could not be spawned or waited for.
+    Io(io::Error),
+}
+
+impl RunTimeoutError {
+    /// Returns the output collected before the timeout expired, or `None` for another error.
+    pub fn output(&self) -> Option<&Output> {
+        match self {
+            RunTimeoutError::TimedOut(output) => Some(output),
+            RunTimeoutError::Io(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for RunTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunTimeoutError::TimedOut(_) => f.write_str("operation timed out"),
+            RunTimeoutError::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for RunTimeoutError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RunTimeoutError::TimedOut(_) => None,
+            RunTimeoutError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<RunTimeoutError> for io::Error {
+    fn from(error: RunTimeoutError) -> Self {
+        match error {
+            RunTimeoutError::TimedOut(output) => {
+                io::Error::new(io::ErrorKind::TimedOut, OutputTimeout { output })
+            }
+            RunTimeoutError::Io(e) => e,
+        }
+    }
+}
+
+// How long the pipes are still read once the process has been killed, so that the data it wrote
+// before is not lost. The pipes may be kept open by processes which were not killed with it.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(100);
+
+// Waits for the child with its output, reading its piped streams until the timeout. The child is
+// killed and reaped if the timeout expires, and the output read until then is returned in the error.
+pub(crate) fn wait_with_output<C, O, E>(
+    child: &mut C,
+    stdout: Option<O>,
+    stderr: Option<E>,
+    timeout: Duration,
+) -> io::Result<Output>
+where
+    C: ChildWT + ?Sized,
+    O: Read + Send + 'static,
+    E: Read + Send + 'static,
+{
+    let deadline = deadline_after(timeout);
+    // The child is not waited for until the readers are running, so that it cannot block on a full pipe
+    let stdout = stdout.map(PipeReader::spawn).transpose()?;
+    let stderr = stderr.map(PipeReader::spawn).transpose()?;
+
+    let (status, killed) = match child.wait_timeout(remaining_until(deadline)) {
+        Ok(status) => (status, false),
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            // The exit status of the killed child is kept by its handle once reaped
+            match child.wait_or_kill(Duration::ZERO) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+                result => {
+                    result?;
+                }
+            }
+            (child.wait_timeout(Duration::MAX)?, true)
+        }
+        Err(e) => return Err(e),
+    };
+
+    let drain = match killed {
+        true => deadline_after(DRAIN_TIMEOUT),
+        false => deadline,
+    };
+    let mut timed_out = killed;
+    let mut read = |reader: Option<PipeReader>| match reader {
+        Some(reader) => match reader.finish(drain) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                timed_out = true;
+                Ok(reader.take())
+            }
+            result => result,
+        },
+        None => Ok(Vec::new()),
+    };
+    let output = Output {
+        status,
+        stdout: read(stdout)?,
+        stderr: read(stderr)?,
+    };
+
+    if timed_out {
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            OutputTimeout { output },
+        ))
+    } else {
+        Ok(output)
+    }
+}
+
+// A pipe of the child read to its end by a thread
+struct PipeReader {
+    // The data read so far
+    buffer: Arc<Mutex<Vec<u8>>>,
+    // Receives the result of the thread once the pipe is closed
+    done: Receiver<io::Result<()>>,
+}
+
+impl PipeReader {
+    fn spawn(mut pipe: impl Read + Send + 'static) -> io::Result<Self> {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let (sender, done) = mpsc::channel();
+
+        let shared = buffer.clone();
+        thread::Builder::new()
+            .name("child_wait_timeout".to_string())
+            .spawn(move || {
+                let result = read_pipe(&mut pipe, |chunk| {
+                    shared
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .extend_from_slice(chunk)
+                });
+                let _ = sender.send(result);
+            })?;
+        Ok(PipeReader { buffer, done })
+    }
+
+    // Waits for the pipe to be closed until the deadline and returns the data read
+    fn finish(&self, deadline: Option<Instant>) -> io::Result<Vec<u8>> {
+        let result = match deadline {
+            Some(_) => self.done.recv_timeout(remaining_until(deadline)),
+            None => self.done.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match result {
+            Ok(Ok(())) => Ok(self.take()),
+            Ok(Err(e)) => Err(e),
+            Err(RecvTimeoutError::Timeout) => _generate_timeout_error(),
+            Err(RecvTimeoutError::Disconnected) => _generate_default_error(),
+        }
+    }
+
+    // Returns the data read so far
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.buffer.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// A chunk of the output of a child process, passed to the callback of
+/// [`ChildWT::stream_output_timeout`] as soon as it is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputChunk<'a> {
+    /// Data written to the standard output.
+    Stdout(&'a [u8]),
+    /// Data written to the standard error.
+    Stderr(&'a [u8]),
+}
+
+// Passes the output of the child to the callback as it is read, then waits for the child to exit.
+// The child is killed and reaped if the deadline passes before.
+pub(crate) fn stream_output<C, O, E, F>(
+    child: &mut C,
+    stdout: Option<O>,
+    stderr: Option<E>,
+    timeout: Duration,
+    mut on_output: F,
+) -> io::Result<ExitStatus>
+where
+    C: ChildWT + ?Sized,
+    O: Read + Send + 'static,
+    E: Read + Send + 'static,
+    F: FnMut(OutputChunk<'_>),
+{
+    let deadline = deadline_after(timeout);
+    let (sender, receiver) = mpsc::channel();
+    if let Some(pipe) = stdout {
+        spawn_streamer(pipe, false, sender.clone())?;
+    }
+    if let Some(pipe) = stderr {
+        spawn_streamer(pipe, true, sender.clone())?;
+    }
+    // The channel is disconnected once both pipes are closed
+    drop(sender);
+
+    loop {
+        let message = match deadline {
+            Some(_) => receiver.recv_timeout(remaining_until(deadline)),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match message {
+            Ok((false, Ok(chunk))) => on_output(OutputChunk::Stdout(&chunk)),
+            Ok((true, Ok(chunk))) => on_output(OutputChunk::Stderr(&chunk)),
+            Ok((_, Err(e))) => return Err(e),
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                return child
+                    .wait_or_kill(Duration::ZERO)
+                    .and_then(|_| _generate_timeout_error())
+            }
+        }
+    }
+    child.wait_or_kill(remaining_until(deadline))
+}
+
+// Sends the chunks read from a pipe of the child, tagged with `true` for the standard error,
+// until it is closed
+fn spawn_streamer(
+    mut pipe: impl Read + Send + 'static,
+    stderr: bool,
+    sender: Sender<(bool, io::Result<Vec<u8>>)>,
+) -> io::Result<()> {
+    thread::Builder::new()
+        .name("child_wait_timeout".to_string())
+        .spawn(move || {
+            let result = read_pipe(&mut pipe, |chunk| {
+                let _ = sender.send((stderr, Ok(chunk.to_vec())));
+            });
+            if let Err(e) = result {
+                let _ = sender.send((stderr, Err(e)));
+            }
+        })?;
+    Ok(())
+}
+
+// Reads the pipe until it is closed, passing each chunk read to the callback
+fn read_pipe(pipe: &mut impl Read, mut on_chunk: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut chunk = [0; 8192];
+    loop {
+        match pipe.read(&mut chunk) {
+            Ok(0) => return Ok(()),
+            Ok(read) => on_chunk(&chunk[..read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::io;
+use std::process::{ExitStatus, Output};
+use std::time::{Duration, Instant};
+
+use super::{deadline_after, remaining_until, ChildWT};
+
+/// A point in time shared by several operations, each one consuming from the same remaining budget.
+///
+/// A request-scoped timeout is created once with [`Deadline::after`], then passed to the waits of the
+/// different children involved in the request. Each wait is given the time remaining until the deadline,
+/// so the operations never exceed the initial budget together, whatever their number. The deadline is
+/// measured with the monotonic clock of [`Instant`].
+///
+/// The time remaining can also be passed to any function taking a timeout with [`Deadline::remaining`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::{Command, Stdio};
+///
+/// use child_wait_timeout::Deadline;
+///
+/// let deadline = Deadline::after(Duration::from_secs(5));
+///
+/// let mut build = Command::new("sleep").arg("1").spawn()?;
+/// deadline.wait_or_kill(&mut build)?;
+///
+/// let test = Command::new("echo").arg("ok").stdout(Stdio::piped()).spawn()?;
+/// let output = deadline.wait_with_output(test)?;
+/// assert_eq!(output.stdout, b"ok\n");
+/// assert!(deadline.remaining() < Duration::from_secs(5));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Deadline {
+    // `None` if the timeout cannot be represented, the deadline is then never reached
+    instant: Option<Instant>,
+}
+
+impl Deadline {
+    /// Creates a deadline the given duration from now.
+    pub fn after(timeout: Duration) -> Self {
+        Deadline {
+            instant: deadline_after(timeout),
+        }
+    }
+
+    /// Creates a deadline at the given instant.
+    pub fn at(instant: Instant) -> Self {
+        Deadline {
+            instant: Some(instant),
+        }
+    }
+
+    /// Returns the instant of the deadline, or `None` if it is too far in the future to be represented.
+    pub fn instant(&self) -> Option<Instant> {
+        self.instant
+    }
+
+    /// Returns the time remaining until the deadline, zero once it has passed.
+    pub fn remaining(&self) -> Duration {
+        remaining_until(self.instant)
+    }
+
+    /// Returns `true` once the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Waits for the child process to exit until the deadline, see [`ChildWT::wait_timeout`].
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the deadline passes before the child process exits.
+    pub fn wait<C: ChildWT + ?Sized>(&self, child: &mut C) -> io::Result<ExitStatus> {
+        child.wait_timeout(self.remaining())
+    }
+
+    /// Waits for the child process to exit until the deadline, and kills it if the deadline passes, see
+    /// [`ChildWT::wait_or_kill`].
+    ///
+    /// # Errors
+    ///
+    /// This function fails with `ErrorKind::TimedOut` if the deadline passed, in which case the child process
+    /// has been killed and reaped.
+    pub fn wait_or_kill<C: ChildWT + ?Sized>(&self, child: &mut C) -> io::Result<ExitStatus> {
+        child.wait_or_kill(self.remaining())
+    }
+
+    /// Collects the output of the child process until the deadline, see [`ChildWT::wait_with_output_timeout`].
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the deadline passes before the child process exits,
+    /// in which case its process tree is killed and reaped.
+    pub fn wait_with_output<C: ChildWT>(&self, child: C) -> io::Result<Output> {
+        child.wait_with_output_timeout(self.remaining())
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(instant: Instant) -> Self {
+        Deadline::at(instant)
+    }
+}
@@ -0,0 +1,90 @@
+use std::io;
+use std::process::Output;
+use std::time::Duration;
+
+use super::{deadline_after, remaining_until, wait_pid_timeout};
+use crate::error::_generate_timeout_error;
+
+/// Extension trait bounding the expressions started with `duct` by a timeout.
+///
+/// Each process of the expression, such as the stages of a pipeline, is waited for with the backend of
+/// this crate, through [`wait_pid_timeout`], without reaping it. The expression is then reaped by
+/// `duct::Handle::try_wait`, which keeps its bookkeeping and output capture consistent.
+///
+/// This trait is only available with the `duct` feature.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::HandleWT;
+///
+/// let handle = duct::cmd!("echo", "hello")
+///     .pipe(duct::cmd!("tr", "a-z", "A-Z"))
+///     .stdout_capture()
+///     .start()?;
+/// let output = handle.wait_timeout(Duration::from_secs(5))?;
+/// assert_eq!(output.stdout, b"HELLO\n");
+/// #     Ok(())
+/// # }
+/// ```
+pub trait HandleWT {
+    /// Waits for all the processes of the expression to exit or until the timeout expires.
+    ///
+    /// Once they have exited, the threads capturing their output are joined, which blocks until the
+    /// pipes are closed, as with `duct::Handle::wait`.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the processes exit.
+    /// As with `duct::Handle::wait`, it fails with `ErrorKind::Other` if the expression exited with a
+    /// non-zero status which is not marked `unchecked`.
+    fn wait_timeout(&self, timeout: Duration) -> io::Result<&Output>;
+
+    /// Waits for all the processes of the expression to exit, and kills them if the timeout expires.
+    ///
+    /// The processes are killed and reaped with `duct::Handle::kill`, which does not kill the processes
+    /// they spawned themselves.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with `ErrorKind::TimedOut` if the timeout expired, in which case the processes
+    /// have been killed and reaped, and otherwise as [`HandleWT::wait_timeout`].
+    fn wait_or_kill(&self, timeout: Duration) -> io::Result<&Output>;
+}
+
+impl HandleWT for duct::Handle {
+    fn wait_timeout(&self, timeout: Duration) -> io::Result<&Output> {
+        let deadline = deadline_after(timeout);
+
+        loop {
+            for pid in self.pids() {
+                match wait_pid_timeout(pid, remaining_until(deadline)) {
+                    Err(e) if e.kind() == io::ErrorKind::TimedOut => return Err(e),
+                    // The process has already been reaped by `duct`
+                    Ok(()) | Err(_) => {}
+                }
+            }
+
+            if let Some(output) = self.try_wait()? {
+                return Ok(output);
+            }
+            if remaining_until(deadline).is_zero() {
+                return _generate_timeout_error();
+            }
+        }
+    }
+
+    fn wait_or_kill(&self, timeout: Duration) -> io::Result<&Output> {
+        match self.wait_timeout(timeout) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                self.kill()?;
+                Err(e)
+            }
+            result => result,
+        }
+    }
+}
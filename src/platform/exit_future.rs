@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+
+use super::ChildWT;
+use crate::error::_generate_default_error;
+
+/// A future resolving to the exit status of a child process, returned by
+/// [`ChildWT::exit_future`](super::ChildWT::exit_future).
+///
+/// The future does not depend on any runtime: on its first poll, a thread is spawned to wait for the
+/// child without reaping it, and wakes the task once it exits. The child is reaped when the future
+/// completes. The thread is shared by the futures awaiting the same child, as well as by the waits of
+/// the `thread` backend, and keeps running until the child exits if they are dropped before.
+///
+/// The future of a child only known through [`ChildWT::wait_timeout`], as returned by the default
+/// [`ChildWT::exit_future`], checks it with [`ChildWT::try_wait_timeout`] every 10 milliseconds instead.
+/// The futures of all such children are woken by a single thread.
+///
+/// The future has no timeout of its own, it can be combined with the timers and `select!` of any
+/// runtime.
+#[derive(Debug)]
+pub struct ChildExitFuture<'a> {
+    child: Target<'a>,
+    // `None` until the first poll registers with the watcher thread
+    watcher: Option<Arc<Watcher>>,
+    // Identifies the waker of the future among the ones registered with the watcher
+    id: u64,
+}
+
+// How the exit of the child is observed
+enum Target<'a> {
+    // By a watcher thread, without reaping the child
+    Watched(&'a mut (dyn ExitTarget + Send)),
+    // By checking the child on every tick, reaping it once it has exited
+    Polled(Box<dyn FnMut() -> io::Result<Option<ExitStatus>> + Send + 'a>),
+}
+
+impl fmt::Debug for Target<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Watched(child) => f.debug_tuple("Watched").field(child).finish(),
+            Target::Polled(_) => f.write_str("Polled(..)"),
+        }
+    }
+}
+
+// State shared between the thread waiting for a child and the waits and futures awaiting it
+#[derive(Debug, Default)]
+pub(crate) struct Watcher {
+    // `None` while the child is running, then the result of the wait
+    exited: Mutex<Option<io::Result<()>>>,
+    cond: Condvar,
+    wakers: Mutex<HashMap<u64, Waker>>,
+}
+
+impl Watcher {
+    // Records the result of the wait, then wakes the waits and the futures awaiting the child
+    pub(crate) fn notify(&self, result: io::Result<()>) {
+        let mut wakers = self.wakers.lock().unwrap_or_else(|e| e.into_inner());
+        *self.exited.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+        self.cond.notify_all();
+        for (_, waker) in wakers.drain() {
+            waker.wake();
+        }
+    }
+
+    // Blocks until the result of the wait is known or the timeout expires, `None` in the latter case
+    #[cfg(all(
+        unix,
+        not(any(
+            target_vendor = "apple",
+            target_os = "freebsd",
+            target_os = "dragonfly",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "redox",
+            target_os = "haiku",
+            target_os = "fuchsia"
+        ))
+    ))]
+    pub(crate) fn wait_timeout(&self, timeout: Duration) -> Option<io::Result<()>> {
+        let exited = self.exited.lock().unwrap_or_else(|e| e.into_inner());
+        let (exited, _) = self
+            .cond
+            .wait_timeout_while(exited, timeout, |exited| exited.is_none())
+            .unwrap_or_else(|e| e.into_inner());
+        exited.as_ref().map(shared_result)
+    }
+
+    // Returns the result of the wait if known, otherwise registers the waker of the future
+    fn poll(&self, id: u64, waker: &Waker) -> Option<io::Result<()>> {
+        // The waker is stored under the lock so that the exit cannot be missed in between
+        let mut wakers = self.wakers.lock().unwrap_or_else(|e| e.into_inner());
+        let exited = self.exited.lock().unwrap_or_else(|e| e.into_inner());
+        if exited.is_none() {
+            wakers.insert(id, waker.clone());
+        }
+        exited.as_ref().map(shared_result)
+    }
+
+    fn forget(&self, id: u64) {
+        let mut wakers = self.wakers.lock().unwrap_or_else(|e| e.into_inner());
+        wakers.remove(&id);
+    }
+}
+
+// Copies the result of a wait, whose error is shared by everything awaiting the child
+fn shared_result(result: &io::Result<()>) -> io::Result<()> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => Err(match e.raw_os_error() {
+            Some(code) => io::Error::from_raw_os_error(code),
+            None => io::Error::new(e.kind(), e.to_string()),
+        }),
+    }
+}
+
+// The watchers of the children awaited by several futures, by the key of the child
+#[cfg(not(all(
+    unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "redox",
+        target_os = "haiku",
+        target_os = "fuchsia"
+    ))
+)))]
+fn watchers() -> &'static Mutex<HashMap<u32, Arc<Watcher>>> {
+    static WATCHERS: OnceLock<Mutex<HashMap<u32, Arc<Watcher>>>> = OnceLock::new();
+    WATCHERS.get_or_init(Default::default)
+}
+
+// Interval between the checks of the polled children
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// The wakers of the futures awaiting a polled child, woken on every tick by a single thread
+#[derive(Debug, Default)]
+struct Ticker {
+    wakers: HashMap<u64, Waker>,
+    running: bool,
+}
+
+fn ticker() -> &'static Mutex<Ticker> {
+    static TICKER: OnceLock<Mutex<Ticker>> = OnceLock::new();
+    TICKER.get_or_init(Default::default)
+}
+
+// The child processes whose exit can be awaited
+pub(crate) trait ExitTarget: fmt::Debug {
+    // Returns the exit status if the child has exited, reaping it
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>>;
+
+    // Returns the blocking wait for the exit of the child, which does not reap it
+    fn exited(&self) -> io::Result<BlockingWait>;
+
+    // Returns the key under which the futures awaiting the child share its watcher, `None` if each
+    // future has its own
+    fn watch_key(&self) -> Option<u32>;
+}
+
+pub(crate) type BlockingWait = Box<dyn FnOnce() -> io::Result<()> + Send>;
+
+impl ExitTarget for Child {
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        super::try_wait_untraced(self)
+    }
+
+    // The pid cannot be reused before the child is reaped, which unregisters its watcher first
+    fn watch_key(&self) -> Option<u32> {
+        Some(self.id())
+    }
+
+    #[cfg(all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    ))]
+    fn exited(&self) -> io::Result<BlockingWait> {
+        Ok(exited(self.id()))
+    }
+
+    #[cfg(windows)]
+    fn exited(&self) -> io::Result<BlockingWait> {
+        use std::os::windows::io::AsHandle;
+
+        exited(self.as_handle())
+    }
+
+    // The polling backend cannot observe the exit without reaping the child
+    #[cfg(any(not(any(unix, windows)), target_os = "redox", target_os = "haiku"))]
+    fn exited(&self) -> io::Result<BlockingWait> {
+        crate::error::_generate_unsupported_error()
+    }
+
+    #[cfg(target_os = "fuchsia")]
+    fn exited(&self) -> io::Result<BlockingWait> {
+        exited(self.id())
+    }
+}
+
+impl<'a> ChildExitFuture<'a> {
+    pub(crate) fn new(child: &'a mut (dyn ExitTarget + Send)) -> Self {
+        Self::with_target(Target::Watched(child))
+    }
+
+    // Returns the future of a child only known through its `ChildWT` implementation
+    pub(crate) fn polled<C: ChildWT + Send + ?Sized>(child: &'a mut C) -> Self {
+        Self::with_target(Target::Polled(Box::new(move || {
+            child.try_wait_timeout(Duration::ZERO)
+        })))
+    }
+
+    fn with_target(child: Target<'a>) -> Self {
+        static IDS: AtomicU64 = AtomicU64::new(0);
+
+        ChildExitFuture {
+            child,
+            watcher: None,
+            id: IDS.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    // Returns the exit status if the child has exited, reaping it, without waiting for it
+    pub(crate) fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        match &mut self.child {
+            Target::Watched(child) => child.try_wait(),
+            Target::Polled(check) => check(),
+        }
+    }
+}
+
+impl Drop for ChildExitFuture<'_> {
+    fn drop(&mut self) {
+        if let Some(watcher) = &self.watcher {
+            watcher.forget(self.id);
+        } else if let Target::Polled(_) = self.child {
+            let mut ticker = ticker().lock().unwrap_or_else(|e| e.into_inner());
+            ticker.wakers.remove(&self.id);
+        }
+    }
+}
+
+impl Future for ChildExitFuture<'_> {
+    type Output = io::Result<ExitStatus>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(status) = this.try_wait()? {
+            return Poll::Ready(Ok(status));
+        }
+
+        let child = match &this.child {
+            Target::Watched(child) => &**child,
+            Target::Polled(_) => {
+                schedule(this.id, cx.waker())?;
+                return Poll::Pending;
+            }
+        };
+        let child_key = child.watch_key();
+        let watcher = match &this.watcher {
+            Some(watcher) => watcher.clone(),
+            None => {
+                let watcher = watch(child)?;
+                this.watcher = Some(watcher.clone());
+                watcher
+            }
+        };
+
+        match watcher.poll(this.id, cx.waker()) {
+            Some(Err(e)) => Poll::Ready(Err(e)),
+            // The child is a zombie, reaping it does not block
+            Some(Ok(())) => match this.try_wait() {
+                Ok(Some(status)) => Poll::Ready(Ok(status)),
+                // The notification may come from a previous child that had the same pid, in which
+                // case the next poll watches the current one
+                Ok(None) if child_key.is_some() => {
+                    this.watcher = None;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+                Ok(None) => Poll::Ready(_generate_default_error()),
+                Err(e) => Poll::Ready(Err(e)),
+            },
+            None => Poll::Pending,
+        }
+    }
+}
+
+// Returns the watcher of the child, shared with the other waits awaiting it if the child has a key
+fn watch(child: &dyn ExitTarget) -> io::Result<Arc<Watcher>> {
+    match child.watch_key() {
+        Some(key) => watch_shared(child, key),
+        None => spawn_watcher(child.exited()?, |_| {}),
+    }
+}
+
+// The thread backend watches the children by pid, its watchers are shared with its waits
+#[cfg(all(
+    unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "redox",
+        target_os = "haiku",
+        target_os = "fuchsia"
+    ))
+))]
+fn watch_shared(_child: &dyn ExitTarget, pid: u32) -> io::Result<Arc<Watcher>> {
+    super::unix_thread::watch(pid as libc::pid_t)
+}
+
+// Registers the watcher of the child and spawns its thread, unless another future awaiting the child
+// already did
+#[cfg(not(all(
+    unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "redox",
+        target_os = "haiku",
+        target_os = "fuchsia"
+    ))
+)))]
+fn watch_shared(child: &dyn ExitTarget, key: u32) -> io::Result<Arc<Watcher>> {
+    let mut registry = watchers().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(watcher) = registry.get(&key) {
+        return Ok(watcher.clone());
+    }
+    let watcher = spawn_watcher(child.exited()?, move |watcher| {
+        // Unregister before notifying, once reaped the key can be reused by another child
+        let mut registry = watchers().lock().unwrap_or_else(|e| e.into_inner());
+        if registry.get(&key).is_some_and(|w| Arc::ptr_eq(w, watcher)) {
+            registry.remove(&key);
+        }
+    })?;
+    registry.insert(key, watcher.clone());
+    Ok(watcher)
+}
+
+// Spawns the thread notifying the watcher once the child has exited, after calling `on_exit`
+fn spawn_watcher(
+    wait: BlockingWait,
+    on_exit: impl FnOnce(&Arc<Watcher>) + Send + 'static,
+) -> io::Result<Arc<Watcher>> {
+    let watcher = Arc::new(Watcher::default());
+    let thread_watcher = watcher.clone();
+    thread::Builder::new()
+        .name("child_wait_timeout".to_string())
+        .spawn(move || {
+            let result = wait();
+            on_exit(&thread_watcher);
+            thread_watcher.notify(result);
+        })?;
+    Ok(watcher)
+}
+
+// Registers the waker of the future awaiting a polled child until the next tick, spawning the
+// thread of the ticks unless it is already running
+fn schedule(id: u64, waker: &Waker) -> io::Result<()> {
+    let mut ticker = ticker().lock().unwrap_or_else(|e| e.into_inner());
+    if !ticker.running {
+        thread::Builder::new()
+            .name("child_wait_timeout".to_string())
+            .spawn(tick)?;
+        ticker.running = true;
+    }
+    ticker.wakers.insert(id, waker.clone());
+    Ok(())
+}
+
+// Wakes the registered futures on every tick, until a tick passes without any of them registering
+// again
+fn tick() {
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let wakers = {
+            let mut ticker = ticker().lock().unwrap_or_else(|e| e.into_inner());
+            if ticker.wakers.is_empty() {
+                ticker.running = false;
+                return;
+            }
+            std::mem::take(&mut ticker.wakers)
+        };
+        for (_, waker) in wakers {
+            waker.wake();
+        }
+    }
+}
+
+// Returns the blocking wait for the exit of the child with the given pid, which does not reap it
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+))]
+pub(crate) fn exited(pid: u32) -> BlockingWait {
+    use libc::{id_t, P_PID};
+
+    Box::new(move || super::unix::wait_exited(P_PID, pid as id_t))
+}
+
+// Returns the blocking wait for the exit of the process, on a duplicate of its handle which
+// stays valid if the child is dropped in the meantime
+#[cfg(windows)]
+pub(crate) fn exited(
+    process: std::os::windows::io::BorrowedHandle<'_>,
+) -> io::Result<BlockingWait> {
+    use std::os::windows::io::AsRawHandle;
+
+    use super::win32::{WaitForSingleObject, HANDLE, INFINITE, WAIT_OBJECT_0};
+
+    use crate::error::_generate_os_error;
+
+    let process = process.try_clone_to_owned()?;
+    Ok(Box::new(move || {
+        if unsafe { WaitForSingleObject(process.as_raw_handle() as HANDLE, INFINITE) }
+            == WAIT_OBJECT_0
+        {
+            Ok(())
+        } else {
+            _generate_os_error()
+        }
+    }))
+}
+
+// Returns the blocking wait for the exit of the process, on a duplicate of its handle which
+// stays valid if the child is dropped in the meantime
+#[cfg(target_os = "fuchsia")]
+pub(crate) fn exited(handle: u32) -> io::Result<BlockingWait> {
+    use super::imp::{_wait_handle_untraced, Handle};
+
+    let process = Handle::duplicate(handle)?;
+    Ok(Box::new(move || {
+        _wait_handle_untraced(process.raw(), Duration::MAX)
+    }))
+}
@@ -0,0 +1,93 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use crate::error::_generate_default_error;
+
+/// A future resolving to the exit status of a child process handed over with
+/// [`AsyncChildWT::exit_oneshot`](crate::AsyncChildWT::exit_oneshot).
+///
+/// It owns no reference to the child, which is owned by the task reaping it: the future can be moved
+/// into a `select!` along with other branches, polled again in a loop, or dropped, in which case the
+/// child is still reaped once it exits. It does not depend on any runtime.
+///
+/// This type is only available with the `tokio` or `async-io` feature.
+#[derive(Debug)]
+pub struct ExitOneshot {
+    shared: Arc<Mutex<Shared>>,
+}
+
+// The sending end, completing the future with the result of the wait
+#[derive(Debug)]
+pub(crate) struct ExitSender {
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    result: Option<io::Result<ExitStatus>>,
+    // Set once the result has been sent, or the sender dropped without sending it
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+// Creates the two ends of the notification of the exit of a child
+pub(crate) fn exit_oneshot() -> (ExitSender, ExitOneshot) {
+    let shared = Arc::new(Mutex::new(Shared::default()));
+    (
+        ExitSender {
+            shared: shared.clone(),
+        },
+        ExitOneshot { shared },
+    )
+}
+
+impl ExitOneshot {
+    // Creates a future resolving right away, when the child could not be handed over
+    pub(crate) fn ready(result: io::Result<ExitStatus>) -> Self {
+        let shared = Shared {
+            result: Some(result),
+            closed: true,
+            waker: None,
+        };
+        ExitOneshot {
+            shared: Arc::new(Mutex::new(shared)),
+        }
+    }
+}
+
+impl ExitSender {
+    pub(crate) fn send(self, result: io::Result<ExitStatus>) {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner()).result = Some(result);
+    }
+}
+
+impl Drop for ExitSender {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        shared.closed = true;
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Future for ExitOneshot {
+    type Output = io::Result<ExitStatus>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(result) = shared.result.take() {
+            return Poll::Ready(result);
+        }
+        // The task reaping the child ended without a result, or the result was already returned
+        if shared.closed {
+            return Poll::Ready(_generate_default_error());
+        }
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
@@ -0,0 +1,180 @@
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::time::{Duration, Instant};
+
+use super::kill_tree;
+
+/// A structured record of a bounded run of a child process, meant to be persisted, such as the results
+/// of the jobs of a CI runner or a grading system.
+///
+/// With the `serde` feature, the report implements `Serialize` and `Deserialize`. The durations are
+/// serialized as their seconds and nanoseconds, as by `serde` for [`Duration`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::ExitReport;
+///
+/// let mut child = Command::new("sleep").arg("10").spawn()?;
+/// let report = ExitReport::wait_or_kill(&mut child, Duration::from_millis(100))?;
+/// assert!(report.killed());
+/// assert!(!report.success());
+/// assert!(report.wall_time() >= Duration::from_millis(100));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExitReport {
+    code: Option<i32>,
+    signal: Option<i32>,
+    wall_time: Duration,
+    usage: Option<UsageReport>,
+    killed: bool,
+}
+
+/// The resources used by a child process, recorded in an [`ExitReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UsageReport {
+    /// The CPU time spent in user mode.
+    pub user_time: Duration,
+    /// The CPU time spent in kernel mode.
+    pub system_time: Duration,
+    /// The maximum resident set size, in bytes.
+    pub max_rss: u64,
+}
+
+impl ExitReport {
+    /// Creates a report from the exit status of a child process, the time it ran and whether it was killed.
+    pub fn new(status: ExitStatus, wall_time: Duration, killed: bool) -> Self {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+        #[cfg(not(unix))]
+        let signal = None;
+
+        ExitReport {
+            code: status.code(),
+            signal,
+            wall_time,
+            usage: None,
+            killed,
+        }
+    }
+
+    /// Sets the resources used by the child process.
+    pub fn with_usage(mut self, usage: UsageReport) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Waits for the child process to exit and reports it, killing it along with the processes it
+    /// spawned if the timeout expires, see [`kill_tree`].
+    ///
+    /// The wall time is measured from the call. On Unix, the child process is reaped with `wait4` to
+    /// collect its resource usage, after which [`Child::wait`] fails with the `ECHILD` OS error, see
+    /// [`ChildWTExt::wait_timeout_rusage`](super::ChildWTExt::wait_timeout_rusage). Elsewhere, the report
+    /// holds no resource usage.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the child process could not be waited for or killed. Unlike
+    /// [`ChildWT::wait_or_kill`](super::ChildWT::wait_or_kill), the expiry of the timeout is not an error,
+    /// it is reported by [`ExitReport::killed`].
+    pub fn wait_or_kill(child: &mut Child, timeout: Duration) -> io::Result<Self> {
+        let start = Instant::now();
+        let (status, usage, killed) = wait_usage(child, timeout)?;
+        let report = ExitReport::new(status, start.elapsed(), killed);
+        Ok(match usage {
+            Some(usage) => report.with_usage(usage),
+            None => report,
+        })
+    }
+
+    /// Returns the exit code of the child process, or `None` if it was terminated by a signal.
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
+
+    /// Returns the signal which terminated the child process on Unix, or `None` if it exited.
+    pub fn signal(&self) -> Option<i32> {
+        self.signal
+    }
+
+    /// Returns `true` if the child process exited with a zero exit code.
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// Returns the time the child process ran.
+    pub fn wall_time(&self) -> Duration {
+        self.wall_time
+    }
+
+    /// Returns the resources used by the child process, if they were collected.
+    pub fn usage(&self) -> Option<UsageReport> {
+        self.usage
+    }
+
+    /// Returns `true` if the child process was killed because its timeout expired.
+    pub fn killed(&self) -> bool {
+        self.killed
+    }
+}
+
+// Reaps the child with `wait4` to collect its resource usage
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+))]
+fn wait_usage(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<(ExitStatus, Option<UsageReport>, bool)> {
+    use super::{wait_until_deadline, ChildWTExt};
+
+    // Waits without timeout in steps which every backend can represent
+    const BLOCKING_STEP: Duration = Duration::from_secs(60 * 60);
+
+    let ((status, usage), killed) = match child.wait_timeout_rusage(timeout) {
+        Ok(exited) => (exited, false),
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            kill_tree(child)?;
+            let exited =
+                wait_until_deadline(Duration::MAX, |_| child.wait_timeout_rusage(BLOCKING_STEP))?;
+            (exited, true)
+        }
+        Err(e) => return Err(e),
+    };
+    let usage = UsageReport {
+        user_time: usage.user_time(),
+        system_time: usage.system_time(),
+        max_rss: usage.max_rss(),
+    };
+    Ok((status, Some(usage), killed))
+}
+
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+)))]
+fn wait_usage(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<(ExitStatus, Option<UsageReport>, bool)> {
+    use super::ChildWT;
+
+    match child.wait_timeout(timeout) {
+        Ok(status) => Ok((status, None, false)),
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            kill_tree(child)?;
+            Ok((child.wait()?, None, true))
+        }
+        Err(e) => Err(e),
+    }
+}
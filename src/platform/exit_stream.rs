@@ -0,0 +1,215 @@
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+
+use super::WaitSet;
+
+// Waits without timeout in steps which every backend can represent
+const BLOCKING_STEP: Duration = Duration::from_secs(60 * 60);
+
+/// A stream of the exits of a set of child processes, in the order they happen.
+///
+/// Each exit is yielded once with the key the child process was registered with, the child process
+/// removed from the stream and its exit status, until no child process is left. The stream is consumed
+/// with a blocking iterator from [`ExitStream::iter`], which waits on the children together as [`WaitSet`]
+/// does, or with the `tokio` or `async-io` feature as a `futures_core::Stream` from any executor.
+///
+/// As a `Stream`, the children are watched by a thread each, spawned on the first poll, which wakes
+/// the task once the child exits without reaping it, as for [`ChildWT::exit_future`](super::ChildWT::exit_future).
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::process::Command;
+///
+/// use child_wait_timeout::ExitStream;
+///
+/// let mut exits = ExitStream::new();
+/// exits.insert(Command::new("sleep").arg("1").spawn()?);
+/// exits.insert(Command::new("sleep").arg("0").spawn()?);
+///
+/// for exit in &mut exits {
+///     let (key, _child, status) = exit?;
+///     println!("job {} completed with {}", key, status);
+/// }
+/// assert!(exits.is_empty());
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ExitStream {
+    set: WaitSet,
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    watch: watch::Watch,
+}
+
+impl ExitStream {
+    /// Creates an empty stream.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a child process and returns the key identifying it in the stream.
+    pub fn insert(&mut self, child: Child) -> usize {
+        self.set.insert(child)
+    }
+
+    /// Removes the child process with the given key and returns it, its exit is then not yielded.
+    pub fn remove(&mut self, key: usize) -> Option<Child> {
+        self.set.remove(key)
+    }
+
+    /// Returns the number of child processes whose exit has not been yielded yet.
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Returns `true` if no child process is left in the stream.
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Waits for the next exit or until the timeout expires.
+    ///
+    /// # Returns
+    /// - `Some` with the key, the child process removed from the stream and its exit status.
+    /// - `None` if the stream is empty.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before any child process exits.
+    pub fn next_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> io::Result<Option<(usize, Child, ExitStatus)>> {
+        self.set.wait_next(timeout)
+    }
+
+    /// Returns a blocking iterator over the exits, which ends once no child process is left.
+    pub fn iter(&mut self) -> ExitStreamIter<'_> {
+        ExitStreamIter { stream: self }
+    }
+}
+
+impl<'a> IntoIterator for &'a mut ExitStream {
+    type Item = io::Result<(usize, Child, ExitStatus)>;
+    type IntoIter = ExitStreamIter<'a>;
+
+    fn into_iter(self) -> ExitStreamIter<'a> {
+        self.iter()
+    }
+}
+
+/// The blocking iterator over the exits of an [`ExitStream`], returned by [`ExitStream::iter`].
+#[derive(Debug)]
+pub struct ExitStreamIter<'a> {
+    stream: &'a mut ExitStream,
+}
+
+impl Iterator for ExitStreamIter<'_> {
+    type Item = io::Result<(usize, Child, ExitStatus)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stream.set.wait_next(BLOCKING_STEP) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+                result => return result.transpose(),
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+mod watch {
+    use std::collections::HashSet;
+    use std::io;
+    use std::pin::Pin;
+    use std::process::{Child, ExitStatus};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+
+    use futures_core::Stream;
+
+    use super::super::exit_future::ExitTarget;
+    use super::ExitStream;
+
+    // The children watched by a thread, and the state shared with those threads
+    #[derive(Debug, Default)]
+    pub(super) struct Watch {
+        watched: HashSet<usize>,
+        shared: Arc<Mutex<Shared>>,
+    }
+
+    #[derive(Debug, Default)]
+    struct Shared {
+        // The task to wake once a child exits
+        waker: Option<Waker>,
+        // The errors of the watcher threads, returned by the next poll
+        errors: Vec<io::Error>,
+    }
+
+    impl Stream for ExitStream {
+        type Item = io::Result<(usize, Child, ExitStatus)>;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = self.get_mut();
+
+            // The waker is stored first, so that an exit after the checks below still wakes the task
+            {
+                let mut shared = this.watch.shared.lock().unwrap_or_else(|e| e.into_inner());
+                shared.waker = Some(cx.waker().clone());
+                if let Some(e) = shared.errors.pop() {
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+
+            let keys: Vec<usize> = this.set.keys().collect();
+            if keys.is_empty() {
+                return Poll::Ready(None);
+            }
+            // The children removed or yielded by the iterator in the meantime are forgotten
+            this.watch.watched.retain(|key| keys.contains(key));
+            for key in keys {
+                let child = this.set.get_mut(key).expect("the key is in the set");
+                match child.try_wait() {
+                    Ok(Some(status)) => {
+                        let child = this.set.remove(key).expect("the key is in the set");
+                        return Poll::Ready(Some(Ok((key, child, status))));
+                    }
+                    Ok(None) => {}
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                }
+
+                if this.watch.watched.insert(key) {
+                    if let Err(e) = spawn_watcher(child, this.watch.shared.clone()) {
+                        this.watch.watched.remove(&key);
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                }
+            }
+            Poll::Pending
+        }
+    }
+
+    // Spawns the thread waking the task once the child has exited
+    fn spawn_watcher(child: &Child, shared: Arc<Mutex<Shared>>) -> io::Result<()> {
+        let wait = child.exited()?;
+        thread::Builder::new()
+            .name("child_wait_timeout".to_string())
+            .spawn(move || {
+                let result = wait();
+
+                let mut shared = shared.lock().unwrap_or_else(|e| e.into_inner());
+                if let Err(e) = result {
+                    shared.errors.push(e);
+                }
+                if let Some(waker) = &shared.waker {
+                    waker.wake_by_ref();
+                }
+            })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+use std::io;
+use std::process::{ExitStatus, Output};
+use std::thread;
+use std::time::Duration;
+
+use super::{Backend, CancelToken, ChildExitFuture, ChildWT, Interrupter, OutputChunk};
+use crate::error::{_generate_timeout_error, WaitError};
+
+/// A fault injected into a wait of a [`FaultyChild`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// The wait fails with `ErrorKind::Interrupted`, as a system call interrupted by a signal. On Unix,
+    /// the error holds the `EINTR` OS error.
+    Interrupted,
+    /// The wait fails with the OS error code, such as `EAGAIN` or `ENOMEM` on Unix.
+    OsError(i32),
+    /// The wait fails with an error of the kind.
+    Error(io::ErrorKind),
+    /// The wait fails with `ErrorKind::TimedOut` at once, as a backend reporting a timeout early.
+    TimedOut,
+    /// The wait is performed, but its result is only returned once the duration has elapsed.
+    Delay(Duration),
+}
+
+/// A wrapper around a child injecting faults into its waits, to test the retries and the error handling
+/// built around [`ChildWT::wait_timeout`].
+///
+/// The faults are queued with [`FaultyChild::inject`] and each wait consumes the next one, in order:
+/// a failing fault is returned without waiting on the wrapped child. Once the queue is empty, the waits are
+/// forwarded to the wrapped child as is. The waits provided by [`ChildWT`] on top of `wait_timeout`, such
+/// as [`ChildWT::wait_timeout_precise`], consume a fault for each of the waits they perform. The futures of
+/// [`ChildWT::exit_future`] are never faulted.
+///
+/// The wrapped child can be any implementation of [`ChildWT`], such as [`std::process::Child`], or a
+/// `MockChild` with the `mock` feature.
+///
+/// This type is only available with the `test-util` feature.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::io::ErrorKind;
+/// use std::process::Command;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::test_util::{Fault, FaultyChild};
+/// use child_wait_timeout::ChildWT;
+///
+/// let child = Command::new("sleep").arg("1").spawn()?;
+/// let mut child = FaultyChild::new(child);
+/// child.inject(Fault::Interrupted);
+///
+/// // The first wait fails, the retry reaches the child
+/// let error = child.wait_timeout(Duration::from_secs(5)).unwrap_err();
+/// assert_eq!(error.kind(), ErrorKind::Interrupted);
+/// assert!(child.wait_timeout(Duration::from_secs(5))?.success());
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FaultyChild<C> {
+    inner: C,
+    faults: VecDeque<Fault>,
+}
+
+impl<C: ChildWT> FaultyChild<C> {
+    /// Wraps a child, without any fault queued.
+    pub fn new(inner: C) -> Self {
+        FaultyChild {
+            inner,
+            faults: VecDeque::new(),
+        }
+    }
+
+    /// Queues a fault, injected into the wait following the ones already queued.
+    pub fn inject(&mut self, fault: Fault) -> &mut Self {
+        self.faults.push_back(fault);
+        self
+    }
+
+    /// Queues a fault for each of the next `count` waits following the ones already queued.
+    pub fn inject_n(&mut self, fault: Fault, count: usize) -> &mut Self {
+        self.faults.extend(std::iter::repeat_n(fault, count));
+        self
+    }
+
+    /// Returns the number of faults which have not been injected yet.
+    pub fn pending_faults(&self) -> usize {
+        self.faults.len()
+    }
+
+    /// Removes the faults which have not been injected yet.
+    pub fn clear_faults(&mut self) {
+        self.faults.clear();
+    }
+
+    /// Returns a reference to the wrapped child.
+    pub fn as_inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped child, whose waits are never faulted.
+    pub fn as_inner_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Consumes the wrapper and returns the wrapped child.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+// Runs a wait on the wrapped child, unless the fault replaces it
+fn faulted<T>(fault: Option<Fault>, wait: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    match fault {
+        None => wait(),
+        Some(Fault::Delay(delay)) => {
+            let result = wait();
+            thread::sleep(delay);
+            result
+        }
+        Some(fault) => fault.into_error(),
+    }
+}
+
+impl Fault {
+    // Returns the error of a failing fault
+    fn into_error<T>(self) -> io::Result<T> {
+        let error = match self {
+            #[cfg(unix)]
+            Fault::Interrupted => io::Error::from_raw_os_error(libc::EINTR),
+            #[cfg(not(unix))]
+            Fault::Interrupted => return Err(WaitError::Interrupted.into()),
+            Fault::OsError(code) => io::Error::from_raw_os_error(code),
+            Fault::Error(kind) => io::Error::new(kind, "injected fault"),
+            Fault::TimedOut => return _generate_timeout_error(),
+            Fault::Delay(_) => unreachable!("a delay does not fail the wait"),
+        };
+        Err(WaitError::Backend(error).into())
+    }
+}
+
+/// The faults are injected into every wait but [`ChildWT::exit_future`].
+///
+/// This implementation is only available with the `test-util` feature.
+impl<C: ChildWT + Send> ChildWT for FaultyChild<C> {
+    fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        faulted(self.faults.pop_front(), || self.inner.wait_timeout(timeout))
+    }
+
+    fn wait_timeout_with_backend(
+        &mut self,
+        backend: Backend,
+        timeout: Duration,
+    ) -> io::Result<ExitStatus> {
+        faulted(self.faults.pop_front(), || {
+            self.inner.wait_timeout_with_backend(backend, timeout)
+        })
+    }
+
+    fn wait_or_kill(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        faulted(self.faults.pop_front(), || self.inner.wait_or_kill(timeout))
+    }
+
+    fn shutdown(&mut self, timeout: Duration, grace: Duration) -> io::Result<ExitStatus> {
+        faulted(self.faults.pop_front(), || {
+            self.inner.shutdown(timeout, grace)
+        })
+    }
+
+    fn exit_future(&mut self) -> ChildExitFuture<'_> {
+        self.inner.exit_future()
+    }
+
+    fn wait_timeout_cancellable(
+        &mut self,
+        timeout: Duration,
+        token: &CancelToken,
+    ) -> io::Result<ExitStatus> {
+        faulted(self.faults.pop_front(), || {
+            self.inner.wait_timeout_cancellable(timeout, token)
+        })
+    }
+
+    fn wait_timeout_interruptible(
+        &mut self,
+        timeout: Duration,
+        interrupter: &Interrupter,
+    ) -> io::Result<ExitStatus> {
+        faulted(self.faults.pop_front(), || {
+            self.inner.wait_timeout_interruptible(timeout, interrupter)
+        })
+    }
+
+    fn wait_cpu_timeout(&mut self, limit: Duration) -> io::Result<ExitStatus> {
+        faulted(self.faults.pop_front(), || {
+            self.inner.wait_cpu_timeout(limit)
+        })
+    }
+
+    // A failing fault drops the wrapped child without waiting on it
+    fn wait_with_output_timeout(mut self, timeout: Duration) -> io::Result<Output> {
+        let inner = self.inner;
+        faulted(self.faults.pop_front(), || {
+            inner.wait_with_output_timeout(timeout)
+        })
+    }
+
+    fn stream_output_timeout<F>(
+        &mut self,
+        timeout: Duration,
+        on_output: F,
+    ) -> io::Result<ExitStatus>
+    where
+        F: FnMut(OutputChunk<'_>),
+    {
+        faulted(self.faults.pop_front(), || {
+            self.inner.stream_output_timeout(timeout, on_output)
+        })
+    }
+}
@@ -0,0 +1,157 @@
+// Fuchsia processes are Zircon tasks, their handle is asserted `ZX_TASK_TERMINATED` once they exit.
+// The standard library returns the raw handle of the process as the id of the child.
+
+#![allow(non_camel_case_types)]
+
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+
+use super::Backend;
+use crate::error::{_generate_timeout_error, _generate_unsupported_error};
+
+pub(crate) type zx_handle_t = u32;
+type zx_status_t = i32;
+type zx_signals_t = u32;
+type zx_time_t = i64;
+type zx_duration_t = i64;
+type zx_rights_t = u32;
+
+#[repr(C)]
+struct zx_wait_item_t {
+    handle: zx_handle_t,
+    waitfor: zx_signals_t,
+    pending: zx_signals_t,
+}
+
+const ZX_OK: zx_status_t = 0;
+const ZX_ERR_TIMED_OUT: zx_status_t = -21;
+const ZX_TASK_TERMINATED: zx_signals_t = 1 << 3;
+const ZX_RIGHT_SAME_RIGHTS: zx_rights_t = 1 << 31;
+const ZX_WAIT_MANY_MAX_ITEMS: usize = 64;
+
+#[link(name = "zircon")]
+extern "C" {
+    fn zx_deadline_after(nanoseconds: zx_duration_t) -> zx_time_t;
+    fn zx_object_wait_one(
+        handle: zx_handle_t,
+        signals: zx_signals_t,
+        deadline: zx_time_t,
+        observed: *mut zx_signals_t,
+    ) -> zx_status_t;
+    fn zx_object_wait_many(
+        items: *mut zx_wait_item_t,
+        count: usize,
+        deadline: zx_time_t,
+    ) -> zx_status_t;
+    fn zx_handle_duplicate(
+        handle: zx_handle_t,
+        rights: zx_rights_t,
+        out: *mut zx_handle_t,
+    ) -> zx_status_t;
+    fn zx_handle_close(handle: zx_handle_t) -> zx_status_t;
+}
+
+// A duplicate of a process handle, closed when dropped
+pub(crate) struct Handle(zx_handle_t);
+
+impl Handle {
+    pub(crate) fn duplicate(handle: zx_handle_t) -> io::Result<Self> {
+        let mut out = 0;
+        let status = unsafe { zx_handle_duplicate(handle, ZX_RIGHT_SAME_RIGHTS, &mut out) };
+        if status != ZX_OK {
+            return status_error(status);
+        }
+        Ok(Handle(out))
+    }
+
+    pub(crate) fn raw(&self) -> zx_handle_t {
+        self.0
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe { zx_handle_close(self.0) };
+    }
+}
+
+pub(crate) fn _active_backend() -> Backend {
+    Backend::FuchsiaWaitOne
+}
+
+pub(crate) fn _wait_timeout_untraced(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    _wait_handle_untraced(child.id(), timeout).map(|()| None)
+}
+
+// Waits for the process to exit, the handle must have the `ZX_RIGHT_WAIT` right
+pub(crate) fn _wait_handle_untraced(handle: zx_handle_t, timeout: Duration) -> io::Result<()> {
+    let mut observed = 0;
+    let status =
+        unsafe { zx_object_wait_one(handle, ZX_TASK_TERMINATED, deadline(timeout), &mut observed) };
+
+    match status {
+        ZX_OK => Ok(()),
+        ZX_ERR_TIMED_OUT => _generate_timeout_error(),
+        status => status_error(status),
+    }
+}
+
+pub(crate) fn _wait_any_untraced(children: &[Child], timeout: Duration) -> io::Result<usize> {
+    if children.len() > ZX_WAIT_MANY_MAX_ITEMS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too many children to wait on",
+        ));
+    }
+
+    let mut items: Vec<zx_wait_item_t> = children
+        .iter()
+        .map(|child| zx_wait_item_t {
+            handle: child.id(),
+            waitfor: ZX_TASK_TERMINATED,
+            pending: 0,
+        })
+        .collect();
+
+    let status = unsafe { zx_object_wait_many(items.as_mut_ptr(), items.len(), deadline(timeout)) };
+    match status {
+        ZX_OK => {}
+        ZX_ERR_TIMED_OUT => return _generate_timeout_error(),
+        status => return status_error(status),
+    }
+
+    match items
+        .iter()
+        .position(|item| item.pending & ZX_TASK_TERMINATED != 0)
+    {
+        Some(index) => Ok(index),
+        None => _generate_timeout_error(),
+    }
+}
+
+// There is no way to ask a Fuchsia process to terminate, it is killed right away
+pub(crate) fn terminate(_pid: u32) -> io::Result<()> {
+    _generate_unsupported_error()
+}
+
+// The processes spawned by the child are in the job of the current process, only the child is killed
+pub(crate) fn kill_tree_with(_pid: u32, kill: impl FnOnce() -> io::Result<()>) -> io::Result<()> {
+    kill()
+}
+
+// Returns the monotonic deadline after the timeout, saturating at `ZX_TIME_INFINITE`
+fn deadline(timeout: Duration) -> zx_time_t {
+    let nanoseconds = timeout.as_nanos().min(zx_duration_t::MAX as u128) as zx_duration_t;
+    unsafe { zx_deadline_after(nanoseconds) }
+}
+
+fn status_error<T>(status: zx_status_t) -> io::Result<T> {
+    Err(io::Error::other(format!(
+        "zircon call failed with status {}",
+        status
+    )))
+}
@@ -0,0 +1,204 @@
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+use super::{deadline_after, kill_tree, remaining_until, sampling, trace, ChildWT};
+
+/// The limits enforced by [`run_with_limits`], none by default.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::Limits;
+///
+/// let limits = Limits::new()
+///     .wall_time(Duration::from_secs(10))
+///     .cpu_time(Duration::from_secs(2))
+///     .memory(256 << 20);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    wall_time: Option<Duration>,
+    cpu_time: Option<Duration>,
+    memory: Option<u64>,
+}
+
+impl Limits {
+    /// Creates limits which do not limit anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum time the child process may run, measured with the monotonic clock.
+    pub fn wall_time(mut self, limit: Duration) -> Self {
+        self.wall_time = Some(limit);
+        self
+    }
+
+    /// Sets the maximum user and system CPU time the child process may consume, as with
+    /// [`ChildWT::wait_cpu_timeout`].
+    pub fn cpu_time(mut self, limit: Duration) -> Self {
+        self.cpu_time = Some(limit);
+        self
+    }
+
+    /// Sets the maximum resident memory of the child process in bytes, as with
+    /// [`WaitOptions::memory_limit`](super::WaitOptions::memory_limit).
+    pub fn memory(mut self, bytes: u64) -> Self {
+        self.memory = Some(bytes);
+        self
+    }
+}
+
+/// A limit of [`Limits`], reported by [`LimitReport::tripped`] once the child process exceeded it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// The child process ran longer than [`Limits::wall_time`].
+    WallTime,
+    /// The child process consumed more CPU time than [`Limits::cpu_time`].
+    CpuTime,
+    /// The resident memory of the child process exceeded [`Limits::memory`].
+    Memory,
+}
+
+/// The outcome of a run with [`run_with_limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitReport {
+    status: ExitStatus,
+    tripped: Option<Limit>,
+    elapsed: Duration,
+}
+
+impl LimitReport {
+    /// Returns the exit status of the child process, the one of its kill if it exceeded a limit.
+    pub fn status(&self) -> ExitStatus {
+        self.status
+    }
+
+    /// Returns the limit the child process exceeded, or `None` if it exited within all of them.
+    pub fn tripped(&self) -> Option<Limit> {
+        self.tripped
+    }
+
+    /// Returns the time the child process ran, from its spawn until it was reaped.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// Spawns the command and runs it within the wall-clock, CPU time and memory limits.
+///
+/// The CPU time and the resident memory of the child process are sampled while it is waited for, as
+/// with [`ChildWT::wait_cpu_timeout`] and [`WaitOptions::memory_limit`](super::WaitOptions::memory_limit).
+/// Once a limit is exceeded, the child process is killed along with the processes it spawned, see
+/// [`kill_tree`], and reaped, and the limit is reported instead of an error.
+///
+/// # Errors
+///
+/// This function fails if the command could not be spawned, see [`Command::spawn`], or if the child
+/// process could not be waited for or killed. It fails with `ErrorKind::Unsupported` when a CPU time or
+/// memory limit is set on a system where they cannot be sampled, only Linux, Android and Windows are
+/// supported. The child process has been killed and reaped whenever an error is returned after its spawn.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{run_with_limits, Limit, Limits};
+///
+/// let mut command = if cfg!(target_os = "windows") {
+///     let mut command = Command::new("ping");
+///     command.args(["-n", "10", "127.0.0.1"]);
+///     command
+/// } else {
+///     let mut command = Command::new("sleep");
+///     command.arg("10");
+///     command
+/// };
+/// let report = run_with_limits(&mut command, &Limits::new().wall_time(Duration::from_millis(100)))?;
+/// assert_eq!(report.tripped(), Some(Limit::WallTime));
+/// assert!(!report.status().success());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn run_with_limits(command: &mut Command, limits: &Limits) -> io::Result<LimitReport> {
+    let start = Instant::now();
+    let mut child = command.spawn()?;
+    trace::spawned(child.id(), command);
+
+    let tripped = match enforce(&mut child, limits) {
+        Ok(Ok(status)) => {
+            return Ok(LimitReport {
+                status,
+                tripped: None,
+                elapsed: start.elapsed(),
+            })
+        }
+        Ok(Err(limit)) => Some(limit),
+        Err(e) => {
+            let _ = kill_tree(&mut child);
+            let _ = child.wait();
+            return Err(e);
+        }
+    };
+
+    kill_tree(&mut child)?;
+    Ok(LimitReport {
+        status: child.wait()?,
+        tripped,
+        elapsed: start.elapsed(),
+    })
+}
+
+// Waits for the child in steps, sampling its resources in between, until it exits or exceeds a limit
+fn enforce(child: &mut Child, limits: &Limits) -> io::Result<Result<ExitStatus, Limit>> {
+    let pid = child.id();
+    let deadline = limits.wall_time.and_then(deadline_after);
+
+    loop {
+        let mut step = remaining_until(deadline);
+
+        if let Some(limit) = limits.cpu_time {
+            let used = match sampling::cpu_time(pid) {
+                Ok(used) => used,
+                // The child may have exited in the meantime, then its status is returned
+                Err(e) => return child.wait_timeout(Duration::ZERO).map(Ok).map_err(|_| e),
+            };
+            match limit.checked_sub(used).filter(|left| !left.is_zero()) {
+                // A single thread cannot consume the remaining CPU time in less wall-clock time
+                Some(left) => {
+                    step = step.min(left.clamp(sampling::MIN_INTERVAL, sampling::MAX_INTERVAL))
+                }
+                None => return Ok(Err(Limit::CpuTime)),
+            }
+        }
+
+        if let Some(limit) = limits.memory {
+            match sampling::resident_memory(pid) {
+                Ok(resident) if resident > limit => return Ok(Err(Limit::Memory)),
+                Ok(_) => step = step.min(sampling::MEMORY_SAMPLE_INTERVAL),
+                Err(e) => return child.wait_timeout(Duration::ZERO).map(Ok).map_err(|_| e),
+            }
+        }
+
+        let result = if step == Duration::MAX {
+            child.wait()
+        } else {
+            child.wait_timeout(step)
+        };
+        match result {
+            Ok(status) => return Ok(Ok(status)),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                if remaining_until(deadline).is_zero() {
+                    return Ok(Err(Limit::WallTime));
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
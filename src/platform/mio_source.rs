@@ -0,0 +1,359 @@
+use std::io;
+use std::process::{Child, ExitStatus};
+
+use mio::event::Source;
+use mio::{Interest, Registry, Token};
+
+/// A [`mio`] event source becoming readable when a child process exits.
+///
+/// The source is registered in a `mio::Poll` with [`Interest::READABLE`]. Once the event is reported,
+/// [`ChildExitSource::try_reap`] returns the exit status of the child without blocking.
+///
+/// The source is backed by a pidfd on Linux and by a `kqueue` with an `EVFILT_PROC` filter on macOS and
+/// the BSDs, both registered as file descriptors. On Windows, the process handle is waited on by the
+/// system thread pool, which wakes the poll with a `mio::Waker` on the token of the source.
+///
+/// This type is only available with the `mio` feature.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::process::Command;
+///
+/// use child_wait_timeout::ChildExitSource;
+/// use mio::{Events, Interest, Poll, Token};
+///
+/// let mut child = Command::new("sleep").arg("1").spawn()?;
+/// let mut source = ChildExitSource::new(&child)?;
+///
+/// let mut poll = Poll::new()?;
+/// poll.registry().register(&mut source, Token(0), Interest::READABLE)?;
+///
+/// let mut events = Events::with_capacity(8);
+/// let status = loop {
+///     poll.poll(&mut events, None)?;
+///     if let Some(status) = source.try_reap(&mut child)? {
+///         break status;
+///     }
+/// };
+/// assert!(status.success());
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ChildExitSource {
+    inner: imp::Inner,
+}
+
+impl ChildExitSource {
+    /// Creates an event source for the exit of the child.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ErrorKind::Unsupported` on Linux if `pidfd_open` is not available on this system.
+    pub fn new(child: &Child) -> io::Result<Self> {
+        Ok(ChildExitSource {
+            inner: imp::Inner::new(child)?,
+        })
+    }
+
+    /// Returns the exit status of the child if it has exited, reaping it, or `None` without blocking.
+    ///
+    /// The child must be the one the source was created for.
+    pub fn try_reap(&mut self, child: &mut Child) -> io::Result<Option<ExitStatus>> {
+        self.inner.clear();
+        child.try_wait()
+    }
+}
+
+impl Source for ChildExitSource {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.inner.register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.inner.reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        self.inner.deregister(registry)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod imp {
+    use std::io;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+    use std::process::Child;
+
+    use mio::unix::SourceFd;
+    use mio::{Interest, Registry, Token};
+
+    use crate::error::_generate_unsupported_error;
+    use crate::platform::unix::pidfd_open;
+
+    // The pidfd of the child, readable once it has exited
+    #[derive(Debug)]
+    pub(super) struct Inner {
+        pidfd: OwnedFd,
+    }
+
+    impl Inner {
+        pub(super) fn new(child: &Child) -> io::Result<Self> {
+            match pidfd_open(child.id() as libc::pid_t, 0)? {
+                Some(pidfd) => Ok(Inner {
+                    pidfd: unsafe { OwnedFd::from_raw_fd(pidfd) },
+                }),
+                None => _generate_unsupported_error(),
+            }
+        }
+
+        // The readiness of a pidfd is not consumed
+        pub(super) fn clear(&mut self) {}
+
+        pub(super) fn register(
+            &mut self,
+            registry: &Registry,
+            token: Token,
+            interests: Interest,
+        ) -> io::Result<()> {
+            registry.register(&mut SourceFd(&self.pidfd.as_raw_fd()), token, interests)
+        }
+
+        pub(super) fn reregister(
+            &mut self,
+            registry: &Registry,
+            token: Token,
+            interests: Interest,
+        ) -> io::Result<()> {
+            registry.reregister(&mut SourceFd(&self.pidfd.as_raw_fd()), token, interests)
+        }
+
+        pub(super) fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+            registry.deregister(&mut SourceFd(&self.pidfd.as_raw_fd()))
+        }
+    }
+}
+
+#[cfg(any(
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+))]
+mod imp {
+    use std::io;
+    use std::mem;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+    use std::process::Child;
+    use std::ptr;
+
+    use libc::{kevent, pid_t, ESRCH, EVFILT_PROC, EV_ADD, EV_ONESHOT, NOTE_EXIT};
+    use mio::unix::SourceFd;
+    use mio::{Interest, Registry, Token};
+
+    use crate::error::_generate_os_error;
+    use crate::platform::unix::has_exited;
+
+    // A kqueue watching the exit of the child, readable once the event is pending
+    #[derive(Debug)]
+    pub(super) struct Inner {
+        kq: OwnedFd,
+    }
+
+    impl Inner {
+        pub(super) fn new(child: &Child) -> io::Result<Self> {
+            let pid = child.id() as pid_t;
+
+            let kq = unsafe { libc::kqueue() };
+            if kq == -1 {
+                return _generate_os_error();
+            }
+            let kq = unsafe { OwnedFd::from_raw_fd(kq) };
+
+            let mut change: libc::kevent = unsafe { mem::zeroed() };
+            change.ident = pid as _;
+            change.filter = EVFILT_PROC;
+            change.flags = EV_ADD | EV_ONESHOT;
+            change.fflags = NOTE_EXIT;
+
+            let result =
+                unsafe { kevent(kq.as_raw_fd(), &change, 1, ptr::null_mut(), 0, ptr::null()) };
+            if result == -1 {
+                let error = io::Error::last_os_error();
+                // The child may have already exited before the registration, a timer expiring
+                // immediately is registered instead so that the kqueue is readable right away
+                if error.raw_os_error() != Some(ESRCH) || !has_exited(pid) {
+                    return Err(error);
+                }
+                trigger(&kq)?;
+            }
+            Ok(Inner { kq })
+        }
+
+        // Retrieves the pending event so that the kqueue is no longer readable
+        pub(super) fn clear(&mut self) {
+            let mut event: libc::kevent = unsafe { mem::zeroed() };
+            let timeout: libc::timespec = unsafe { mem::zeroed() };
+            unsafe { kevent(self.kq.as_raw_fd(), ptr::null(), 0, &mut event, 1, &timeout) };
+        }
+
+        pub(super) fn register(
+            &mut self,
+            registry: &Registry,
+            token: Token,
+            interests: Interest,
+        ) -> io::Result<()> {
+            registry.register(&mut SourceFd(&self.kq.as_raw_fd()), token, interests)
+        }
+
+        pub(super) fn reregister(
+            &mut self,
+            registry: &Registry,
+            token: Token,
+            interests: Interest,
+        ) -> io::Result<()> {
+            registry.reregister(&mut SourceFd(&self.kq.as_raw_fd()), token, interests)
+        }
+
+        pub(super) fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+            registry.deregister(&mut SourceFd(&self.kq.as_raw_fd()))
+        }
+    }
+
+    // Makes the kqueue readable with a timer expiring immediately
+    fn trigger(kq: &OwnedFd) -> io::Result<()> {
+        let mut change: libc::kevent = unsafe { mem::zeroed() };
+        change.filter = libc::EVFILT_TIMER;
+        change.flags = EV_ADD | EV_ONESHOT;
+        let result = unsafe { kevent(kq.as_raw_fd(), &change, 1, ptr::null_mut(), 0, ptr::null()) };
+        if result == -1 {
+            return _generate_os_error();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+    use std::os::windows::io::{AsHandle, AsRawHandle, OwnedHandle};
+    use std::process::Child;
+    use std::ptr;
+
+    use mio::{Interest, Registry, Token, Waker};
+
+    use crate::error::_generate_os_error;
+    use crate::platform::win32::{
+        RegisterWaitForSingleObject, UnregisterWaitEx, BOOLEAN, HANDLE, INFINITE,
+        INVALID_HANDLE_VALUE, PVOID, WT_EXECUTEONLYONCE,
+    };
+
+    // A duplicate of the process handle, waited on by the thread pool while registered
+    #[derive(Debug)]
+    pub(super) struct Inner {
+        process: OwnedHandle,
+        // The registered wait and the waker it wakes
+        wait: Option<(usize, *mut Waker)>,
+    }
+
+    // The raw pointer is only dereferenced by the callback and released after its wait is unregistered
+    unsafe impl Send for Inner {}
+    unsafe impl Sync for Inner {}
+
+    impl Inner {
+        pub(super) fn new(child: &Child) -> io::Result<Self> {
+            Ok(Inner {
+                process: child.as_handle().try_clone_to_owned()?,
+                wait: None,
+            })
+        }
+
+        // The readiness is reported once by the waker
+        pub(super) fn clear(&mut self) {}
+
+        pub(super) fn register(
+            &mut self,
+            registry: &Registry,
+            token: Token,
+            _interests: Interest,
+        ) -> io::Result<()> {
+            if self.wait.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "source already registered",
+                ));
+            }
+
+            let waker = Box::into_raw(Box::new(Waker::new(registry, token)?));
+            let mut wait: HANDLE = ptr::null_mut();
+            let registered = unsafe {
+                RegisterWaitForSingleObject(
+                    &mut wait,
+                    self.process.as_raw_handle() as HANDLE,
+                    Some(wake),
+                    waker as PVOID,
+                    INFINITE,
+                    WT_EXECUTEONLYONCE,
+                )
+            };
+            if registered == 0 {
+                let error = _generate_os_error();
+                drop(unsafe { Box::from_raw(waker) });
+                return error;
+            }
+            self.wait = Some((wait as usize, waker));
+            Ok(())
+        }
+
+        pub(super) fn reregister(
+            &mut self,
+            registry: &Registry,
+            token: Token,
+            interests: Interest,
+        ) -> io::Result<()> {
+            self.deregister(registry)?;
+            self.register(registry, token, interests)
+        }
+
+        pub(super) fn deregister(&mut self, _registry: &Registry) -> io::Result<()> {
+            if let Some((wait, waker)) = self.wait.take() {
+                unregister(wait, waker);
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Inner {
+        fn drop(&mut self) {
+            if let Some((wait, waker)) = self.wait.take() {
+                unregister(wait, waker);
+            }
+        }
+    }
+
+    // Unregisters the wait, waiting for a running callback to return, then releases the waker
+    fn unregister(wait: usize, waker: *mut Waker) {
+        unsafe { UnregisterWaitEx(wait as HANDLE, INVALID_HANDLE_VALUE) };
+        drop(unsafe { Box::from_raw(waker) });
+    }
+
+    // Called by the thread pool once the process has exited
+    unsafe extern "system" fn wake(waker: PVOID, _timed_out: BOOLEAN) {
+        let waker = unsafe { &*(waker as *const Waker) };
+        let _ = waker.wake();
+    }
+}
@@ -0,0 +1,370 @@
+use std::io;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::exit_future::{BlockingWait, ExitTarget};
+use super::{CancelToken, ChildExitFuture, ChildWT, Interrupter, WaitTiming};
+use crate::error::{
+    _generate_interrupted_error, _generate_never_exits_error, _generate_timeout_error,
+};
+
+// The identifiers of the mock children, which are not the ones of any process
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// A virtual clock driving the [`MockChild`] created from it.
+///
+/// The clock starts at zero and only moves forward, either when [`MockClock::advance`] is called or when a
+/// wait on a mock child jumps to the exit of the child or to the expiry of its timeout. No wait ever sleeps.
+///
+/// The clock is a shared handle: its clones read and move the same time.
+///
+/// This type is only available with the `mock` feature.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl MockClock {
+    /// Creates a clock starting at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the virtual time elapsed since the creation of the clock.
+    pub fn now(&self) -> Duration {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Moves the clock forward by the duration, so that the children whose exit is due have exited.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now = now.saturating_add(duration);
+    }
+
+    // Moves the clock forward to the time, unless it is already past it
+    fn advance_to(&self, time: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now = (*now).max(time);
+    }
+}
+
+/// A fake child process whose exit is driven by a [`MockClock`], to test the code waiting on children
+/// deterministically, without spawning processes nor sleeping.
+///
+/// A mock child runs until its scheduled exit, see [`MockChild::exit_after`], or until it is killed. Its
+/// implementation of [`ChildWT`] stands for the backends: a wait returns at once, moving the clock to the
+/// exit of the child if it is due before the timeout expires, and to the expiry of the timeout otherwise.
+/// A wait without timeout on a child which has no scheduled exit fails instead of blocking forever.
+///
+/// A child killed after a timeout exits with `SIGKILL` on Unix and with the exit code 1 on Windows, as with
+/// [`Child::kill`](std::process::Child::kill). A child asked to terminate by [`ChildWT::shutdown`] exits
+/// with `SIGTERM` on Unix and with the exit code 1 on Windows, unless [`MockChild::ignore_terminate`] is set.
+///
+/// The waits measuring the CPU time of the child, [`ChildWT::wait_cpu_timeout`], fail with
+/// `ErrorKind::Unsupported`. The output of a mock child is always empty.
+///
+/// This type is only available with the `mock` feature.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::{ChildWT, MockChild, MockClock};
+///
+/// let clock = MockClock::new();
+/// let mut child = MockChild::new(&clock).exit_after(Duration::from_secs(60), 0);
+///
+/// // The timeout expires in virtual time, without sleeping
+/// let error = child.wait_timeout(Duration::from_secs(10)).unwrap_err();
+/// assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+/// assert_eq!(clock.now(), Duration::from_secs(10));
+///
+/// let status = child.wait_timeout(Duration::from_secs(3600))?;
+/// assert!(status.success());
+/// assert_eq!(clock.now(), Duration::from_secs(60));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MockChild {
+    clock: MockClock,
+    id: u32,
+    // The virtual time and status of the scheduled exit
+    exit: Option<(Duration, ExitStatus)>,
+    ignore_terminate: bool,
+    reaped: Option<ExitStatus>,
+}
+
+impl MockChild {
+    /// Creates a mock child driven by the clock, running until it is killed.
+    pub fn new(clock: &MockClock) -> Self {
+        MockChild {
+            clock: clock.clone(),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            exit: None,
+            ignore_terminate: false,
+            reaped: None,
+        }
+    }
+
+    /// Schedules the exit of the child with the exit code once the delay has elapsed on the clock, counted
+    /// from now.
+    ///
+    /// On Unix, only the lowest 8 bits of the exit code are kept, as by `exit`.
+    pub fn exit_after(self, delay: Duration, code: i32) -> Self {
+        self.exit_with_status_after(delay, exit_status(code))
+    }
+
+    /// Schedules the exit of the child with the exit status once the delay has elapsed on the clock, counted
+    /// from now.
+    pub fn exit_with_status_after(mut self, delay: Duration, status: ExitStatus) -> Self {
+        self.exit = Some((self.clock.now().saturating_add(delay), status));
+        self
+    }
+
+    /// Makes the child ignore the requests to terminate of [`ChildWT::shutdown`], so that it is killed once
+    /// the grace period expires.
+    pub fn ignore_terminate(mut self) -> Self {
+        self.ignore_terminate = true;
+        self
+    }
+
+    /// Makes the child exit now with the exit code, unless it has already exited.
+    pub fn exit(&mut self, code: i32) {
+        self.exit_now(exit_status(code));
+    }
+
+    /// Returns the identifier of the mock child, unique among the mock children and unrelated to the
+    /// identifiers of the processes.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the clock driving the child.
+    pub fn clock(&self) -> &MockClock {
+        &self.clock
+    }
+
+    /// Returns the exit status if the child has exited by the current time of the clock, without moving it.
+    ///
+    /// It never fails, the `Result` is returned for the symmetry with [`Child::try_wait`](std::process::Child::try_wait).
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if self.reaped.is_none() {
+            self.reaped = match self.exit {
+                Some((time, status)) if time <= self.clock.now() => Some(status),
+                _ => None,
+            };
+        }
+        Ok(self.reaped)
+    }
+
+    /// Waits for the child to exit, moving the clock to its exit.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the child has no scheduled exit, since it would never return.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.wait_timeout(Duration::MAX)
+    }
+
+    /// Kills the child, which exits now unless it has already exited.
+    ///
+    /// It never fails, the `Result` is returned for the symmetry with [`Child::kill`](std::process::Child::kill).
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.exit_now(killed_status());
+        Ok(())
+    }
+
+    fn exit_now(&mut self, status: ExitStatus) {
+        if self.try_wait().ok().flatten().is_none() {
+            self.exit = Some((self.clock.now(), status));
+        }
+    }
+
+    // Stops the child after its timeout expired, asking it to terminate first during the grace period
+    fn stop(&mut self, grace: Option<Duration>) {
+        if let Some(grace) = grace {
+            if !self.ignore_terminate {
+                self.exit_now(terminated_status());
+            }
+            if self.wait_timeout(grace).is_ok() {
+                return;
+            }
+        }
+        self.exit_now(killed_status());
+        self.reaped = self.exit.map(|(_, status)| status);
+    }
+}
+
+impl ExitTarget for MockChild {
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        MockChild::try_wait(self)
+    }
+
+    fn exited(&self) -> io::Result<BlockingWait> {
+        let Some((time, _)) = self.exit else {
+            return _generate_never_exits_error();
+        };
+        let clock = self.clock.clone();
+        Ok(Box::new(move || {
+            clock.advance_to(time);
+            Ok(())
+        }))
+    }
+
+    // The mock children have no identifier of their own
+    fn watch_key(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// The waits return at once, moving the [`MockClock`] of the child to its exit or to the expiry of the
+/// timeout. The backend given to [`ChildWT::wait_timeout_with_backend`] is ignored.
+///
+/// This implementation is only available with the `mock` feature.
+impl ChildWT for MockChild {
+    fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        if let Some(status) = self.try_wait()? {
+            return Ok(status);
+        }
+
+        let deadline = self.clock.now().saturating_add(timeout);
+        match self.exit {
+            Some((time, status)) if time <= deadline => {
+                self.clock.advance_to(time);
+                self.reaped = Some(status);
+                Ok(status)
+            }
+            _ if timeout == Duration::MAX => _generate_never_exits_error(),
+            _ => {
+                self.clock.advance_to(deadline);
+                _generate_timeout_error()
+            }
+        }
+    }
+
+    fn wait_or_kill(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        match self.wait_timeout(timeout) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                self.stop(None);
+                Err(e)
+            }
+            result => result,
+        }
+    }
+
+    fn shutdown(&mut self, timeout: Duration, grace: Duration) -> io::Result<ExitStatus> {
+        match self.wait_timeout(timeout) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                self.stop(Some(grace));
+                Err(e)
+            }
+            result => result,
+        }
+    }
+
+    // The virtual waits never return before their timeout expires
+    fn wait_timeout_precise(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        self.wait_timeout(timeout)
+    }
+
+    fn wait_timeout_with_tick<F>(
+        &mut self,
+        timeout: Duration,
+        tick: Duration,
+        mut on_tick: F,
+    ) -> io::Result<ExitStatus>
+    where
+        F: FnMut(Duration),
+    {
+        let start = self.clock.now();
+        let deadline = start.saturating_add(timeout);
+        loop {
+            let remaining = deadline.saturating_sub(self.clock.now());
+            match self.wait_timeout(remaining.min(tick)) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut && self.clock.now() < deadline => {
+                    on_tick(self.clock.now() - start)
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn wait_timeout_timed(&mut self, timeout: Duration) -> io::Result<WaitTiming> {
+        let start = self.clock.now();
+        let status = match self.wait_timeout(timeout) {
+            Ok(status) => Some(status),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => None,
+            Err(e) => return Err(e),
+        };
+        Ok(WaitTiming::new(status, self.clock.now() - start, timeout))
+    }
+
+    fn exit_future(&mut self) -> ChildExitFuture<'_> {
+        ChildExitFuture::new(self)
+    }
+
+    // A virtual wait is never in progress when the token is cancelled, so it is only checked beforehand
+    fn wait_timeout_cancellable(
+        &mut self,
+        timeout: Duration,
+        token: &CancelToken,
+    ) -> io::Result<ExitStatus> {
+        if token.is_cancelled() {
+            return _generate_interrupted_error();
+        }
+        self.wait_timeout(timeout)
+    }
+
+    fn wait_timeout_interruptible(
+        &mut self,
+        timeout: Duration,
+        _interrupter: &Interrupter,
+    ) -> io::Result<ExitStatus> {
+        self.wait_timeout(timeout)
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+
+    ExitStatus::from_raw(code as u32)
+}
+
+#[cfg(unix)]
+fn killed_status() -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+
+    ExitStatus::from_raw(libc::SIGKILL)
+}
+
+#[cfg(unix)]
+fn terminated_status() -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+
+    ExitStatus::from_raw(libc::SIGTERM)
+}
+
+// `TerminateProcess` is called with the exit code 1 by `Child::kill`
+#[cfg(windows)]
+fn killed_status() -> ExitStatus {
+    exit_status(1)
+}
+
+#[cfg(windows)]
+fn terminated_status() -> ExitStatus {
+    exit_status(1)
+}
@@ -1,45 +1,439 @@
 use std::{
     io,
-    process::{Child, ExitStatus},
-    time::Duration,
+    process::{Child, ExitStatus, Output},
+    time::{Duration, Instant},
 };
 
+use crate::error::{_generate_timeout_error, _generate_unsupported_error};
+
 #[cfg(windows)]
 #[path = "windows.rs"]
 mod imp;
 
-#[cfg(all(unix, feature = "pidfd"))]
-#[path = "unix_pidfd.rs"]
+#[cfg(windows)]
+mod win32;
+#[cfg(windows)]
+mod windows_job;
+#[cfg(windows)]
+pub use imp::{alertable_waits, raw_exit_code, set_alertable_waits};
+#[cfg(windows)]
+pub use windows_job::Job;
+#[cfg(windows)]
+mod windows_callback;
+#[cfg(windows)]
+pub use windows_callback::ExitCallback;
+
+#[cfg(target_os = "fuchsia")]
+#[path = "fuchsia.rs"]
+mod imp;
+
+#[cfg(all(unix, not(target_os = "fuchsia")))]
+mod unix;
+#[cfg(any(not(unix), target_os = "fuchsia"))]
+use imp::{kill_tree_with, terminate};
+#[cfg(all(unix, not(target_os = "fuchsia")))]
+use unix::{kill_tree_with, terminate};
+
+// The last resort backend, the default on the targets without a native wait primitive, including
+// Redox and Haiku where it does not need the `polling` feature. It can be selected per call on the
+// other ones.
+#[cfg(any(
+    feature = "polling",
+    not(any(unix, windows)),
+    target_os = "redox",
+    target_os = "haiku"
+))]
+mod polling;
+#[cfg(any(not(any(unix, windows)), target_os = "redox", target_os = "haiku"))]
+use polling as imp;
+
+#[cfg(all(not(any(unix, windows)), not(feature = "polling")))]
+compile_error!(
+    "no native wait primitive is implemented for this target, enable the `polling` feature"
+);
+
+#[cfg(any(
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+))]
+#[path = "unix_kqueue.rs"]
 mod imp;
 
+#[cfg(any(target_os = "illumos", target_os = "solaris"))]
+mod unix_port;
+#[cfg(any(target_os = "illumos", target_os = "solaris"))]
+use unix_port as imp;
+
+// The backends of the other Unix systems. Each one is compiled when its feature is enabled, so that
+// it can be selected per call, and `imp` is the one with the highest priority. `thread` is always
+// compiled, `pidfd` falls back to it when `pidfd_open` is not available at runtime. `pidfd` is only
+// available on Linux and Android, and is ignored elsewhere, such as on AIX. On illumos and Solaris,
+// `imp` is always the event ports backend.
+#[cfg(all(any(target_os = "linux", target_os = "android"), feature = "pidfd"))]
+mod unix_pidfd;
+
+#[cfg(all(
+    unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "redox",
+        target_os = "haiku",
+        target_os = "fuchsia"
+    ))
+))]
+mod unix_thread;
+
 #[cfg(all(
     unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "redox",
+        target_os = "haiku",
+        target_os = "fuchsia"
+    )),
+    feature = "signal"
+))]
+mod unix_signal;
+
+#[cfg(all(any(target_os = "linux", target_os = "android"), feature = "pidfd"))]
+use unix_pidfd as imp;
+
+#[cfg(all(target_os = "linux", feature = "uring"))]
+mod unix_uring;
+
+mod backend;
+pub use backend::{active_backend, Backend};
+
+mod trace;
+
+mod sampling;
+
+mod wait_set;
+pub use wait_set::WaitSet;
+
+mod exit_stream;
+pub use exit_stream::{ExitStream, ExitStreamIter};
+
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+mod wait_pid;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use wait_pid::wait_fd_timeout;
+#[cfg(windows)]
+pub use wait_pid::wait_handle_timeout;
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+pub use wait_pid::{wait_pid_timeout, wait_timeout_pid};
+
+mod shared_child;
+pub use shared_child::SharedChild;
+
+mod monitored_child;
+pub use monitored_child::MonitoredChild;
+
+mod timed_child;
+pub use timed_child::TimedChild;
+
+mod timeout_guard;
+pub use timeout_guard::{with_child, TimeoutGuard};
+
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+mod process_group;
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+pub use process_group::ProcessGroup;
+
+#[cfg(all(target_os = "linux", feature = "cgroup"))]
+mod unix_cgroup;
+#[cfg(all(target_os = "linux", feature = "cgroup"))]
+pub use unix_cgroup::Cgroup;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod subreaper;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use subreaper::{is_child_subreaper, set_child_subreaper};
+
+#[cfg(all(
+    feature = "duct",
     any(
-        all(feature = "thread", not(feature = "pidfd")),
+        windows,
         all(
-            not(feature = "signal"),
-            not(feature = "thread"),
-            not(feature = "pidfd")
+            unix,
+            not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
         )
     )
 ))]
-#[path = "unix_thread.rs"]
-mod imp;
+mod duct_handle;
+#[cfg(all(
+    feature = "duct",
+    any(
+        windows,
+        all(
+            unix,
+            not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+        )
+    )
+))]
+pub use duct_handle::HandleWT;
+
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+mod child_handle;
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+pub use child_handle::ChildHandle;
+
+mod command;
+pub use command::{run_with_timeout, CommandWT, OutputChunk, OutputTimeout, RunTimeoutError};
+use command::{stream_output, wait_with_output};
+
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+mod read_timeout;
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    )
+))]
+pub use read_timeout::ReadTimeout;
+
+mod supervisor;
+pub use supervisor::{RestartPolicy, Service, ServiceExit, Supervisor};
+
+mod exit_future;
+pub use exit_future::ChildExitFuture;
+
+mod cancel;
+use cancel::{wait_cancellable, wait_interruptible};
+pub use cancel::{CancelToken, Interrupter};
+
+mod options;
+pub use options::{wait_with, Clock, WaitOptions};
+
+mod limits;
+pub use limits::{run_with_limits, Limit, LimitReport, Limits};
+
+mod exit_report;
+pub use exit_report::{ExitReport, UsageReport};
+
+mod deadline;
+pub use deadline::Deadline;
+
+mod wait_timing;
+pub use wait_timing::WaitTiming;
+
+#[cfg(feature = "reaper")]
+mod reaper;
+#[cfg(feature = "reaper")]
+pub use reaper::{ExitReceiver, Reaper};
+
+#[cfg(feature = "test-util")]
+mod faulty_child;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+#[cfg(all(feature = "mock", any(unix, windows)))]
+mod mock;
+#[cfg(all(feature = "mock", any(unix, windows)))]
+pub use mock::{MockChild, MockClock};
+
+// Re-exported by the crate root, its `wait_timeout` would be ambiguous with `ChildWT` here
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub(crate) mod async_child;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+mod exit_oneshot;
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub use exit_oneshot::ExitOneshot;
+
+#[cfg(feature = "tokio")]
+mod tokio_child;
+
+#[cfg(all(
+    feature = "mio",
+    any(
+        windows,
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd"
+    )
+))]
+mod mio_source;
+#[cfg(all(
+    feature = "mio",
+    any(
+        windows,
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd"
+    )
+))]
+pub use mio_source::ChildExitSource;
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+))]
+mod unix_group;
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+))]
+pub use unix_group::{reap_all_timeout, wait_group_all_timeout, wait_group_any_timeout};
 
 #[cfg(all(
     unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+))]
+mod unix_ext;
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+))]
+pub use unix::{restart_on_interrupt, set_restart_on_interrupt};
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+))]
+pub use unix_ext::{ChildWTExt, ExitInfo, ExitReason, ResourceUsage, WaitState};
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia")),
+    feature = "nix"
+))]
+mod unix_nix;
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia")),
+    feature = "nix"
+))]
+pub use unix_nix::{wait_group_all_timeout_nix, wait_group_any_timeout_nix, wait_pid_timeout_nix};
+
+#[cfg(all(any(target_os = "linux", target_os = "android"), feature = "pidfd"))]
+pub use unix_pidfd::{PidFd, PidFdChild, PidFdEpoll};
+
+#[cfg(all(
+    unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "redox",
+        target_os = "haiku",
+        target_os = "fuchsia"
+    )),
+    not(all(any(target_os = "linux", target_os = "android"), feature = "pidfd")),
+    any(feature = "thread", not(feature = "signal"))
+))]
+use unix_thread as imp;
+
+#[cfg(all(
+    unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "redox",
+        target_os = "haiku",
+        target_os = "fuchsia"
+    )),
     feature = "signal",
     not(feature = "thread"),
-    not(feature = "pidfd")
+    not(all(any(target_os = "linux", target_os = "android"), feature = "pidfd"))
 ))]
-#[path = "unix_signal.rs"]
-mod imp;
+use unix_signal as imp;
+
+#[cfg(all(
+    unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "redox",
+        target_os = "haiku",
+        target_os = "fuchsia"
+    )),
+    feature = "signal"
+))]
+pub use unix_signal::{notification_signal, set_notification_signal};
 
 pub trait ChildWT {
     /// Waits for the child process to exit or until the timeout expires.
     ///
     /// # Parameters
-    /// - `timeout`: The maximum time to wait for the child process to exit. This function handles durations exceeding `u32::MAX` milliseconds.
+    /// - `timeout`: The maximum time to wait for the child process to exit. It is not truncated to milliseconds,
+    ///   see [`ChildWT::wait_timeout_precise`] for the resolution of each backend. A zero timeout behaves like a
+    ///   non-blocking [`Child::try_wait`], returning the exit status if the child process has already exited.
+    ///   The timeout is measured with the monotonic clock of [`Instant`], so changes of the system time neither
+    ///   shorten nor lengthen the wait, see [`Clock`] to measure it with the system clock instead.
     ///
     /// # Returns
     /// - `Result<ExitStatus>`:
@@ -50,7 +444,22 @@ pub trait ChildWT {
     ///
     /// ## Windows
     ///
-    /// This function is implemented using `WaitForSingleObject`.
+    /// This function is implemented using `WaitForSingleObject`. Timeouts which are not a whole number of milliseconds
+    /// use a high-resolution waitable timer waited on with `WaitForMultipleObjects` instead. With `set_alertable_waits`,
+    /// the waits are alertable so that the APCs queued to the thread still run, and are then resumed with the remaining time.
+    /// The Win32 functions are bound through `winapi` with the default `winapi` feature, and declared by the crate itself
+    /// without it, with the same behavior.
+    ///
+    /// ## macOS, other Apple targets and the BSDs
+    ///
+    /// On Apple targets, FreeBSD, DragonFly BSD, OpenBSD and NetBSD, this function is implemented using `kqueue`
+    /// with an `EVFILT_PROC`/`NOTE_EXIT` filter.
+    /// The `pidfd`, `thread` and `signal` features are ignored on these targets.
+    ///
+    /// ## Fuchsia
+    ///
+    /// This function is implemented using `zx_object_wait_one` on the process handle, waiting for the
+    /// `ZX_TASK_TERMINATED` signal. The Unix features are ignored on Fuchsia.
     ///
     /// ## Unix
     ///
@@ -61,7 +470,7 @@ pub trait ChildWT {
     ///
     /// ### Priority Order Rationale
     ///
-    /// - **`pidfd`**: Prioritized because if a crate depends on Linux 5.3 or later, it is safe to always use `pidfd`. It provides lower overhead and efficient handling of child process termination.
+    /// - **`pidfd`**: Prioritized because it is safe to always use `pidfd`, older kernels transparently fall back to `thread`. It provides lower overhead and efficient handling of child process termination.
     /// - **`thread`**: Chosen next due to its moderate overhead and reliability.
     /// - **`signal`**: Selected last due to potential issues with race conditions and higher overhead. It allows `signal` to be forcefully disabled if a crate uses incompatible signal handling.
     ///
@@ -71,16 +480,27 @@ pub trait ChildWT {
     /// ## `pidfd`
     ///
     /// The `pidfd` feature uses `pidfd_open` to wait for the child process.
-    /// This is a relatively new and efficient method available on newer Linux kernels (5.3 and later).
+    /// This is a relatively new and efficient method available on newer Linux kernels (5.4 and later).
+    /// If `pidfd_open` is not available at runtime, because the kernel is too old (`ENOSYS`) or the call is blocked by a
+    /// seccomp policy (`EPERM`), the `thread` implementation is used instead for the rest of the process lifetime.
+    /// The same goes on Linux 5.3, where `waitid` does not accept pidfds (`EINVAL`): a wait already polling the pidfd
+    /// reaps the child with [`Child::wait`] once it becomes readable, then the next waits use `thread`.
     ///
     /// ### Benchmark
     /// See [Benchmark Results](#benchmark-results).
     ///
+    /// ## `uring`
+    ///
+    /// The `uring` feature enables `pidfd` and waits on the pidfd with an io_uring `POLL_ADD` operation linked to a timeout,
+    /// using one ring per waiting thread. If io_uring is not available at runtime, `ppoll` is used instead.
+    /// `PidFd::poll_entry` builds the same operation for applications submitting it into their own ring.
+    ///
     /// ## `thread`
     ///
     /// If no features are specified, the default implementation is `thread`.
-    /// It uses a separate thread that waits for the child process to exit and cancels the wait if the timeout expires.
-    /// It is POSIX compliant.
+    /// It uses a separate thread that waits for the child process to exit while the caller waits on a condition variable with the timeout.
+    /// If the timeout expires, the thread keeps running until the child process exits and is reused by the subsequent calls on the same child.
+    /// It is POSIX compliant and implemented in pure Rust, no C toolchain is required.
     ///
     /// ### Benchmark
     /// See [Benchmark Results](#benchmark-results).
@@ -89,11 +509,88 @@ pub trait ChildWT {
     ///
     /// The `signal` feature uses `SIGCHLD` to detect child process termination.
     /// This method can introduce complexity and potential race conditions in signal handling.
+    /// `SIGCHLD` is only blocked in the calling thread for the duration of the wait, its previous signal mask is then restored.
+    /// A notification delivered to another thread is missed, so the children are checked again every 100 ms while waiting.
+    /// The notifications of the other children received during the wait are consumed and never raised again: the other waits of
+    /// this backend notice the exit of their children by the next check, up to 100 ms later, but the signal handlers of the
+    /// application miss them.
+    /// Another notification signal than `SIGCHLD` can be configured with `set_notification_signal`.
     /// It is POSIX compliant.
     ///
+    /// ## `signalfd`
+    ///
+    /// The `signalfd` feature enables `signal` and, on Linux, consumes `SIGCHLD` from a `signalfd` instead of using `sigtimedwait`,
+    /// which plays better with applications built around an event loop. The `signalfd` is polled with `ppoll` along with a
+    /// `timerfd` armed once with the deadline on the monotonic clock, so that a storm of signals waking the thread never delays the timeout.
+    /// As required by `signalfd`, `SIGCHLD` should be blocked in every thread of the process. Otherwise the notification may be
+    /// delivered to another thread, and the exit of the child is only noticed by the next check, up to 100 ms later.
+    ///
     /// ### Benchmark
     /// See [Benchmark Results](#benchmark-results).
     ///
+    /// ## `tracing`
+    ///
+    /// The `tracing` feature enters a `wait_timeout` span at debug level for each wait, recording the pid of the
+    /// child, the backend and the timeout. Within it, events are emitted when the wait starts, when the child is
+    /// reaped with its exit status, and when the timeout expires. Killing or terminating a child after its timeout
+    /// is reported at info level, and falling back from `pidfd_open` or io_uring at runtime at warn level. Without the
+    /// feature, the instrumentation is compiled out.
+    ///
+    /// ## `log`
+    ///
+    /// The `log` feature emits the same instrumentation as `log` records, for applications not using `tracing`.
+    /// The waits, their outcome and the spawns of commands by `CommandWT`, `with_child` and `Supervisor`, which
+    /// relate the pid of the following records to the program, are logged at debug level. Killing or terminating a
+    /// child after its timeout, and falling back from `pidfd_open` or io_uring when they are unavailable at
+    /// runtime, are logged at warn level. Both features can be enabled together.
+    ///
+    /// ## `metrics`
+    ///
+    /// The `metrics` feature reports the waits through the `metrics` facade, to the recorder installed by the
+    /// application:
+    /// - `child_wait_timeout_waits_total`: counter of the waits started, labelled with the `backend`.
+    /// - `child_wait_timeout_timeouts_total`: counter of the waits whose timeout expired.
+    /// - `child_wait_timeout_kills_total`: counter of the children killed after their timeout.
+    /// - `child_wait_timeout_wait_duration_seconds`: histogram of the duration of the waits, labelled with their
+    ///   `outcome`, one of `exited`, `timed_out` or `failed`.
+    ///
+    /// ## `nix`
+    ///
+    /// The `nix` feature provides variants of the low-level Unix waits taking a `nix::unistd::Pid` and returning
+    /// `nix::sys::wait::WaitStatus`, such as `wait_pid_timeout_nix` and `wait_group_any_timeout_nix`, along with
+    /// the conversions of `ExitInfo` and `WaitState` to `WaitStatus`.
+    ///
+    /// ## `cgroup`
+    ///
+    /// The `cgroup` feature provides `Cgroup` on Linux, which spawns the child into a fresh cgroup v2 and waits for
+    /// `cgroup.events` to report `populated 0` with `poll`, once every descendant has exited, including the ones
+    /// which left the process group or were orphaned. The cgroup hierarchy must be writable by the current process.
+    ///
+    /// ## `serde`
+    ///
+    /// The `serde` feature derives `Serialize` and `Deserialize` for `ExitReport` and `UsageReport`, so that the
+    /// results of bounded runs can be persisted.
+    ///
+    /// ## `test-util`
+    ///
+    /// The `test-util` feature provides the `test_util` module, with portable fake children to test the timeout
+    /// handling built on this crate: sleepers, children exiting immediately with a given code, and sleepers
+    /// ignoring the requests to terminate. Its `FaultyChild` wraps any implementation of `ChildWT` to inject
+    /// interruptions, OS errors, early timeouts or delayed results into its next waits.
+    ///
+    /// ## `mock`
+    ///
+    /// The `mock` feature provides `MockChild`, a fake child implementing `ChildWT` whose exit is scheduled on
+    /// the virtual time of a `MockClock`. The waits return at once and move the clock to the exit of the child
+    /// or to the expiry of their timeout, so that the code generic over `ChildWT` can be tested deterministically,
+    /// without spawning processes nor sleeping. It is available on Unix and Windows.
+    ///
+    /// ## Traced children
+    ///
+    /// On Unix, the ptrace stops of a child traced by the calling process are not mistaken for its exit: the
+    /// wait goes on until the child exits, and they are left pending for the tracer to collect, for instance
+    /// with `ChildWTExt::wait_state_timeout`. While a ptrace stop is pending, the `thread` backend checks the
+    /// child again every 10 milliseconds.
     ///
     /// # Errors
     ///
@@ -161,30 +658,1119 @@ pub trait ChildWT {
     /// The performance overheads seem negligible compared to process creation.
     ///
     fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus>;
-}
 
-impl ChildWT for Child {
-    fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
-        if let Ok(Some(res)) = self.try_wait() {
-            return Ok(res);
-        }
+    /// Waits for the child process to exit or until the timeout expires, with the given backend instead
+    /// of the default one.
+    ///
+    /// This lets a program mix strategies, for instance the `pidfd` backend for most of its children but the
+    /// `thread` backend for a child spawned in a sandbox where `pidfd_open` is blocked by seccomp. Unlike the
+    /// default backend, the given one never falls back to another backend at runtime.
+    ///
+    /// The default implementation ignores the backend and calls [`ChildWT::wait_timeout`], for the children
+    /// which are not waited for by the backends of this crate.
+    ///
+    /// The backends which can be selected are the ones compiled in: [`Backend::Thread`] on the Unix systems
+    /// other than macOS and the BSDs, along with [`Backend::Pidfd`], [`Backend::Uring`] and [`Backend::Signal`]
+    /// or [`Backend::Signalfd`] when their feature is enabled, [`Backend::Kqueue`] on macOS and the BSDs,
+    /// [`Backend::EventPorts`] on illumos and Solaris, and [`Backend::WindowsWaitObject`] on Windows. [`Backend::Polling`] can be selected on every target
+    /// with the `polling` feature.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process exits,
+    /// or with `ErrorKind::Unsupported` if the backend is not compiled in or not available at runtime.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::{Backend, ChildWT};
+    ///
+    /// let mut child = Command::new("sleep").arg("1").spawn()?;
+    /// # #[cfg(unix)]
+    /// let status = child.wait_timeout_with_backend(Backend::Thread, Duration::from_secs(5))?;
+    /// # #[cfg(windows)]
+    /// # let status = child.wait_timeout_with_backend(Backend::WindowsWaitObject, Duration::from_secs(5))?;
+    /// assert!(status.success());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_timeout_with_backend(
+        &mut self,
+        _backend: Backend,
+        timeout: Duration,
+    ) -> io::Result<ExitStatus> {
+        self.wait_timeout(timeout)
+    }
+
+    /// Waits for the child process to exit, then kills and reaps it if the timeout expires.
+    ///
+    /// The child process is never left running once this function returns: if the timeout expires,
+    /// it is killed and waited for before the timeout error is returned.
+    ///
+    /// The default implementation has no way to kill the child, it only calls [`ChildWT::wait_timeout`] and
+    /// leaves the child running when the timeout expires.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with `ErrorKind::TimedOut` if the timeout expired, in which case the child process has been
+    /// killed and reaped, except by the default implementation. It may also fail if the child process could not be
+    /// killed.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// let mut child = Command::new("sleep").arg("10").spawn()?;
+    /// let result = child.wait_or_kill(Duration::from_millis(100));
+    /// assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+    /// // The child has already been reaped
+    /// assert!(child.try_wait()?.is_some());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_or_kill(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        self.wait_timeout(timeout)
+    }
 
-        const U32_MAX: u128 = u32::MAX as u128;
-        let mut timeout_ms = timeout.as_millis();
+    /// Waits for the child process to exit, then asks it to terminate if the timeout expires,
+    /// and kills it if it is still running once the grace period expires.
+    ///
+    /// The child process is never left running once this function returns. Its exit status can be
+    /// retrieved with [`Child::try_wait`] to know whether it terminated gracefully.
+    ///
+    /// # Platform-Specific Behavior
+    ///
+    /// On Unix, the child process is asked to terminate with `SIGTERM`, then killed with `SIGKILL`.
+    ///
+    /// On Windows, `WM_CLOSE` is posted to the top-level windows of the child process, like `taskkill` without `/F`,
+    /// then it is killed with `TerminateProcess`. Console processes without windows are killed once the grace period expires.
+    ///
+    /// The default implementation has no way to terminate the child, it calls [`ChildWT::wait_or_kill`] and ignores
+    /// the grace period.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with `ErrorKind::TimedOut` if the timeout expired, in which case the child process has been
+    /// terminated or killed, and reaped, unless [`ChildWT::wait_or_kill`] left it running. It may also fail if the
+    /// child process could not be killed.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// let mut child = Command::new("sleep").arg("10").spawn()?;
+    /// let result = child.shutdown(Duration::from_millis(100), Duration::from_secs(1));
+    /// assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+    /// println!("terminated with {:?}", child.try_wait()?);
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn shutdown(&mut self, timeout: Duration, _grace: Duration) -> io::Result<ExitStatus> {
+        self.wait_or_kill(timeout)
+    }
 
-        while timeout_ms > U32_MAX {
-            match imp::_wait_timeout_untraced_ms(self, u32::MAX) {
-                // the child.wait will end instantly
-                Ok(()) => return self.wait(),
-                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
-                    // continue looping
-                }
-                Err(e) => return Err(e),
-            };
-            timeout_ms -= U32_MAX;
-        }
-        imp::_wait_timeout_untraced_ms(self, timeout_ms as u32)?;
+    /// Waits for the child process to exit or until the deadline passes.
+    ///
+    /// This is equivalent to [`ChildWT::wait_timeout`] with the time remaining until the deadline,
+    /// which avoids recomputing the timeout when retrying against the same deadline.
+    /// If the deadline has already passed, the child process is only checked once without blocking.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the deadline passes before the child process exits.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::{Duration, Instant};
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// let deadline = Instant::now() + Duration::from_secs(5);
+    /// let mut first = Command::new("sleep").arg("1").spawn()?;
+    /// let mut second = Command::new("sleep").arg("1").spawn()?;
+    ///
+    /// // Both children share the same deadline
+    /// first.wait_deadline(deadline)?;
+    /// second.wait_deadline(deadline)?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_deadline(&mut self, deadline: Instant) -> io::Result<ExitStatus> {
+        self.wait_timeout(deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Waits for the child process to exit, or until the timeout expires if one is given.
+    ///
+    /// With `None`, this waits forever using the native blocking wait, like [`Child::wait`],
+    /// so generic code does not need a separate branch for unbounded waits.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if a timeout is given and expires before the child process exits.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// let mut child = Command::new("sleep").arg("1").spawn()?;
+    /// let timeout: Option<Duration> = None;
+    /// let status = child.wait_timeout_opt(timeout)?;
+    /// assert!(status.success());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_timeout_opt(&mut self, timeout: Option<Duration>) -> io::Result<ExitStatus> {
+        self.wait_timeout(timeout.unwrap_or(Duration::MAX))
+    }
+
+    /// Waits for the child process to exit or until the full timeout has elapsed.
+    ///
+    /// Timer expirations may be rounded by the system, so [`ChildWT::wait_timeout`] can report a timeout
+    /// slightly before the duration has elapsed. This function keeps waiting for the remaining time in that
+    /// case, and never returns `ErrorKind::TimedOut` before the timeout has fully elapsed.
+    ///
+    /// # Resolution
+    ///
+    /// The timeout is passed with nanosecond precision to every backend:
+    /// - On Unix, as a `timespec`. The effective resolution depends on the kernel timers and the timer slack
+    ///   of the thread, typically around 50 microseconds on Linux.
+    /// - On Windows, in units of 100 nanoseconds to a high-resolution waitable timer when available. The effective
+    ///   resolution is otherwise the system timer resolution, typically between 1 and 15.6 milliseconds.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the specified duration expires before the child process exits.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::{Duration, Instant};
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// let mut child = Command::new("sleep").arg("1").spawn()?;
+    /// let start = Instant::now();
+    /// let result = child.wait_timeout_precise(Duration::from_micros(500));
+    /// assert!(result.is_err() && start.elapsed() >= Duration::from_micros(500));
+    /// child.kill()?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_timeout_precise(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        let deadline = deadline_after(timeout);
+        loop {
+            match self.wait_timeout(remaining_until(deadline)) {
+                Err(e)
+                    if e.kind() == io::ErrorKind::TimedOut
+                        && !remaining_until(deadline).is_zero() => {}
+                result => return result,
+            }
+        }
+    }
+
+    /// Waits for the child process to exit or until the timeout expires, without treating
+    /// the timeout as an error.
+    ///
+    /// # Returns
+    /// - `Ok(Some(status))` if the child process exited.
+    /// - `Ok(None)` if the timeout expired before the child process exited.
+    ///
+    /// # Errors
+    ///
+    /// Unlike [`ChildWT::wait_timeout`], `Err` is only returned for failures of the underlying
+    /// system calls.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// let mut child = Command::new("sleep").arg("10").spawn()?;
+    /// match child.try_wait_timeout(Duration::from_millis(100))? {
+    ///     Some(status) => println!("exited with {}", status),
+    ///     None => child.kill()?,
+    /// }
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn try_wait_timeout(&mut self, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+        match self.wait_timeout(timeout) {
+            Ok(status) => Ok(Some(status)),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Waits for the child process to exit or until the timeout expires, calling the closure every `tick` in the meantime.
+    ///
+    /// The wait is split into steps of at most `tick`, and the closure is called with the time elapsed since the
+    /// beginning of the wait after each step ending before the child process exits, for instance to refresh a
+    /// progress indicator, extend a lease or log that the child process is still running. The timeout is shared by
+    /// all the steps, the closure is not called once it has expired. A zero `tick` calls the closure between
+    /// non-blocking checks of the child process.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process exits.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// let mut child = Command::new("sleep").arg("1").spawn()?;
+    /// let mut ticks = 0;
+    /// let status = child.wait_timeout_with_tick(
+    ///     Duration::from_secs(5),
+    ///     Duration::from_millis(100),
+    ///     |elapsed| {
+    ///         ticks += 1;
+    ///         println!("still running after {:?}", elapsed);
+    ///     },
+    /// )?;
+    /// assert!(status.success() && ticks > 0);
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_timeout_with_tick<F>(
+        &mut self,
+        timeout: Duration,
+        tick: Duration,
+        mut on_tick: F,
+    ) -> io::Result<ExitStatus>
+    where
+        F: FnMut(Duration),
+    {
+        let start = Instant::now();
+        let deadline = deadline_after(timeout);
+        loop {
+            match self.wait_timeout(remaining_until(deadline).min(tick)) {
+                Err(e)
+                    if e.kind() == io::ErrorKind::TimedOut
+                        && !remaining_until(deadline).is_zero() =>
+                {
+                    on_tick(start.elapsed())
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Waits for the child process to exit or until the full timeout has elapsed, and reports how long the wait took.
+    ///
+    /// The wait is measured with the monotonic clock around the whole call, so that schedulers can account for
+    /// the time spent without measuring it themselves. As with [`ChildWT::wait_timeout_precise`], the timeout is
+    /// only reported once it has fully elapsed: the elapsed time is then at least the timeout, and no time remains.
+    ///
+    /// # Returns
+    /// - A [`WaitTiming`] holding the exit status, or `None` if the timeout expired, along with the elapsed time.
+    ///
+    /// # Errors
+    ///
+    /// Unlike [`ChildWT::wait_timeout`], the timeout is not an error, `Err` is only returned for failures of the
+    /// underlying system calls.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// let mut child = Command::new("sleep").arg("1").spawn()?;
+    /// let timing = child.wait_timeout_timed(Duration::from_secs(5))?;
+    /// assert!(timing.status().is_some_and(|status| status.success()));
+    /// println!("exited after {:?}, {:?} left", timing.elapsed(), timing.remaining());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_timeout_timed(&mut self, timeout: Duration) -> io::Result<WaitTiming> {
+        let start = Instant::now();
+        let status = match self.wait_timeout_precise(timeout) {
+            Ok(status) => Some(status),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => None,
+            Err(e) => return Err(e),
+        };
+        Ok(WaitTiming::new(status, start.elapsed(), timeout))
+    }
+
+    /// Returns a future resolving to the exit status of the child process.
+    ///
+    /// The future does not depend on any runtime and has no timeout of its own, so that it can be combined
+    /// with the timeout and `select!` combinators of the application. See [`ChildExitFuture`].
+    ///
+    /// The future of the default implementation checks the child with [`ChildWT::try_wait_timeout`] every
+    /// 10 milliseconds.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::future::Future;
+    /// use std::pin::pin;
+    /// use std::process::Command;
+    /// use std::sync::Arc;
+    /// use std::task::{Context, Poll, Wake};
+    /// use std::thread::{self, Thread};
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// // A minimal executor, any runtime can be used instead
+    /// struct ThreadWaker(Thread);
+    /// impl Wake for ThreadWaker {
+    ///     fn wake(self: Arc<Self>) {
+    ///         self.0.unpark();
+    ///     }
+    /// }
+    ///
+    /// let mut child = Command::new("sleep").arg("1").spawn()?;
+    /// let mut future = pin!(child.exit_future());
+    /// let waker = Arc::new(ThreadWaker(thread::current())).into();
+    /// let status = loop {
+    ///     match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+    ///         Poll::Ready(status) => break status?,
+    ///         Poll::Pending => thread::park(),
+    ///     }
+    /// };
+    /// assert!(status.success());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn exit_future(&mut self) -> ChildExitFuture<'_>
+    where
+        Self: Send,
+    {
+        ChildExitFuture::polled(self)
+    }
+
+    /// Waits for the child process to exit, the timeout to expire or the token to be cancelled.
+    ///
+    /// The token can be cancelled from any thread, see [`CancelToken`]. The wait is done by a watcher
+    /// thread, as for [`ChildWT::exit_future`], which keeps running until the child exits if the wait
+    /// is cancelled or times out. The child process is left running in both cases.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child
+    /// process exits, or with `ErrorKind::Interrupted` if the token is cancelled first.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::{CancelToken, ChildWT};
+    ///
+    /// let token = CancelToken::new();
+    /// let mut child = Command::new("sleep").arg("1").spawn()?;
+    /// let status = child.wait_timeout_cancellable(Duration::from_secs(5), &token)?;
+    /// assert!(status.success());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_timeout_cancellable(
+        &mut self,
+        timeout: Duration,
+        token: &CancelToken,
+    ) -> io::Result<ExitStatus>
+    where
+        Self: Send,
+    {
+        wait_cancellable(self.exit_future(), timeout, token)
+    }
+
+    /// Waits for the child process to exit, the timeout to expire or the wait to be interrupted.
+    ///
+    /// The wait can be interrupted from any thread with a clone of the [`Interrupter`], which stays
+    /// usable for the following waits. As for [`ChildWT::wait_timeout_cancellable`], the child process is
+    /// left running when the wait is interrupted or times out.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child
+    /// process exits, or with `ErrorKind::Interrupted` if the wait is interrupted first.
+    fn wait_timeout_interruptible(
+        &mut self,
+        timeout: Duration,
+        interrupter: &Interrupter,
+    ) -> io::Result<ExitStatus>
+    where
+        Self: Send,
+    {
+        wait_interruptible(self.exit_future(), timeout, interrupter)
+    }
+
+    /// Waits for the child process to exit or until it has consumed the given CPU time.
+    ///
+    /// The limit is the user and system CPU time of the child, rather than the wall-clock time, so that a
+    /// child sleeping or waiting for input is not punished, as in judging systems. The CPU time is sampled
+    /// from `/proc/<pid>/stat` on Linux and Android and with `GetProcessTimes` on Windows, between waits lasting
+    /// at most 100 milliseconds: a child running several threads may exceed the limit by the CPU time it
+    /// consumes in the meantime. The CPU time of the processes spawned by the child is not counted.
+    ///
+    /// The child process is left running when the limit is reached. As there is no wall-clock timeout, a child
+    /// blocked without consuming CPU time is waited for until it exits.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the child process consumes the CPU time before it
+    /// exits. It fails with `ErrorKind::Unsupported` on the other systems, where `RLIMIT_CPU` can be set
+    /// before executing the command instead, and by the default implementation.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// # if cfg!(any(target_os = "linux", target_os = "android")) {
+    /// // Sleeping does not consume CPU time
+    /// let mut child = Command::new("sleep").arg("1").spawn()?;
+    /// let status = child.wait_cpu_timeout(Duration::from_millis(500))?;
+    /// assert!(status.success());
+    ///
+    /// let mut child = Command::new("sh").args(["-c", "while :; do :; done"]).spawn()?;
+    /// let error = child.wait_cpu_timeout(Duration::from_millis(200)).unwrap_err();
+    /// assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+    /// child.kill()?;
+    /// child.wait()?;
+    /// # }
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_cpu_timeout(&mut self, _limit: Duration) -> io::Result<ExitStatus> {
+        _generate_unsupported_error()
+    }
+
+    /// Waits for the child process to exit with a timeout and collects its output.
+    ///
+    /// This mirrors [`Child::wait_with_output`]: the piped standard output and error of the child are
+    /// read on their own threads while it runs, so that it is not blocked by full pipes. The streams which
+    /// are not piped are returned empty.
+    ///
+    /// The default implementation cannot reach the output of the child, it calls [`ChildWT::wait_or_kill`]
+    /// and returns both streams empty. The child is then only killed on timeout if `wait_or_kill` is
+    /// implemented.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process
+    /// exits, in which case its process tree is killed and reaped, or before its output pipes are closed by
+    /// the processes it spawned. The output collected until then is held by an [`OutputTimeout`].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::{Command, Stdio};
+    ///
+    /// use child_wait_timeout::ChildWT;
+    ///
+    /// let child = Command::new("echo").arg("hello").stdout(Stdio::piped()).spawn()?;
+    /// let output = child.wait_with_output_timeout(Duration::from_secs(5))?;
+    /// assert_eq!(output.stdout, b"hello\n");
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_with_output_timeout(mut self, timeout: Duration) -> io::Result<Output>
+    where
+        Self: Sized,
+    {
+        let output = |status| Output {
+            status,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        };
+        match self.wait_or_kill(timeout) {
+            Ok(status) => Ok(output(status)),
+            // If the child has been killed and reaped, its exit status is returned again
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                match self.wait_timeout(Duration::ZERO) {
+                    Ok(status) => Err(RunTimeoutError::TimedOut(output(status)).into()),
+                    Err(_) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Waits for the child process to exit with a timeout, passing its output to the callback as it is
+    /// read.
+    ///
+    /// The piped standard output and error of the child are read on their own threads, and each chunk
+    /// is passed to the callback from the calling thread as soon as it arrives. The timeout covers both
+    /// the output and the exit of the child: the pipes must be closed and the child must exit before it
+    /// expires. The streams which are not piped are not read.
+    ///
+    /// The default implementation cannot reach the output of the child, it only calls [`ChildWT::wait_or_kill`],
+    /// which by default leaves the child running when the timeout expires.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires first, in which case the
+    /// process tree of the child is killed and the child is reaped.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::{Command, Stdio};
+    ///
+    /// use child_wait_timeout::{ChildWT, OutputChunk};
+    ///
+    /// let mut child = Command::new("sh")
+    ///     .args(["-c", "echo out; echo err >&2"])
+    ///     .stdout(Stdio::piped())
+    ///     .stderr(Stdio::piped())
+    ///     .spawn()?;
+    /// let status = child.stream_output_timeout(Duration::from_secs(5), |chunk| match chunk {
+    ///     OutputChunk::Stdout(data) => print!("{}", String::from_utf8_lossy(data)),
+    ///     OutputChunk::Stderr(data) => eprint!("{}", String::from_utf8_lossy(data)),
+    /// })?;
+    /// assert!(status.success());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn stream_output_timeout<F>(
+        &mut self,
+        timeout: Duration,
+        _on_output: F,
+    ) -> io::Result<ExitStatus>
+    where
+        Self: Sized,
+        F: FnMut(OutputChunk<'_>),
+    {
+        self.wait_or_kill(timeout)
+    }
+}
+
+impl ChildWT for Child {
+    fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        let _span = trace::WaitSpan::enter(self.id(), active_backend, timeout);
+        wait_timeout_with(self, timeout, imp::_wait_timeout_untraced)
+    }
+
+    fn wait_timeout_with_backend(
+        &mut self,
+        backend: Backend,
+        timeout: Duration,
+    ) -> io::Result<ExitStatus> {
+        let _span = trace::WaitSpan::enter(self.id(), || backend, timeout);
+        wait_timeout_with(self, timeout, |child, timeout| {
+            wait_untraced_with(backend, child, timeout)
+        })
+    }
+
+    fn wait_timeout_opt(&mut self, timeout: Option<Duration>) -> io::Result<ExitStatus> {
+        match timeout {
+            Some(timeout) => self.wait_timeout(timeout),
+            None => self.wait(),
+        }
+    }
+
+    fn wait_or_kill(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        let result = self.wait_timeout(timeout);
+        kill_on_timeout(self, result)
+    }
+
+    fn shutdown(&mut self, timeout: Duration, grace: Duration) -> io::Result<ExitStatus> {
+        let result = self.wait_timeout(timeout);
+        terminate_on_timeout(self, result, grace)
+    }
+
+    fn exit_future(&mut self) -> ChildExitFuture<'_> {
+        ChildExitFuture::new(self)
+    }
+
+    fn wait_cpu_timeout(&mut self, limit: Duration) -> io::Result<ExitStatus> {
+        let pid = self.id();
+        sampling::wait_cpu_timeout(self, pid, limit)
+    }
+
+    fn wait_with_output_timeout(mut self, timeout: Duration) -> io::Result<Output> {
+        let (stdout, stderr) = (self.stdout.take(), self.stderr.take());
+        wait_with_output(&mut self, stdout, stderr, timeout)
+    }
+
+    fn stream_output_timeout<F>(
+        &mut self,
+        timeout: Duration,
+        on_output: F,
+    ) -> io::Result<ExitStatus>
+    where
+        F: FnMut(OutputChunk<'_>),
+    {
+        let (stdout, stderr) = (self.stdout.take(), self.stderr.take());
+        stream_output(self, stdout, stderr, timeout, on_output)
+    }
+}
+
+// Asks the child to terminate if the wait timed out, then kills and reaps it once the grace
+// period expires. The timeout error is returned whether the child terminated or was killed.
+pub(crate) fn terminate_on_timeout(
+    child: &mut Child,
+    result: io::Result<ExitStatus>,
+    grace: Duration,
+) -> io::Result<ExitStatus> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            trace::terminating(child.id(), grace);
+            // Without a way to ask the child to terminate, it is killed right away
+            let result = match terminate(child.id()) {
+                Ok(()) => child.wait_timeout(grace),
+                Err(_) => _generate_timeout_error(),
+            };
+            kill_on_timeout(child, result)?;
+            Err(e)
+        }
+        result => result,
+    }
+}
+
+/// Waits for any of the child processes to exit or until the timeout expires.
+///
+/// The child process which exited is reaped and its index in the slice is returned along with its
+/// exit status. The other child processes are left running. A child process which has already been
+/// waited for is returned right away, so it should be removed from the slice before waiting again.
+///
+/// The children are waited on together by the backend: `WaitForMultipleObjects` on Windows, with helper
+/// threads waiting on chunks of 63 children past its limit of 64 handles, a single
+/// `kqueue` on macOS and the BSDs, a single event port on illumos and Solaris, `ppoll` over their pidfds with the `pidfd` feature, `SIGCHLD` with the
+/// `signal` feature, and their watcher threads otherwise.
+///
+/// # Errors
+///
+/// This function may fail with `ErrorKind::TimedOut` if the timeout expires before any child process exits,
+/// or with `ErrorKind::InvalidInput` if the slice is empty.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::wait_any_timeout;
+///
+/// let mut children = vec![
+///     Command::new("sleep").arg("2").spawn()?,
+///     Command::new("sleep").arg("1").spawn()?,
+/// ];
+/// let (index, status) = wait_any_timeout(&mut children, Duration::from_secs(5))?;
+/// assert_eq!(index, 1);
+/// assert!(status.success());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn wait_any_timeout(
+    children: &mut [Child],
+    timeout: Duration,
+) -> io::Result<(usize, ExitStatus)> {
+    if children.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no child to wait on",
+        ));
+    }
+
+    // Children which have already exited are returned without calling into the backend
+    for (index, child) in children.iter_mut().enumerate() {
+        if let Some(status) = child.try_wait()? {
+            return Ok((index, status));
+        }
+    }
+    if timeout.is_zero() {
+        return _generate_timeout_error();
+    }
+
+    let index = wait_until_deadline(timeout, |remaining| {
+        imp::_wait_any_untraced(children, remaining)
+    })?;
+    Ok((index, children[index].wait()?))
+}
+
+/// Waits for all the child processes to exit or until the timeout expires.
+///
+/// The timeout is shared by all the children: each one is waited for with the time remaining until
+/// the common deadline, so the whole call never exceeds the timeout.
+/// On Windows, the children are waited on together with `WaitForMultipleObjects`, in chunks of 64.
+///
+/// # Returns
+/// - The exit statuses of the child processes, in the order of the slice.
+///
+/// # Errors
+///
+/// This function may fail with `ErrorKind::TimedOut` if the timeout expires before all the child processes exit.
+/// The child processes which exited before the timeout are reaped, and their exit status can be retrieved with
+/// [`Child::try_wait`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::wait_all_timeout;
+///
+/// let mut children = vec![
+///     Command::new("sleep").arg("1").spawn()?,
+///     Command::new("sleep").arg("1").spawn()?,
+/// ];
+/// let statuses = wait_all_timeout(&mut children, Duration::from_secs(5))?;
+/// assert!(statuses.iter().all(|status| status.success()));
+/// #     Ok(())
+/// # }
+/// ```
+pub fn wait_all_timeout(children: &mut [Child], timeout: Duration) -> io::Result<Vec<ExitStatus>> {
+    // On Windows, all the children are waited on together before being reaped
+    #[cfg(windows)]
+    if let Err(e) = wait_until_deadline(timeout, |remaining| {
+        imp::_wait_all_untraced(children, remaining)
+    }) {
+        reap_exited(children)?;
+        return Err(e);
+    }
+
+    let deadline = deadline_after(timeout);
+    let result = children
+        .iter_mut()
+        .map(|child| child.wait_timeout(remaining_until(deadline)))
+        .collect::<io::Result<Vec<_>>>();
+    // The children following the one which timed out may have exited in the meantime
+    if result.is_err() {
+        reap_exited(children)?;
+    }
+    result
+}
+
+// Reaps the children which have exited, leaving the other ones running
+fn reap_exited(children: &mut [Child]) -> io::Result<()> {
+    for child in children.iter_mut() {
+        try_wait_untraced(child)?;
+    }
+    Ok(())
+}
+
+/// Waits for the child processes until the deadline, reporting each one to the callback as soon as it exits.
+///
+/// The callback is called with the index of the child process in the slice and its exit status, in the
+/// order of their exits. Unlike [`wait_all_timeout`], the deadline passing is not an error: the child
+/// processes still running by then are returned, left running, so that the caller decides whether to
+/// keep waiting for them or to kill them. The children are waited on together as with [`wait_any_timeout`],
+/// and the slice keeps its order.
+///
+/// # Returns
+/// - The indices of the child processes which were still running when the deadline passed, in
+///   increasing order, empty once all of them have exited.
+///
+/// # Errors
+///
+/// This function may fail if a child process could not be waited for, in which case the child processes
+/// already reported to the callback have been reaped.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{wait_many, Deadline};
+///
+/// let mut children = vec![
+///     Command::new("sleep").arg("10").spawn()?,
+///     Command::new("sleep").arg("0").spawn()?,
+/// ];
+/// let running = wait_many(&mut children, Deadline::after(Duration::from_secs(1)), |index, status| {
+///     println!("child {} exited with {}", index, status);
+/// })?;
+/// assert_eq!(running, [0]);
+/// children[0].kill()?;
+/// children[0].wait()?;
+/// #     Ok(())
+/// # }
+/// ```
+pub fn wait_many(
+    children: &mut [Child],
+    deadline: Deadline,
+    mut on_exit: impl FnMut(usize, ExitStatus),
+) -> io::Result<Vec<usize>> {
+    // The children which exited are moved past the pending ones, `order` holding the index in the
+    // slice of the child at each position
+    let mut order: Vec<usize> = (0..children.len()).collect();
+    let mut pending = children.len();
+
+    let mut result = Ok(());
+    while pending > 0 {
+        match wait_any_timeout(&mut children[..pending], deadline.remaining()) {
+            Ok((position, status)) => {
+                on_exit(order[position], status);
+                pending -= 1;
+                children.swap(position, pending);
+                order.swap(position, pending);
+            }
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => break,
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+    let mut running = order[..pending].to_vec();
+    running.sort_unstable();
+
+    // Put each child back at its index
+    for position in 0..order.len() {
+        while order[position] != position {
+            let index = order[position];
+            children.swap(position, index);
+            order.swap(position, index);
+        }
+    }
+
+    result.map(|()| running)
+}
+
+/// Kills the child process along with the processes it spawned, without reaping it.
+///
+/// This is used by [`ChildWT::wait_or_kill`] and [`ChildWT::shutdown`] so that grandchildren, such as
+/// the commands run by a shell or a build tool, are not left running.
+///
+/// # Platform-Specific Behavior
+///
+/// On Unix, the signals are sent to the process group of the child process if it leads its own group,
+/// which is the case when it is spawned with `CommandExt::process_group(0)`. Otherwise only the child
+/// process is signaled.
+///
+/// On Windows, the descendants of the child process are found by walking a Toolhelp snapshot of the
+/// running processes, and are terminated after the child process.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::process::Command;
+/// # #[cfg(unix)]
+/// use std::os::unix::process::CommandExt;
+///
+/// use child_wait_timeout::kill_tree;
+///
+/// let mut command = Command::new("sh");
+/// command.args(["-c", "sleep 10 & sleep 10"]);
+/// # #[cfg(unix)]
+/// command.process_group(0);
+/// let mut child = command.spawn()?;
+/// kill_tree(&mut child)?;
+/// child.wait()?;
+/// #     Ok(())
+/// # }
+/// ```
+pub fn kill_tree(child: &mut Child) -> io::Result<()> {
+    kill_tree_with(child.id(), || child.kill())
+}
+
+// Kills and reaps the child if the wait timed out, then returns the result of the wait
+pub(crate) fn kill_on_timeout(
+    child: &mut Child,
+    result: io::Result<ExitStatus>,
+) -> io::Result<ExitStatus> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            trace::killing(child.id());
+            kill_tree(child)?;
+            child.wait()?;
+            Err(e)
+        }
+        result => result,
+    }
+}
+
+// Waits on the child with a backend function taking the timeout
+pub(crate) fn wait_timeout_with(
+    child: &mut Child,
+    timeout: Duration,
+    mut wait: impl FnMut(&mut Child, Duration) -> io::Result<Option<ExitStatus>>,
+) -> io::Result<ExitStatus> {
+    let start = Instant::now();
+    let result = (|| {
+        // A zero timeout only checks the child once, without calling into the backend
+        if timeout.is_zero() {
+            return match try_wait_untraced(child)? {
+                Some(status) => Ok(status),
+                None => _generate_timeout_error(),
+            };
+        }
+
+        if let Ok(Some(res)) = try_wait_untraced(child) {
+            return Ok(res);
+        }
+
+        let status = wait_until_deadline(timeout, |remaining| wait(child, remaining))?;
+        reap(child, status)
+    })();
+    trace::wait_result(child.id(), start.elapsed(), &result);
+    result
+}
+
+// Checks the child without blocking, unless it is in a pending ptrace stop which the standard
+// library would collect as its exit status
+pub(crate) fn try_wait_untraced(child: &mut Child) -> io::Result<Option<ExitStatus>> {
+    #[cfg(all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    ))]
+    if unix::is_trace_stopped(child.id() as libc::pid_t) {
+        return Ok(None);
+    }
+    child.try_wait()
+}
+
+// Waits for the child to exit with the backend, without reaping it
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+))]
+fn wait_untraced_with(
+    backend: Backend,
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    backend::wait_pid_with(backend, child.id(), timeout).map(|()| None)
+}
+
+#[cfg(windows)]
+fn wait_untraced_with(
+    backend: Backend,
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    use std::os::windows::io::AsRawHandle;
+
+    backend::wait_handle_with(backend, child.as_raw_handle() as _, timeout).map(|()| None)
+}
+
+#[cfg(any(not(any(unix, windows)), target_os = "redox", target_os = "haiku"))]
+fn wait_untraced_with(
+    backend: Backend,
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    match backend {
+        Backend::Polling => imp::_wait_timeout_untraced(child, timeout),
+        _ => crate::error::_generate_unsupported_error(),
+    }
+}
+
+#[cfg(target_os = "fuchsia")]
+fn wait_untraced_with(
+    backend: Backend,
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    match backend {
+        Backend::FuchsiaWaitOne => imp::_wait_handle_untraced(child.id(), timeout).map(|()| None),
+        #[cfg(feature = "polling")]
+        Backend::Polling => polling::_wait_timeout_untraced(child, timeout),
+        _ => crate::error::_generate_unsupported_error(),
+    }
+}
+
+// Returns the instant at which the timeout expires, or `None` if it cannot be represented
+pub(crate) fn deadline_after(timeout: Duration) -> Option<Instant> {
+    Instant::now().checked_add(timeout)
+}
+
+// Returns the time remaining until the deadline, `Duration::MAX` if there is none
+pub(crate) fn remaining_until(deadline: Option<Instant>) -> Duration {
+    deadline.map_or(Duration::MAX, |deadline| {
+        deadline.saturating_duration_since(Instant::now())
+    })
+}
+
+// Calls the wait with the time remaining until the deadline, again if it times out before the
+// deadline has passed. The deadline is measured with the monotonic clock of `Instant`, so that the
+// waits are neither shortened nor lengthened when the system clock is changed, whatever the clock
+// used by the backend.
+pub(crate) fn wait_until_deadline<T>(
+    timeout: Duration,
+    mut wait: impl FnMut(Duration) -> io::Result<T>,
+) -> io::Result<T> {
+    let deadline = deadline_after(timeout);
+    loop {
+        match wait(remaining_until(deadline)) {
+            Err(e)
+                if e.kind() == io::ErrorKind::TimedOut && !remaining_until(deadline).is_zero() => {}
+            result => return result,
+        }
+    }
+}
+
+// Reaps the child once the backend has observed its exit. If the backend already
+// collected the exit status, it is used when the child has been reaped elsewhere.
+fn reap(child: &mut Child, status: Option<ExitStatus>) -> io::Result<ExitStatus> {
+    match status {
+        Some(status) => Ok(child.try_wait().ok().flatten().unwrap_or(status)),
         // the child.wait will end instantly
-        self.try_wait().and_then(|v| Ok(v.expect("aa")))
+        None => child.wait(),
     }
 }
@@ -0,0 +1,159 @@
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::time::Duration;
+
+use super::{try_wait_untraced, ChildWT};
+
+/// A child process whose state can be queried at any time, before and after it has been reaped.
+///
+/// The exit status is kept once the child process has been reaped by any of the methods of the wrapper,
+/// so that [`MonitoredChild::exit_status`] keeps returning it without a system call, and the waits
+/// return it at once. [`MonitoredChild::is_running`] replaces the usual `try_wait().ok().flatten()`.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::process::Command;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::MonitoredChild;
+///
+/// let mut child = MonitoredChild::spawn(Command::new("sleep").arg("1"))?;
+/// assert!(child.is_running());
+/// assert_eq!(child.exit_status(), None);
+///
+/// let status = child.wait_timeout(Duration::from_secs(5))?;
+/// assert!(!child.is_running());
+/// assert_eq!(child.exit_status(), Some(status));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MonitoredChild {
+    child: Child,
+    status: Option<ExitStatus>,
+}
+
+impl MonitoredChild {
+    /// Wraps a child process to monitor it.
+    pub fn new(child: Child) -> Self {
+        MonitoredChild {
+            child,
+            status: None,
+        }
+    }
+
+    /// Spawns the command and wraps the child process.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the command could not be spawned, see [`Command::spawn`].
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        command.spawn().map(Self::new)
+    }
+
+    /// Returns the OS-assigned process identifier of the child process.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Returns `true` if the child process has not exited yet, checking it without blocking.
+    ///
+    /// A child process which has exited is reaped, and its exit status kept. A child process which
+    /// cannot be checked, such as one reaped elsewhere, is reported as not running.
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.try_wait(), Ok(None))
+    }
+
+    /// Returns the exit status of the child process if it has been reaped, without any system call.
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.status
+    }
+
+    /// Returns the exit status if the child process has exited, without blocking.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the underlying system calls fail, see [`Child::try_wait`].
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if self.status.is_none() {
+            self.status = try_wait_untraced(&mut self.child)?;
+        }
+        Ok(self.status)
+    }
+
+    /// Waits for the child process to exit or until the timeout expires, as with [`ChildWT::wait_timeout`].
+    ///
+    /// Once the child process has been reaped, the kept exit status is returned at once.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process exits.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        match self.status {
+            Some(status) => Ok(status),
+            None => self.keep(|child| child.wait_timeout(timeout)),
+        }
+    }
+
+    /// Waits for the child process to exit, without timeout.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the underlying system calls fail, see [`Child::wait`].
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        match self.status {
+            Some(status) => Ok(status),
+            None => self.keep(Child::wait),
+        }
+    }
+
+    /// Kills the child process, unless it has already been reaped.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the child process could not be killed, see [`Child::kill`].
+    pub fn kill(&mut self) -> io::Result<()> {
+        // A reaped child is never signaled, its pid may belong to another process
+        match self.try_wait()? {
+            Some(_) => Ok(()),
+            None => self.child.kill(),
+        }
+    }
+
+    /// Returns a reference to the wrapped child process.
+    pub fn as_child(&self) -> &Child {
+        &self.child
+    }
+
+    /// Returns a mutable reference to the wrapped child process.
+    ///
+    /// A child process reaped through this reference is noticed by the next call to
+    /// [`MonitoredChild::try_wait`] or [`MonitoredChild::is_running`].
+    pub fn as_child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Consumes the wrapper and returns the child process.
+    pub fn into_inner(self) -> Child {
+        self.child
+    }
+
+    // Runs a wait on the child and keeps the exit status it returns
+    fn keep(
+        &mut self,
+        wait: impl FnOnce(&mut Child) -> io::Result<ExitStatus>,
+    ) -> io::Result<ExitStatus> {
+        let status = wait(&mut self.child)?;
+        self.status = Some(status);
+        Ok(status)
+    }
+}
+
+impl From<Child> for MonitoredChild {
+    fn from(child: Child) -> Self {
+        MonitoredChild::new(child)
+    }
+}
@@ -0,0 +1,266 @@
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::{deadline_after, kill_tree, remaining_until, sampling, ChildWT};
+use crate::error::_generate_memory_limit_error;
+
+// The longest step of a wait following the system clock, after which the clock is read again
+const SYSTEM_CLOCK_STEP: Duration = Duration::from_secs(1);
+
+/// The clock measuring the timeout of a wait, see [`WaitOptions::clock`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Clock {
+    /// The monotonic clock of [`Instant`](std::time::Instant), used by all the other waits.
+    ///
+    /// On Linux and Android, it does not advance while the system is suspended.
+    #[default]
+    Monotonic,
+    /// The system clock of [`SystemTime`], which advances while the system is suspended and follows the
+    /// changes of the system time.
+    ///
+    /// The wait is split into steps of at most 1 second, after which the clock is read again.
+    System,
+}
+
+/// The behaviors of a wait performed by [`wait_with`].
+///
+/// By default, the child process is waited for without timeout. When a timeout is set, the child process is
+/// left running once it expires, unless [`WaitOptions::kill_on_timeout`] is set. It is then killed along with
+/// the processes it spawned, as with [`ChildWT::wait_or_kill`], or asked to terminate first if a grace period
+/// is set, as with [`ChildWT::shutdown`].
+///
+/// A [`WaitOptions::memory_limit`] can also bound the resident memory of the child process during the wait.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{wait_with, WaitOptions};
+///
+/// let options = WaitOptions::new()
+///     .timeout(Duration::from_millis(100))
+///     .kill_on_timeout(true)
+///     .grace_period(Duration::from_secs(1));
+///
+/// let mut child = Command::new("sleep").arg("10").spawn()?;
+/// let result = wait_with(&mut child, &options);
+/// assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+/// // The child has already been reaped
+/// assert!(child.try_wait()?.is_some());
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WaitOptions {
+    timeout: Option<Duration>,
+    kill_on_timeout: bool,
+    #[cfg(all(unix, not(target_os = "fuchsia")))]
+    term_signal: i32,
+    grace_period: Option<Duration>,
+    clock: Clock,
+    kill_tree: bool,
+    memory_limit: Option<u64>,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        WaitOptions {
+            timeout: None,
+            kill_on_timeout: false,
+            #[cfg(all(unix, not(target_os = "fuchsia")))]
+            term_signal: libc::SIGTERM,
+            grace_period: None,
+            clock: Clock::default(),
+            kill_tree: true,
+            memory_limit: None,
+        }
+    }
+}
+
+impl WaitOptions {
+    /// Creates the default options, waiting without timeout.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum time to wait for the child process to exit.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets whether the child process is killed and reaped once the timeout expires.
+    pub fn kill_on_timeout(mut self, kill_on_timeout: bool) -> Self {
+        self.kill_on_timeout = kill_on_timeout;
+        self
+    }
+
+    /// Sets the signal asking the child process to terminate at the start of the grace period, `SIGTERM` by
+    /// default.
+    ///
+    /// It is only sent if a grace period is set. The child process is killed with `SIGKILL` once the grace
+    /// period expires, or right away without a grace period.
+    ///
+    /// This function is only available on Unix, except Fuchsia.
+    #[cfg(all(unix, not(target_os = "fuchsia")))]
+    pub fn term_signal(mut self, signal: i32) -> Self {
+        self.term_signal = signal;
+        self
+    }
+
+    /// Sets the time the child process is given to terminate once the timeout expires, before it is killed.
+    ///
+    /// The child process is asked to terminate with the [`WaitOptions::term_signal`] on Unix, and with
+    /// `WM_CLOSE` posted to its windows on Windows.
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = Some(grace_period);
+        self
+    }
+
+    /// Sets the clock measuring the timeout, [`Clock::Monotonic`] by default.
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sets whether the processes spawned by the child process are killed along with it, `true` by default.
+    ///
+    /// See [`kill_tree`] for the processes which are killed.
+    pub fn kill_tree(mut self, kill_tree: bool) -> Self {
+        self.kill_tree = kill_tree;
+        self
+    }
+
+    /// Sets the maximum resident memory of the child process in bytes, its working set on Windows.
+    ///
+    /// The resident memory is sampled every 50 milliseconds during the wait, so a child process allocating
+    /// quickly may exceed the limit in between. Once it is exceeded, the child process is stopped as on a
+    /// timeout, whether [`WaitOptions::kill_on_timeout`] is set or not, and the wait fails with
+    /// [`WaitError::MemoryLimitExceeded`](crate::WaitError::MemoryLimitExceeded).
+    ///
+    /// Only the memory of the child process itself is counted, not the one of the processes it spawned.
+    /// Sampling the memory is only supported on Linux, Android and Windows, the wait fails with
+    /// `ErrorKind::Unsupported` elsewhere.
+    pub fn memory_limit(mut self, bytes: u64) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+}
+
+/// Waits for the child process to exit with the given options.
+///
+/// # Errors
+///
+/// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process exits,
+/// in which case the child process has been killed and reaped if [`WaitOptions::kill_on_timeout`] is set.
+/// It fails with [`WaitError::MemoryLimitExceeded`](crate::WaitError::MemoryLimitExceeded), an error of
+/// kind `ErrorKind::Other`, if the child process exceeded the memory limit, in which case it has been killed
+/// and reaped. It may also fail if the child process could not be killed.
+pub fn wait_with(child: &mut Child, options: &WaitOptions) -> io::Result<ExitStatus> {
+    if options.timeout.is_none() && options.memory_limit.is_none() {
+        return child.wait();
+    }
+
+    let deadline = ClockDeadline::new(options.clock, options.timeout.unwrap_or(Duration::MAX));
+    let mut step = match options.clock {
+        Clock::Monotonic => Duration::MAX,
+        Clock::System => SYSTEM_CLOCK_STEP,
+    };
+    if options.memory_limit.is_some() {
+        step = step.min(sampling::MEMORY_SAMPLE_INTERVAL);
+    }
+
+    let result = loop {
+        if let Some(limit) = options.memory_limit {
+            match sampling::resident_memory(child.id()) {
+                Ok(resident) if resident > limit => {
+                    stop(child, options)?;
+                    return _generate_memory_limit_error(limit, resident);
+                }
+                Ok(_) => {}
+                // The child may have exited in the meantime, then its status is returned
+                Err(e) => break child.wait_timeout(Duration::ZERO).map_err(|_| e),
+            }
+        }
+
+        let remaining = deadline.remaining();
+        match child.wait_timeout(remaining.min(step)) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut && !remaining.is_zero() => {}
+            result => break result,
+        }
+    };
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::TimedOut && options.kill_on_timeout => {
+            stop(child, options)?;
+            Err(e)
+        }
+        result => result,
+    }
+}
+
+// The deadline of a wait, measured by its clock so that a suspension of the system is noticed with
+// `Clock::System`
+enum ClockDeadline {
+    Monotonic(Option<Instant>),
+    System(Option<SystemTime>),
+}
+
+impl ClockDeadline {
+    fn new(clock: Clock, timeout: Duration) -> Self {
+        match clock {
+            Clock::Monotonic => ClockDeadline::Monotonic(deadline_after(timeout)),
+            Clock::System => ClockDeadline::System(SystemTime::now().checked_add(timeout)),
+        }
+    }
+
+    // Returns the time remaining until the deadline, `Duration::MAX` if there is none
+    fn remaining(&self) -> Duration {
+        match self {
+            ClockDeadline::Monotonic(deadline) => remaining_until(*deadline),
+            ClockDeadline::System(deadline) => deadline.map_or(Duration::MAX, |deadline| {
+                deadline
+                    .duration_since(SystemTime::now())
+                    .unwrap_or(Duration::ZERO)
+            }),
+        }
+    }
+}
+
+// Asks the child to terminate during the grace period if there is one, then kills and reaps it
+fn stop(child: &mut Child, options: &WaitOptions) -> io::Result<()> {
+    if let Some(grace_period) = options.grace_period {
+        // Without a way to ask the child to terminate, it is killed right away
+        if terminate(child, options).is_ok() && child.wait_timeout(grace_period).is_ok() {
+            return Ok(());
+        }
+    }
+
+    if options.kill_tree {
+        kill_tree(child)?;
+    } else {
+        child.kill()?;
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "fuchsia")))]
+fn terminate(child: &Child, options: &WaitOptions) -> io::Result<()> {
+    if options.kill_tree {
+        return super::unix::signal_tree(child.id(), options.term_signal);
+    }
+    if unsafe { libc::kill(child.id() as libc::pid_t, options.term_signal) } == -1 {
+        return crate::error::_generate_os_error();
+    }
+    Ok(())
+}
+
+#[cfg(any(not(unix), target_os = "fuchsia"))]
+fn terminate(child: &Child, _options: &WaitOptions) -> io::Result<()> {
+    super::terminate(child.id())
+}
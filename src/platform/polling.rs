@@ -0,0 +1,100 @@
+// A last resort backend checking the child in a loop, with sleeps growing from 1ms to 50ms so that
+// short-lived children are noticed quickly without spinning on long-lived ones
+
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::thread;
+use std::time::Duration;
+
+use super::{deadline_after, remaining_until, Backend};
+use crate::error::_generate_timeout_error;
+
+const MIN_INTERVAL: Duration = Duration::from_millis(1);
+const MAX_INTERVAL: Duration = Duration::from_millis(50);
+
+pub(crate) fn _active_backend() -> Backend {
+    Backend::Polling
+}
+
+// Reaps the child with `try_wait` once it has exited, which the other backends avoid
+pub(crate) fn _wait_timeout_untraced(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    _poll(timeout, || child.try_wait()).map(Some)
+}
+
+// Waits for the child with the given pid to exit, without reaping it
+#[cfg(all(
+    unix,
+    not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+))]
+pub(crate) fn _wait_pid_untraced(pid: u32, timeout: Duration) -> io::Result<()> {
+    use super::unix::has_exited;
+
+    _poll(timeout, || Ok(has_exited(pid as libc::pid_t).then_some(())))
+}
+
+// Waits for the process to exit, the handle must have the `SYNCHRONIZE` access right
+#[cfg(windows)]
+pub(crate) fn _wait_handle_untraced(
+    handle: super::win32::HANDLE,
+    timeout: Duration,
+) -> io::Result<()> {
+    use super::win32::{WaitForSingleObject, WAIT_OBJECT_0, WAIT_TIMEOUT};
+
+    use crate::error::_generate_os_error;
+
+    _poll(timeout, || {
+        match unsafe { WaitForSingleObject(handle, 0) } {
+            WAIT_OBJECT_0 => Ok(Some(())),
+            WAIT_TIMEOUT => Ok(None),
+            _ => _generate_os_error(),
+        }
+    })
+}
+
+// Reaps the first child found to have exited, its exit status stays available to `Child::wait`
+pub(crate) fn _wait_any_untraced(children: &mut [Child], timeout: Duration) -> io::Result<usize> {
+    _poll(timeout, || {
+        for (index, child) in children.iter_mut().enumerate() {
+            if child.try_wait()?.is_some() {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    })
+}
+
+// Calls the check until it returns a value or the timeout expires
+pub(crate) fn _poll<T>(
+    timeout: Duration,
+    mut check: impl FnMut() -> io::Result<Option<T>>,
+) -> io::Result<T> {
+    let deadline = deadline_after(timeout);
+    let mut interval = MIN_INTERVAL;
+
+    loop {
+        if let Some(value) = check()? {
+            return Ok(value);
+        }
+        let remaining = remaining_until(deadline);
+        if remaining.is_zero() {
+            return _generate_timeout_error();
+        }
+        thread::sleep(interval.min(remaining));
+        interval = (interval * 2).min(MAX_INTERVAL);
+    }
+}
+
+// Without a portable way to ask a process to terminate, the child is killed right away
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn terminate(_pid: u32) -> io::Result<()> {
+    crate::error::_generate_unsupported_error()
+}
+
+// Without a portable way to find the descendants of a process, only the child is killed
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn kill_tree_with(_pid: u32, kill: impl FnOnce() -> io::Result<()>) -> io::Result<()> {
+    kill()
+}
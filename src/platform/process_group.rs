@@ -0,0 +1,267 @@
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::time::Duration;
+
+#[cfg(windows)]
+use super::Job;
+use super::{deadline_after, remaining_until, ChildWT};
+
+/// A child process spawned into a new process group, waited for and killed along with all its members.
+///
+/// On Unix, the child calls `setsid` before executing the command: it leads a new session and process
+/// group, which its descendants inherit, and is detached from the controlling terminal. On Windows, it is
+/// spawned with `CREATE_NEW_PROCESS_GROUP` and assigned to a [`Job`], which its descendants join.
+///
+/// The process tree then has the same lifecycle on every platform: [`ProcessGroup::wait_timeout`] waits
+/// for the child and all its descendants, and [`ProcessGroup::kill`] kills them all, even when the
+/// intermediate processes have already exited. Descendants which create their own process group on Unix,
+/// or break away from the job on Windows, are no longer part of it.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::ProcessGroup;
+///
+/// let mut command = if cfg!(target_os = "windows") {
+///     let mut command = Command::new("cmd");
+///     command.args(["/c", "exit 0"]);
+///     command
+/// } else {
+///     let mut command = Command::new("sh");
+///     command.args(["-c", "sleep 1 & exit 0"]);
+///     command
+/// };
+/// let mut group = ProcessGroup::spawn(&mut command)?;
+/// // Returns once the background `sleep` has exited too
+/// let status = group.wait_or_kill(Duration::from_secs(5))?;
+/// assert!(status.success());
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ProcessGroup {
+    child: Child,
+    #[cfg(windows)]
+    job: Job,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    subreaper: bool,
+}
+
+impl ProcessGroup {
+    /// Spawns the command into a new process group.
+    ///
+    /// On Windows, the processes spawned by the child before it is assigned to the job, right after
+    /// being spawned, are not part of the group.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the command could not be spawned, see [`Command::spawn`], or on Windows
+    /// if the job could not be created. A child which could not be assigned to the job is killed and reaped.
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+
+            // `setsid` is async-signal-safe, it only fails if the child already leads a group
+            unsafe {
+                command.pre_exec(|| {
+                    if libc::setsid() == -1 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    Ok(())
+                })
+            };
+            Ok(ProcessGroup {
+                child: command.spawn()?,
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                subreaper: false,
+            })
+        }
+
+        #[cfg(windows)]
+        {
+            use super::win32::CREATE_NEW_PROCESS_GROUP;
+            use std::os::windows::process::CommandExt;
+
+            let job = Job::new()?;
+            let mut child = command.creation_flags(CREATE_NEW_PROCESS_GROUP).spawn()?;
+            if let Err(e) = job.assign(&child) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e);
+            }
+            Ok(ProcessGroup { child, job })
+        }
+    }
+
+    /// Spawns the command into a new process group, making the current process the subreaper of the
+    /// members which are orphaned.
+    ///
+    /// On Linux and Android, the current process is set as a child subreaper with
+    /// [`set_child_subreaper`](super::set_child_subreaper), which is left enabled for the whole process.
+    /// The members orphaned by the exit or the kill of their parent are then re-parented to the current
+    /// process, which reaps them within the same timeout in [`ProcessGroup::wait_timeout`] and right away
+    /// in [`ProcessGroup::kill`], instead of leaving them to `init`.
+    ///
+    /// On Windows, the orphaned processes already remain in the job of the group, this is the same as
+    /// [`ProcessGroup::spawn`].
+    ///
+    /// # Errors
+    ///
+    /// This function fails as [`ProcessGroup::spawn`], or if the current process could not be set as a
+    /// subreaper. It fails with `ErrorKind::Unsupported` on the other Unix systems.
+    pub fn spawn_subreaper(command: &mut Command) -> io::Result<Self> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            super::set_child_subreaper(true)?;
+            let mut group = Self::spawn(command)?;
+            group.subreaper = true;
+            Ok(group)
+        }
+
+        #[cfg(windows)]
+        {
+            Self::spawn(command)
+        }
+
+        #[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+        {
+            let _ = command;
+            crate::error::_generate_unsupported_error()
+        }
+    }
+
+    /// Returns the pid of the child, which is also the id of the process group on Unix.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Returns a reference to the child leading the group.
+    pub fn as_child(&self) -> &Child {
+        &self.child
+    }
+
+    /// Returns a mutable reference to the child leading the group.
+    ///
+    /// Waiting on the child directly returns as soon as it exits, the other members may still be running.
+    pub fn as_child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Waits for the child and all the members of its group to exit or until the timeout expires.
+    ///
+    /// The child is reaped first, then the other members, which are not children of the current process
+    /// on Unix, are waited for by checking the group every few milliseconds.
+    ///
+    /// # Returns
+    /// - The exit status of the child leading the group.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before all the members
+    /// exit. The child may already have been reaped, its exit status is then still returned by later waits.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        let deadline = deadline_after(timeout);
+        let status = self.child.wait_timeout(timeout)?;
+        self.wait_members(remaining_until(deadline))?;
+        Ok(status)
+    }
+
+    /// Waits for all the members of the group to exit, and kills them all if the timeout expires.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with `ErrorKind::TimedOut` if the timeout expired, in which case all the members
+    /// have been killed and the child has been reaped. It may also fail if the group could not be killed.
+    pub fn wait_or_kill(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        match self.wait_timeout(timeout) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                self.kill()?;
+                Err(e)
+            }
+            result => result,
+        }
+    }
+
+    /// Kills all the members of the group and reaps the child.
+    ///
+    /// On Unix, the other members are reaped by their own parent, they remain in the group until then,
+    /// unless the group was spawned with [`ProcessGroup::spawn_subreaper`] on Linux and Android.
+    pub fn kill(&mut self) -> io::Result<()> {
+        #[cfg(unix)]
+        if unsafe { libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL) } == -1 {
+            let error = io::Error::last_os_error();
+            // No member is left in the group
+            if error.raw_os_error() != Some(libc::ESRCH) {
+                return Err(error);
+            }
+        }
+
+        #[cfg(windows)]
+        self.job.terminate(1)?;
+
+        self.child.wait()?;
+
+        // The killed members are orphaned one after the other as their parents are reaped
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if self.subreaper {
+            let pgid = self.child.id() as libc::pid_t;
+            loop {
+                if unsafe { libc::waitpid(-pgid, std::ptr::null_mut(), 0) } == -1 {
+                    let error = io::Error::last_os_error();
+                    match error.raw_os_error() {
+                        Some(libc::EINTR) => continue,
+                        // No member of the group is left among the children of the current process
+                        Some(libc::ECHILD) => break,
+                        _ => return Err(error),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn wait_members(&self, timeout: Duration) -> io::Result<()> {
+        use std::thread;
+
+        use crate::error::_generate_timeout_error;
+
+        const MAX_INTERVAL: Duration = Duration::from_millis(50);
+
+        let pgid = self.child.id() as libc::pid_t;
+        let deadline = deadline_after(timeout);
+        let mut interval = Duration::from_millis(1);
+
+        loop {
+            // The members orphaned while the current process is their subreaper are its children
+            while unsafe { libc::waitpid(-pgid, std::ptr::null_mut(), libc::WNOHANG) } > 0 {}
+
+            if unsafe { libc::kill(-pgid, 0) } == -1 {
+                let error = io::Error::last_os_error();
+                return match error.raw_os_error() {
+                    Some(libc::ESRCH) => Ok(()),
+                    _ => Err(error),
+                };
+            }
+
+            let remaining = remaining_until(deadline);
+            if remaining.is_zero() {
+                return _generate_timeout_error();
+            }
+            thread::sleep(interval.min(remaining));
+            interval = (interval * 2).min(MAX_INTERVAL);
+        }
+    }
+
+    #[cfg(windows)]
+    fn wait_members(&self, timeout: Duration) -> io::Result<()> {
+        self.job.wait_timeout(timeout)
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::{self, Read};
+use std::process::{ChildStderr, ChildStdout};
+use std::time::Duration;
+
+/// Extension trait reading from the pipes of a child process with a timeout.
+///
+/// A child which exits on time but stops writing while keeping its output pipe open, or which hands the
+/// pipe over to a process it spawned, hangs its reader just as much as a slow exit. Each read is bounded
+/// by its own timeout instead.
+///
+/// On Unix, the pipe is polled with `poll` before being read. On Windows, where anonymous pipes do not
+/// support overlapped reads, the data available is checked with `PeekNamedPipe` every few milliseconds,
+/// then only that data is read.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::{Command, Stdio};
+///
+/// use child_wait_timeout::ReadTimeout;
+///
+/// let mut child = if cfg!(target_os = "windows") {
+///     Command::new("cmd").args(["/c", "echo hello"]).stdout(Stdio::piped()).spawn()?
+/// } else {
+///     Command::new("echo").arg("hello").stdout(Stdio::piped()).spawn()?
+/// };
+/// let mut stdout = child.stdout.take().unwrap();
+/// let mut buf = [0; 64];
+/// let read = stdout.read_timeout(&mut buf, Duration::from_secs(5))?;
+/// assert!(buf[..read].starts_with(b"hello"));
+/// child.wait()?;
+/// #     Ok(())
+/// # }
+/// ```
+pub trait ReadTimeout {
+    /// Reads some bytes into the buffer, waiting at most the timeout for data to be available.
+    ///
+    /// As with [`Read::read`], the number of bytes read is returned, and `0` once the pipe is closed by
+    /// every process holding its write end, or if the buffer is empty.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with `ErrorKind::TimedOut` if no data is available before the timeout expires,
+    /// in which case nothing has been read and the pipe can be read again.
+    fn read_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize>;
+}
+
+impl ReadTimeout for ChildStdout {
+    fn read_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        read_pipe_timeout(self, buf, timeout)
+    }
+}
+
+impl ReadTimeout for ChildStderr {
+    fn read_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        read_pipe_timeout(self, buf, timeout)
+    }
+}
+
+#[cfg(unix)]
+fn read_pipe_timeout(
+    pipe: &mut (impl Read + std::os::unix::io::AsRawFd),
+    buf: &mut [u8],
+    timeout: Duration,
+) -> io::Result<usize> {
+    use libc::{c_int, pollfd, POLLIN};
+
+    use super::unix::restart_interrupted;
+    use crate::error::{_generate_os_error, _generate_timeout_error};
+
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    // The pipe is also reported when it is closed or broken, the read then returns 0 or the error
+    let mut pollfd = pollfd {
+        fd: pipe.as_raw_fd(),
+        events: POLLIN,
+        revents: 0,
+    };
+    restart_interrupted(timeout, |remaining| {
+        // Round the timeout up so that the poll never returns before it expires
+        let timeout_ms = remaining
+            .as_nanos()
+            .div_ceil(1_000_000)
+            .min(c_int::MAX as u128) as c_int;
+        match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+            -1 => _generate_os_error(),
+            0 => _generate_timeout_error(),
+            _ => Ok(()),
+        }
+    })?;
+    pipe.read(buf)
+}
+
+#[cfg(windows)]
+fn read_pipe_timeout(
+    pipe: &mut (impl Read + std::os::windows::io::AsRawHandle),
+    buf: &mut [u8],
+    timeout: Duration,
+) -> io::Result<usize> {
+    use std::ptr;
+    use std::thread;
+
+    use super::win32::{PeekNamedPipe, DWORD, ERROR_BROKEN_PIPE, HANDLE};
+
+    use super::{deadline_after, remaining_until};
+    use crate::error::_generate_timeout_error;
+
+    const MAX_INTERVAL: Duration = Duration::from_millis(50);
+
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    let deadline = deadline_after(timeout);
+    let mut interval = Duration::from_millis(1);
+    loop {
+        let mut available: DWORD = 0;
+        let peeked = unsafe {
+            PeekNamedPipe(
+                pipe.as_raw_handle() as HANDLE,
+                ptr::null_mut(),
+                0,
+                ptr::null_mut(),
+                &mut available,
+                ptr::null_mut(),
+            )
+        };
+        if peeked == 0 {
+            let error = io::Error::last_os_error();
+            // Every write end has been closed, as reported by a read at the end of the pipe
+            if error.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32) {
+                return Ok(0);
+            }
+            return Err(error);
+        }
+
+        // Reading only the available data never blocks
+        if available > 0 {
+            let len = buf.len().min(available as usize);
+            return pipe.read(&mut buf[..len]);
+        }
+
+        let remaining = remaining_until(deadline);
+        if remaining.is_zero() {
+            return _generate_timeout_error();
+        }
+        thread::sleep(interval.min(remaining));
+        interval = (interval * 2).min(MAX_INTERVAL);
+    }
+}
@@ -0,0 +1,294 @@
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use self::timer::SharedCallback;
+use super::deadline_after;
+use crate::error::{_generate_default_error, _generate_timeout_error};
+
+#[cfg(windows)]
+#[path = "reaper_windows.rs"]
+mod poller;
+
+#[cfg(any(
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+))]
+#[path = "reaper_kqueue.rs"]
+mod poller;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[path = "reaper_epoll.rs"]
+mod poller;
+
+#[cfg(all(
+    unix,
+    not(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "linux",
+        target_os = "android"
+    ))
+))]
+#[path = "reaper_thread.rs"]
+mod poller;
+
+#[path = "reaper_timer.rs"]
+mod timer;
+
+// Called with the exit status of a child once it has been reaped
+pub(crate) type ExitCallback = Box<dyn FnOnce(io::Result<ExitStatus>) + Send>;
+
+// A child handed over to the reaper, with the callback its exit status is passed to
+pub(crate) struct Registration {
+    pub(crate) child: Child,
+    pub(crate) notify: ExitCallback,
+}
+
+impl Registration {
+    // Reaps the child, which must have exited, and passes its exit status to the callback
+    pub(crate) fn reap(mut self) {
+        let status = self.child.wait();
+        (self.notify)(status);
+    }
+}
+
+/// A background reaper waiting on many children from a single thread.
+///
+/// Children handed over with [`Reaper::watch`] are owned by the reaper, which reaps them as they exit and sends
+/// their exit status to the returned [`ExitReceiver`]. Waiting on a large number of children this way does not
+/// block a thread per child, as the `thread` backend does.
+///
+/// The reaper waits on pidfds with `epoll` on Linux, on `EVFILT_PROC` with `kqueue` on macOS and the BSDs, and
+/// registers its waits with the system thread pool on Windows. On Linux systems where `pidfd_open` is unavailable,
+/// and on other Unix systems, a thread is spawned per child.
+///
+/// This type is only available with the `reaper` feature.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::Reaper;
+///
+/// let reaper = Reaper::global()?;
+/// let receivers = (0..10)
+///     .map(|_| reaper.watch(Command::new("sleep").arg("1").spawn()?))
+///     .collect::<Result<Vec<_>, _>>()?;
+///
+/// for receiver in receivers {
+///     let status = receiver.wait_timeout(Duration::from_secs(5))?;
+///     println!("child {} exited with {}", receiver.id(), status);
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Reaper {
+    poller: poller::Poller,
+    timer: timer::Timer,
+}
+
+impl Reaper {
+    /// Returns the reaper shared by the whole process, starting it on first use.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the reaper could not be started, in which case the next call tries again.
+    pub fn global() -> io::Result<&'static Reaper> {
+        static REAPER: OnceLock<Reaper> = OnceLock::new();
+
+        if let Some(reaper) = REAPER.get() {
+            return Ok(reaper);
+        }
+        let reaper = Reaper {
+            poller: poller::Poller::new()?,
+            timer: timer::Timer::default(),
+        };
+        // Another thread may have started a reaper in the meantime, which is kept
+        Ok(REAPER.get_or_init(|| reaper))
+    }
+
+    /// Hands the child over to the reaper and returns the receiver of its exit status.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the child could not be registered, in which case it is dropped without being waited on.
+    pub fn watch(&self, child: Child) -> io::Result<ExitReceiver> {
+        let (sender, receiver) = mpsc::channel();
+        let id = child.id();
+        self.poller.watch(Registration {
+            child,
+            // The receiver may have been dropped, the child is reaped anyway
+            notify: Box::new(move |status| drop(sender.send(status))),
+        })?;
+        Ok(ExitReceiver { id, receiver })
+    }
+
+    /// Hands the child over to the reaper and returns a plain channel receiving its exit status.
+    ///
+    /// The receiver can be waited on with `recv_timeout` or alongside the other channels of a threaded
+    /// application. If the child could not be reaped, the channel is disconnected without receiving
+    /// anything, see [`Reaper::watch`] to receive the error instead.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the child could not be registered, in which case it is dropped without being waited on.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::Reaper;
+    ///
+    /// let child = Command::new("sleep").arg("1").spawn()?;
+    /// let exits = Reaper::global()?.exit_notifier(child)?;
+    /// let status = exits.recv_timeout(Duration::from_secs(5))?;
+    /// assert!(status.success());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn exit_notifier(&self, child: Child) -> io::Result<Receiver<ExitStatus>> {
+        let (sender, receiver) = mpsc::channel();
+        self.poller.watch(Registration {
+            child,
+            notify: Box::new(move |status| {
+                if let Ok(status) = status {
+                    // The receiver may have been dropped, the child is reaped anyway
+                    let _ = sender.send(status);
+                }
+            }),
+        })?;
+        Ok(receiver)
+    }
+
+    /// Hands the child over to the reaper and calls the callback once it exits or the timeout expires.
+    ///
+    /// The callback is called once, with the exit status of the child, or with an error of kind
+    /// `ErrorKind::TimedOut` if the timeout expires first. In the latter case, the child is left running
+    /// and is still reaped by the reaper once it exits, so that fire-and-forget children do not become
+    /// zombies.
+    ///
+    /// The callback is called from a thread of the reaper, and should return quickly so that the exits of
+    /// the other children are not delayed.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the child could not be registered, in which case it is dropped without being waited on
+    /// and the callback is never called.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::Reaper;
+    ///
+    /// let child = Command::new("sleep").arg("1").spawn()?;
+    /// Reaper::global()?.on_exit(child, Duration::from_secs(5), |status| match status {
+    ///     Ok(status) => println!("exited with {}", status),
+    ///     Err(e) => println!("still running: {}", e),
+    /// })?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn on_exit<F>(&self, child: Child, timeout: Duration, callback: F) -> io::Result<()>
+    where
+        F: FnOnce(io::Result<ExitStatus>) + Send + 'static,
+    {
+        let callback: SharedCallback = Arc::new(Mutex::new(Some(Box::new(callback))));
+        let timeout = match deadline_after(timeout) {
+            Some(deadline) => Some(self.timer.add(deadline, callback.clone())?),
+            None => None,
+        };
+
+        let timer = self.timer.clone();
+        let notify = move |status| {
+            if let Some(key) = timeout {
+                timer.remove(key);
+            }
+            let callback = callback.lock().unwrap_or_else(|e| e.into_inner()).take();
+            // The callback has already been called if the timeout expired
+            if let Some(callback) = callback {
+                callback(status);
+            }
+        };
+        let result = self.poller.watch(Registration {
+            child,
+            notify: Box::new(notify),
+        });
+        if let (Err(_), Some(key)) = (&result, timeout) {
+            self.timer.remove(key);
+        }
+        result
+    }
+}
+
+/// The receiving end of the exit status of a child watched by a [`Reaper`].
+///
+/// This type is only available with the `reaper` feature.
+#[derive(Debug)]
+pub struct ExitReceiver {
+    id: u32,
+    receiver: Receiver<io::Result<ExitStatus>>,
+}
+
+impl ExitReceiver {
+    /// Returns the OS-assigned process identifier of the child.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the exit status of the child if it has been reaped, without blocking.
+    pub fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        match self.receiver.try_recv() {
+            Ok(result) => result.map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => _generate_default_error(),
+        }
+    }
+
+    /// Waits for the child to be reaped and returns its exit status.
+    pub fn wait(&self) -> io::Result<ExitStatus> {
+        self.receiver
+            .recv()
+            .unwrap_or_else(|_| _generate_default_error())
+    }
+
+    /// Waits for the child to be reaped with a timeout and returns its exit status.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ErrorKind::TimedOut` if the child has not exited before the timeout.
+    pub fn wait_timeout(&self, timeout: Duration) -> io::Result<ExitStatus> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => _generate_timeout_error(),
+            Err(RecvTimeoutError::Disconnected) => _generate_default_error(),
+        }
+    }
+
+    /// Returns the underlying channel, to be used with other channel-based code.
+    pub fn into_receiver(self) -> Receiver<io::Result<ExitStatus>> {
+        self.receiver
+    }
+}
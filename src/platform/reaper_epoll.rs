@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+use libc::{epoll_event, EFD_CLOEXEC, EFD_NONBLOCK, EINTR, EPOLLIN, EPOLL_CLOEXEC, EPOLL_CTL_ADD};
+
+use super::Registration;
+use crate::error::_generate_os_error;
+use crate::platform::unix::pidfd_open;
+
+// Key of the eventfd waking up the reaper thread when children are registered
+const WAKE_KEY: u64 = u64::MAX;
+
+// Hands registrations over to a thread waiting on the pidfds of the children with epoll
+#[derive(Debug)]
+pub(crate) struct Poller {
+    registrations: Mutex<Sender<Registration>>,
+    wake: OwnedFd,
+}
+
+impl Poller {
+    pub(crate) fn new() -> io::Result<Self> {
+        let epoll = unsafe { libc::epoll_create1(EPOLL_CLOEXEC) };
+        if epoll == -1 {
+            return _generate_os_error();
+        }
+        let epoll = unsafe { OwnedFd::from_raw_fd(epoll) };
+
+        let wake = unsafe { libc::eventfd(0, EFD_CLOEXEC | EFD_NONBLOCK) };
+        if wake == -1 {
+            return _generate_os_error();
+        }
+        let wake = unsafe { OwnedFd::from_raw_fd(wake) };
+        add(&epoll, wake.as_raw_fd(), WAKE_KEY)?;
+
+        let (sender, receiver) = mpsc::channel();
+        let thread_wake = wake.try_clone()?;
+        thread::Builder::new()
+            .name("child-reaper".into())
+            .spawn(move || run(epoll, thread_wake, receiver))?;
+
+        Ok(Poller {
+            registrations: Mutex::new(sender),
+            wake,
+        })
+    }
+
+    pub(crate) fn watch(&self, registration: Registration) -> io::Result<()> {
+        let sender = self.registrations.lock().unwrap_or_else(|e| e.into_inner());
+        // The thread never exits, the registration cannot be returned
+        let _ = sender.send(registration);
+
+        let value: u64 = 1;
+        let result = unsafe {
+            libc::write(
+                self.wake.as_raw_fd(),
+                &value as *const u64 as *const libc::c_void,
+                mem::size_of::<u64>(),
+            )
+        };
+        // The counter only overflows if the thread is already due to wake up
+        if result == -1 && io::Error::last_os_error().raw_os_error() != Some(libc::EAGAIN) {
+            return _generate_os_error();
+        }
+        Ok(())
+    }
+}
+
+// Waits for the children to exit, registering new ones whenever the eventfd is signaled
+fn run(epoll: OwnedFd, wake: OwnedFd, registrations: Receiver<Registration>) {
+    let mut watched: HashMap<u64, (OwnedFd, Registration)> = HashMap::new();
+    let mut next_key = 0;
+    let mut events: [epoll_event; 64] = unsafe { mem::zeroed() };
+
+    loop {
+        let count = unsafe {
+            libc::epoll_wait(
+                epoll.as_raw_fd(),
+                events.as_mut_ptr(),
+                events.len() as libc::c_int,
+                -1,
+            )
+        };
+        if count == -1 {
+            // Interrupted by a signal, any other error leaves nothing to retry
+            if io::Error::last_os_error().raw_os_error() == Some(EINTR) {
+                continue;
+            }
+            return;
+        }
+
+        for event in &events[..count as usize] {
+            let key = event.u64;
+            if key != WAKE_KEY {
+                // Closing the pidfd removes it from the epoll set
+                if let Some((_pidfd, registration)) = watched.remove(&key) {
+                    registration.reap();
+                }
+                continue;
+            }
+
+            let mut value: u64 = 0;
+            unsafe {
+                libc::read(
+                    wake.as_raw_fd(),
+                    &mut value as *mut u64 as *mut libc::c_void,
+                    mem::size_of::<u64>(),
+                )
+            };
+            for registration in registrations.try_iter() {
+                match open_pidfd(&epoll, &registration, next_key) {
+                    Some(pidfd) => {
+                        watched.insert(next_key, (pidfd, registration));
+                        next_key += 1;
+                    }
+                    // pidfd_open is unavailable or failed, the child is waited on by its own thread
+                    None => {
+                        thread::spawn(move || registration.reap());
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Opens the pidfd of the child and adds it to the epoll set, `None` if either fails
+fn open_pidfd(epoll: &OwnedFd, registration: &Registration, key: u64) -> Option<OwnedFd> {
+    let pidfd = pidfd_open(registration.child.id() as libc::pid_t, 0).ok()??;
+    let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd) };
+    add(epoll, pidfd.as_raw_fd(), key).ok()?;
+    Some(pidfd)
+}
+
+// Adds the file descriptor to the epoll set, reported with the given key when readable
+fn add(epoll: &OwnedFd, fd: RawFd, key: u64) -> io::Result<()> {
+    let mut event = epoll_event {
+        events: EPOLLIN as u32,
+        u64: key,
+    };
+    if unsafe { libc::epoll_ctl(epoll.as_raw_fd(), EPOLL_CTL_ADD, fd, &mut event) } == -1 {
+        return _generate_os_error();
+    }
+    Ok(())
+}
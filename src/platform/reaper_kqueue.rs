@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::ptr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+use libc::{kevent, pid_t, EINTR, ESRCH, EVFILT_PROC, EVFILT_READ, EV_ADD, EV_ONESHOT, NOTE_EXIT};
+
+use super::Registration;
+use crate::error::_generate_os_error;
+use crate::platform::unix::has_exited;
+
+// Hands registrations over to a thread waiting on the exits of the children with kqueue
+#[derive(Debug)]
+pub(crate) struct Poller {
+    registrations: Mutex<Sender<Registration>>,
+    // Write end of the pipe waking up the thread when children are registered
+    wake: OwnedFd,
+}
+
+impl Poller {
+    pub(crate) fn new() -> io::Result<Self> {
+        let kq = unsafe { libc::kqueue() };
+        if kq == -1 {
+            return _generate_os_error();
+        }
+        let kq = unsafe { OwnedFd::from_raw_fd(kq) };
+
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            return _generate_os_error();
+        }
+        let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        for fd in [&read, &write] {
+            unsafe {
+                libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC);
+                libc::fcntl(fd.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK);
+            }
+        }
+
+        let mut change: libc::kevent = unsafe { mem::zeroed() };
+        change.ident = read.as_raw_fd() as _;
+        change.filter = EVFILT_READ;
+        change.flags = EV_ADD;
+        register(&kq, &change)?;
+
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("child-reaper".into())
+            .spawn(move || run(kq, read, receiver))?;
+
+        Ok(Poller {
+            registrations: Mutex::new(sender),
+            wake: write,
+        })
+    }
+
+    pub(crate) fn watch(&self, registration: Registration) -> io::Result<()> {
+        let sender = self.registrations.lock().unwrap_or_else(|e| e.into_inner());
+        // The thread never exits, the registration cannot be returned
+        let _ = sender.send(registration);
+
+        let byte = 0u8;
+        let result = unsafe { libc::write(self.wake.as_raw_fd(), &byte as *const u8 as _, 1) };
+        // A full pipe already wakes up the thread
+        if result == -1 && io::Error::last_os_error().raw_os_error() != Some(libc::EAGAIN) {
+            return _generate_os_error();
+        }
+        Ok(())
+    }
+}
+
+// Waits for the children to exit, registering new ones whenever the pipe is readable
+fn run(kq: OwnedFd, wake: OwnedFd, registrations: Receiver<Registration>) {
+    let mut watched: HashMap<pid_t, Registration> = HashMap::new();
+    let mut events: [libc::kevent; 64] = unsafe { mem::zeroed() };
+
+    loop {
+        let count = unsafe {
+            kevent(
+                kq.as_raw_fd(),
+                ptr::null(),
+                0,
+                events.as_mut_ptr(),
+                events.len() as _,
+                ptr::null(),
+            )
+        };
+        if count == -1 {
+            // Interrupted by a signal, any other error leaves nothing to retry
+            if io::Error::last_os_error().raw_os_error() == Some(EINTR) {
+                continue;
+            }
+            return;
+        }
+
+        for event in &events[..count as usize] {
+            if event.filter == EVFILT_PROC {
+                if let Some(registration) = watched.remove(&(event.ident as pid_t)) {
+                    registration.reap();
+                }
+                continue;
+            }
+
+            let mut buffer = [0u8; 64];
+            while unsafe { libc::read(wake.as_raw_fd(), buffer.as_mut_ptr() as _, buffer.len()) }
+                > 0
+            {}
+            for registration in registrations.try_iter() {
+                let pid = registration.child.id() as pid_t;
+                let mut change: libc::kevent = unsafe { mem::zeroed() };
+                change.ident = pid as _;
+                change.filter = EVFILT_PROC;
+                change.flags = EV_ADD | EV_ONESHOT;
+                change.fflags = NOTE_EXIT;
+
+                match register(&kq, &change) {
+                    Ok(()) => {
+                        watched.insert(pid, registration);
+                    }
+                    // The child may have already exited before the registration
+                    Err(e) if e.raw_os_error() == Some(ESRCH) && has_exited(pid) => {
+                        registration.reap();
+                    }
+                    // The child is waited on by its own thread
+                    Err(_) => {
+                        thread::spawn(move || registration.reap());
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Applies the change to the kqueue, registration errors are returned by the call
+fn register(kq: &OwnedFd, change: &libc::kevent) -> io::Result<()> {
+    let result = unsafe { kevent(kq.as_raw_fd(), change, 1, ptr::null_mut(), 0, ptr::null()) };
+    if result == -1 {
+        return _generate_os_error();
+    }
+    Ok(())
+}
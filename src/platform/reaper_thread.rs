@@ -0,0 +1,21 @@
+use std::io;
+use std::thread;
+
+use super::Registration;
+
+// Waits on each child from its own thread, on systems without a way to wait on many at once
+#[derive(Debug)]
+pub(crate) struct Poller;
+
+impl Poller {
+    pub(crate) fn new() -> io::Result<Self> {
+        Ok(Poller)
+    }
+
+    pub(crate) fn watch(&self, registration: Registration) -> io::Result<()> {
+        thread::Builder::new()
+            .name("child-reaper".into())
+            .spawn(move || registration.reap())?;
+        Ok(())
+    }
+}
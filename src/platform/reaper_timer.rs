@@ -0,0 +1,119 @@
+// The timeouts of the callbacks registered with `Reaper::on_exit`, expired by a single thread
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use super::ExitCallback;
+use crate::error::_generate_timeout_error;
+
+// A callback shared by the reaper and the timer, called by whichever of them takes it first
+pub(crate) type SharedCallback = Arc<Mutex<Option<ExitCallback>>>;
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Timer {
+    state: Arc<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    // The callbacks by deadline, with a sequence number telling apart equal deadlines
+    timeouts: Mutex<Timeouts>,
+    changed: Condvar,
+    // Set once the timer thread has been started
+    started: Mutex<bool>,
+}
+
+#[derive(Default)]
+struct Timeouts {
+    entries: BTreeMap<(Instant, u64), SharedCallback>,
+    next: u64,
+}
+
+impl std::fmt::Debug for Timeouts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Timeouts")
+            .field("pending", &self.entries.len())
+            .finish()
+    }
+}
+
+// Identifies a timeout so that it can be removed once the child has exited
+pub(crate) type TimeoutKey = (Instant, u64);
+
+impl Timer {
+    // Calls the callback with a timeout error at the deadline, unless it has been taken before
+    pub(crate) fn add(
+        &self,
+        deadline: Instant,
+        callback: SharedCallback,
+    ) -> io::Result<TimeoutKey> {
+        self.start()?;
+
+        let mut timeouts = self.state.lock();
+        let key = (deadline, timeouts.next);
+        timeouts.next += 1;
+        timeouts.entries.insert(key, callback);
+        drop(timeouts);
+        self.state.changed.notify_one();
+        Ok(key)
+    }
+
+    // Removes the timeout of a callback which has already been called
+    pub(crate) fn remove(&self, key: TimeoutKey) {
+        self.state.lock().entries.remove(&key);
+    }
+
+    fn start(&self) -> io::Result<()> {
+        let mut started = self.state.started.lock().unwrap_or_else(|e| e.into_inner());
+        if !*started {
+            let state = self.state.clone();
+            thread::Builder::new()
+                .name("child_wait_timeout_timer".to_string())
+                .spawn(move || run(&state))?;
+            *started = true;
+        }
+        Ok(())
+    }
+}
+
+impl State {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Timeouts> {
+        self.timeouts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Calls the callbacks whose deadline has passed, sleeping until the next one
+fn run(state: &State) {
+    let mut timeouts = state.lock();
+    loop {
+        let now = Instant::now();
+        match timeouts.entries.first_key_value() {
+            Some((&(deadline, _), _)) if deadline <= now => {
+                let (_, callback) = timeouts.entries.pop_first().expect("the entry exists");
+                // The callback is called without the lock, so that it can register other children
+                drop(timeouts);
+                let callback = callback.lock().unwrap_or_else(|e| e.into_inner()).take();
+                if let Some(callback) = callback {
+                    callback(_generate_timeout_error());
+                }
+                timeouts = state.lock();
+            }
+            Some((&(deadline, _), _)) => {
+                timeouts = state
+                    .changed
+                    .wait_timeout(timeouts, deadline - now)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+            }
+            None => {
+                timeouts = state
+                    .changed
+                    .wait(timeouts)
+                    .unwrap_or_else(|e| e.into_inner());
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::io;
+use std::os::windows::io::AsRawHandle;
+use std::sync::{Arc, Mutex};
+
+use super::Registration;
+use crate::error::_generate_os_error;
+use crate::platform::win32::{
+    RegisterWaitForSingleObject, UnregisterWait, BOOLEAN, HANDLE, INFINITE, PVOID,
+    WT_EXECUTEONLYONCE,
+};
+
+// Registers a wait on each child with the system thread pool, which waits on many handles per thread
+#[derive(Debug)]
+pub(crate) struct Poller;
+
+// Shared with the callback of the wait
+struct Context {
+    registration: Mutex<Option<Registration>>,
+    // Handle of the registered wait, stored while the lock is held by `watch`
+    wait: Mutex<usize>,
+}
+
+impl Poller {
+    pub(crate) fn new() -> io::Result<Self> {
+        Ok(Poller)
+    }
+
+    pub(crate) fn watch(&self, registration: Registration) -> io::Result<()> {
+        let process = registration.child.as_raw_handle() as HANDLE;
+        let context = Arc::new(Context {
+            registration: Mutex::new(Some(registration)),
+            wait: Mutex::new(0),
+        });
+
+        // The callback may run before the handle of the wait is stored, it waits for the lock
+        let mut wait = context.wait.lock().unwrap_or_else(|e| e.into_inner());
+        let raw = Arc::into_raw(context.clone());
+        let mut handle: HANDLE = std::ptr::null_mut();
+        let registered = unsafe {
+            RegisterWaitForSingleObject(
+                &mut handle,
+                process,
+                Some(reap),
+                raw as PVOID,
+                INFINITE,
+                WT_EXECUTEONLYONCE,
+            )
+        };
+        if registered == 0 {
+            let error = _generate_os_error();
+            drop(unsafe { Arc::from_raw(raw) });
+            return error;
+        }
+        *wait = handle as usize;
+        Ok(())
+    }
+}
+
+// Called by the thread pool once the child has exited
+unsafe extern "system" fn reap(context: PVOID, _timed_out: BOOLEAN) {
+    let context = unsafe { Arc::from_raw(context as *const Context) };
+
+    let wait = *context.wait.lock().unwrap_or_else(|e| e.into_inner());
+    // Does not block, the wait is released once the callback returns
+    unsafe { UnregisterWait(wait as HANDLE) };
+
+    let registration = context
+        .registration
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take();
+    if let Some(registration) = registration {
+        registration.reap();
+    }
+}
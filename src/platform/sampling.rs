@@ -0,0 +1,172 @@
+// Samples the resources consumed by a running child, to enforce the limits which are not a wall-clock timeout
+
+use std::io;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use super::ChildWT;
+use crate::error::_generate_timeout_error;
+
+// Bounds the wall-clock time between two samples, a child running several threads may consume more CPU
+// time than the wall-clock time elapsed
+pub(crate) const MIN_INTERVAL: Duration = Duration::from_millis(1);
+pub(crate) const MAX_INTERVAL: Duration = Duration::from_millis(100);
+
+// The time between two samples of the resident memory of a child
+pub(crate) const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+
+// Waits for the child to exit or until it has consumed `limit` of CPU time
+pub(crate) fn wait_cpu_timeout<C: ChildWT + ?Sized>(
+    child: &mut C,
+    pid: u32,
+    limit: Duration,
+) -> io::Result<ExitStatus> {
+    loop {
+        let used = match cpu_time(pid) {
+            Ok(used) => used,
+            // The child may have exited and been reaped in the meantime, then its status is returned
+            Err(e) => return child.wait_timeout(Duration::ZERO).map_err(|_| e),
+        };
+        let Some(remaining) = limit
+            .checked_sub(used)
+            .filter(|remaining| !remaining.is_zero())
+        else {
+            return _generate_timeout_error();
+        };
+
+        // A single thread cannot consume the remaining CPU time in less wall-clock time
+        match child.wait_timeout(remaining.clamp(MIN_INTERVAL, MAX_INTERVAL)) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+            result => return result,
+        }
+    }
+}
+
+// Returns the user and system CPU time consumed by the process, from `/proc/<pid>/stat`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn cpu_time(pid: u32) -> io::Result<Duration> {
+    use std::fs;
+
+    use crate::error::_generate_default_error;
+
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid))?;
+    // The name of the command may contain spaces and parentheses, the fields follow the last one
+    let Some(fields) = stat.rsplit_once(')').map(|(_, fields)| fields) else {
+        return _generate_default_error();
+    };
+    // `utime` and `stime` are the 14th and 15th fields, the first one after the name being the 3rd
+    let mut ticks = fields.split_whitespace().skip(11).take(2);
+    let (Some(utime), Some(stime)) = (ticks.next(), ticks.next()) else {
+        return _generate_default_error();
+    };
+    let ticks = utime.parse::<u64>().unwrap_or(0) + stime.parse::<u64>().unwrap_or(0);
+
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_second <= 0 {
+        return _generate_default_error();
+    }
+    Ok(Duration::from_nanos(
+        (ticks as u128 * 1_000_000_000 / ticks_per_second as u128) as u64,
+    ))
+}
+
+// Returns the user and kernel CPU time consumed by the process, from `GetProcessTimes`
+#[cfg(windows)]
+pub(crate) fn cpu_time(pid: u32) -> io::Result<Duration> {
+    use std::mem;
+
+    use super::win32::{
+        CloseHandle, GetProcessTimes, OpenProcess, FALSE, FILETIME,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    use crate::error::_generate_os_error;
+
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid) };
+    if process.is_null() {
+        return _generate_os_error();
+    }
+
+    let mut times: [FILETIME; 4] = unsafe { mem::zeroed() };
+    let [creation, exit, kernel, user] = &mut times;
+    let result = unsafe { GetProcessTimes(process, creation, exit, kernel, user) };
+    let error = io::Error::last_os_error();
+    unsafe { CloseHandle(process) };
+    if result == 0 {
+        return Err(error);
+    }
+
+    // The times are counted in 100 nanoseconds intervals
+    let intervals =
+        |time: &FILETIME| (time.dwHighDateTime as u64) << 32 | time.dwLowDateTime as u64;
+    Ok(Duration::from_nanos(
+        (intervals(kernel) + intervals(user)).saturating_mul(100),
+    ))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+pub(crate) fn cpu_time(_pid: u32) -> io::Result<Duration> {
+    crate::error::_generate_unsupported_error()
+}
+
+// Returns the resident set size of the process in bytes, from `/proc/<pid>/statm`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn resident_memory(pid: u32) -> io::Result<u64> {
+    use std::fs;
+
+    use crate::error::_generate_default_error;
+
+    let statm = fs::read_to_string(format!("/proc/{}/statm", pid))?;
+    // The resident pages are the 2nd field, after the total size of the process
+    let Some(pages) = statm
+        .split_whitespace()
+        .nth(1)
+        .and_then(|pages| pages.parse::<u64>().ok())
+    else {
+        return _generate_default_error();
+    };
+
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        return _generate_default_error();
+    }
+    Ok(pages.saturating_mul(page_size as u64))
+}
+
+// Returns the working set size of the process in bytes, from `GetProcessMemoryInfo`
+#[cfg(windows)]
+pub(crate) fn resident_memory(pid: u32) -> io::Result<u64> {
+    use std::mem;
+
+    use super::win32::{
+        CloseHandle, GetProcessMemoryInfo, OpenProcess, DWORD, FALSE, PROCESS_MEMORY_COUNTERS,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    use crate::error::_generate_os_error;
+
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid) };
+    if process.is_null() {
+        return _generate_os_error();
+    }
+
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { mem::zeroed() };
+    let result = unsafe {
+        GetProcessMemoryInfo(
+            process,
+            &mut counters,
+            mem::size_of::<PROCESS_MEMORY_COUNTERS>() as DWORD,
+        )
+    };
+    let error = io::Error::last_os_error();
+    unsafe { CloseHandle(process) };
+    if result == 0 {
+        return Err(error);
+    }
+    Ok(counters.WorkingSetSize as u64)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+pub(crate) fn resident_memory(_pid: u32) -> io::Result<u64> {
+    crate::error::_generate_unsupported_error()
+}
@@ -0,0 +1,192 @@
+use std::io;
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use super::{deadline_after, imp, remaining_until, try_wait_untraced};
+use crate::error::_generate_timeout_error;
+
+/// A child process which can be waited on and killed from several threads at once.
+///
+/// [`SharedChild::wait_timeout`] takes `&self`, so that one thread can wait for the child while another
+/// kills it, for instance from a signal handler thread or a watchdog. The wait is done by the backend
+/// without holding any lock, and the child is only reaped under the lock also taken by
+/// [`SharedChild::kill`]. A child which has been reaped is never signaled, so the kill cannot reach
+/// another process reusing its pid.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::process::Command;
+/// use std::sync::Arc;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::SharedChild;
+///
+/// let child = Arc::new(SharedChild::spawn(Command::new("sleep").arg("10"))?);
+/// let waiter = {
+///     let child = Arc::clone(&child);
+///     thread::spawn(move || child.wait_timeout(Duration::from_secs(5)))
+/// };
+/// child.kill()?;
+/// assert!(!waiter.join().unwrap()?.success());
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SharedChild {
+    child: Mutex<Child>,
+    id: u32,
+}
+
+impl SharedChild {
+    /// Wraps a child process to share it between threads.
+    pub fn new(child: Child) -> Self {
+        let id = child.id();
+        SharedChild {
+            child: Mutex::new(child),
+            id,
+        }
+    }
+
+    /// Spawns the command and wraps the child process.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the command could not be spawned, see [`Command::spawn`].
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        command.spawn().map(Self::new)
+    }
+
+    /// Returns the OS-assigned process identifier of the child process.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Waits for the child process to exit or until the timeout expires.
+    ///
+    /// Several threads can wait at the same time, they all receive the exit status. A zero timeout
+    /// only checks the child process once, like [`SharedChild::try_wait`].
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process exits.
+    pub fn wait_timeout(&self, timeout: Duration) -> io::Result<ExitStatus> {
+        let deadline = deadline_after(timeout);
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+
+            let remaining = remaining_until(deadline);
+            if remaining.is_zero() {
+                return _generate_timeout_error();
+            }
+            match self.wait_untraced(remaining) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => return Err(e),
+                // Another thread may have reaped the child in the meantime
+                Err(e) => return self.try_wait()?.ok_or(e),
+                Ok(()) => {}
+            }
+        }
+    }
+
+    /// Waits for the child process to exit, without timeout.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the underlying system calls fail.
+    pub fn wait(&self) -> io::Result<ExitStatus> {
+        self.wait_timeout(Duration::MAX)
+    }
+
+    /// Returns the exit status if the child process has exited, without blocking.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the underlying system calls fail, see [`Child::try_wait`].
+    pub fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        try_wait_untraced(&mut self.lock())
+    }
+
+    /// Kills the child process, unless it has already been reaped.
+    ///
+    /// The pending and future waits return the exit status of the killed child process.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the child process could not be killed, see [`Child::kill`].
+    pub fn kill(&self) -> io::Result<()> {
+        let mut child = self.lock();
+        // Reaping only happens under the lock, so the pid still belongs to the child
+        match try_wait_untraced(&mut child)? {
+            Some(_) => Ok(()),
+            None => child.kill(),
+        }
+    }
+
+    /// Takes the handle to the standard input of the child process, if it was piped and not taken yet.
+    pub fn take_stdin(&self) -> Option<ChildStdin> {
+        self.lock().stdin.take()
+    }
+
+    /// Takes the handle to the standard output of the child process, if it was piped and not taken yet.
+    pub fn take_stdout(&self) -> Option<ChildStdout> {
+        self.lock().stdout.take()
+    }
+
+    /// Takes the handle to the standard error of the child process, if it was piped and not taken yet.
+    pub fn take_stderr(&self) -> Option<ChildStderr> {
+        self.lock().stderr.take()
+    }
+
+    /// Consumes the wrapper and returns the child process.
+    pub fn into_inner(self) -> Child {
+        self.child.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Child> {
+        self.child.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Waits for the child to exit with the backend, without reaping it
+    #[cfg(all(
+        unix,
+        not(any(target_os = "redox", target_os = "haiku", target_os = "fuchsia"))
+    ))]
+    fn wait_untraced(&self, timeout: Duration) -> io::Result<()> {
+        imp::_wait_pid_untraced(self.id, timeout)
+    }
+
+    // The handle stays valid while the child is owned, the lock is released before waiting
+    #[cfg(windows)]
+    fn wait_untraced(&self, timeout: Duration) -> io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+
+        use super::win32::HANDLE;
+
+        let handle = self.lock().as_raw_handle() as HANDLE;
+        imp::_wait_handle_untraced(handle, timeout)
+    }
+
+    // The child is reaped by the polling backend, the lock is only held while checking it
+    #[cfg(any(not(any(unix, windows)), target_os = "redox", target_os = "haiku"))]
+    fn wait_untraced(&self, timeout: Duration) -> io::Result<()> {
+        imp::_poll(timeout, || self.lock().try_wait()).map(drop)
+    }
+
+    // The handle is the id of the child, and stays valid while the child is owned
+    #[cfg(target_os = "fuchsia")]
+    fn wait_untraced(&self, timeout: Duration) -> io::Result<()> {
+        imp::_wait_handle_untraced(self.id, timeout)
+    }
+}
+
+impl From<Child> for SharedChild {
+    fn from(child: Child) -> Self {
+        SharedChild::new(child)
+    }
+}
@@ -0,0 +1,45 @@
+use std::io;
+
+use crate::error::_generate_os_error;
+
+/// Sets whether the current process is a child subreaper, with `prctl(PR_SET_CHILD_SUBREAPER)`.
+///
+/// The descendants of a subreaper which are orphaned, such as the processes spawned by a child killed on
+/// timeout, are re-parented to it instead of `init`. They can then be waited for and reaped by the
+/// current process, see [`ProcessGroup::spawn_subreaper`](super::ProcessGroup::spawn_subreaper), and no
+/// longer escape its supervision. The attribute applies to the whole process, and orphans which are
+/// not reaped remain zombies until it exits.
+///
+/// This function is only available on Linux and Android.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use child_wait_timeout::{is_child_subreaper, set_child_subreaper};
+///
+/// set_child_subreaper(true)?;
+/// assert!(is_child_subreaper()?);
+/// #     Ok(())
+/// # }
+/// ```
+pub fn set_child_subreaper(enabled: bool) -> io::Result<()> {
+    let enabled = libc::c_ulong::from(enabled);
+    if unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, enabled, 0, 0, 0) } == -1 {
+        return _generate_os_error();
+    }
+    Ok(())
+}
+
+/// Returns whether the current process is a child subreaper, with `prctl(PR_GET_CHILD_SUBREAPER)`.
+///
+/// This function is only available on Linux and Android.
+pub fn is_child_subreaper() -> io::Result<bool> {
+    let mut enabled: libc::c_int = 0;
+    let enabled_ptr = &mut enabled as *mut libc::c_int;
+    if unsafe { libc::prctl(libc::PR_GET_CHILD_SUBREAPER, enabled_ptr) } == -1 {
+        return _generate_os_error();
+    }
+    Ok(enabled != 0)
+}
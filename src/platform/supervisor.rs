@@ -0,0 +1,318 @@
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+use super::{
+    deadline_after, kill_on_timeout, kill_tree, remaining_until, terminate, trace, ChildWT, WaitSet,
+};
+
+/// When a [`Supervisor`] restarts a service after its process exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// The service is restarted whatever its exit status.
+    Always,
+    /// The service is restarted if it exits unsuccessfully or is killed at its timeout.
+    #[default]
+    OnFailure,
+    /// The service is never restarted.
+    Never,
+}
+
+/// A command run by a [`Supervisor`], with its restart policy.
+///
+/// By default, the service is restarted on failure, without a timeout nor a restart limit.
+#[derive(Debug)]
+pub struct Service {
+    command: Command,
+    policy: RestartPolicy,
+    timeout: Option<Duration>,
+    max_restarts: Option<u32>,
+}
+
+impl Service {
+    /// Creates a service running the command.
+    pub fn new(command: Command) -> Self {
+        Service {
+            command,
+            policy: RestartPolicy::default(),
+            timeout: None,
+            max_restarts: None,
+        }
+    }
+
+    /// Sets when the service is restarted.
+    pub fn restart(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the time each start of the service may run before its process tree is killed.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the number of times the service may be restarted.
+    pub fn max_restarts(mut self, max_restarts: u32) -> Self {
+        self.max_restarts = Some(max_restarts);
+        self
+    }
+}
+
+/// The exit of a service, returned by [`Supervisor::wait_next`].
+#[derive(Debug, Clone, Copy)]
+pub struct ServiceExit {
+    key: usize,
+    status: ExitStatus,
+    timed_out: bool,
+    restarted: bool,
+}
+
+impl ServiceExit {
+    /// Returns the key of the service in the supervisor.
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    /// Returns the exit status of the process of the service.
+    pub fn status(&self) -> ExitStatus {
+        self.status
+    }
+
+    /// Returns `true` if the process was killed because its timeout expired.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Returns `true` if the service has been started again.
+    pub fn restarted(&self) -> bool {
+        self.restarted
+    }
+}
+
+/// Runs a set of services, restarting their processes according to their [`RestartPolicy`].
+///
+/// The services are started when they are added. [`Supervisor::wait_next`] waits for the next one to exit,
+/// kills the ones running past their timeout, and restarts them until their restart limit is reached.
+/// The running processes are waited on together with a [`WaitSet`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{RestartPolicy, Service, Supervisor};
+///
+/// let mut supervisor = Supervisor::new();
+/// let mut command = Command::new("sh");
+/// command.args(["-c", "exit 1"]);
+/// supervisor.add(
+///     Service::new(command)
+///         .restart(RestartPolicy::OnFailure)
+///         .timeout(Duration::from_secs(5))
+///         .max_restarts(2),
+/// )?;
+///
+/// while let Some(exit) = supervisor.wait_next(Duration::from_secs(10))? {
+///     println!("service {} exited with {}", exit.key(), exit.status());
+/// }
+/// assert_eq!(supervisor.restarts(0), Some(2));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    services: Vec<Supervised>,
+    children: WaitSet,
+}
+
+// A service along with the state of its current start
+#[derive(Debug)]
+struct Supervised {
+    service: Service,
+    restarts: u32,
+    // The key of the running process in the wait set
+    child: Option<usize>,
+    // The instant at which the running process is killed
+    deadline: Option<Instant>,
+}
+
+impl Supervisor {
+    /// Creates a supervisor without any service.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the service and starts it, returning the key identifying it in the supervisor.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the command could not be spawned, in which case the service is not added.
+    pub fn add(&mut self, service: Service) -> io::Result<usize> {
+        self.services.push(Supervised {
+            service,
+            restarts: 0,
+            child: None,
+            deadline: None,
+        });
+        let key = self.services.len() - 1;
+        if let Err(e) = self.start(key) {
+            self.services.pop();
+            return Err(e);
+        }
+        Ok(key)
+    }
+
+    /// Returns `true` if the process of the service is running.
+    pub fn is_running(&self, key: usize) -> bool {
+        self.services
+            .get(key)
+            .is_some_and(|supervised| supervised.child.is_some())
+    }
+
+    /// Returns the number of times the service has been restarted.
+    pub fn restarts(&self, key: usize) -> Option<u32> {
+        self.services.get(key).map(|supervised| supervised.restarts)
+    }
+
+    /// Waits for the next service to exit or until the timeout expires, restarting it if its policy
+    /// allows it.
+    ///
+    /// A service running past its own timeout has its process tree killed, and is reported as having
+    /// timed out.
+    ///
+    /// # Returns
+    /// - `Some` with the exit of the service.
+    /// - `None` if no service is running anymore.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before any service exits.
+    /// If the command of a service could not be spawned again, the error is returned and the service is
+    /// left stopped.
+    pub fn wait_next(&mut self, timeout: Duration) -> io::Result<Option<ServiceExit>> {
+        let deadline = deadline_after(timeout);
+        loop {
+            // The wait is cut short at the earliest timeout of the running services
+            let expiry = self
+                .services
+                .iter()
+                .filter_map(|supervised| supervised.deadline)
+                .min();
+            let wait = remaining_until(deadline).min(remaining_until(expiry));
+
+            match self.children.wait_next(wait) {
+                Ok(Some((child, _, status))) => {
+                    let key = self.key_of(child);
+                    return self.exited(key, status, false).map(Some);
+                }
+                Ok(None) => return Ok(None),
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    let now = Instant::now();
+                    let expired = self.services.iter().position(|supervised| {
+                        supervised.child.is_some()
+                            && supervised.deadline.is_some_and(|deadline| deadline <= now)
+                    });
+                    if let Some(key) = expired {
+                        let status = self.kill(key)?;
+                        return self.exited(key, status, true).map(Some);
+                    }
+                    if remaining_until(deadline).is_zero() {
+                        return Err(e);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Stops all the services without restarting them.
+    ///
+    /// The processes are asked to terminate, then their process trees are killed if they are still running
+    /// once the grace period expires. The grace period is shared by all the processes.
+    pub fn shutdown(&mut self, grace: Duration) -> io::Result<()> {
+        let mut children = Vec::new();
+        for supervised in &mut self.services {
+            supervised.deadline = None;
+            if let Some(child) = supervised.child.take() {
+                children.extend(self.children.remove(child));
+            }
+        }
+        for child in &children {
+            let _ = terminate(child.id());
+        }
+
+        let deadline = deadline_after(grace);
+        let mut result = Ok(());
+        for mut child in children {
+            let status = child.wait_timeout(remaining_until(deadline));
+            match kill_on_timeout(&mut child, status) {
+                Err(e) if e.kind() != io::ErrorKind::TimedOut && result.is_ok() => result = Err(e),
+                _ => {}
+            }
+        }
+        result
+    }
+
+    // Spawns the command of the service
+    fn start(&mut self, key: usize) -> io::Result<()> {
+        let supervised = &mut self.services[key];
+        let child = supervised.service.command.spawn()?;
+        trace::spawned(child.id(), &supervised.service.command);
+        supervised.child = Some(self.children.insert(child));
+        supervised.deadline = supervised.service.timeout.and_then(deadline_after);
+        Ok(())
+    }
+
+    // Kills the process tree of the service and reaps its process
+    fn kill(&mut self, key: usize) -> io::Result<ExitStatus> {
+        let mut child: Child = self.services[key]
+            .child
+            .and_then(|child| self.children.remove(child))
+            .expect("the service is running");
+        kill_tree(&mut child)?;
+        child.wait()
+    }
+
+    // Records the exit of the service and restarts it if its policy allows it
+    fn exited(
+        &mut self,
+        key: usize,
+        status: ExitStatus,
+        timed_out: bool,
+    ) -> io::Result<ServiceExit> {
+        let supervised = &mut self.services[key];
+        supervised.child = None;
+        supervised.deadline = None;
+
+        let restart = match supervised.service.policy {
+            RestartPolicy::Always => true,
+            RestartPolicy::OnFailure => timed_out || !status.success(),
+            RestartPolicy::Never => false,
+        } && supervised
+            .service
+            .max_restarts
+            .is_none_or(|max_restarts| supervised.restarts < max_restarts);
+        if restart {
+            supervised.restarts += 1;
+            self.start(key)?;
+        }
+
+        Ok(ServiceExit {
+            key,
+            status,
+            timed_out,
+            restarted: restart,
+        })
+    }
+
+    fn key_of(&self, child: usize) -> usize {
+        self.services
+            .iter()
+            .position(|supervised| supervised.child == Some(child))
+            .expect("the child belongs to a service")
+    }
+}
@@ -0,0 +1,145 @@
+//! Portable fake children, to test the timeout handling built on this crate.
+//!
+//! Each helper comes in two forms: a `*_command` function returning a [`Command`] which can be further
+//! configured before being spawned, and a `*_child` function spawning it right away. The standard output
+//! of the children is discarded.
+//!
+//! [`FaultyChild`] injects faults into the waits on a child, such as interruptions, transient failures
+//! and delayed results, to test the error handling built around them.
+//!
+//! This module is only available with the `test-util` feature.
+//!
+//! # Example
+//! ```rust
+//! # use std::error::Error;
+//! #
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! use std::time::Duration;
+//!
+//! use child_wait_timeout::test_util;
+//! use child_wait_timeout::ChildWT;
+//!
+//! let mut child = test_util::sleep_child(Duration::from_secs(10))?;
+//! let error = child.wait_or_kill(Duration::from_millis(100)).unwrap_err();
+//! assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+//!
+//! let mut child = test_util::exit_child(3)?;
+//! assert_eq!(child.wait_timeout(Duration::from_secs(5))?.code(), Some(3));
+//! #     Ok(())
+//! # }
+//! ```
+
+use std::io;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+pub use super::faulty_child::{Fault, FaultyChild};
+
+/// Returns a command sleeping for the duration, then exiting with a zero exit code.
+///
+/// # Platform-Specific Behavior
+///
+/// On Unix, the command runs `sleep` with a fractional number of seconds. On Windows, it runs `ping` on
+/// the loopback address, which sleeps for whole seconds: the duration is rounded up to the next second.
+/// Unlike `timeout.exe`, `ping` does not require a console input.
+pub fn sleep_command(duration: Duration) -> Command {
+    let mut command = imp_sleep_command(duration);
+    command.stdout(Stdio::null());
+    command
+}
+
+/// Spawns a child process sleeping for the duration, see [`sleep_command`].
+///
+/// # Errors
+///
+/// This function fails if the command could not be spawned, see [`Command::spawn`].
+pub fn sleep_child(duration: Duration) -> io::Result<Child> {
+    sleep_command(duration).spawn()
+}
+
+/// Returns a command exiting immediately with the exit code.
+///
+/// # Platform-Specific Behavior
+///
+/// On Unix, the command runs `sh` and only the lowest 8 bits of the exit code are kept, as by `exit`.
+/// On Windows, it runs `cmd`.
+pub fn exit_command(code: i32) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", &format!("exit {code}")]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", &format!("exit {code}")]);
+        command
+    };
+    command.stdout(Stdio::null());
+    command
+}
+
+/// Spawns a child process exiting immediately with the exit code, see [`exit_command`].
+///
+/// # Errors
+///
+/// This function fails if the command could not be spawned, see [`Command::spawn`].
+pub fn exit_child(code: i32) -> io::Result<Child> {
+    exit_command(code).spawn()
+}
+
+/// Returns a command sleeping for the duration while ignoring the requests to terminate, so that only a
+/// kill ends it early.
+///
+/// It stands for the children which do not honor [`ChildWT::shutdown`](super::ChildWT::shutdown),
+/// to test that they are still killed once the grace period expires.
+///
+/// # Platform-Specific Behavior
+///
+/// On Unix, `SIGHUP`, `SIGINT` and `SIGTERM` are ignored by a shell which then executes `sleep`, keeping
+/// the process identifier. On Windows, the command is the same as [`sleep_command`]: a console process
+/// without windows never receives the `WM_CLOSE` of a graceful termination.
+pub fn ignore_signals_command(duration: Duration) -> Command {
+    let mut command = if cfg!(windows) {
+        imp_sleep_command(duration)
+    } else {
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            &format!(
+                "trap '' HUP INT TERM; exec sleep {}",
+                unix_seconds(duration)
+            ),
+        ]);
+        command
+    };
+    command.stdout(Stdio::null());
+    command
+}
+
+/// Spawns a child process sleeping for the duration while ignoring the requests to terminate, see
+/// [`ignore_signals_command`].
+///
+/// # Errors
+///
+/// This function fails if the command could not be spawned, see [`Command::spawn`].
+pub fn ignore_signals_child(duration: Duration) -> io::Result<Child> {
+    ignore_signals_command(duration).spawn()
+}
+
+fn imp_sleep_command(duration: Duration) -> Command {
+    if cfg!(windows) {
+        // `ping` waits a second between its echo requests, after the first one
+        let seconds = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+        let mut command = Command::new("ping");
+        command.args(["-n", &(seconds + 1).to_string(), "127.0.0.1"]);
+        command
+    } else {
+        let mut command = Command::new("sleep");
+        command.arg(unix_seconds(duration));
+        command
+    }
+}
+
+// Formats the duration as the fractional seconds accepted by `sleep`
+fn unix_seconds(duration: Duration) -> String {
+    format!("{}.{:09}", duration.as_secs(), duration.subsec_nanos())
+}
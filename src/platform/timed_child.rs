@@ -0,0 +1,167 @@
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+use super::{trace, ChildWT, Deadline};
+
+/// A child process given an overall lifetime budget, from which the timeout of every wait is derived.
+///
+/// The instant the child process was spawned is recorded, so that [`TimedChild::uptime`] and
+/// [`TimedChild::remaining_budget`] can be queried at any time. The waits without a timeout, such as
+/// [`TimedChild::wait`], last at most until the budget is spent, however many of them are made, and
+/// [`TimedChild::wait_timeout`] never waits past it either.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::process::Command;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::TimedChild;
+///
+/// let mut child = TimedChild::spawn(Command::new("sleep").arg("10"), Duration::from_millis(200))?;
+/// assert!(child.remaining_budget() <= Duration::from_millis(200));
+///
+/// // The child is killed once its budget is spent
+/// let error = child.wait_or_kill().unwrap_err();
+/// assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+/// assert!(child.uptime() >= Duration::from_millis(200));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TimedChild {
+    child: Child,
+    spawned: Instant,
+    budget: Duration,
+    deadline: Deadline,
+    // The instant the child was reaped through the wrapper, which ends its uptime
+    reaped: Option<Instant>,
+}
+
+impl TimedChild {
+    /// Wraps a child process with a lifetime budget, counted from now.
+    ///
+    /// The child process should be wrapped right after being spawned, or spawned with [`TimedChild::spawn`],
+    /// for the uptime to start with its spawn.
+    pub fn new(child: Child, budget: Duration) -> Self {
+        let spawned = Instant::now();
+        TimedChild {
+            child,
+            spawned,
+            budget,
+            deadline: Deadline::after(budget),
+            reaped: None,
+        }
+    }
+
+    /// Spawns the command and wraps the child process with a lifetime budget.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the command could not be spawned, see [`Command::spawn`].
+    pub fn spawn(command: &mut Command, budget: Duration) -> io::Result<Self> {
+        let child = command.spawn()?;
+        trace::spawned(child.id(), command);
+        Ok(Self::new(child, budget))
+    }
+
+    /// Returns the OS-assigned process identifier of the child process.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Returns the instant the child process was spawned.
+    pub fn spawned_at(&self) -> Instant {
+        self.spawned
+    }
+
+    /// Returns the time the child process has been running, until it was reaped through the wrapper.
+    pub fn uptime(&self) -> Duration {
+        match self.reaped {
+            Some(reaped) => reaped.duration_since(self.spawned),
+            None => self.spawned.elapsed(),
+        }
+    }
+
+    /// Returns the lifetime budget given to the child process.
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Returns the part of the lifetime budget left, zero once it is spent.
+    pub fn remaining_budget(&self) -> Duration {
+        self.deadline.remaining()
+    }
+
+    /// Returns the deadline at which the lifetime budget is spent.
+    pub fn deadline(&self) -> Deadline {
+        self.deadline
+    }
+
+    /// Waits for the child process to exit or until the lifetime budget is spent.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the budget is spent before the child process exits.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        let deadline = self.deadline;
+        self.keep(|child| deadline.wait(child))
+    }
+
+    /// Waits for the child process to exit or until the timeout expires, without waiting past the
+    /// lifetime budget.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires or the budget is spent
+    /// before the child process exits.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        let timeout = timeout.min(self.remaining_budget());
+        self.keep(|child| child.wait_timeout(timeout))
+    }
+
+    /// Waits for the child process to exit, and kills it if the lifetime budget is spent, as with
+    /// [`ChildWT::wait_or_kill`].
+    ///
+    /// # Errors
+    ///
+    /// This function fails with `ErrorKind::TimedOut` if the budget was spent, in which case the child
+    /// process has been killed and reaped.
+    pub fn wait_or_kill(&mut self) -> io::Result<ExitStatus> {
+        let deadline = self.deadline;
+        let result = self.keep(|child| deadline.wait_or_kill(child));
+        // The child has been reaped after its kill
+        if matches!(&result, Err(e) if e.kind() == io::ErrorKind::TimedOut) {
+            self.reaped.get_or_insert_with(Instant::now);
+        }
+        result
+    }
+
+    /// Returns a reference to the wrapped child process.
+    pub fn as_child(&self) -> &Child {
+        &self.child
+    }
+
+    /// Returns a mutable reference to the wrapped child process.
+    pub fn as_child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Consumes the wrapper and returns the child process.
+    pub fn into_inner(self) -> Child {
+        self.child
+    }
+
+    // Runs a wait on the child and ends its uptime once it has been reaped
+    fn keep(
+        &mut self,
+        wait: impl FnOnce(&mut Child) -> io::Result<ExitStatus>,
+    ) -> io::Result<ExitStatus> {
+        let status = wait(&mut self.child)?;
+        self.reaped.get_or_insert_with(Instant::now);
+        Ok(status)
+    }
+}
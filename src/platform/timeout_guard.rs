@@ -0,0 +1,195 @@
+use std::io;
+use std::process::{Child, Command, ExitStatus, Output};
+use std::time::{Duration, Instant};
+
+use super::{trace, Backend, ChildExitFuture, ChildWT, OutputChunk};
+
+/// A child process which is killed and reaped if it is still running when the guard is dropped.
+///
+/// Wrapping a child in a `TimeoutGuard` makes sure it is not left running nor turned into a zombie when
+/// the code waiting for it returns early, for instance with `?` after a timeout, or panics. The guard
+/// implements [`ChildWT`], so the child is waited on through it as usual.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{ChildWT, TimeoutGuard};
+///
+/// fn run() -> std::io::Result<()> {
+///     let mut child = TimeoutGuard::new(Command::new("sleep").arg("10").spawn()?);
+///     // The child is killed and reaped as the error is returned
+///     child.wait_timeout(Duration::from_millis(100))?;
+///     Ok(())
+/// }
+///
+/// assert_eq!(run().unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TimeoutGuard {
+    // Only taken when the guard is consumed
+    child: Option<Child>,
+}
+
+impl TimeoutGuard {
+    /// Wraps a child process, which is killed and reaped when the guard is dropped.
+    pub fn new(child: Child) -> Self {
+        TimeoutGuard { child: Some(child) }
+    }
+
+    /// Returns a reference to the wrapped child process.
+    pub fn as_child(&self) -> &Child {
+        self.child
+            .as_ref()
+            .expect("the child is only taken on consumption")
+    }
+
+    /// Returns a mutable reference to the wrapped child process.
+    pub fn as_child_mut(&mut self) -> &mut Child {
+        self.child
+            .as_mut()
+            .expect("the child is only taken on consumption")
+    }
+
+    /// Consumes the guard and returns the child process, which is no longer killed.
+    pub fn into_inner(mut self) -> Child {
+        self.child
+            .take()
+            .expect("the child is only taken on consumption")
+    }
+}
+
+/// Spawns the command and runs the closure with the child process, which is waited for or killed before returning.
+///
+/// Once the closure returns, the child process is waited for until the deadline, then killed along with the
+/// processes it spawned, as with [`ChildWT::wait_or_kill`]. If the closure panics, the child process is killed
+/// and reaped before the panic propagates. Either way, no child process outlives the call, nor is left as a
+/// zombie.
+///
+/// # Returns
+/// - The value returned by the closure, along with the exit status of the child process.
+///
+/// # Errors
+///
+/// This function fails if the command could not be spawned, see [`Command::spawn`]. It fails with
+/// `ErrorKind::TimedOut` if the deadline passes before the child process exits, in which case its process
+/// tree has been killed and reaped, and the value returned by the closure is dropped.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::io::Read;
+/// use std::process::{Command, Stdio};
+/// use std::time::{Duration, Instant};
+///
+/// use child_wait_timeout::with_child;
+///
+/// let deadline = Instant::now() + Duration::from_secs(5);
+/// let mut command = Command::new("echo");
+/// command.arg("hello").stdout(Stdio::piped());
+/// let (output, status) = with_child(&mut command, deadline, |child| {
+///     let mut output = String::new();
+///     child.stdout.take().unwrap().read_to_string(&mut output).map(|_| output)
+/// })?;
+/// assert_eq!(output?, "hello\n");
+/// assert!(status.success());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn with_child<F, T>(
+    command: &mut Command,
+    deadline: Instant,
+    f: F,
+) -> io::Result<(T, ExitStatus)>
+where
+    F: FnOnce(&mut Child) -> T,
+{
+    // The guard kills and reaps the child if the closure panics or the wait fails
+    let mut guard = TimeoutGuard::new(command.spawn()?);
+    trace::spawned(guard.as_child().id(), command);
+    let value = f(guard.as_child_mut());
+    let status = guard
+        .as_child_mut()
+        .wait_or_kill(deadline.saturating_duration_since(Instant::now()))?;
+    Ok((value, status))
+}
+
+impl From<Child> for TimeoutGuard {
+    fn from(child: Child) -> Self {
+        TimeoutGuard::new(child)
+    }
+}
+
+impl Drop for TimeoutGuard {
+    fn drop(&mut self) {
+        let Some(child) = self.child.as_mut() else {
+            return;
+        };
+        // Errors cannot be reported from a drop, the child is reaped whenever it can be killed
+        if let Ok(None) = child.try_wait() {
+            if child.kill().is_ok() {
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+impl ChildWT for TimeoutGuard {
+    fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        self.as_child_mut().wait_timeout(timeout)
+    }
+
+    fn wait_timeout_with_backend(
+        &mut self,
+        backend: Backend,
+        timeout: Duration,
+    ) -> io::Result<ExitStatus> {
+        self.as_child_mut()
+            .wait_timeout_with_backend(backend, timeout)
+    }
+
+    fn wait_timeout_opt(&mut self, timeout: Option<Duration>) -> io::Result<ExitStatus> {
+        self.as_child_mut().wait_timeout_opt(timeout)
+    }
+
+    fn wait_or_kill(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        self.as_child_mut().wait_or_kill(timeout)
+    }
+
+    fn shutdown(&mut self, timeout: Duration, grace: Duration) -> io::Result<ExitStatus> {
+        self.as_child_mut().shutdown(timeout, grace)
+    }
+
+    fn exit_future(&mut self) -> ChildExitFuture<'_> {
+        self.as_child_mut().exit_future()
+    }
+
+    fn wait_cpu_timeout(&mut self, limit: Duration) -> io::Result<ExitStatus> {
+        self.as_child_mut().wait_cpu_timeout(limit)
+    }
+
+    // The child is killed and reaped by the wait itself if the timeout expires
+    fn wait_with_output_timeout(self, timeout: Duration) -> io::Result<Output> {
+        self.into_inner().wait_with_output_timeout(timeout)
+    }
+
+    fn stream_output_timeout<F>(
+        &mut self,
+        timeout: Duration,
+        on_output: F,
+    ) -> io::Result<ExitStatus>
+    where
+        F: FnMut(OutputChunk<'_>),
+    {
+        self.as_child_mut()
+            .stream_output_timeout(timeout, on_output)
+    }
+}
@@ -0,0 +1,244 @@
+// Implementations of the traits for the children of the Tokio runtime
+
+use std::fs::File;
+use std::io;
+use std::process::{ExitStatus, Output};
+use std::time::Duration;
+
+use tokio::process::Child;
+
+use super::async_child::AsyncChildWT;
+#[cfg(windows)]
+use super::backend::wait_handle_with;
+#[cfg(unix)]
+use super::backend::wait_pid_with;
+use super::exit_future::{exited, BlockingWait, ExitTarget};
+use super::exit_oneshot::{exit_oneshot, ExitOneshot};
+use super::{
+    imp, kill_tree_with, sampling, stream_output, terminate, wait_until_deadline, wait_with_output,
+    Backend, ChildExitFuture, ChildWT, OutputChunk,
+};
+use crate::error::{_generate_default_error, _generate_timeout_error};
+
+impl ExitTarget for Child {
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        Child::try_wait(self)
+    }
+
+    #[cfg(unix)]
+    fn exited(&self) -> io::Result<BlockingWait> {
+        match self.id() {
+            Some(pid) => Ok(exited(pid)),
+            // The child has already been reaped
+            None => Ok(Box::new(|| Ok(()))),
+        }
+    }
+
+    #[cfg(windows)]
+    fn exited(&self) -> io::Result<BlockingWait> {
+        use std::os::windows::io::BorrowedHandle;
+
+        match self.raw_handle() {
+            Some(handle) => exited(unsafe { BorrowedHandle::borrow_raw(handle) }),
+            // The child has already been reaped
+            None => Ok(Box::new(|| Ok(()))),
+        }
+    }
+
+    fn watch_key(&self) -> Option<u32> {
+        self.id()
+    }
+}
+
+/// The waits block the calling thread, like their counterparts for [`std::process::Child`], and must not
+/// be called from the worker threads of the runtime. The child is reaped through [`Child::try_wait`], so
+/// that the runtime knows its exit status.
+///
+/// This implementation is only available with the `tokio` feature.
+impl ChildWT for Child {
+    fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        if let Some(status) = self.try_wait()? {
+            return Ok(status);
+        }
+        if timeout.is_zero() {
+            return _generate_timeout_error();
+        }
+
+        wait_until_deadline(timeout, |remaining| wait_untraced(self, remaining))?;
+        // The child is a zombie, reaping it does not block
+        match self.try_wait()? {
+            Some(status) => Ok(status),
+            None => _generate_default_error(),
+        }
+    }
+
+    fn wait_timeout_with_backend(
+        &mut self,
+        backend: Backend,
+        timeout: Duration,
+    ) -> io::Result<ExitStatus> {
+        if let Some(status) = self.try_wait()? {
+            return Ok(status);
+        }
+        if timeout.is_zero() {
+            return _generate_timeout_error();
+        }
+
+        wait_until_deadline(timeout, |remaining| {
+            wait_untraced_with(self, backend, remaining)
+        })?;
+        // The child is a zombie, reaping it does not block
+        match self.try_wait()? {
+            Some(status) => Ok(status),
+            None => _generate_default_error(),
+        }
+    }
+
+    fn wait_or_kill(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        let result = ChildWT::wait_timeout(self, timeout);
+        kill_on_timeout(self, result)
+    }
+
+    fn shutdown(&mut self, timeout: Duration, grace: Duration) -> io::Result<ExitStatus> {
+        match ChildWT::wait_timeout(self, timeout) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                // Without a way to ask the child to terminate, it is killed right away
+                let result = match self.id().map(terminate) {
+                    Some(Ok(())) => ChildWT::wait_timeout(self, grace),
+                    _ => _generate_timeout_error(),
+                };
+                kill_on_timeout(self, result)?;
+                Err(e)
+            }
+            result => result,
+        }
+    }
+
+    fn exit_future(&mut self) -> ChildExitFuture<'_> {
+        ChildExitFuture::new(self)
+    }
+
+    fn wait_cpu_timeout(&mut self, limit: Duration) -> io::Result<ExitStatus> {
+        match self.id() {
+            Some(pid) => sampling::wait_cpu_timeout(self, pid, limit),
+            // The child has already been reaped
+            None => ChildWT::wait_timeout(self, Duration::ZERO),
+        }
+    }
+
+    fn wait_with_output_timeout(mut self, timeout: Duration) -> io::Result<Output> {
+        let stdout = self.stdout.take().map(IntoFile::into_file).transpose()?;
+        let stderr = self.stderr.take().map(IntoFile::into_file).transpose()?;
+        wait_with_output(&mut self, stdout, stderr, timeout)
+    }
+
+    fn stream_output_timeout<F>(
+        &mut self,
+        timeout: Duration,
+        on_output: F,
+    ) -> io::Result<ExitStatus>
+    where
+        F: FnMut(OutputChunk<'_>),
+    {
+        let stdout = self.stdout.take().map(IntoFile::into_file).transpose()?;
+        let stderr = self.stderr.take().map(IntoFile::into_file).transpose()?;
+        stream_output(self, stdout, stderr, timeout, on_output)
+    }
+}
+
+/// The wait uses [`Child::wait`], driven by the current Tokio runtime.
+///
+/// This implementation is only available with the `tokio` feature.
+impl AsyncChildWT for Child {
+    async fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        tokio::time::timeout(timeout, self.wait())
+            .await
+            .unwrap_or_else(|_| _generate_timeout_error())
+    }
+
+    fn exit_oneshot(mut self) -> ExitOneshot {
+        let (sender, receiver) = exit_oneshot();
+        tokio::spawn(async move { sender.send(self.wait().await) });
+        receiver
+    }
+}
+
+// Kills the child and its process tree if the wait timed out, then reaps it
+fn kill_on_timeout(child: &mut Child, result: io::Result<ExitStatus>) -> io::Result<ExitStatus> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            if let Some(pid) = child.id() {
+                kill_tree_with(pid, || child.start_kill())?;
+            }
+            ChildWT::wait_timeout(child, Duration::MAX)?;
+            Err(e)
+        }
+        result => result,
+    }
+}
+
+// Waits for the child to exit with the backend, without reaping it
+#[cfg(unix)]
+fn wait_untraced(child: &Child, timeout: Duration) -> io::Result<()> {
+    match child.id() {
+        Some(pid) => imp::_wait_pid_untraced(pid, timeout),
+        // The child has already been reaped
+        None => Ok(()),
+    }
+}
+
+#[cfg(windows)]
+fn wait_untraced(child: &Child, timeout: Duration) -> io::Result<()> {
+    use super::win32::HANDLE;
+
+    match child.raw_handle() {
+        Some(handle) => imp::_wait_handle_untraced(handle as HANDLE, timeout),
+        // The child has already been reaped
+        None => Ok(()),
+    }
+}
+
+// Waits for the child to exit with the given backend, without reaping it
+#[cfg(unix)]
+fn wait_untraced_with(child: &Child, backend: Backend, timeout: Duration) -> io::Result<()> {
+    match child.id() {
+        Some(pid) => wait_pid_with(backend, pid, timeout),
+        // The child has already been reaped
+        None => Ok(()),
+    }
+}
+
+#[cfg(windows)]
+fn wait_untraced_with(child: &Child, backend: Backend, timeout: Duration) -> io::Result<()> {
+    use super::win32::HANDLE;
+
+    match child.raw_handle() {
+        Some(handle) => wait_handle_with(backend, handle as HANDLE, timeout),
+        // The child has already been reaped
+        None => Ok(()),
+    }
+}
+
+// The pipes of the child, converted into blocking files read outside of the runtime
+trait IntoFile {
+    fn into_file(self) -> io::Result<File>;
+}
+
+macro_rules! impl_into_file {
+    ($pipe:ty) => {
+        impl IntoFile for $pipe {
+            #[cfg(unix)]
+            fn into_file(self) -> io::Result<File> {
+                self.into_owned_fd().map(File::from)
+            }
+
+            #[cfg(windows)]
+            fn into_file(self) -> io::Result<File> {
+                self.into_owned_handle().map(File::from)
+            }
+        }
+    };
+}
+
+impl_into_file!(tokio::process::ChildStdout);
+impl_into_file!(tokio::process::ChildStderr);
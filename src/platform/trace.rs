@@ -0,0 +1,118 @@
+// Instrumentation of the waits, emitted as `tracing` spans and events with the `tracing` feature, as
+// `log` records with the `log` feature and as `metrics` with the `metrics` feature, compiled out otherwise
+#![cfg_attr(
+    not(any(feature = "tracing", feature = "log")),
+    allow(unused_variables)
+)]
+
+use std::io;
+use std::process::{Command, ExitStatus};
+use std::time::Duration;
+
+use super::Backend;
+
+// Covers a wait until it is dropped, the events of the wait are recorded within it
+pub(crate) struct WaitSpan {
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+impl WaitSpan {
+    // The backend is only computed when it is recorded, finding it may take system calls
+    pub(crate) fn enter(pid: u32, backend: impl FnOnce() -> Backend, timeout: Duration) -> Self {
+        #[cfg(any(feature = "tracing", feature = "log", feature = "metrics"))]
+        let backend = backend();
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("wait_timeout", pid, backend = backend.name(), ?timeout).entered();
+        #[cfg(feature = "tracing")]
+        tracing::debug!("waiting for the child process");
+        #[cfg(feature = "log")]
+        log::debug!("waiting for child process {pid} with the {backend} backend for {timeout:?}");
+        #[cfg(feature = "metrics")]
+        metrics::counter!("child_wait_timeout_waits_total", "backend" => backend.name())
+            .increment(1);
+
+        WaitSpan {
+            #[cfg(feature = "tracing")]
+            _span,
+        }
+    }
+}
+
+// Records the spawn of a command, relating the pid of the following records to the program
+pub(crate) fn spawned(pid: u32, command: &Command) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(pid, program = ?command.get_program(), "command spawned");
+    #[cfg(feature = "log")]
+    log::debug!("spawned {:?} as child process {pid}", command.get_program());
+}
+
+// Records the outcome of a wait which lasted `elapsed`: the reaping of the child, the expiry of the
+// timeout or a failure
+pub(crate) fn wait_result(pid: u32, elapsed: Duration, result: &io::Result<ExitStatus>) {
+    #[cfg(feature = "tracing")]
+    match result {
+        Ok(status) => tracing::debug!(pid, %status, ?elapsed, "child process reaped"),
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            tracing::debug!(
+                pid,
+                ?elapsed,
+                "timeout expired before the child process exited"
+            )
+        }
+        Err(e) => tracing::warn!(pid, error = %e, "wait failed"),
+    }
+    #[cfg(feature = "log")]
+    match result {
+        Ok(status) => log::debug!("child process {pid} reaped with {status} after {elapsed:?}"),
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            log::debug!("timeout expired before child process {pid} exited")
+        }
+        Err(e) => log::warn!("wait for child process {pid} failed: {e}"),
+    }
+    #[cfg(feature = "metrics")]
+    {
+        let outcome = match result {
+            Ok(_) => "exited",
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => "timed_out",
+            Err(_) => "failed",
+        };
+        if outcome == "timed_out" {
+            metrics::counter!("child_wait_timeout_timeouts_total").increment(1);
+        }
+        metrics::histogram!("child_wait_timeout_wait_duration_seconds", "outcome" => outcome)
+            .record(elapsed);
+    }
+}
+
+// Records that the child is asked to terminate once the timeout expired
+pub(crate) fn terminating(pid: u32, grace: Duration) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        pid,
+        ?grace,
+        "asking the child process to terminate after the timeout"
+    );
+    #[cfg(feature = "log")]
+    log::warn!("asking child process {pid} to terminate within {grace:?} after the timeout");
+}
+
+// Records that the child is killed along with its descendants once the timeout expired
+pub(crate) fn killing(pid: u32) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(pid, "killing the child process tree after the timeout");
+    #[cfg(feature = "log")]
+    log::warn!("killing the process tree of child process {pid} after the timeout");
+    #[cfg(feature = "metrics")]
+    metrics::counter!("child_wait_timeout_kills_total").increment(1);
+}
+
+// Records that a backend is unavailable at runtime, `to` being used instead for the rest of the process lifetime
+#[cfg(all(any(target_os = "linux", target_os = "android"), feature = "pidfd"))]
+pub(crate) fn backend_fallback(from: &str, to: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(from, to, "backend unavailable, falling back");
+    #[cfg(feature = "log")]
+    log::warn!("{from} is unavailable on this system, falling back to {to}");
+}
@@ -0,0 +1,255 @@
+// Helpers shared by the Unix backends. Redox and Haiku only use the helpers killing processes,
+// their children are waited on by the polling backend.
+
+use std::io;
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+use std::mem;
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+use std::os::unix::process::ExitStatusExt;
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+use std::process::ExitStatus;
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+use std::thread;
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+use std::time::Duration;
+
+use libc::{c_int, pid_t, ESRCH, SIGKILL, SIGTERM};
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+use libc::{id_t, idtype_t, siginfo_t, waitid, P_PID, WEXITED, WNOHANG, WNOWAIT, WSTOPPED};
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+use libc::{CLD_DUMPED, CLD_EXITED, CLD_KILLED, CLD_TRAPPED, EINTR};
+
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+use super::{deadline_after, remaining_until};
+use crate::error::_generate_os_error;
+
+// Whether the waits interrupted by a signal are restarted, set by `set_restart_on_interrupt`
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+static RESTART_ON_INTERRUPT: AtomicBool = AtomicBool::new(true);
+
+/// Sets whether the waits interrupted by a signal are restarted with the remaining timeout.
+///
+/// By default, a wait interrupted by a signal handled in the waiting thread is restarted until the
+/// child process exits or the initial timeout expires, so that an unrelated signal neither fails
+/// the wait nor extends it. When disabled, the waits fail with `ErrorKind::Interrupted` instead,
+/// for applications handling their signals between the waits. The setting applies to the whole
+/// process.
+///
+/// The io_uring waits of the `uring` feature are always restarted, and the `thread` backend is
+/// not interrupted by signals.
+///
+/// This function is only available on Unix.
+///
+/// # Example
+/// ```rust
+/// use child_wait_timeout::{restart_on_interrupt, set_restart_on_interrupt};
+///
+/// set_restart_on_interrupt(false);
+/// assert!(!restart_on_interrupt());
+/// # set_restart_on_interrupt(true);
+/// ```
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+pub fn set_restart_on_interrupt(restart: bool) {
+    RESTART_ON_INTERRUPT.store(restart, Ordering::Relaxed);
+}
+
+/// Returns whether the waits interrupted by a signal are restarted, `true` by default.
+///
+/// This function is only available on Unix.
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+pub fn restart_on_interrupt() -> bool {
+    RESTART_ON_INTERRUPT.load(Ordering::Relaxed)
+}
+
+// Calls the wait with the time remaining until the deadline, again each time it is interrupted
+// by a signal unless the interruptions are surfaced, or if it times out before the deadline
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+pub(crate) fn restart_interrupted<T>(
+    timeout: Duration,
+    mut wait: impl FnMut(Duration) -> io::Result<T>,
+) -> io::Result<T> {
+    let deadline = deadline_after(timeout);
+    loop {
+        match wait(remaining_until(deadline)) {
+            Err(e) if e.raw_os_error() == Some(EINTR) && restart_on_interrupt() => {}
+            Err(e)
+                if e.kind() == io::ErrorKind::TimedOut && !remaining_until(deadline).is_zero() => {}
+            result => return result,
+        }
+    }
+}
+
+// Checks without blocking that the child is a zombie waiting to be reaped
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+pub(crate) fn has_exited(pid: pid_t) -> bool {
+    let mut info: siginfo_t = unsafe { mem::zeroed() };
+    let result = unsafe { waitid(P_PID, pid as id_t, &mut info, WEXITED | WNOWAIT | WNOHANG) };
+    // A traced child in a ptrace stop is reported by `waitid` even without `WSTOPPED`
+    result != -1 && unsafe { info.si_pid() } == pid && exit_status(&info).is_some()
+}
+
+// How often a blocked wait checks again a traced child in a ptrace stop, which `waitid` keeps
+// reporting until its tracer collects it
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+const TRACE_STOP_INTERVAL: Duration = Duration::from_millis(10);
+
+// Blocks until a child designated by `waitid` exits, without removing it from its zombie state.
+// Used by the threads watching children, which are not interrupted by a signal.
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+pub(crate) fn wait_exited(idtype: idtype_t, id: id_t) -> io::Result<()> {
+    loop {
+        let mut info: siginfo_t = unsafe { mem::zeroed() };
+        if unsafe { waitid(idtype, id, &mut info, WEXITED | WNOWAIT) } == -1 {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(EINTR) {
+                return Err(error);
+            }
+        } else if exit_status(&info).is_some() {
+            return Ok(());
+        } else {
+            // The ptrace stops of a traced child are reported as well
+            thread::sleep(TRACE_STOP_INTERVAL);
+        }
+    }
+}
+
+// Checks without blocking that the child is in a ptrace stop not yet collected by its tracer,
+// which `waitpid` reports to the tracer as if it were an exit
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+pub(crate) fn is_trace_stopped(pid: pid_t) -> bool {
+    let mut info: siginfo_t = unsafe { mem::zeroed() };
+    let result = unsafe { waitid(P_PID, pid as id_t, &mut info, WSTOPPED | WNOWAIT | WNOHANG) };
+    result != -1 && unsafe { info.si_pid() } == pid && info.si_code == CLD_TRAPPED
+}
+
+// Rebuilds the raw wait status from the siginfo filled by `waitid`, `None` if the
+// process has not exited
+#[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+pub(crate) fn exit_status(info: &siginfo_t) -> Option<ExitStatus> {
+    let si_status = unsafe { info.si_status() };
+    let raw = match info.si_code {
+        CLD_EXITED => (si_status & 0xff) << 8,
+        CLD_KILLED | CLD_DUMPED => killed_status(si_status, info.si_code == CLD_DUMPED),
+        _ => return None,
+    };
+    Some(ExitStatus::from_raw(raw))
+}
+
+#[cfg(not(any(target_os = "aix", target_os = "redox", target_os = "haiku")))]
+fn killed_status(signal: c_int, dumped: bool) -> c_int {
+    if dumped {
+        signal | 0x80
+    } else {
+        signal
+    }
+}
+
+// AIX keeps the terminating signal in the third byte, the low byte only has to be non-zero and
+// without the stopped bit. It does not report core dumps in the status.
+#[cfg(target_os = "aix")]
+fn killed_status(signal: c_int, _dumped: bool) -> c_int {
+    (signal & 0xff) << 16 | (signal & 0x3f)
+}
+
+// Returns `Ok(None)` if the kernel does not support `pidfd_open` or if it is blocked by seccomp,
+// which is checked beforehand on Android
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn pidfd_open(
+    pid: pid_t,
+    flags: libc::c_uint,
+) -> io::Result<Option<std::os::unix::io::RawFd>> {
+    #[cfg(target_os = "android")]
+    if !android_allows_pidfd() {
+        return Ok(None);
+    }
+
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, flags) };
+    if fd < 0 {
+        let error = io::Error::last_os_error();
+        return match error.raw_os_error() {
+            Some(libc::ENOSYS) | Some(libc::EPERM) => Ok(None),
+            _ => Err(error),
+        };
+    }
+    Ok(Some(fd as std::os::unix::io::RawFd))
+}
+
+// The seccomp policy of Android apps kills the process with `SIGSYS` on the system calls it does
+// not allow instead of failing them, and only allows `pidfd_open` since Android 12 (API level 31)
+#[cfg(target_os = "android")]
+fn android_allows_pidfd() -> bool {
+    use std::ffi::CStr;
+    use std::sync::OnceLock;
+
+    static ALLOWED: OnceLock<bool> = OnceLock::new();
+    *ALLOWED.get_or_init(|| {
+        let mut value = [0 as libc::c_char; libc::PROP_VALUE_MAX as usize];
+        let name = b"ro.build.version.sdk\0";
+        if unsafe { libc::__system_property_get(name.as_ptr().cast(), value.as_mut_ptr()) } <= 0 {
+            return false;
+        }
+        let sdk = unsafe { CStr::from_ptr(value.as_ptr()) };
+        sdk.to_str()
+            .ok()
+            .and_then(|sdk| sdk.parse::<u32>().ok())
+            .is_some_and(|sdk| sdk >= 31)
+    })
+}
+
+// Converts the timeout to a timespec structure, saturating if it does not fit
+#[cfg(any(
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "illumos",
+    target_os = "solaris",
+    target_os = "linux",
+    target_os = "android",
+    all(feature = "signal", not(any(target_os = "redox", target_os = "haiku")))
+))]
+pub(crate) fn to_timespec(timeout: std::time::Duration) -> libc::timespec {
+    libc::timespec {
+        tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+        tv_nsec: timeout.subsec_nanos() as libc::c_long,
+    }
+}
+
+// Asks the child and its process group to terminate with SIGTERM
+pub(crate) fn terminate(pid: u32) -> io::Result<()> {
+    signal_tree(pid, SIGTERM)
+}
+
+// Kills the process group of the child with SIGKILL if it leads its own group, or only
+// the child with the given function otherwise
+pub(crate) fn kill_tree_with(pid: u32, kill: impl FnOnce() -> io::Result<()>) -> io::Result<()> {
+    if !leads_group(pid) {
+        return kill();
+    }
+    match signal_tree(pid, SIGKILL) {
+        // The child has already been reaped and no process remains in its group
+        Err(e) if e.raw_os_error() == Some(ESRCH) => Ok(()),
+        result => result,
+    }
+}
+
+// Sends the signal to the process group of the child if it leads its own group, as when spawned
+// with `CommandExt::process_group(0)`, or to the child only otherwise
+pub(crate) fn signal_tree(pid: u32, signal: c_int) -> io::Result<()> {
+    let pid = pid as pid_t;
+    let target = if leads_group(pid as u32) { -pid } else { pid };
+
+    if unsafe { libc::kill(target, signal) } == -1 {
+        return _generate_os_error();
+    }
+    Ok(())
+}
+
+// Checks that the child leads its own process group, which is not the group of the caller
+fn leads_group(pid: u32) -> bool {
+    let pid = pid as pid_t;
+    unsafe { libc::getpgid(pid) == pid && libc::getpgrp() != pid }
+}
@@ -0,0 +1,263 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use libc::{c_int, pollfd, POLLPRI};
+
+use super::unix::restart_interrupted;
+use crate::error::{_generate_default_error, _generate_os_error, _generate_timeout_error};
+
+// Tells apart the cgroups created by the current process
+static NEXT_CGROUP: AtomicUsize = AtomicUsize::new(0);
+
+/// A fresh cgroup v2 holding a process tree, emptied only once every descendant has exited.
+///
+/// The processes spawned by a process of the cgroup are part of it too, and no process can leave it
+/// without the privileges to write to another cgroup: unlike a process group, a descendant which calls
+/// `setsid` or is orphaned remains in the cgroup. Waiting for the cgroup to be empty, as reported by
+/// `populated 0` in `cgroup.events`, is then the only airtight way to know that the whole tree has exited.
+///
+/// The cgroup is created below the cgroup of the current process, which must be on a cgroup v2 hierarchy
+/// writable by the current process, such as a cgroup delegated by systemd. It is removed when dropped if
+/// it is empty by then.
+///
+/// This type is only available on Linux with the `cgroup` feature.
+///
+/// # Example
+/// ```rust,no_run
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::Cgroup;
+///
+/// let cgroup = Cgroup::new()?;
+/// let mut child = cgroup.spawn(Command::new("sh").args(["-c", "setsid sleep 1 & exit 0"]))?;
+/// child.wait()?;
+/// // Returns once the detached `sleep` has exited too
+/// cgroup.wait_or_kill(Duration::from_secs(5))?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Cgroup {
+    path: PathBuf,
+    procs: File,
+    events: File,
+}
+
+impl Cgroup {
+    /// Creates an empty cgroup below the cgroup of the current process.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with `ErrorKind::Unsupported` if the current process is not in a cgroup v2
+    /// hierarchy, and otherwise if the cgroup could not be created, typically with `ErrorKind::PermissionDenied`
+    /// if the hierarchy is not writable by the current process.
+    pub fn new() -> io::Result<Self> {
+        let name = format!(
+            "child_wait_timeout-{}-{}",
+            process::id(),
+            NEXT_CGROUP.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = current_cgroup()?.join(name);
+        fs::create_dir(&path)?;
+
+        let open = || -> io::Result<(File, File)> {
+            let procs = OpenOptions::new()
+                .write(true)
+                .open(path.join("cgroup.procs"))?;
+            let events = File::open(path.join("cgroup.events"))?;
+            Ok((procs, events))
+        };
+        match open() {
+            Ok((procs, events)) => Ok(Cgroup {
+                path,
+                procs,
+                events,
+            }),
+            Err(e) => {
+                let _ = fs::remove_dir(&path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the path of the cgroup in the cgroup filesystem.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Spawns the command into the cgroup.
+    ///
+    /// The child moves itself into the cgroup before executing the command, so that none of the processes
+    /// it spawns is missed.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the command could not be spawned, see [`Command::spawn`], or if the child
+    /// could not move itself into the cgroup.
+    pub fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+        let procs = self.procs.as_raw_fd();
+        // Writing `0` moves the writing process, `write` is async-signal-safe
+        unsafe {
+            command.pre_exec(move || {
+                if libc::write(procs, b"0".as_ptr().cast(), 1) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            })
+        };
+        command.spawn()
+    }
+
+    /// Moves the child process into the cgroup.
+    ///
+    /// The processes spawned by the child process before it is moved remain in their cgroup, so the child
+    /// process should rather be spawned with [`Cgroup::spawn`].
+    pub fn assign(&self, child: &Child) -> io::Result<()> {
+        (&self.procs).write_all(child.id().to_string().as_bytes())
+    }
+
+    /// Returns `true` if a process of the cgroup is still running.
+    pub fn is_populated(&self) -> io::Result<bool> {
+        let mut buf = [0; 256];
+        let len = self.events.read_at(&mut buf, 0)?;
+        let events = String::from_utf8_lossy(&buf[..len]);
+        match events
+            .lines()
+            .find_map(|line| line.strip_prefix("populated "))
+        {
+            Some(populated) => Ok(populated.trim() != "0"),
+            None => _generate_default_error(),
+        }
+    }
+
+    /// Waits for all the processes of the cgroup to exit or until the timeout expires.
+    ///
+    /// The processes leave the cgroup as they exit, before being reaped, so the wait does not reap the
+    /// children of the current process, whose exit status is still retrieved with [`Child::wait`].
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before all the processes exit.
+    pub fn wait_timeout(&self, timeout: Duration) -> io::Result<()> {
+        let mut pollfd = pollfd {
+            fd: self.events.as_raw_fd(),
+            events: POLLPRI,
+            revents: 0,
+        };
+        restart_interrupted(timeout, |remaining| {
+            // Reading the file acknowledges its modification, it is then checked after each notification
+            if !self.is_populated()? {
+                return Ok(());
+            }
+            if remaining.is_zero() {
+                return _generate_timeout_error();
+            }
+
+            // Round the timeout up so that the poll never returns before it expires
+            let timeout_ms = remaining
+                .as_nanos()
+                .div_ceil(1_000_000)
+                .min(c_int::MAX as u128) as c_int;
+            if unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } == -1 {
+                return _generate_os_error();
+            }
+            if self.is_populated()? {
+                return _generate_timeout_error();
+            }
+            Ok(())
+        })
+    }
+
+    /// Waits for all the processes of the cgroup to exit, and kills them if the timeout expires.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with `ErrorKind::TimedOut` if the timeout expired, in which case all the processes
+    /// of the cgroup have been killed. It may also fail if they could not be killed.
+    pub fn wait_or_kill(&self, timeout: Duration) -> io::Result<()> {
+        match self.wait_timeout(timeout) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                self.kill()?;
+                Err(e)
+            }
+            result => result,
+        }
+    }
+
+    /// Kills all the processes of the cgroup with `SIGKILL`.
+    ///
+    /// The processes are killed at once through `cgroup.kill` since Linux 5.14. On older kernels, the
+    /// processes listed in `cgroup.procs` are killed again and again until none is left, to catch the
+    /// processes forked in between.
+    pub fn kill(&self) -> io::Result<()> {
+        match fs::write(self.path.join("cgroup.kill"), b"1") {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            result => return result,
+        }
+
+        loop {
+            let procs = fs::read_to_string(self.path.join("cgroup.procs"))?;
+            let mut killed = false;
+            for pid in procs
+                .lines()
+                .filter_map(|pid| pid.parse::<libc::pid_t>().ok())
+            {
+                // The processes killed by a previous pass are listed until they have exited
+                if unsafe { libc::kill(pid, libc::SIGKILL) } == 0 {
+                    killed = true;
+                }
+            }
+            if !killed {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        // A cgroup can only be removed once empty, a populated one is left behind
+        let _ = fs::remove_dir(&self.path);
+    }
+}
+
+// Returns the path of the cgroup v2 of the current process, from `/proc/self/cgroup` and the mount
+// point of the cgroup2 filesystem in `/proc/self/mountinfo`
+fn current_cgroup() -> io::Result<PathBuf> {
+    use crate::error::_generate_unsupported_error;
+
+    let cgroups = fs::read_to_string("/proc/self/cgroup")?;
+    let Some(cgroup) = cgroups.lines().find_map(|line| line.strip_prefix("0::")) else {
+        return _generate_unsupported_error();
+    };
+
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo")?;
+    let mount = mountinfo.lines().find_map(|line| {
+        let (mount, filesystem) = line.split_once(" - ")?;
+        if !filesystem.starts_with("cgroup2 ") {
+            return None;
+        }
+        // The root of the mount within the hierarchy and its mount point are the 4th and 5th fields
+        let mut fields = mount.split(' ').skip(3);
+        Some((fields.next()?, fields.next()?))
+    });
+    let Some((root, mount_point)) = mount else {
+        return _generate_unsupported_error();
+    };
+
+    let Some(relative) = cgroup.strip_prefix(root) else {
+        return _generate_unsupported_error();
+    };
+    Ok(Path::new(mount_point).join(relative.trim_start_matches('/')))
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Instant;
+
+use libc::{c_int, epoll_create1, epoll_ctl, epoll_event, epoll_wait, EINTR};
+use libc::{EPOLLIN, EPOLL_CLOEXEC, EPOLL_CTL_ADD, EPOLL_CTL_DEL};
+
+use super::PidFd;
+use crate::error::_generate_os_error;
+use crate::platform::unix::restart_on_interrupt;
+
+/// A set of pidfds registered in a single `epoll` instance.
+///
+/// Each pidfd is registered with a key chosen by the caller, and [`PidFdEpoll::wait_deadline`]
+/// returns the keys of the processes which have exited. This allows waiting on many children
+/// from a single thread, instead of one thread or one serialized timeout per child.
+///
+/// The pidfds are level-triggered: the key of an exited process is returned by every call to
+/// [`PidFdEpoll::wait_deadline`] until it is removed with [`PidFdEpoll::remove`].
+///
+/// This type is only available with the `pidfd` feature.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::{Duration, Instant};
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{PidFd, PidFdEpoll};
+///
+/// let mut children = vec![
+///     Command::new("sleep").arg("0").spawn()?,
+///     Command::new("sleep").arg("1").spawn()?,
+/// ];
+///
+/// let mut epoll = PidFdEpoll::new()?;
+/// for (key, child) in children.iter().enumerate() {
+///     epoll.add(key, PidFd::from_child(child)?)?;
+/// }
+///
+/// let deadline = Instant::now() + Duration::from_secs(5);
+/// while !epoll.is_empty() {
+///     let exited: Vec<usize> = epoll.wait_deadline(deadline)?.collect();
+///     if exited.is_empty() {
+///         break; // the deadline has passed
+///     }
+///     for key in exited {
+///         epoll.remove(key)?;
+///         println!("child {} exited with {:?}", key, children[key].wait()?);
+///     }
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PidFdEpoll {
+    epfd: OwnedFd,
+    pidfds: HashMap<usize, PidFd>,
+}
+
+impl PidFdEpoll {
+    /// Creates an empty set.
+    pub fn new() -> io::Result<Self> {
+        let epfd = unsafe { epoll_create1(EPOLL_CLOEXEC) };
+        if epfd == -1 {
+            return _generate_os_error();
+        }
+
+        Ok(PidFdEpoll {
+            epfd: unsafe { OwnedFd::from_raw_fd(epfd) },
+            pidfds: HashMap::new(),
+        })
+    }
+
+    /// Registers a pidfd with the given key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ErrorKind::AlreadyExists` if the key is already registered.
+    pub fn add(&mut self, key: usize, pidfd: PidFd) -> io::Result<()> {
+        if self.pidfds.contains_key(&key) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "key already registered",
+            ));
+        }
+
+        let mut event = epoll_event {
+            events: EPOLLIN as u32,
+            u64: key as u64,
+        };
+        let result = unsafe {
+            epoll_ctl(
+                self.epfd.as_raw_fd(),
+                EPOLL_CTL_ADD,
+                pidfd.as_raw_fd(),
+                &mut event,
+            )
+        };
+        if result == -1 {
+            return _generate_os_error();
+        }
+
+        self.pidfds.insert(key, pidfd);
+        Ok(())
+    }
+
+    /// Unregisters the pidfd with the given key and returns it.
+    pub fn remove(&mut self, key: usize) -> io::Result<Option<PidFd>> {
+        let Some(pidfd) = self.pidfds.remove(&key) else {
+            return Ok(None);
+        };
+
+        let mut event = epoll_event { events: 0, u64: 0 };
+        let result = unsafe {
+            epoll_ctl(
+                self.epfd.as_raw_fd(),
+                EPOLL_CTL_DEL,
+                pidfd.as_raw_fd(),
+                &mut event,
+            )
+        };
+        if result == -1 {
+            return _generate_os_error();
+        }
+
+        Ok(Some(pidfd))
+    }
+
+    /// Returns the pidfd registered with the given key.
+    pub fn get(&self, key: usize) -> Option<&PidFd> {
+        self.pidfds.get(&key)
+    }
+
+    /// Returns the number of registered pidfds.
+    pub fn len(&self) -> usize {
+        self.pidfds.len()
+    }
+
+    /// Returns `true` if no pidfd is registered.
+    pub fn is_empty(&self) -> bool {
+        self.pidfds.is_empty()
+    }
+
+    /// Waits until at least one of the registered processes has exited or until the deadline passes.
+    ///
+    /// # Returns
+    /// - An iterator over the keys of the processes which have exited. It is empty if the deadline
+    ///   passed before any of them exited.
+    pub fn wait_deadline(&mut self, deadline: Instant) -> io::Result<impl Iterator<Item = usize>> {
+        let mut events = vec![epoll_event { events: 0, u64: 0 }; self.pidfds.len().max(1)];
+
+        loop {
+            // Round the remaining time up so that the deadline is never missed
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let timeout_ms = remaining
+                .as_nanos()
+                .div_ceil(1_000_000)
+                .min(c_int::MAX as u128);
+
+            let result = unsafe {
+                epoll_wait(
+                    self.epfd.as_raw_fd(),
+                    events.as_mut_ptr(),
+                    events.len() as c_int,
+                    timeout_ms as c_int,
+                )
+            };
+
+            if result == -1 {
+                let error = io::Error::last_os_error();
+                if error.raw_os_error() == Some(EINTR) && restart_on_interrupt() {
+                    continue;
+                }
+                return Err(error);
+            }
+
+            // The timeout may have been clamped, keep waiting until the deadline has passed
+            if result > 0 || Instant::now() >= deadline {
+                events.truncate(result as usize);
+                return Ok(events.into_iter().map(|event| event.u64 as usize));
+            }
+        }
+    }
+}
@@ -0,0 +1,540 @@
+use std::io;
+use std::mem;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, ExitStatus};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use libc::{c_int, CLD_CONTINUED, CLD_DUMPED, CLD_EXITED, CLD_KILLED, CLD_STOPPED, CLD_TRAPPED};
+use libc::{id_t, pid_t, rusage, siginfo_t, wait4, waitid, ECHILD, EINTR, P_PID};
+use libc::{WCONTINUED, WEXITED, WNOHANG, WNOWAIT, WSTOPPED};
+
+use super::imp;
+use super::unix::exit_status;
+use crate::error::{_generate_default_error, _generate_os_error, _generate_timeout_error};
+
+/// Unix-specific extensions to the waits of [`ChildWT`](super::ChildWT).
+///
+/// This trait is only available on Unix.
+pub trait ChildWTExt {
+    /// Waits for the child process to exit with a timeout, and returns its exit status along with the
+    /// resources it used.
+    ///
+    /// The exit is awaited with the backend, then the child is reaped with `wait4`, which reports its
+    /// resource usage. The standard library is not aware of it: [`Child::wait`] and [`Child::try_wait`]
+    /// fail with the `ECHILD` OS error afterwards, as for a child reaped by other means.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process
+    /// exits, or with the `ECHILD` OS error if the child has already been reaped.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWTExt;
+    ///
+    /// let mut child = Command::new("sleep").arg("1").spawn()?;
+    /// let (status, usage) = child.wait_timeout_rusage(Duration::from_secs(5))?;
+    /// assert!(status.success());
+    /// println!("max RSS: {} bytes", usage.max_rss());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_timeout_rusage(&mut self, timeout: Duration)
+        -> io::Result<(ExitStatus, ResourceUsage)>;
+
+    /// Waits for the child process to exit with a timeout, and returns how it exited.
+    ///
+    /// The `siginfo` of the exit is read with `waitid` before the child is reaped, telling apart a
+    /// normal exit from a termination by a signal and a core dump. Unlike
+    /// [`ChildWTExt::wait_timeout_rusage`], the child is reaped through [`Child::try_wait`], which keeps
+    /// its exit status.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process
+    /// exits.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::{ChildWTExt, ExitReason};
+    ///
+    /// let mut child = Command::new("sleep").arg("10").spawn()?;
+    /// child.kill()?;
+    /// let info = child.wait_timeout_info(Duration::from_secs(5))?;
+    /// assert_eq!(info.reason(), ExitReason::Killed(libc::SIGKILL));
+    /// assert_eq!(info.pid(), child.id());
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_timeout_info(&mut self, timeout: Duration) -> io::Result<ExitInfo>;
+
+    /// Waits for the child process to exit with a timeout, without reaping it.
+    ///
+    /// The exit is read with `waitid(..., WNOWAIT)`, so the zombie is left in place for another
+    /// component to reap, such as [`Child::wait`] or the runtime owning the child. Until then, the
+    /// exit can be peeked at again.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the child process
+    /// exits, or with the `ECHILD` OS error if the child has already been reaped.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::ChildWTExt;
+    ///
+    /// let mut child = Command::new("sleep").arg("1").spawn()?;
+    /// let status = child.peek_status_timeout(Duration::from_secs(5))?;
+    /// assert!(status.success());
+    ///
+    /// // The child is reaped by its owner
+    /// assert_eq!(child.wait()?, status);
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn peek_status_timeout(&self, timeout: Duration) -> io::Result<ExitStatus>;
+
+    /// Waits for the child process to exit, stop or continue with a timeout, and returns its new state.
+    ///
+    /// This is the equivalent of `waitpid` with `WUNTRACED` and `WCONTINUED`: the stops by `SIGSTOP` and
+    /// the other stop signals, and the resumptions by `SIGCONT`, are reported along with the exit. A
+    /// state change is only reported once, and the child is reaped through [`Child::try_wait`] once it
+    /// has exited.
+    ///
+    /// The state changes are awaited by a separate thread, which keeps running after a timeout until the
+    /// next change of the child, without consuming it.
+    ///
+    /// This is also the wait to use on a child traced with `ptrace` by the calling process, such as a
+    /// debuggee started with `PTRACE_TRACEME`: its ptrace stops are reported as [`WaitState::Traced`] and
+    /// consumed, like in the `waitpid` loop of a tracer.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the state of the
+    /// child process changes.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// use std::time::Duration;
+    /// use std::process::Command;
+    ///
+    /// use child_wait_timeout::{ChildWTExt, WaitState};
+    ///
+    /// let mut child = Command::new("sleep").arg("10").spawn()?;
+    /// unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGSTOP) };
+    /// let state = child.wait_state_timeout(Duration::from_secs(5))?;
+    /// assert_eq!(state, WaitState::Stopped(libc::SIGSTOP));
+    /// # child.kill()?;
+    /// # child.wait()?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    fn wait_state_timeout(&mut self, timeout: Duration) -> io::Result<WaitState>;
+}
+
+impl ChildWTExt for Child {
+    fn wait_timeout_rusage(
+        &mut self,
+        timeout: Duration,
+    ) -> io::Result<(ExitStatus, ResourceUsage)> {
+        let pid = self.id() as pid_t;
+        if let Some(exited) = reap_rusage(pid, WNOHANG)? {
+            return Ok(exited);
+        }
+        if timeout.is_zero() {
+            return _generate_timeout_error();
+        }
+
+        imp::_wait_pid_untraced(self.id(), timeout)?;
+        // The child is a zombie, reaping it does not block
+        match reap_rusage(pid, 0)? {
+            Some(exited) => Ok(exited),
+            None => _generate_timeout_error(),
+        }
+    }
+
+    fn wait_timeout_info(&mut self, timeout: Duration) -> io::Result<ExitInfo> {
+        if let Some(info) = reap_info(self)? {
+            return Ok(info);
+        }
+        if timeout.is_zero() {
+            return _generate_timeout_error();
+        }
+
+        imp::_wait_pid_untraced(self.id(), timeout)?;
+        match reap_info(self)? {
+            Some(info) => Ok(info),
+            None => _generate_default_error(),
+        }
+    }
+
+    fn wait_state_timeout(&mut self, timeout: Duration) -> io::Result<WaitState> {
+        if let Some(state) = take_state(self)? {
+            return Ok(state);
+        }
+        if timeout.is_zero() {
+            return _generate_timeout_error();
+        }
+
+        let pid = self.id();
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("child_wait_timeout".to_string())
+            .spawn(move || {
+                let _ = sender.send(wait_change(pid));
+            })?;
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result?,
+            Err(RecvTimeoutError::Timeout) => return _generate_timeout_error(),
+            Err(RecvTimeoutError::Disconnected) => return _generate_default_error(),
+        }
+
+        match take_state(self)? {
+            Some(state) => Ok(state),
+            // The change has been consumed by another waiter in the meantime
+            None => _generate_default_error(),
+        }
+    }
+
+    fn peek_status_timeout(&self, timeout: Duration) -> io::Result<ExitStatus> {
+        if let Some(info) = peek_info(self.id())? {
+            return Ok(info.status);
+        }
+        if timeout.is_zero() {
+            return _generate_timeout_error();
+        }
+
+        imp::_wait_pid_untraced(self.id(), timeout)?;
+        match peek_info(self.id())? {
+            Some(info) => Ok(info.status),
+            None => _generate_default_error(),
+        }
+    }
+}
+
+/// A change of the state of a child process, returned by [`ChildWTExt::wait_state_timeout`].
+///
+/// This type is only available on Unix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WaitState {
+    /// The process exited and has been reaped.
+    Exited(ExitStatus),
+    /// The process was stopped by the signal.
+    Stopped(i32),
+    /// The process was resumed by `SIGCONT`.
+    Continued,
+    /// The process, traced by the calling process with `ptrace`, entered a ptrace stop with the signal.
+    ///
+    /// The process stays stopped until it is resumed with `PTRACE_CONT` or a similar request.
+    Traced(i32),
+}
+
+/// How a child process exited, from the `si_code` and `si_status` of its `siginfo`.
+///
+/// This type is only available on Unix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExitReason {
+    /// The process exited with the code (`CLD_EXITED`).
+    Exited(i32),
+    /// The process was terminated by the signal (`CLD_KILLED`).
+    Killed(i32),
+    /// The process was terminated by the signal and dumped a core (`CLD_DUMPED`).
+    Dumped(i32),
+}
+
+/// The exit of a child process, returned by [`ChildWTExt::wait_timeout_info`].
+///
+/// This type is only available on Unix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitInfo {
+    pid: u32,
+    reason: ExitReason,
+    status: ExitStatus,
+}
+
+impl ExitInfo {
+    /// Returns the pid of the process.
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Returns how the process exited.
+    pub fn reason(&self) -> ExitReason {
+        self.reason
+    }
+
+    /// Returns the exit code if the process exited normally.
+    pub fn code(&self) -> Option<i32> {
+        match self.reason {
+            ExitReason::Exited(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Returns the signal which terminated the process, if any.
+    pub fn signal(&self) -> Option<i32> {
+        match self.reason {
+            ExitReason::Killed(signal) | ExitReason::Dumped(signal) => Some(signal),
+            ExitReason::Exited(_) => None,
+        }
+    }
+
+    /// Returns `true` if the process dumped a core when it was terminated.
+    pub fn core_dumped(&self) -> bool {
+        matches!(self.reason, ExitReason::Dumped(_))
+    }
+
+    /// Returns the exit status of the process, as returned by [`Child::wait`].
+    pub fn status(&self) -> ExitStatus {
+        self.status
+    }
+}
+
+// Reads the exit of the child with `waitid` then reaps it, `None` if it is still running
+fn reap_info(child: &mut Child) -> io::Result<Option<ExitInfo>> {
+    match peek_info(child.id()) {
+        Ok(Some(info)) => {
+            // The child is a zombie, reaping it does not block
+            child.try_wait()?;
+            Ok(Some(info))
+        }
+        Ok(None) => Ok(None),
+        // The child may have already been reaped by the standard library, which kept its status
+        Err(e) if e.raw_os_error() == Some(ECHILD) => match child.try_wait()? {
+            Some(status) => Ok(Some(ExitInfo {
+                pid: child.id(),
+                reason: reason_of(status),
+                status,
+            })),
+            None => Err(e),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+// Reads the exit of the child with `waitid` without reaping it, `None` if it is still running
+fn peek_info(pid: u32) -> io::Result<Option<ExitInfo>> {
+    let mut info: siginfo_t = unsafe { mem::zeroed() };
+    let result = unsafe { waitid(P_PID, pid as id_t, &mut info, WEXITED | WNOWAIT | WNOHANG) };
+    if result == -1 {
+        return _generate_os_error();
+    }
+    if unsafe { info.si_pid() } == 0 {
+        return Ok(None);
+    }
+
+    let si_status = unsafe { info.si_status() };
+    let (reason, status) = match (info.si_code, exit_status(&info)) {
+        (CLD_EXITED, Some(status)) => (ExitReason::Exited(si_status), status),
+        (CLD_KILLED, Some(status)) => (ExitReason::Killed(si_status), status),
+        (CLD_DUMPED, Some(status)) => (ExitReason::Dumped(si_status), status),
+        _ => return _generate_default_error(),
+    };
+    Ok(Some(ExitInfo {
+        pid,
+        reason,
+        status,
+    }))
+}
+
+// All the state changes reported by `waitid`
+const STATE_CHANGES: c_int = WEXITED | WSTOPPED | WCONTINUED;
+
+// Takes the pending state change of the child, `None` if there is none. A stop or a continuation
+// is consumed, while an exit is reaped through the standard library.
+fn take_state(child: &mut Child) -> io::Result<Option<WaitState>> {
+    let pid = child.id();
+    let mut info: siginfo_t = unsafe { mem::zeroed() };
+    let result = unsafe {
+        waitid(
+            P_PID,
+            pid as id_t,
+            &mut info,
+            STATE_CHANGES | WNOWAIT | WNOHANG,
+        )
+    };
+    if result == -1 {
+        let error = io::Error::last_os_error();
+        // The child may have already been reaped by the standard library, which kept its status
+        return match (error.raw_os_error(), child.try_wait()?) {
+            (Some(ECHILD), Some(status)) => Ok(Some(WaitState::Exited(status))),
+            _ => Err(error),
+        };
+    }
+    if unsafe { info.si_pid() } == 0 {
+        return Ok(None);
+    }
+
+    let (state, consume) = match info.si_code {
+        CLD_STOPPED => (WaitState::Stopped(unsafe { info.si_status() }), WSTOPPED),
+        CLD_TRAPPED => (WaitState::Traced(unsafe { info.si_status() }), WSTOPPED),
+        CLD_CONTINUED => (WaitState::Continued, WCONTINUED),
+        // The child is a zombie, reaping it does not block
+        _ => {
+            return match child.try_wait()? {
+                Some(status) => Ok(Some(WaitState::Exited(status))),
+                None => _generate_default_error(),
+            }
+        }
+    };
+    // Only the stops or continuations are waited for, so that the child cannot be reaped here
+    let result = unsafe { waitid(P_PID, pid as id_t, &mut info, consume | WNOHANG) };
+    if result == -1 {
+        return _generate_os_error();
+    }
+    Ok(Some(state))
+}
+
+// Blocks until the state of the child changes, without consuming the change
+fn wait_change(pid: u32) -> io::Result<()> {
+    let mut info: siginfo_t = unsafe { mem::zeroed() };
+    loop {
+        if unsafe { waitid(P_PID, pid as id_t, &mut info, STATE_CHANGES | WNOWAIT) } != -1 {
+            return Ok(());
+        }
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(EINTR) {
+            return Err(error);
+        }
+    }
+}
+
+// Rebuilds the reason of an exit from its status
+fn reason_of(status: ExitStatus) -> ExitReason {
+    match (status.code(), status.signal()) {
+        (_, Some(signal)) if status.core_dumped() => ExitReason::Dumped(signal),
+        (_, Some(signal)) => ExitReason::Killed(signal),
+        (code, None) => ExitReason::Exited(code.unwrap_or_default()),
+    }
+}
+
+/// The resources used by a child process, as reported by `wait4`.
+///
+/// This type is only available on Unix.
+#[derive(Clone, Copy)]
+pub struct ResourceUsage {
+    rusage: rusage,
+}
+
+impl ResourceUsage {
+    /// Returns the CPU time spent in user mode.
+    pub fn user_time(&self) -> Duration {
+        to_duration(self.rusage.ru_utime)
+    }
+
+    /// Returns the CPU time spent in kernel mode.
+    pub fn system_time(&self) -> Duration {
+        to_duration(self.rusage.ru_stime)
+    }
+
+    /// Returns the maximum resident set size, in bytes.
+    pub fn max_rss(&self) -> u64 {
+        let max_rss = self.rusage.ru_maxrss.max(0) as u64;
+        // Reported in bytes on Apple platforms and in kilobytes elsewhere
+        if cfg!(target_vendor = "apple") {
+            max_rss
+        } else {
+            max_rss * 1024
+        }
+    }
+
+    /// Returns the number of page faults serviced without any I/O.
+    pub fn minor_faults(&self) -> u64 {
+        self.rusage.ru_minflt.max(0) as u64
+    }
+
+    /// Returns the number of page faults which required I/O.
+    pub fn major_faults(&self) -> u64 {
+        self.rusage.ru_majflt.max(0) as u64
+    }
+
+    /// Returns the number of times the process gave up the processor voluntarily, usually to wait for a
+    /// resource.
+    pub fn voluntary_context_switches(&self) -> u64 {
+        self.rusage.ru_nvcsw.max(0) as u64
+    }
+
+    /// Returns the number of times the process was preempted.
+    pub fn involuntary_context_switches(&self) -> u64 {
+        self.rusage.ru_nivcsw.max(0) as u64
+    }
+
+    /// Returns the raw structure filled by `wait4`.
+    pub fn as_raw(&self) -> &rusage {
+        &self.rusage
+    }
+}
+
+impl std::fmt::Debug for ResourceUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResourceUsage")
+            .field("user_time", &self.user_time())
+            .field("system_time", &self.system_time())
+            .field("max_rss", &self.max_rss())
+            .field("minor_faults", &self.minor_faults())
+            .field("major_faults", &self.major_faults())
+            .field(
+                "voluntary_context_switches",
+                &self.voluntary_context_switches(),
+            )
+            .field(
+                "involuntary_context_switches",
+                &self.involuntary_context_switches(),
+            )
+            .finish()
+    }
+}
+
+// Reaps the child with `wait4`, `None` if it is still running and `WNOHANG` is given
+fn reap_rusage(
+    pid: pid_t,
+    options: libc::c_int,
+) -> io::Result<Option<(ExitStatus, ResourceUsage)>> {
+    let mut status = 0;
+    let mut usage: rusage = unsafe { mem::zeroed() };
+    loop {
+        match unsafe { wait4(pid, &mut status, options, &mut usage) } {
+            0 => return Ok(None),
+            -1 => {
+                let error = io::Error::last_os_error();
+                if error.raw_os_error() != Some(EINTR) {
+                    return Err(error);
+                }
+            }
+            _ => {
+                return Ok(Some((
+                    ExitStatus::from_raw(status),
+                    ResourceUsage { rusage: usage },
+                )))
+            }
+        }
+    }
+}
+
+fn to_duration(time: libc::timeval) -> Duration {
+    Duration::new(time.tv_sec.max(0) as u64, time.tv_usec.max(0) as u32 * 1000)
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::process::ExitStatus;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use libc::{id_t, idtype_t, siginfo_t, waitid, ECHILD, P_ALL, P_PGID, WEXITED, WNOHANG};
+
+use super::unix::{exit_status, wait_exited};
+use super::{deadline_after, remaining_until};
+use crate::error::_generate_timeout_error;
+use crate::error::{_generate_os_error, _generate_raw_os_error};
+
+/// Waits for any child process of the process group to exit or until the timeout expires, and reaps it.
+///
+/// Only the children of the current process are waited for, as with `waitid(P_PGID, ...)`. Process
+/// groups are created by spawning the leader with `CommandExt::process_group(0)` or by calling
+/// `setsid`/`setpgid` in the child.
+///
+/// The member is reaped, so the [`Child`](std::process::Child) it was spawned as should not be waited
+/// for anymore. The wait is performed by a thread watching the group, which keeps running after a timeout
+/// and is shared by the next waits on the group, until it has no child process left.
+///
+/// This function is only available on Unix.
+///
+/// # Returns
+/// - The pid and the exit status of the member which exited.
+///
+/// # Errors
+///
+/// This function may fail with `ErrorKind::TimedOut` if the timeout expires before a member exits,
+/// or with the `ECHILD` OS error if the process group has no child process left.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::os::unix::process::CommandExt;
+/// use std::process::Command;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::wait_group_any_timeout;
+///
+/// let leader = Command::new("sleep").arg("1").process_group(0).spawn()?;
+/// let (pid, status) = wait_group_any_timeout(leader.id(), Duration::from_secs(5))?;
+/// assert_eq!(pid, leader.id());
+/// assert!(status.success());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn wait_group_any_timeout(pgid: u32, timeout: Duration) -> io::Result<(u32, ExitStatus)> {
+    let deadline = deadline_after(timeout);
+
+    loop {
+        if let Some(exited) = reap_any(P_PGID, pgid as id_t)? {
+            return Ok(exited);
+        }
+
+        // Another part of the program may reap the member first, keep waiting in that case
+        let remaining = remaining_until(deadline);
+        if remaining.is_zero() || !watch_any(P_PGID, pgid as id_t, remaining)? {
+            return _generate_timeout_error();
+        }
+    }
+}
+
+/// Waits for all the child processes of the process group to exit or until the timeout expires, and reaps them.
+///
+/// The pid and the exit status of each member are pushed to `exited` as they are reaped, including when
+/// the timeout expires. See [`wait_group_any_timeout`] for the members which are waited for.
+///
+/// This function is only available on Unix.
+///
+/// # Errors
+///
+/// This function may fail with `ErrorKind::TimedOut` if the timeout expires before all the members exit.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::os::unix::process::CommandExt;
+/// use std::process::Command;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::wait_group_all_timeout;
+///
+/// let leader = Command::new("sleep").arg("1").process_group(0).spawn()?;
+/// Command::new("sleep").arg("0").process_group(leader.id() as i32).spawn()?;
+///
+/// let mut exited = Vec::new();
+/// wait_group_all_timeout(leader.id(), Duration::from_secs(5), &mut exited)?;
+/// assert_eq!(exited.len(), 2);
+/// #     Ok(())
+/// # }
+/// ```
+pub fn wait_group_all_timeout(
+    pgid: u32,
+    timeout: Duration,
+    exited: &mut Vec<(u32, ExitStatus)>,
+) -> io::Result<()> {
+    let deadline = deadline_after(timeout);
+
+    loop {
+        match wait_group_any_timeout(pgid, remaining_until(deadline)) {
+            Ok(member) => exited.push(member),
+            Err(e) if e.raw_os_error() == Some(ECHILD) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Reaps all the child processes of the current process which have exited, then waits for the others
+/// to exit until the timeout expires.
+///
+/// The pid and the exit status of each child are pushed to `exited` as they are reaped, including when
+/// the timeout expires. This is the reaping loop of a process running as `init`, PID 1 in a container,
+/// or as a [child subreaper](super::set_child_subreaper), which adopts orphaned processes it never
+/// spawned: with a zero timeout, it only collects the zombies and returns right away.
+///
+/// Every child is reaped, including the ones spawned as a [`Child`](std::process::Child) by another
+/// part of the program, which should not be waited for anymore. The wait is performed by a single thread
+/// watching the children, which keeps running after a timeout and is shared by the next calls, so that
+/// the function can be called in a loop.
+///
+/// This function is only available on Unix.
+///
+/// # Errors
+///
+/// This function may fail with `ErrorKind::TimedOut` if the timeout expires before all the children
+/// exit, which is the case with a zero timeout as long as a child is still running.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::process::Command;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::reap_all_timeout;
+///
+/// Command::new("sleep").arg("0").spawn()?;
+/// Command::new("sleep").arg("1").spawn()?;
+///
+/// let mut exited = Vec::new();
+/// reap_all_timeout(Duration::from_secs(5), &mut exited)?;
+/// assert_eq!(exited.len(), 2);
+/// #     Ok(())
+/// # }
+/// ```
+pub fn reap_all_timeout(timeout: Duration, exited: &mut Vec<(u32, ExitStatus)>) -> io::Result<()> {
+    let deadline = deadline_after(timeout);
+
+    loop {
+        match reap_any(P_ALL, 0) {
+            Ok(Some(child)) => {
+                exited.push(child);
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) if e.raw_os_error() == Some(ECHILD) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+
+        let remaining = remaining_until(deadline);
+        if remaining.is_zero() {
+            return _generate_timeout_error();
+        }
+        match watch_any(P_ALL, 0, remaining) {
+            Ok(true) => {}
+            Ok(false) => return _generate_timeout_error(),
+            Err(e) if e.raw_os_error() == Some(ECHILD) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Reaps a child of the set designated by `waitid` which has exited, if any
+fn reap_any(idtype: idtype_t, id: id_t) -> io::Result<Option<(u32, ExitStatus)>> {
+    let mut info: siginfo_t = unsafe { mem::zeroed() };
+    if unsafe { waitid(idtype, id, &mut info, WEXITED | WNOHANG) } == -1 {
+        return _generate_os_error();
+    }
+
+    // With WNOHANG, the pid is left to zero if no child has exited
+    let pid = unsafe { info.si_pid() };
+    Ok(exit_status(&info)
+        .filter(|_| pid != 0)
+        .map(|status| (pid as u32, status)))
+}
+
+// The watcher of a set of children designated by `waitid`, shared by the waits on the set. A thread
+// cannot be cancelled while it is blocked in `waitid`, so a single one is kept per set and reused by
+// the next waits instead of spawning a new one each time.
+#[derive(Default)]
+struct SetWatcher {
+    state: Mutex<SetState>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct SetState {
+    // Set by a wait asking the thread to watch the set once more
+    armed: bool,
+    // The result of the last `waitid` with its error code, until a wait consumes an exit. An error
+    // ends the thread and is kept for all the waits holding the watcher.
+    exited: Option<Result<(), i32>>,
+}
+
+// The watchers currently running, indexed by the type and the identifier of their set
+type SetWatchers = HashMap<(idtype_t, id_t), Arc<SetWatcher>>;
+
+fn set_watchers() -> &'static Mutex<SetWatchers> {
+    static WATCHERS: OnceLock<Mutex<SetWatchers>> = OnceLock::new();
+    WATCHERS.get_or_init(Default::default)
+}
+
+fn set_watcher(idtype: idtype_t, id: id_t) -> io::Result<Arc<SetWatcher>> {
+    let key = (idtype, id);
+    let mut registry = set_watchers().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(watcher) = registry.get(&key) {
+        return Ok(watcher.clone());
+    }
+
+    let watcher = Arc::new(SetWatcher::default());
+    let thread_watcher = watcher.clone();
+    thread::Builder::new()
+        .name("child_wait_timeout".to_string())
+        .spawn(move || loop {
+            let mut state = thread_watcher
+                .state
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            while !state.armed {
+                state = thread_watcher
+                    .cond
+                    .wait(state)
+                    .unwrap_or_else(|e| e.into_inner());
+            }
+            drop(state);
+
+            let result = wait_exited(idtype, id);
+
+            // Unregister on error, such as `ECHILD` once the set has no child left
+            if result.is_err() {
+                let mut registry = set_watchers().lock().unwrap_or_else(|e| e.into_inner());
+                if registry
+                    .get(&key)
+                    .is_some_and(|w| Arc::ptr_eq(w, &thread_watcher))
+                {
+                    registry.remove(&key);
+                }
+            }
+
+            let mut state = thread_watcher
+                .state
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            state.armed = false;
+            let ended = result.is_err();
+            state.exited = Some(result.map_err(|e| e.raw_os_error().unwrap_or(0)));
+            thread_watcher.cond.notify_all();
+            if ended {
+                return;
+            }
+        })?;
+
+    registry.insert(key, watcher.clone());
+    Ok(watcher)
+}
+
+// Waits for a child of the set to exit without reaping it, returns `false` if the timeout expired
+fn watch_any(idtype: idtype_t, id: id_t, timeout: Duration) -> io::Result<bool> {
+    let watcher = set_watcher(idtype, id)?;
+    let mut state = watcher.state.lock().unwrap_or_else(|e| e.into_inner());
+    if state.exited.is_none() && !state.armed {
+        state.armed = true;
+        watcher.cond.notify_all();
+    }
+
+    let (mut state, _) = watcher
+        .cond
+        .wait_timeout_while(state, timeout, |state| state.exited.is_none())
+        .unwrap_or_else(|e| e.into_inner());
+    match state.exited {
+        None => Ok(false),
+        Some(Ok(())) => {
+            state.exited = None;
+            Ok(true)
+        }
+        Some(Err(errno)) => _generate_raw_os_error(errno),
+    }
+}
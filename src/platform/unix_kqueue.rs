@@ -0,0 +1,124 @@
+use std::io;
+use std::mem;
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+
+use libc::{close, kevent, kqueue, pid_t};
+use libc::{ESRCH, EVFILT_PROC, EV_ADD, EV_ERROR, EV_ONESHOT, NOTE_EXIT};
+
+use super::unix::{has_exited, restart_interrupted, to_timespec};
+use super::Backend;
+use crate::error::{_generate_os_error, _generate_raw_os_error, _generate_timeout_error};
+
+pub(crate) fn _active_backend() -> Backend {
+    Backend::Kqueue
+}
+
+pub(crate) fn _wait_timeout_untraced(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    _wait_pid_untraced(child.id(), timeout).map(|()| None)
+}
+
+// Waits for the child with the given pid to exit, without reaping it
+pub(crate) fn _wait_pid_untraced(pid: u32, timeout: Duration) -> io::Result<()> {
+    let pid = pid as pid_t;
+
+    let kq = unsafe { kqueue() };
+    if kq == -1 {
+        return _generate_os_error();
+    }
+
+    // Register interest in the exit of the child
+    let mut change: libc::kevent = unsafe { mem::zeroed() };
+    change.ident = pid as _;
+    change.filter = EVFILT_PROC;
+    change.flags = EV_ADD | EV_ONESHOT;
+    change.fflags = NOTE_EXIT;
+
+    // The registration is repeated if the wait is restarted, `EV_ADD` only updates it
+    let mut event: libc::kevent = unsafe { mem::zeroed() };
+    let result = restart_interrupted(timeout, |remaining| {
+        let ts = to_timespec(remaining);
+        match unsafe { kevent(kq, &change, 1, &mut event, 1, &ts) } {
+            -1 => _generate_os_error(),
+            result => Ok(result),
+        }
+    });
+
+    unsafe { close(kq) };
+
+    match result {
+        // The child may have already exited before the registration
+        Err(e) if e.raw_os_error() == Some(ESRCH) && has_exited(pid) => Ok(()),
+        Err(e) => Err(e),
+        Ok(0) => _generate_timeout_error(),
+        Ok(_) if event.flags & EV_ERROR == 0 || (event.data == ESRCH as _ && has_exited(pid)) => {
+            Ok(())
+        }
+        // The error code of the registration is returned in the data of the event
+        Ok(_) => _generate_raw_os_error(event.data as i32),
+    }
+}
+
+pub(crate) fn _wait_any_untraced(children: &[Child], timeout: Duration) -> io::Result<usize> {
+    let pids: Vec<pid_t> = children.iter().map(|child| child.id() as pid_t).collect();
+
+    let kq = unsafe { kqueue() };
+    if kq == -1 {
+        return _generate_os_error();
+    }
+
+    // Register interest in the exit of every child
+    let changes: Vec<libc::kevent> = pids
+        .iter()
+        .map(|&pid| {
+            let mut change: libc::kevent = unsafe { mem::zeroed() };
+            change.ident = pid as _;
+            change.filter = EVFILT_PROC;
+            change.flags = EV_ADD | EV_ONESHOT;
+            change.fflags = NOTE_EXIT;
+            change
+        })
+        .collect();
+
+    // The registration errors are returned as events, so there is room for one per child
+    let mut events: Vec<libc::kevent> = vec![unsafe { mem::zeroed() }; pids.len()];
+    let result = restart_interrupted(timeout, |remaining| {
+        let ts = to_timespec(remaining);
+        let result = unsafe {
+            kevent(
+                kq,
+                changes.as_ptr(),
+                changes.len() as _,
+                events.as_mut_ptr(),
+                events.len() as _,
+                &ts,
+            )
+        };
+        match result {
+            -1 => _generate_os_error(),
+            result => Ok(result),
+        }
+    });
+
+    unsafe { close(kq) };
+
+    let result = result?;
+    if result == 0 {
+        return _generate_timeout_error();
+    }
+
+    for event in &events[..result as usize] {
+        let Some(index) = pids.iter().position(|&pid| event.ident == pid as _) else {
+            continue;
+        };
+        // The child may have already exited before the registration
+        if event.flags & EV_ERROR == 0 || (event.data == ESRCH as _ && has_exited(pids[index])) {
+            return Ok(index);
+        }
+        return _generate_raw_os_error(event.data as i32);
+    }
+    _generate_timeout_error()
+}
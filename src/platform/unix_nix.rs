@@ -0,0 +1,158 @@
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use nix::sys::signal::Signal;
+use nix::sys::wait::WaitStatus;
+use nix::unistd::Pid;
+
+use super::unix_ext::{ExitInfo, ExitReason, WaitState};
+use super::{wait_group_all_timeout, wait_group_any_timeout, wait_pid_timeout};
+use crate::error::_generate_default_error;
+
+// The pids of processes are positive, the negative ones designate process groups in `kill` and `waitpid`
+fn raw_pid(pid: Pid) -> io::Result<u32> {
+    u32::try_from(pid.as_raw()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
+
+fn nix_pid(pid: u32) -> Pid {
+    Pid::from_raw(pid as libc::pid_t)
+}
+
+// Converts the exit status of a process which has terminated
+fn exit_wait_status(pid: Pid, status: ExitStatus) -> io::Result<WaitStatus> {
+    if let Some(code) = status.code() {
+        return Ok(WaitStatus::Exited(pid, code));
+    }
+    match status.signal() {
+        Some(signal) => Ok(WaitStatus::Signaled(
+            pid,
+            Signal::try_from(signal)?,
+            status.core_dumped(),
+        )),
+        None => _generate_default_error(),
+    }
+}
+
+/// Waits for the process with the nix `Pid` to exit or until the timeout expires.
+///
+/// This is [`wait_pid_timeout`] for codebases built on the `nix` crate.
+///
+/// This function is only available on Unix with the `nix` feature.
+///
+/// # Errors
+///
+/// This function fails with `ErrorKind::InvalidInput` if the pid is not positive, and otherwise as
+/// [`wait_pid_timeout`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::wait_pid_timeout_nix;
+/// use nix::unistd::Pid;
+///
+/// let mut child = Command::new("sleep").arg("1").spawn()?;
+/// wait_pid_timeout_nix(Pid::from_raw(child.id() as i32), Duration::from_secs(5))?;
+/// assert!(child.wait()?.success());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn wait_pid_timeout_nix(pid: Pid, timeout: Duration) -> io::Result<()> {
+    wait_pid_timeout(raw_pid(pid)?, timeout)
+}
+
+/// Waits for any member of the process group to exit, and returns its nix `WaitStatus`.
+///
+/// This is [`wait_group_any_timeout`] for codebases built on the `nix` crate, the `WaitStatus` holding
+/// the pid of the member which exited.
+///
+/// This function is only available on Unix with the `nix` feature.
+///
+/// # Errors
+///
+/// This function fails with `ErrorKind::InvalidInput` if the process group id is not positive, and
+/// otherwise as [`wait_group_any_timeout`].
+pub fn wait_group_any_timeout_nix(pgid: Pid, timeout: Duration) -> io::Result<WaitStatus> {
+    let (pid, status) = wait_group_any_timeout(raw_pid(pgid)?, timeout)?;
+    exit_wait_status(nix_pid(pid), status)
+}
+
+/// Waits for all the members of the process group to exit, pushing the nix `WaitStatus` of each one
+/// to `exited` as it is reaped.
+///
+/// This is [`wait_group_all_timeout`] for codebases built on the `nix` crate.
+///
+/// This function is only available on Unix with the `nix` feature.
+///
+/// # Errors
+///
+/// This function fails with `ErrorKind::InvalidInput` if the process group id is not positive, and
+/// otherwise as [`wait_group_all_timeout`]. The members reaped before the error are still pushed.
+pub fn wait_group_all_timeout_nix(
+    pgid: Pid,
+    timeout: Duration,
+    exited: &mut Vec<WaitStatus>,
+) -> io::Result<()> {
+    let mut members = Vec::new();
+    let result = wait_group_all_timeout(raw_pid(pgid)?, timeout, &mut members);
+    for (pid, status) in members {
+        exited.push(exit_wait_status(nix_pid(pid), status)?);
+    }
+    result
+}
+
+impl ExitInfo {
+    /// Returns the pid of the process as a nix `Pid`.
+    ///
+    /// This method is only available with the `nix` feature.
+    pub fn nix_pid(&self) -> Pid {
+        nix_pid(self.pid())
+    }
+
+    /// Returns the exit of the process as a nix `WaitStatus`.
+    ///
+    /// This method is only available with the `nix` feature.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the `EINVAL` OS error if the signal which terminated the process is unknown to nix.
+    pub fn to_wait_status(&self) -> io::Result<WaitStatus> {
+        let pid = self.nix_pid();
+        Ok(match self.reason() {
+            ExitReason::Exited(code) => WaitStatus::Exited(pid, code),
+            ExitReason::Killed(signal) => {
+                WaitStatus::Signaled(pid, Signal::try_from(signal)?, false)
+            }
+            ExitReason::Dumped(signal) => {
+                WaitStatus::Signaled(pid, Signal::try_from(signal)?, true)
+            }
+        })
+    }
+}
+
+impl WaitState {
+    /// Returns the change of state of the process with the pid as a nix `WaitStatus`.
+    ///
+    /// A ptrace stop is returned as `WaitStatus::Stopped`, as reported by `waitpid`.
+    ///
+    /// This method is only available with the `nix` feature.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the `EINVAL` OS error if the signal of the state is unknown to nix.
+    pub fn to_wait_status(&self, pid: Pid) -> io::Result<WaitStatus> {
+        Ok(match *self {
+            WaitState::Exited(status) => return exit_wait_status(pid, status),
+            WaitState::Stopped(signal) | WaitState::Traced(signal) => {
+                WaitStatus::Stopped(pid, Signal::try_from(signal)?)
+            }
+            WaitState::Continued => WaitStatus::Continued(pid),
+        })
+    }
+}
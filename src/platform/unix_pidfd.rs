@@ -1,52 +1,515 @@
 use std::io;
 use std::mem;
-use std::os::unix::io::RawFd;
-use std::process::Child;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::process::{Child, ExitStatus, Output};
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-use libc::{c_uint, close, pid_t, select, suseconds_t, time_t, timeval};
+use libc::{c_int, close, nfds_t, pid_t, ppoll, ECHILD, EINVAL};
+use libc::{id_t, pollfd, siginfo_t, waitid, POLLIN, P_PIDFD, WEXITED, WNOHANG, WNOWAIT};
 
-use crate::error::{_generate_default_error, _generate_timeout_error};
+use super::unix::{exit_status, pidfd_open, restart_interrupted, to_timespec};
+use super::unix_thread as fallback;
+#[cfg(all(target_os = "linux", feature = "uring"))]
+use super::unix_uring;
+use super::{active_backend, sampling, trace};
+use super::{kill_on_timeout, terminate_on_timeout, wait_timeout_with, Backend};
+use super::{stream_output, wait_with_output, ChildExitFuture, ChildWT, OutputChunk};
+use crate::error::_generate_unsupported_error;
+use crate::error::{
+    _generate_default_error, _generate_no_such_process_error, _generate_os_error,
+    _generate_timeout_error,
+};
 
-fn pidfd_open(pid: pid_t, flags: c_uint) -> io::Result<RawFd> {
-    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, flags) };
-    if fd < 0 {
-        _generate_default_error()?;
+#[path = "unix_epoll.rs"]
+mod epoll;
+pub use epoll::PidFdEpoll;
+
+// Set once `pidfd_open` is known to be unavailable on this system
+static PIDFD_UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+// Set once `pidfd_open` has been probed successfully on the current process
+static PIDFD_PROBED: AtomicBool = AtomicBool::new(false);
+
+// Records that pidfds are unavailable, the fallback is reported the first time only
+fn set_pidfd_unavailable() {
+    if !PIDFD_UNAVAILABLE.swap(true, Ordering::Relaxed) {
+        trace::backend_fallback("pidfd_open", fallback::_active_backend().name());
     }
-    Ok(fd as RawFd)
 }
 
-pub(crate) fn _wait_timeout_untraced_ms(child: &mut Child, timeout_ms: u32) -> io::Result<()> {
-    let pid = child.id() as pid_t;
+pub(crate) fn _active_backend() -> Backend {
+    // Probed once on the current process, the waits record its unavailability afterwards
+    if !PIDFD_UNAVAILABLE.load(Ordering::Relaxed) && !PIDFD_PROBED.load(Ordering::Relaxed) {
+        match pidfd_open(unsafe { libc::getpid() }, 0) {
+            Ok(Some(pidfd)) => {
+                // The current process is not its own child, `waitid` fails with `ECHILD` unless it
+                // rejects pidfds, which records their unavailability
+                let _ = pidfd_status(pidfd, WNOHANG);
+                unsafe { close(pidfd) };
+                PIDFD_PROBED.store(true, Ordering::Relaxed);
+            }
+            Ok(None) => set_pidfd_unavailable(),
+            // Probed again by the next call, such as after running out of file descriptors
+            Err(_) => {}
+        }
+    }
+    if PIDFD_UNAVAILABLE.load(Ordering::Relaxed) {
+        return fallback::_active_backend();
+    }
 
-    let pidfd = pidfd_open(pid, 0)?;
+    #[cfg(all(target_os = "linux", feature = "uring"))]
+    if unix_uring::is_available() {
+        return Backend::Uring;
+    }
+    Backend::Pidfd
+}
 
-    // Convert the timeout to a timespec structure
-    let mut tv = timeval {
-        tv_sec: (timeout_ms / 1000) as time_t,
-        tv_usec: (timeout_ms % 1000) as suseconds_t * 1000,
-    };
+pub(crate) fn _wait_timeout_untraced(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    wait_pid(child.id() as pid_t, timeout)
+}
 
-    let mut fd_set: libc::fd_set = unsafe { mem::zeroed() };
-    unsafe { libc::FD_SET(pidfd, &mut fd_set) };
+// Waits for the child with the given pid to exit, without reaping it
+pub(crate) fn _wait_pid_untraced(pid: u32, timeout: Duration) -> io::Result<()> {
+    wait_pid(pid as pid_t, timeout).map(|_| ())
+}
 
-    let result = unsafe {
-        select(
-            pidfd + 1,
-            &mut fd_set,
-            ptr::null_mut(),
-            ptr::null_mut(),
-            &mut tv,
-        )
+fn wait_pid(pid: pid_t, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    if PIDFD_UNAVAILABLE.load(Ordering::Relaxed) {
+        return fallback::_wait_pid_untraced(pid as u32, timeout).map(|()| None);
+    }
+
+    let Some(pidfd) = pidfd_open(pid, 0)? else {
+        set_pidfd_unavailable();
+        return fallback::_wait_pid_untraced(pid as u32, timeout).map(|()| None);
     };
 
+    let result = wait_pidfd(pidfd, timeout);
+
     unsafe { close(pidfd) };
 
-    if result == -1 {
-        _generate_default_error()
-    } else if result == 0 {
+    result
+}
+
+pub(crate) fn _wait_any_untraced(children: &[Child], timeout: Duration) -> io::Result<usize> {
+    if PIDFD_UNAVAILABLE.load(Ordering::Relaxed) {
+        return fallback::_wait_any_untraced(children, timeout);
+    }
+
+    let mut pidfds = Vec::with_capacity(children.len());
+    for child in children {
+        let Some(pidfd) = pidfd_open(child.id() as pid_t, 0)? else {
+            set_pidfd_unavailable();
+            return fallback::_wait_any_untraced(children, timeout);
+        };
+        pidfds.push(unsafe { OwnedFd::from_raw_fd(pidfd) });
+    }
+
+    let mut pollfds: Vec<pollfd> = pidfds
+        .iter()
+        .map(|pidfd| pollfd {
+            fd: pidfd.as_raw_fd(),
+            events: POLLIN,
+            revents: 0,
+        })
+        .collect();
+
+    let result = restart_interrupted(timeout, |remaining| {
+        let ts = to_timespec(remaining);
+        let result = unsafe {
+            ppoll(
+                pollfds.as_mut_ptr(),
+                pollfds.len() as nfds_t,
+                &ts,
+                ptr::null(),
+            )
+        };
+        match result {
+            -1 => _generate_os_error(),
+            result => Ok(result),
+        }
+    })?;
+
+    if result == 0 {
         _generate_timeout_error()
     } else {
-        Ok(())
+        match pollfds.iter().position(|pollfd| pollfd.revents != 0) {
+            Some(index) => Ok(index),
+            None => _generate_default_error(),
+        }
+    }
+}
+
+fn wait_pidfd(pidfd: RawFd, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    poll_pidfd(pidfd, timeout)?;
+    match exited_status(pidfd) {
+        // `waitid` rejects pidfds before Linux 5.4, the child has exited and is reaped by `Child::wait`
+        Err(e) if e.kind() == io::ErrorKind::Unsupported => Ok(None),
+        result => result,
+    }
+}
+
+// Waits for the pidfd to become readable, with io_uring if available and enabled
+fn poll_pidfd(pidfd: RawFd, timeout: Duration) -> io::Result<()> {
+    #[cfg(all(target_os = "linux", feature = "uring"))]
+    match unix_uring::poll_pidfd(pidfd, timeout)? {
+        Some(true) => return Ok(()),
+        Some(false) => return _generate_timeout_error(),
+        None => {
+            // io_uring is not available, wait with ppoll
+        }
+    }
+
+    ppoll_pidfd(pidfd, timeout)
+}
+
+// Collects the exit status once the pidfd is readable, `None` if another thread reaped the child in
+// the meantime, which `waitid` reports with `ECHILD`
+fn exited_status(pidfd: RawFd) -> io::Result<Option<ExitStatus>> {
+    match pidfd_status(pidfd, 0) {
+        Err(e) if e.raw_os_error() == Some(ECHILD) => Ok(None),
+        result => result,
+    }
+}
+
+// Waits for the child with the given pid to exit with `ppoll` on its pidfd, or with io_uring if `uring`
+// is set, without falling back to another backend
+pub(crate) fn _wait_pid_with(pid: u32, timeout: Duration, uring: bool) -> io::Result<()> {
+    let Some(pidfd) = pidfd_open(pid as pid_t, 0)? else {
+        set_pidfd_unavailable();
+        return _generate_unsupported_error();
+    };
+    let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd) };
+
+    if !uring {
+        return ppoll_pidfd(pidfd.as_raw_fd(), timeout);
+    }
+    #[cfg(all(target_os = "linux", feature = "uring"))]
+    match unix_uring::poll_pidfd(pidfd.as_raw_fd(), timeout)? {
+        Some(true) => return Ok(()),
+        Some(false) => return _generate_timeout_error(),
+        None => {
+            // io_uring is not available
+        }
+    }
+    _generate_unsupported_error()
+}
+
+fn ppoll_pidfd(pidfd: RawFd, timeout: Duration) -> io::Result<()> {
+    // Unlike `select`, `poll` works whatever the value of the file descriptor
+    let mut pollfd = pollfd {
+        fd: pidfd,
+        events: POLLIN,
+        revents: 0,
+    };
+
+    restart_interrupted(timeout, |remaining| {
+        // Convert the timeout to a timespec structure, `ppoll` is used rather than `poll`
+        // to keep the precision of the timeout beyond milliseconds
+        let ts = to_timespec(remaining);
+
+        match unsafe { ppoll(&mut pollfd, 1, &ts, ptr::null()) } {
+            -1 => _generate_os_error(),
+            0 => _generate_timeout_error(),
+            _ => Ok(()),
+        }
+    })
+}
+
+// Collects the exit status through the pidfd. The zombie is left in place so that
+// `Child` can still reap it, but the status is known even if another part of the
+// program reaps the child first.
+fn pidfd_status(pidfd: RawFd, options: c_int) -> io::Result<Option<ExitStatus>> {
+    let mut info: siginfo_t = unsafe { mem::zeroed() };
+    let result = unsafe {
+        waitid(
+            P_PIDFD,
+            pidfd as id_t,
+            &mut info,
+            WEXITED | WNOWAIT | options,
+        )
+    };
+    if result == -1 {
+        // `P_PIDFD` is only accepted since Linux 5.4, one version after `pidfd_open`
+        if io::Error::last_os_error().raw_os_error() == Some(EINVAL) {
+            set_pidfd_unavailable();
+            return _generate_unsupported_error();
+        }
+        return _generate_os_error();
+    }
+
+    // With WNOHANG, the pid is left to zero if the process is still running
+    if unsafe { info.si_pid() } == 0 {
+        return Ok(None);
+    }
+
+    Ok(exit_status(&info))
+}
+
+/// A file descriptor referring to a process, as returned by `pidfd_open`.
+///
+/// The descriptor becomes readable when the process exits, so it can be registered in an
+/// `epoll` or `poll` loop through [`AsFd`]/[`AsRawFd`], then [`PidFd::try_wait`] retrieves the
+/// exit status once the loop reports it ready.
+///
+/// The process is never reaped through a `PidFd`, the exit status is read with `WNOWAIT`
+/// and the zombie is left for [`Child::wait`] or [`ChildWT::wait_timeout`] to release.
+/// Retrieving the exit status requires the process to be a child of the current process,
+/// and Linux 5.4 or later: on Linux 5.3, the pidfd can be opened and polled, but reading the
+/// exit status fails with `ErrorKind::Unsupported`.
+///
+/// This type is only available with the `pidfd` feature.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::PidFd;
+///
+/// let mut child = Command::new("sleep").arg("1").spawn()?;
+/// let pidfd = PidFd::from_child(&child)?;
+/// let status = pidfd.wait_timeout(Duration::from_secs(5))?;
+/// assert_eq!(child.wait()?, status);
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PidFd {
+    fd: OwnedFd,
+}
+
+impl PidFd {
+    /// Opens a pidfd referring to the given child process.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ErrorKind::Unsupported` if `pidfd_open` is not available on this system.
+    pub fn from_child(child: &Child) -> io::Result<Self> {
+        Self::from_pid(child.id())
+    }
+
+    /// Opens a pidfd referring to the process with the given pid.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ErrorKind::Unsupported` if `pidfd_open` is not available on this system.
+    pub fn from_pid(pid: u32) -> io::Result<Self> {
+        match pidfd_open(pid as pid_t, 0)? {
+            Some(fd) => Ok(PidFd {
+                fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            }),
+            None => {
+                set_pidfd_unavailable();
+                _generate_unsupported_error()
+            }
+        }
+    }
+
+    /// Waits for the process to exit or until the timeout expires, without reaping it.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the specified duration expires before the process exits,
+    /// or with `ErrorKind::NotFound` if the process was reaped by another part of the program before its exit status
+    /// could be read, or with `ErrorKind::Unsupported` if the system cannot read the exit status through a pidfd.
+    pub fn wait_timeout(&self, timeout: Duration) -> io::Result<ExitStatus> {
+        poll_pidfd(self.as_raw_fd(), timeout)?;
+        match exited_status(self.as_raw_fd())? {
+            Some(status) => Ok(status),
+            None => _generate_no_such_process_error(),
+        }
+    }
+
+    /// Returns the exit status if the process has exited, without blocking nor reaping it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `ErrorKind::Unsupported` if the system cannot read the exit status through a pidfd.
+    pub fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        pidfd_status(self.as_raw_fd(), WNOHANG)
+    }
+
+    /// Builds an io_uring `POLL_ADD` submission entry completing when the process exits.
+    ///
+    /// The entry can be pushed into the application's own ring, then [`PidFd::try_wait`]
+    /// retrieves the exit status once it completes. The pidfd must outlive the operation.
+    ///
+    /// This function is only available with the `uring` feature.
+    #[cfg(all(target_os = "linux", feature = "uring"))]
+    pub fn poll_entry(&self) -> io_uring::squeue::Entry {
+        unix_uring::poll_entry(self.as_raw_fd())
+    }
+}
+
+impl AsFd for PidFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for PidFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for PidFd {
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_raw_fd()
+    }
+}
+
+impl From<PidFd> for OwnedFd {
+    fn from(pidfd: PidFd) -> Self {
+        pidfd.fd
+    }
+}
+
+/// A child process whose pidfd is kept open across calls to [`ChildWT::wait_timeout`].
+///
+/// Waiting on a [`Child`] opens and closes a pidfd on every call. When the same child is
+/// waited on repeatedly with short timeouts, wrapping it in a `PidFdChild` opens the pidfd
+/// on the first wait and keeps it alive until the wrapper is dropped.
+///
+/// This type is only available with the `pidfd` feature.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{ChildWT, PidFdChild};
+///
+/// let mut child = PidFdChild::new(Command::new("sleep").arg("1").spawn()?);
+/// let status = loop {
+///     match child.wait_timeout(Duration::from_millis(100)) {
+///         Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+///         status => break status?,
+///     }
+/// };
+/// assert!(status.success());
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PidFdChild {
+    child: Child,
+    // `None` until the first wait, or if `pidfd_open` is unavailable
+    pidfd: Option<PidFd>,
+}
+
+impl PidFdChild {
+    /// Wraps a child process, the pidfd is opened on the first wait.
+    pub fn new(child: Child) -> Self {
+        PidFdChild { child, pidfd: None }
+    }
+
+    /// Returns a reference to the wrapped child process.
+    pub fn as_child(&self) -> &Child {
+        &self.child
+    }
+
+    /// Returns a mutable reference to the wrapped child process.
+    pub fn as_child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Returns the pidfd of the child process, if it has already been opened.
+    pub fn pidfd(&self) -> Option<&PidFd> {
+        self.pidfd.as_ref()
+    }
+
+    /// Consumes the wrapper and returns the child process, closing the pidfd.
+    pub fn into_inner(self) -> Child {
+        self.child
+    }
+}
+
+impl From<Child> for PidFdChild {
+    fn from(child: Child) -> Self {
+        PidFdChild::new(child)
+    }
+}
+
+impl ChildWT for PidFdChild {
+    fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        let _span = trace::WaitSpan::enter(self.child.id(), active_backend, timeout);
+        let PidFdChild { child, pidfd } = self;
+        wait_timeout_with(child, timeout, |child, timeout| {
+            if pidfd.is_none() && !PIDFD_UNAVAILABLE.load(Ordering::Relaxed) {
+                match PidFd::from_child(child) {
+                    Ok(fd) => *pidfd = Some(fd),
+                    Err(e) if e.kind() == io::ErrorKind::Unsupported => {}
+                    Err(e) => return Err(e),
+                }
+            }
+
+            match pidfd {
+                Some(fd) => wait_pidfd(fd.as_raw_fd(), timeout),
+                None => fallback::_wait_timeout_untraced(child, timeout),
+            }
+        })
+    }
+
+    fn wait_timeout_opt(&mut self, timeout: Option<Duration>) -> io::Result<ExitStatus> {
+        match timeout {
+            Some(timeout) => self.wait_timeout(timeout),
+            None => self.child.wait(),
+        }
+    }
+
+    fn wait_timeout_with_backend(
+        &mut self,
+        backend: Backend,
+        timeout: Duration,
+    ) -> io::Result<ExitStatus> {
+        self.child.wait_timeout_with_backend(backend, timeout)
+    }
+
+    fn wait_or_kill(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        let result = self.wait_timeout(timeout);
+        kill_on_timeout(&mut self.child, result)
+    }
+
+    fn shutdown(&mut self, timeout: Duration, grace: Duration) -> io::Result<ExitStatus> {
+        let result = self.wait_timeout(timeout);
+        terminate_on_timeout(&mut self.child, result, grace)
+    }
+
+    fn exit_future(&mut self) -> ChildExitFuture<'_> {
+        ChildExitFuture::new(&mut self.child)
+    }
+
+    fn wait_cpu_timeout(&mut self, limit: Duration) -> io::Result<ExitStatus> {
+        let pid = self.child.id();
+        sampling::wait_cpu_timeout(self, pid, limit)
+    }
+
+    fn wait_with_output_timeout(mut self, timeout: Duration) -> io::Result<Output> {
+        let (stdout, stderr) = (self.child.stdout.take(), self.child.stderr.take());
+        wait_with_output(&mut self, stdout, stderr, timeout)
+    }
+
+    fn stream_output_timeout<F>(
+        &mut self,
+        timeout: Duration,
+        on_output: F,
+    ) -> io::Result<ExitStatus>
+    where
+        F: FnMut(OutputChunk<'_>),
+    {
+        let (stdout, stderr) = (self.child.stdout.take(), self.child.stderr.take());
+        stream_output(self, stdout, stderr, timeout, on_output)
     }
 }
@@ -0,0 +1,115 @@
+// Event ports have no source for the exit of a process, but the `ctl` file of a process in `/proc`
+// reports `POLLHUP` once it terminates, which is associated with the port as a file descriptor event
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::process::{Child, ExitStatus};
+use std::thread;
+use std::time::Duration;
+
+use libc::{pid_t, port_associate, port_create, port_event, port_get};
+use libc::{EINTR, ENOENT, ETIME, POLLHUP, PORT_SOURCE_FD};
+
+use super::unix::{has_exited, restart_on_interrupt, to_timespec};
+use super::{deadline_after, remaining_until, Backend};
+use crate::error::{_generate_os_error, _generate_timeout_error};
+
+// How often the children are checked when their `/proc` files cannot be opened
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub(crate) fn _active_backend() -> Backend {
+    Backend::EventPorts
+}
+
+pub(crate) fn _wait_timeout_untraced(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    _wait_pid_untraced(child.id(), timeout).map(|()| None)
+}
+
+// Waits for the child with the given pid to exit, without reaping it
+pub(crate) fn _wait_pid_untraced(pid: u32, timeout: Duration) -> io::Result<()> {
+    wait_any_pid(&[pid as pid_t], timeout).map(drop)
+}
+
+pub(crate) fn _wait_any_untraced(children: &[Child], timeout: Duration) -> io::Result<usize> {
+    let pids: Vec<pid_t> = children.iter().map(|child| child.id() as pid_t).collect();
+    wait_any_pid(&pids, timeout)
+}
+
+fn wait_any_pid(pids: &[pid_t], timeout: Duration) -> io::Result<usize> {
+    let port = unsafe { port_create() };
+    if port == -1 {
+        return _generate_os_error();
+    }
+    let port = unsafe { OwnedFd::from_raw_fd(port) };
+
+    // The files are closed once the wait is over, which also removes them from the port
+    let mut files = Vec::with_capacity(pids.len());
+    for (index, &pid) in pids.iter().enumerate() {
+        let file = match OpenOptions::new()
+            .write(true)
+            .open(format!("/proc/{}/ctl", pid))
+        {
+            Ok(file) => file,
+            // The files of a zombie are gone, except its `psinfo` and `status`
+            Err(e) if e.raw_os_error() == Some(ENOENT) && has_exited(pid) => return Ok(index),
+            Err(e) if e.raw_os_error() == Some(ENOENT) => return Err(e),
+            // The file of a child running with other privileges cannot be opened
+            Err(_) => return poll_any_pid(pids, timeout),
+        };
+        associate(&port, &file, index)?;
+        files.push(file);
+    }
+
+    let deadline = deadline_after(timeout);
+    loop {
+        let mut event: port_event = unsafe { mem::zeroed() };
+        let mut ts = to_timespec(remaining_until(deadline));
+        if unsafe { port_get(port.as_raw_fd(), &mut event, &mut ts) } == 0 {
+            return Ok(event.portev_user as usize);
+        }
+
+        let error = io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(ETIME) => return _generate_timeout_error(),
+            // The associations are kept until an event is retrieved
+            Some(EINTR) if restart_on_interrupt() => {}
+            _ => return Err(error),
+        }
+    }
+}
+
+// Associates the file with the port, the index of the child is returned as the user data
+fn associate(port: &OwnedFd, file: &File, index: usize) -> io::Result<()> {
+    let result = unsafe {
+        port_associate(
+            port.as_raw_fd(),
+            PORT_SOURCE_FD,
+            file.as_raw_fd() as _,
+            POLLHUP as _,
+            index as *mut _,
+        )
+    };
+    if result == -1 {
+        return _generate_os_error();
+    }
+    Ok(())
+}
+
+fn poll_any_pid(pids: &[pid_t], timeout: Duration) -> io::Result<usize> {
+    let deadline = deadline_after(timeout);
+    loop {
+        if let Some(index) = pids.iter().position(|&pid| has_exited(pid)) {
+            return Ok(index);
+        }
+        let remaining = remaining_until(deadline);
+        if remaining.is_zero() {
+            return _generate_timeout_error();
+        }
+        thread::sleep(remaining.min(POLL_INTERVAL));
+    }
+}
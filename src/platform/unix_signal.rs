@@ -1,48 +1,285 @@
 use std::io;
 use std::mem;
-use std::process::Child;
+#[cfg(all(any(target_os = "linux", target_os = "android"), feature = "signalfd"))]
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::process::{Child, ExitStatus};
 use std::ptr;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::{Duration, Instant};
 
-use libc::{c_long, pid_t, sigtimedwait, time_t, timespec};
-use libc::{sigemptyset, siginfo_t, SIGCHLD};
+use libc::{pid_t, sigset_t};
+use libc::{pthread_sigmask, sigemptyset, SIGCHLD, SIGKILL, SIGSTOP, SIG_BLOCK, SIG_SETMASK};
 
-use crate::error::{_generate_default_error, _generate_timeout_error};
+use super::unix::{has_exited, restart_on_interrupt, to_timespec};
+use super::{deadline_after, remaining_until, Backend};
+#[cfg(all(any(target_os = "linux", target_os = "android"), feature = "signalfd"))]
+use crate::error::_generate_os_error;
+use crate::error::{_generate_raw_os_error, _generate_timeout_error};
 
-pub(crate) fn _wait_timeout_untraced_ms(child: &mut Child, timeout_ms: u32) -> io::Result<()> {
-    let pid = child.id() as pid_t;
+// Signal notifying the exit of a child, SIGCHLD unless configured otherwise
+static NOTIFICATION_SIGNAL: AtomicI32 = AtomicI32::new(SIGCHLD);
 
-    // Convert the timeout to a timeval structure
-    let ts = timespec {
-        tv_sec: (timeout_ms / 1000) as time_t,
-        tv_nsec: (timeout_ms % 1000) as c_long * 1000000,
-    };
+// Longest wait for the notification signal before the children are checked again, the signal is only
+// blocked in the waiting thread and may be delivered to another one
+const MISSED_SIGNAL_INTERVAL: Duration = Duration::from_millis(100);
 
-    // Set up the signal set to wait for SIGCHLD
-    let mut sigset: libc::sigset_t = unsafe { mem::zeroed() };
+/// Sets the signal waited for by the `signal` backend to detect the exit of a child.
+///
+/// By default, the backend waits for `SIGCHLD`. Applications arranging for their children to
+/// notify their exit with another signal, such as a real-time signal sent from the child or set
+/// as the exit signal with `clone`, can configure it here. The signal only wakes the wait, which
+/// then checks the children with `waitid`. The setting applies to the whole process.
+///
+/// This function is only available with the `signal` feature.
+///
+/// # Errors
+///
+/// Fails with `ErrorKind::InvalidInput` if the signal is not valid or cannot be blocked.
+pub fn set_notification_signal(signal: i32) -> io::Result<()> {
+    let mut sigset: sigset_t = unsafe { mem::zeroed() };
+    let valid =
+        unsafe { sigemptyset(&mut sigset) == 0 && libc::sigaddset(&mut sigset, signal) == 0 };
+    if !valid || signal == SIGKILL || signal == SIGSTOP {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid notification signal",
+        ));
+    }
+
+    NOTIFICATION_SIGNAL.store(signal, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Returns the signal waited for by the `signal` backend, `SIGCHLD` by default.
+///
+/// This function is only available with the `signal` feature.
+pub fn notification_signal() -> i32 {
+    NOTIFICATION_SIGNAL.load(Ordering::Relaxed)
+}
+
+pub(crate) fn _active_backend() -> Backend {
+    if cfg!(all(
+        any(target_os = "linux", target_os = "android"),
+        feature = "signalfd"
+    )) {
+        Backend::Signalfd
+    } else {
+        Backend::Signal
+    }
+}
+
+pub(crate) fn _wait_timeout_untraced(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    _wait_pid_untraced(child.id(), timeout).map(|()| None)
+}
+
+// Waits for the child with the given pid to exit, without reaping it
+pub(crate) fn _wait_pid_untraced(pid: u32, timeout: Duration) -> io::Result<()> {
+    wait_any_pid(&[pid as pid_t], timeout).map(|_| ())
+}
+
+pub(crate) fn _wait_any_untraced(children: &[Child], timeout: Duration) -> io::Result<usize> {
+    let pids: Vec<pid_t> = children.iter().map(|child| child.id() as pid_t).collect();
+    wait_any_pid(&pids, timeout)
+}
+
+// Waits for the notification signal sent by one of the children, returns its index
+fn wait_any_pid(pids: &[pid_t], timeout: Duration) -> io::Result<usize> {
+    let deadline = deadline_after(timeout);
+
+    // Set up the signal set to wait for the notification signal
+    let signal = notification_signal();
+    let mut sigset: sigset_t = unsafe { mem::zeroed() };
     unsafe {
         sigemptyset(&mut sigset);
-        libc::sigaddset(&mut sigset, SIGCHLD);
+        libc::sigaddset(&mut sigset, signal);
     }
 
-    // Block SIGCHLD so it can be caught by sigtimedwait
-    unsafe {
-        libc::sigprocmask(libc::SIG_BLOCK, &sigset, ptr::null_mut());
+    wait_sigchld_from(pids, &sigset, deadline)
+}
+
+// Waits for the notification signal sent by one of the children and returns its index. The
+// notifications of other children are consumed as well, their own waits notice their exit by
+// checking them again on the next interval.
+fn wait_sigchld_from(
+    pids: &[pid_t],
+    sigset: &sigset_t,
+    deadline: Option<Instant>,
+) -> io::Result<usize> {
+    // Block the signal so it can be caught by sigtimedwait or the signalfd, the
+    // previous mask of the thread is restored when the guard is dropped
+    let _mask = SigMaskGuard::block(sigset)?;
+    let notifier = Notifier::new(sigset, deadline)?;
+
+    loop {
+        // The signal is not blocked between the calls and notifications of several
+        // children may be merged, the child may have exited in the meantime
+        if let Some(index) = pids.iter().position(|&pid| has_exited(pid)) {
+            return Ok(index);
+        } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return _generate_timeout_error();
+        }
+
+        // The exit of the children and the timeout are checked at the beginning of the loop, at least
+        // once per interval in case the notification was delivered to another thread
+        notifier.wait(deadline)?;
     }
+}
 
-    // Wait for SIGCHLD with a timeout
-    let mut siginfo: siginfo_t = unsafe { std::mem::zeroed() };
-    let result = unsafe { sigtimedwait(&sigset, &mut siginfo, &ts) };
+// Restores the signal mask of the calling thread when dropped
+struct SigMaskGuard {
+    previous: sigset_t,
+}
 
-    if result == -1 {
-        let errno = unsafe { *libc::__errno_location() };
-        if errno == libc::EAGAIN {
-            _generate_timeout_error()
+impl SigMaskGuard {
+    fn block(sigset: &sigset_t) -> io::Result<Self> {
+        let mut previous: sigset_t = unsafe { mem::zeroed() };
+        let result = unsafe { pthread_sigmask(SIG_BLOCK, sigset, &mut previous) };
+        if result != 0 {
+            return _generate_raw_os_error(result);
+        }
+        Ok(SigMaskGuard { previous })
+    }
+}
+
+impl Drop for SigMaskGuard {
+    fn drop(&mut self) {
+        // Cannot fail, the mask was returned by the previous call
+        unsafe { pthread_sigmask(SIG_SETMASK, &self.previous, ptr::null_mut()) };
+    }
+}
+
+// Receives the notification signal with `sigtimedwait`, the remaining time is converted again
+// before each wait
+#[cfg(not(all(any(target_os = "linux", target_os = "android"), feature = "signalfd")))]
+struct Notifier<'a> {
+    sigset: &'a sigset_t,
+}
+
+#[cfg(not(all(any(target_os = "linux", target_os = "android"), feature = "signalfd")))]
+impl<'a> Notifier<'a> {
+    fn new(sigset: &'a sigset_t, _deadline: Option<Instant>) -> io::Result<Self> {
+        Ok(Notifier { sigset })
+    }
+
+    // Waits for the signal until the deadline or for the interval
+    fn wait(&self, deadline: Option<Instant>) -> io::Result<()> {
+        // Convert the remaining time to a timespec structure
+        let ts = to_timespec(remaining_until(deadline).min(MISSED_SIGNAL_INTERVAL));
+
+        let mut siginfo: libc::siginfo_t = unsafe { mem::zeroed() };
+        let result = unsafe { libc::sigtimedwait(self.sigset, &mut siginfo, &ts) };
+
+        if result == -1 {
+            let error = io::Error::last_os_error();
+            // The wait may be interrupted by a signal delivered to the thread, the caller checks the
+            // children again as if the timeout had expired, unless the interruptions are surfaced
+            if error.raw_os_error() == Some(libc::EAGAIN)
+                || (error.raw_os_error() == Some(libc::EINTR) && restart_on_interrupt())
+            {
+                Ok(())
+            } else {
+                Err(error)
+            }
         } else {
-            _generate_default_error()
+            Ok(())
+        }
+    }
+}
+
+// Receives the notification signal from a signalfd, polled along with a timerfd armed once with
+// the deadline on the monotonic clock, so that the signals waking the thread never reset the
+// timeout nor call for converting it again
+#[cfg(all(any(target_os = "linux", target_os = "android"), feature = "signalfd"))]
+struct Notifier {
+    signal: OwnedFd,
+    timer: OwnedFd,
+}
+
+#[cfg(all(any(target_os = "linux", target_os = "android"), feature = "signalfd"))]
+impl Notifier {
+    fn new(sigset: &sigset_t, deadline: Option<Instant>) -> io::Result<Self> {
+        use libc::{itimerspec, signalfd, timerfd_create, timerfd_settime, CLOCK_MONOTONIC};
+        use libc::{SFD_CLOEXEC, SFD_NONBLOCK, TFD_CLOEXEC, TFD_NONBLOCK};
+
+        let signal = unsafe { signalfd(-1, sigset, SFD_CLOEXEC | SFD_NONBLOCK) };
+        if signal == -1 {
+            return _generate_os_error();
+        }
+        let signal = unsafe { OwnedFd::from_raw_fd(signal) };
+
+        let timer = unsafe { timerfd_create(CLOCK_MONOTONIC, TFD_CLOEXEC | TFD_NONBLOCK) };
+        if timer == -1 {
+            return _generate_os_error();
+        }
+        let timer = unsafe { OwnedFd::from_raw_fd(timer) };
+
+        // The timer is left disarmed without deadline, a zero value would disarm it as well
+        if deadline.is_some() {
+            let remaining = remaining_until(deadline).max(Duration::from_nanos(1));
+            let value = itimerspec {
+                it_interval: unsafe { mem::zeroed() },
+                it_value: to_timespec(remaining),
+            };
+            if unsafe { timerfd_settime(timer.as_raw_fd(), 0, &value, ptr::null_mut()) } == -1 {
+                return _generate_os_error();
+            }
+        }
+
+        Ok(Notifier { signal, timer })
+    }
+
+    // Waits for the signal until the timer expires or for the interval, consuming it. The expiration
+    // is not read, so the following waits return right away until the caller observes the deadline.
+    fn wait(&self, _deadline: Option<Instant>) -> io::Result<()> {
+        use libc::{pollfd, ppoll, signalfd_siginfo, POLLIN};
+
+        let interval = to_timespec(MISSED_SIGNAL_INTERVAL);
+
+        let mut pollfds = [
+            pollfd {
+                fd: self.signal.as_raw_fd(),
+                events: POLLIN,
+                revents: 0,
+            },
+            pollfd {
+                fd: self.timer.as_raw_fd(),
+                events: POLLIN,
+                revents: 0,
+            },
+        ];
+        if unsafe { ppoll(pollfds.as_mut_ptr(), 2, &interval, ptr::null()) } == -1 {
+            let error = io::Error::last_os_error();
+            // An interrupted wait is reported as an expired timeout, the caller checks the
+            // children again, unless the interruptions are surfaced
+            return if error.raw_os_error() == Some(libc::EINTR) && restart_on_interrupt() {
+                Ok(())
+            } else {
+                Err(error)
+            };
+        }
+        if pollfds[0].revents == 0 {
+            return Ok(());
+        }
+
+        let mut siginfo: signalfd_siginfo = unsafe { mem::zeroed() };
+        let read = unsafe {
+            libc::read(
+                self.signal.as_raw_fd(),
+                &mut siginfo as *mut signalfd_siginfo as *mut libc::c_void,
+                mem::size_of::<signalfd_siginfo>(),
+            )
+        };
+        if read == -1 {
+            let error = io::Error::last_os_error();
+            // The signal may have been consumed by another thread in the meantime
+            return match error.raw_os_error() {
+                Some(libc::EAGAIN) => Ok(()),
+                _ => Err(error),
+            };
         }
-    } else if unsafe { siginfo.si_pid() } == pid {
         Ok(())
-    } else {
-        Err(io::Error::new(io::ErrorKind::Other, "another child died"))
     }
 }
@@ -1,23 +1,123 @@
+use std::collections::HashMap;
 use std::io;
-use std::process::Child;
+use std::process::{Child, ExitStatus};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
 
-use libc::{pid_t, ETIMEDOUT};
+use libc::{id_t, pid_t, P_PID};
 
-use crate::error::{_generate_default_error, _generate_timeout_error};
+use super::exit_future::Watcher;
+use super::unix::{has_exited, wait_exited};
+use super::{deadline_after, remaining_until, Backend};
+use crate::error::_generate_timeout_error;
 
-extern "C" {
-    fn wait_timeout_untraced(pid: pid_t, timeout_ms: u32) -> pid_t;
+// The watcher threads currently running, indexed by pid. A thread cannot be
+// cancelled while it is blocked in `waitid`, so it is kept alive until the child
+// exits and reused by the next calls instead of spawning a new one each time.
+// The futures returned by `exit_future` share the same watchers.
+fn watchers() -> &'static Mutex<HashMap<pid_t, Arc<Watcher>>> {
+    static WATCHERS: OnceLock<Mutex<HashMap<pid_t, Arc<Watcher>>>> = OnceLock::new();
+    WATCHERS.get_or_init(Default::default)
 }
 
-pub(crate) fn _wait_timeout_untraced_ms(child: &mut Child, timeout_ms: u32) -> io::Result<()> {
-    let pid = child.id() as pid_t;
+// Counts the exits observed by the watcher threads, notified on each exit so that
+// callers waiting on several children can wake up whichever one exits
+fn exits() -> &'static (Mutex<u64>, Condvar) {
+    static EXITS: OnceLock<(Mutex<u64>, Condvar)> = OnceLock::new();
+    EXITS.get_or_init(Default::default)
+}
+
+pub(crate) fn watch(pid: pid_t) -> io::Result<Arc<Watcher>> {
+    let mut registry = watchers().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(watcher) = registry.get(&pid) {
+        return Ok(watcher.clone());
+    }
+
+    let watcher = Arc::new(Watcher::default());
+
+    let thread_watcher = watcher.clone();
+    thread::Builder::new()
+        .name("child_wait_timeout".to_string())
+        .spawn(move || {
+            let result = wait_exited(P_PID, pid as id_t);
+
+            // Unregister before notifying, once reaped the pid can be reused
+            let mut registry = watchers().lock().unwrap_or_else(|e| e.into_inner());
+            if registry
+                .get(&pid)
+                .is_some_and(|w| Arc::ptr_eq(w, &thread_watcher))
+            {
+                registry.remove(&pid);
+            }
+            drop(registry);
+
+            thread_watcher.notify(result);
+
+            let (count, cond) = exits();
+            *count.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+            cond.notify_all();
+        })?;
+
+    registry.insert(pid, watcher.clone());
+    Ok(watcher)
+}
+
+pub(crate) fn _active_backend() -> Backend {
+    Backend::Thread
+}
+
+pub(crate) fn _wait_timeout_untraced(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    _wait_pid_untraced(child.id(), timeout).map(|()| None)
+}
+
+// Waits for the child with the given pid to exit, without reaping it
+pub(crate) fn _wait_pid_untraced(pid: u32, timeout: Duration) -> io::Result<()> {
+    let pid = pid as pid_t;
+    let deadline = deadline_after(timeout);
+
+    loop {
+        let watcher = watch(pid)?;
+        match watcher.wait_timeout(remaining_until(deadline)) {
+            None => return _generate_timeout_error(),
+            Some(Err(e)) => return Err(e),
+            // The notification may come from a previous child that had the same pid,
+            // in which case a new watcher is registered for the current one
+            Some(Ok(())) if has_exited(pid) => return Ok(()),
+            Some(Ok(())) => {}
+        }
+    }
+}
+
+pub(crate) fn _wait_any_untraced(children: &[Child], timeout: Duration) -> io::Result<usize> {
+    let pids: Vec<pid_t> = children.iter().map(|child| child.id() as pid_t).collect();
+    let deadline = deadline_after(timeout);
+    let (count, cond) = exits();
+
+    loop {
+        // Read before checking the children, so that an exit in the meantime is not missed
+        let observed = *count.lock().unwrap_or_else(|e| e.into_inner());
+
+        for (index, &pid) in pids.iter().enumerate() {
+            if has_exited(pid) {
+                return Ok(index);
+            }
+            if let Some(Err(e)) = watch(pid)?.wait_timeout(Duration::ZERO) {
+                return Err(e);
+            }
+        }
+
+        let remaining = remaining_until(deadline);
+        if remaining.is_zero() {
+            return _generate_timeout_error();
+        }
 
-    let result = unsafe { wait_timeout_untraced(pid, timeout_ms) };
-    if result != -1 {
-        Ok(())
-    } else if std::io::Error::last_os_error().raw_os_error() == Some(ETIMEDOUT) {
-        _generate_timeout_error()
-    } else {
-        _generate_default_error()
+        let count = count.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = cond
+            .wait_timeout_while(count, remaining, |count| *count == observed)
+            .unwrap_or_else(|e| e.into_inner());
     }
 }
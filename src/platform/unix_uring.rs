@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::io;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use io_uring::{opcode, squeue, types, IoUring};
+use libc::{ECANCELED, EINTR, ENOSYS, EPERM, POLLIN};
+
+use super::trace;
+use crate::error::{_generate_default_error, _generate_raw_os_error};
+
+const POLL: u64 = 0;
+const TIMEOUT: u64 = 1;
+
+// Set once io_uring is known to be unavailable on this system
+static URING_UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // Each thread waits on its own ring, created on its first wait
+    static RING: RefCell<Option<IoUring>> = const { RefCell::new(None) };
+}
+
+// Records that io_uring is unavailable, the fallback is reported the first time only
+fn set_uring_unavailable() {
+    if !URING_UNAVAILABLE.swap(true, Ordering::Relaxed) {
+        trace::backend_fallback("io_uring", "ppoll");
+    }
+}
+
+// Checks that io_uring is available on this system, by creating the ring of the thread if no wait has done it yet
+pub(crate) fn is_available() -> bool {
+    if !URING_UNAVAILABLE.load(Ordering::Relaxed) {
+        RING.with(|ring| {
+            let mut ring = ring.borrow_mut();
+            if ring.is_none() {
+                // The ring is kept for the next wait of the thread, so that it is only probed once
+                match IoUring::new(2) {
+                    Ok(new_ring) => *ring = Some(new_ring),
+                    Err(e) if matches!(e.raw_os_error(), Some(ENOSYS) | Some(EPERM)) => {
+                        set_uring_unavailable()
+                    }
+                    Err(_) => {}
+                }
+            }
+        });
+    }
+    !URING_UNAVAILABLE.load(Ordering::Relaxed)
+}
+
+// Builds the POLL_ADD operation completing when the process referred to by the pidfd exits
+pub(crate) fn poll_entry(pidfd: RawFd) -> squeue::Entry {
+    opcode::PollAdd::new(types::Fd(pidfd), POLLIN as u32).build()
+}
+
+// Waits for the pidfd to become readable with a POLL_ADD operation linked to a timeout.
+// Returns `Ok(None)` if io_uring is not available on this system, `Ok(Some(false))` if
+// the timeout expired.
+pub(crate) fn poll_pidfd(pidfd: RawFd, timeout: Duration) -> io::Result<Option<bool>> {
+    if URING_UNAVAILABLE.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+
+    RING.with(|ring| {
+        let mut ring = ring.borrow_mut();
+        if ring.is_none() {
+            match IoUring::new(2) {
+                Ok(new_ring) => *ring = Some(new_ring),
+                // Too old kernel, or io_uring disabled by a sysctl or seccomp
+                Err(e) if matches!(e.raw_os_error(), Some(ENOSYS) | Some(EPERM)) => {
+                    set_uring_unavailable();
+                    return Ok(None);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        match ring.as_mut() {
+            Some(ring) => poll_ring(ring, pidfd, timeout).map(Some),
+            None => Ok(None),
+        }
+    })
+}
+
+fn poll_ring(ring: &mut IoUring, pidfd: RawFd, timeout: Duration) -> io::Result<bool> {
+    // Convert the timeout to a timespec structure, the kernel reads the seconds as signed
+    let ts = types::Timespec::new()
+        .sec(timeout.as_secs().min(i64::MAX as u64))
+        .nsec(timeout.subsec_nanos());
+
+    let poll = poll_entry(pidfd)
+        .flags(squeue::Flags::IO_LINK)
+        .user_data(POLL);
+    let timeout = opcode::LinkTimeout::new(&ts).build().user_data(TIMEOUT);
+
+    // The ring is drained after each wait, so there is always room for both entries
+    if unsafe { ring.submission().push_multiple(&[poll, timeout]) }.is_err() {
+        return _generate_default_error();
+    }
+
+    // Wait for both completions, the poll is cancelled by the timeout if it expires first
+    let mut poll_result = None;
+    let mut completed = 0;
+    while completed < 2 {
+        match ring.submit_and_wait(2 - completed) {
+            Ok(_) => {}
+            Err(e) if e.raw_os_error() == Some(EINTR) => continue,
+            Err(e) => return Err(e),
+        }
+        for cqe in ring.completion() {
+            completed += 1;
+            if cqe.user_data() == POLL {
+                poll_result = Some(cqe.result());
+            }
+        }
+    }
+
+    match poll_result {
+        Some(result) if result >= 0 => Ok(true),
+        Some(result) if result == -ECANCELED => Ok(false),
+        Some(result) => _generate_raw_os_error(-result),
+        None => _generate_default_error(),
+    }
+}
@@ -0,0 +1,416 @@
+use std::io;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::unix::io::BorrowedFd;
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+#[cfg(windows)]
+use std::os::windows::io::RawHandle;
+#[cfg(windows)]
+use std::os::windows::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+#[cfg(windows)]
+use super::win32::{CloseHandle, GetExitCodeProcess, OpenProcess, ERROR_INVALID_PARAMETER, FALSE};
+#[cfg(windows)]
+use super::win32::{HANDLE, PROCESS_QUERY_LIMITED_INFORMATION, SYNCHRONIZE};
+
+#[cfg(any(
+    windows,
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+))]
+use super::imp;
+#[cfg(not(any(
+    windows,
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+)))]
+use super::{deadline_after, remaining_until};
+use super::{trace, wait_until_deadline};
+#[cfg(windows)]
+use crate::error::_generate_no_such_process_error;
+#[cfg(any(windows, target_os = "linux", target_os = "android"))]
+use crate::error::_generate_os_error;
+#[cfg(not(any(
+    windows,
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+)))]
+use crate::error::_generate_timeout_error;
+
+// How often the existence of the process is checked when it cannot be waited on
+#[cfg(not(any(
+    windows,
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+)))]
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Waits for the process with the given pid to exit or until the timeout expires.
+///
+/// Unlike [`ChildWT::wait_timeout`](super::ChildWT::wait_timeout), the process does not need to be a
+/// child of the current process, so that sidecar processes or the processes of another program can be
+/// monitored. As their exit status can only be retrieved by their parent, only their exit is reported.
+/// A child of the current process is not reaped.
+///
+/// # Platform-Specific Behavior
+///
+/// On Linux and Android, the process is waited on through a pidfd opened with `pidfd_open`. On macOS,
+/// other Apple targets and the BSDs, it is waited on with `kqueue` and an `EVFILT_PROC`/`NOTE_EXIT`
+/// filter. On Windows, a handle with the `SYNCHRONIZE` access right is opened with `OpenProcess` and
+/// waited on with `WaitForSingleObject`.
+///
+/// Elsewhere, or if `pidfd_open` is not available at runtime, the existence of the process is checked
+/// with `kill(pid, 0)` every 10 milliseconds. A process which is not a child of the current process is
+/// then only considered to have exited once its parent has reaped it.
+///
+/// # Errors
+///
+/// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the process exits.
+/// It fails with an OS error if no process has this pid, including when it has already exited and been
+/// reaped (`ESRCH` on Unix), or if the process cannot be opened.
+///
+/// On Windows, it fails with `ErrorKind::NotFound`, converted into [`WaitError::NoSuchProcess`](crate::WaitError::NoSuchProcess),
+/// if no process has this pid, and with the `ERROR_ACCESS_DENIED` OS error, of kind `ErrorKind::PermissionDenied`,
+/// if the process cannot be opened with the `SYNCHRONIZE` access right, such as a protected process or one of
+/// another user without the required privileges.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::wait_pid_timeout;
+///
+/// // Any process can be waited on, such as one spawned by another program
+/// let mut child = Command::new("sleep").arg("1").spawn()?;
+/// wait_pid_timeout(child.id(), Duration::from_secs(5))?;
+/// assert!(child.wait()?.success());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn wait_pid_timeout(pid: u32, timeout: Duration) -> io::Result<()> {
+    wait_until_deadline(timeout, |remaining| wait_pid(pid, remaining))
+}
+
+/// Waits for the child process with the given pid to exit or until the timeout expires, and reaps it.
+///
+/// This is [`ChildWT::wait_timeout`](super::ChildWT::wait_timeout) for the callers which only hold the
+/// pid of a child, such as runtimes spawning their children with `fork` or through FFI. Unlike
+/// [`wait_pid_timeout`], the exit status is returned, so the process must be a child of the current
+/// process on Unix. Once reaped, the pid may be reused by another process: a [`Child`](std::process::Child)
+/// spawned with this pid should not be waited for anymore.
+///
+/// # Platform-Specific Behavior
+///
+/// The exit is waited for as with [`wait_pid_timeout`], the child is then reaped with `waitpid` on Unix.
+/// On Windows, where there is nothing to reap, the exit code is read with `GetExitCodeProcess` from a
+/// handle opened with the `SYNCHRONIZE` and `PROCESS_QUERY_LIMITED_INFORMATION` access rights.
+///
+/// # Errors
+///
+/// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the process exits.
+/// On Unix, it fails with the `ECHILD` OS error if the process is not a child of the current process
+/// or has already been reaped. On Windows, it fails as [`wait_pid_timeout`] if the process cannot be opened.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::wait_timeout_pid;
+///
+/// let pid = Command::new("sleep").arg("1").spawn()?.id();
+/// let status = wait_timeout_pid(pid, Duration::from_secs(5))?;
+/// assert!(status.success());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn wait_timeout_pid(pid: u32, timeout: Duration) -> io::Result<ExitStatus> {
+    let start = Instant::now();
+    let result = wait_timeout_pid_untraced(pid, timeout);
+    trace::wait_result(pid, start.elapsed(), &result);
+    result
+}
+
+// Checks the child first, so that a pid which is not a child is rejected before waiting
+#[cfg(unix)]
+fn wait_timeout_pid_untraced(pid: u32, timeout: Duration) -> io::Result<ExitStatus> {
+    use crate::error::{_generate_default_error, _generate_timeout_error};
+
+    if let Some(status) = reap_pid(pid, libc::WNOHANG)? {
+        return Ok(status);
+    }
+    if timeout.is_zero() {
+        return _generate_timeout_error();
+    }
+
+    wait_pid_timeout(pid, timeout)?;
+    // The child has exited, the wait ends at once
+    match reap_pid(pid, 0)? {
+        Some(status) => Ok(status),
+        None => _generate_default_error(),
+    }
+}
+
+// Reaps the child with `waitpid`, returns `None` if it is still running with `WNOHANG`
+#[cfg(unix)]
+fn reap_pid(pid: u32, options: libc::c_int) -> io::Result<Option<ExitStatus>> {
+    let mut status = 0;
+    loop {
+        match unsafe { libc::waitpid(pid as libc::pid_t, &mut status, options) } {
+            -1 => {
+                let error = io::Error::last_os_error();
+                if error.kind() != io::ErrorKind::Interrupted {
+                    return Err(error);
+                }
+            }
+            0 => return Ok(None),
+            _ => return Ok(Some(ExitStatus::from_raw(status))),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn wait_timeout_pid_untraced(pid: u32, timeout: Duration) -> io::Result<ExitStatus> {
+    let process = open_process(SYNCHRONIZE | PROCESS_QUERY_LIMITED_INFORMATION, pid)?;
+
+    let result = wait_until_deadline(timeout, |remaining| {
+        imp::_wait_handle_untraced(process, remaining)
+    })
+    .and_then(|()| {
+        let mut code = 0;
+        if unsafe { GetExitCodeProcess(process, &mut code) } == 0 {
+            return _generate_os_error();
+        }
+        Ok(ExitStatus::from_raw(code))
+    });
+
+    unsafe { CloseHandle(process) };
+
+    result
+}
+
+#[cfg(windows)]
+fn open_process(access: u32, pid: u32) -> io::Result<HANDLE> {
+    let process = unsafe { OpenProcess(access, FALSE, pid) };
+    if process.is_null() {
+        // The pid of a process which has exited and whose last handle has been closed is rejected
+        // as an invalid parameter, as any pid which was never used
+        if io::Error::last_os_error().raw_os_error() == Some(ERROR_INVALID_PARAMETER as i32) {
+            return _generate_no_such_process_error();
+        }
+        return _generate_os_error();
+    }
+    Ok(process)
+}
+
+#[cfg(windows)]
+fn wait_pid(pid: u32, timeout: Duration) -> io::Result<()> {
+    let process = open_process(SYNCHRONIZE, pid)?;
+
+    let result = imp::_wait_handle_untraced(process, timeout);
+
+    unsafe { CloseHandle(process) };
+
+    result
+}
+
+// `NOTE_EXIT` is reported for any process, the exit of a child is also found by `waitid`
+// if it had already exited before the registration
+#[cfg(any(
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+))]
+fn wait_pid(pid: u32, timeout: Duration) -> io::Result<()> {
+    imp::_wait_pid_untraced(pid, timeout)
+}
+
+/// Waits for the process referred to by the pidfd to exit or until the timeout expires.
+///
+/// This is meant for callers which already hold a pidfd, such as one returned by `clone3` with
+/// `CLONE_PIDFD` or received from another process over a Unix socket. The descriptor is only polled:
+/// it is neither closed nor used to reap the process, its exit status can then be read with
+/// `waitid(P_PIDFD, ...)` by the parent of the process.
+///
+/// This function is only available on Linux and Android.
+///
+/// # Errors
+///
+/// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the process exits.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::os::unix::io::{AsFd, FromRawFd, OwnedFd};
+/// use std::process::Command;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::wait_fd_timeout;
+///
+/// let mut child = Command::new("sleep").arg("1").spawn()?;
+/// let pidfd = unsafe { libc::syscall(libc::SYS_pidfd_open, child.id(), 0) };
+/// if pidfd >= 0 {
+///     let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd as i32) };
+///     wait_fd_timeout(pidfd.as_fd(), Duration::from_secs(5))?;
+/// }
+/// assert!(child.wait()?.success());
+/// #     Ok(())
+/// # }
+/// ```
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn wait_fd_timeout(pidfd: BorrowedFd<'_>, timeout: Duration) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    use std::ptr;
+
+    use libc::{pollfd, ppoll, POLLIN};
+
+    use super::unix::{restart_interrupted, to_timespec};
+
+    // The pidfd becomes readable once the process exits, whether it is reaped or not
+    let mut pollfd = pollfd {
+        fd: pidfd.as_raw_fd(),
+        events: POLLIN,
+        revents: 0,
+    };
+    restart_interrupted(timeout, |remaining| {
+        let ts = to_timespec(remaining);
+        match unsafe { ppoll(&mut pollfd, 1, &ts, ptr::null()) } {
+            -1 => _generate_os_error(),
+            0 => _generate_timeout_error(),
+            _ => Ok(()),
+        }
+    })
+}
+
+/// Waits for the process referred to by the handle to exit or until the timeout expires.
+///
+/// This is meant for callers which already hold a process handle, such as one returned by
+/// `DuplicateHandle` or `OpenProcess`. The handle must have the `SYNCHRONIZE` access right, it is
+/// not closed by this function.
+///
+/// This function is only available on Windows.
+///
+/// # Errors
+///
+/// This function may fail with `ErrorKind::TimedOut` if the timeout expires before the process exits,
+/// or with an OS error if the handle is not valid.
+///
+/// # Example
+/// ```rust,no_run
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::os::windows::io::AsRawHandle;
+/// use std::process::Command;
+/// use std::time::Duration;
+///
+/// use child_wait_timeout::wait_handle_timeout;
+///
+/// let mut child = Command::new("timeout").args(["/t", "1"]).spawn()?;
+/// wait_handle_timeout(child.as_raw_handle(), Duration::from_secs(5))?;
+/// assert!(child.wait()?.success());
+/// #     Ok(())
+/// # }
+/// ```
+#[cfg(windows)]
+pub fn wait_handle_timeout(handle: RawHandle, timeout: Duration) -> io::Result<()> {
+    wait_until_deadline(timeout, |remaining| {
+        imp::_wait_handle_untraced(handle as HANDLE, remaining)
+    })
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn wait_pid(pid: u32, timeout: Duration) -> io::Result<()> {
+    use std::os::unix::io::{AsFd, FromRawFd, OwnedFd};
+
+    use super::unix::pidfd_open;
+
+    let Some(pidfd) = pidfd_open(pid as libc::pid_t, 0)? else {
+        return poll_pid(pid, timeout);
+    };
+    let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd) };
+
+    wait_fd_timeout(pidfd.as_fd(), timeout)
+}
+
+#[cfg(not(any(
+    windows,
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "linux",
+    target_os = "android"
+)))]
+fn wait_pid(pid: u32, timeout: Duration) -> io::Result<()> {
+    poll_pid(pid, timeout)
+}
+
+// Checks periodically that the process still exists. A child which has exited is still found
+// by `kill` until it is reaped, so it is checked with `waitid` as well.
+#[cfg(not(any(
+    windows,
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+)))]
+fn poll_pid(pid: u32, timeout: Duration) -> io::Result<()> {
+    use std::thread;
+
+    use libc::{pid_t, EPERM};
+
+    use super::unix::has_exited;
+
+    let pid = pid as pid_t;
+    let deadline = deadline_after(timeout);
+    let mut first = true;
+
+    loop {
+        // The process exists but belongs to another user when `EPERM` is returned
+        if unsafe { libc::kill(pid, 0) } == -1 {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(EPERM) {
+                // A pid which does not exist at the start of the wait is reported as an error
+                return if first { Err(error) } else { Ok(()) };
+            }
+        }
+        if has_exited(pid) {
+            return Ok(());
+        }
+        first = false;
+
+        let remaining = remaining_until(deadline);
+        if remaining.is_zero() {
+            return _generate_timeout_error();
+        }
+        thread::sleep(remaining.min(POLL_INTERVAL));
+    }
+}
@@ -0,0 +1,113 @@
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+
+use super::wait_any_timeout;
+
+/// A set of child processes whose exits are received as they happen.
+///
+/// Children can be inserted and removed at any time, and [`WaitSet::wait_next`] returns the next one
+/// to exit, removed from the set along with its exit status. The children are waited on together with
+/// [`wait_any_timeout`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::WaitSet;
+///
+/// let mut set = WaitSet::new();
+/// set.insert(Command::new("sleep").arg("1").spawn()?);
+/// set.insert(Command::new("sleep").arg("0").spawn()?);
+///
+/// while let Some((key, _child, status)) = set.wait_next(Duration::from_secs(5))? {
+///     println!("child {} exited with {}", key, status);
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct WaitSet {
+    // The key of each child, at the same index
+    keys: Vec<usize>,
+    children: Vec<Child>,
+    next_key: usize,
+}
+
+impl WaitSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a child process and returns the key identifying it in the set.
+    pub fn insert(&mut self, child: Child) -> usize {
+        let key = self.next_key;
+        self.next_key += 1;
+        self.keys.push(key);
+        self.children.push(child);
+        key
+    }
+
+    /// Removes the child process with the given key and returns it.
+    pub fn remove(&mut self, key: usize) -> Option<Child> {
+        let index = self.index(key)?;
+        self.keys.swap_remove(index);
+        Some(self.children.swap_remove(index))
+    }
+
+    /// Returns the child process with the given key.
+    pub fn get(&self, key: usize) -> Option<&Child> {
+        self.index(key).map(|index| &self.children[index])
+    }
+
+    /// Returns a mutable reference to the child process with the given key.
+    pub fn get_mut(&mut self, key: usize) -> Option<&mut Child> {
+        self.index(key).map(|index| &mut self.children[index])
+    }
+
+    /// Returns an iterator over the keys of the child processes in the set.
+    pub fn keys(&self) -> impl Iterator<Item = usize> + '_ {
+        self.keys.iter().copied()
+    }
+
+    /// Returns the number of child processes in the set.
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Returns `true` if the set contains no child process.
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Waits for the next child process to exit or until the timeout expires.
+    ///
+    /// # Returns
+    /// - `Some` with the key, the child process removed from the set and its exit status.
+    /// - `None` if the set is empty.
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the timeout expires before any child process exits.
+    pub fn wait_next(
+        &mut self,
+        timeout: Duration,
+    ) -> io::Result<Option<(usize, Child, ExitStatus)>> {
+        if self.children.is_empty() {
+            return Ok(None);
+        }
+
+        let (index, status) = wait_any_timeout(&mut self.children, timeout)?;
+        let key = self.keys.swap_remove(index);
+        Ok(Some((key, self.children.swap_remove(index), status)))
+    }
+
+    fn index(&self, key: usize) -> Option<usize> {
+        self.keys.iter().position(|&k| k == key)
+    }
+}
@@ -0,0 +1,43 @@
+use std::process::ExitStatus;
+use std::time::Duration;
+
+/// The outcome of a wait along with the time it took, returned by [`ChildWT::wait_timeout_timed`](super::ChildWT::wait_timeout_timed).
+///
+/// The elapsed time is measured with the monotonic clock of [`Instant`](std::time::Instant) around the
+/// whole wait, including the reaping of the child process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitTiming {
+    status: Option<ExitStatus>,
+    elapsed: Duration,
+    timeout: Duration,
+}
+
+impl WaitTiming {
+    pub(crate) fn new(status: Option<ExitStatus>, elapsed: Duration, timeout: Duration) -> Self {
+        WaitTiming {
+            status,
+            elapsed,
+            timeout,
+        }
+    }
+
+    /// Returns the exit status of the child process, or `None` if the timeout expired first.
+    pub fn status(&self) -> Option<ExitStatus> {
+        self.status
+    }
+
+    /// Returns `true` if the timeout expired before the child process exited.
+    pub fn timed_out(&self) -> bool {
+        self.status.is_none()
+    }
+
+    /// Returns the time the wait took.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the part of the timeout which was not used, zero if the timeout expired.
+    pub fn remaining(&self) -> Duration {
+        self.timeout.saturating_sub(self.elapsed)
+    }
+}
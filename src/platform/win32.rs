@@ -0,0 +1,302 @@
+// The Win32 items used by the Windows backend. They are taken from `winapi` with the `winapi` feature,
+// enabled by default, and declared below otherwise, so that the crate builds without `winapi` in the
+// dependency tree. The declarations mirror the ones of `winapi`, so that both behave the same.
+
+// Some of the items are only used with the features of the modules using them
+#[cfg(feature = "winapi")]
+#[allow(unused_imports)]
+pub(crate) use winapi::{
+    shared::{
+        basetsd::ULONG_PTR,
+        minwindef::{BOOL, DWORD, FALSE, FILETIME, LPARAM, LPVOID, TRUE},
+        ntdef::{BOOLEAN, PVOID},
+        windef::HWND,
+        winerror::{ERROR_BROKEN_PIPE, ERROR_INVALID_PARAMETER, WAIT_TIMEOUT},
+    },
+    um::{
+        handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+        ioapiset::{CreateIoCompletionPort, GetQueuedCompletionStatus},
+        jobapi2::{AssignProcessToJobObject, CreateJobObjectW, TerminateJobObject},
+        jobapi2::{QueryInformationJobObject, SetInformationJobObject},
+        minwinbase::{LPOVERLAPPED, STILL_ACTIVE},
+        namedpipeapi::PeekNamedPipe,
+        processthreadsapi::{GetExitCodeProcess, GetProcessTimes, OpenProcess, TerminateProcess},
+        psapi::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS},
+        synchapi::{CreateEventW, CreateWaitableTimerExW, SetEvent, SetWaitableTimer},
+        synchapi::{WaitForMultipleObjects, WaitForMultipleObjectsEx},
+        synchapi::{WaitForSingleObject, WaitForSingleObjectEx},
+        threadpoollegacyapiset::UnregisterWaitEx,
+        tlhelp32::{CreateToolhelp32Snapshot, Process32FirstW, Process32NextW},
+        tlhelp32::{PROCESSENTRY32W, TH32CS_SNAPPROCESS},
+        winbase::{RegisterWaitForSingleObject, UnregisterWait},
+        winbase::{CREATE_NEW_PROCESS_GROUP, INFINITE},
+        winbase::{WAIT_FAILED, WAIT_IO_COMPLETION, WAIT_OBJECT_0},
+        winnt::{JobObjectAssociateCompletionPortInformation, JobObjectBasicAccountingInformation},
+        winnt::{HANDLE, LARGE_INTEGER, MAXIMUM_WAIT_OBJECTS, WT_EXECUTEONLYONCE},
+        winnt::{JOBOBJECT_ASSOCIATE_COMPLETION_PORT, JOBOBJECT_BASIC_ACCOUNTING_INFORMATION},
+        winnt::{JOB_OBJECT_MSG_ACTIVE_PROCESS_ZERO, TIMER_ALL_ACCESS},
+        winnt::{PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE, SYNCHRONIZE},
+        winuser::{EnumWindows, GetWindowThreadProcessId, PostMessageW, WM_CLOSE},
+    },
+};
+
+#[cfg(not(feature = "winapi"))]
+#[allow(unused_imports)]
+pub(crate) use self::ffi::*;
+
+#[cfg(not(feature = "winapi"))]
+#[allow(
+    dead_code,
+    non_camel_case_types,
+    non_snake_case,
+    non_upper_case_globals
+)]
+#[allow(clippy::upper_case_acronyms)]
+mod ffi {
+    use std::ffi::c_void;
+
+    pub(crate) type BOOL = i32;
+    pub(crate) type BOOLEAN = u8;
+    pub(crate) type DWORD = u32;
+    pub(crate) type HANDLE = *mut c_void;
+    pub(crate) type HWND = *mut c_void;
+    pub(crate) type LPARAM = isize;
+    pub(crate) type LPOVERLAPPED = *mut c_void;
+    pub(crate) type LPVOID = *mut c_void;
+    pub(crate) type PVOID = *mut c_void;
+    pub(crate) type ULONG_PTR = usize;
+
+    type JOBOBJECTINFOCLASS = u32;
+    type LPCWSTR = *const u16;
+    type LPSECURITY_ATTRIBUTES = *mut c_void;
+    type PTIMERAPCROUTINE = Option<unsafe extern "system" fn(LPVOID, DWORD, DWORD)>;
+    type WAITORTIMERCALLBACK = Option<unsafe extern "system" fn(PVOID, BOOLEAN)>;
+    type WNDENUMPROC = Option<unsafe extern "system" fn(HWND, LPARAM) -> BOOL>;
+
+    pub(crate) const FALSE: BOOL = 0;
+    pub(crate) const TRUE: BOOL = 1;
+
+    pub(crate) const INFINITE: DWORD = 0xFFFFFFFF;
+    pub(crate) const WAIT_OBJECT_0: DWORD = 0;
+    pub(crate) const WAIT_IO_COMPLETION: DWORD = 0xC0;
+    pub(crate) const WAIT_TIMEOUT: DWORD = 258;
+    pub(crate) const WAIT_FAILED: DWORD = 0xFFFFFFFF;
+    pub(crate) const MAXIMUM_WAIT_OBJECTS: DWORD = 64;
+    pub(crate) const STILL_ACTIVE: DWORD = 0x103;
+    pub(crate) const INVALID_HANDLE_VALUE: HANDLE = -1isize as HANDLE;
+
+    pub(crate) const ERROR_INVALID_PARAMETER: DWORD = 87;
+    pub(crate) const ERROR_BROKEN_PIPE: DWORD = 109;
+
+    pub(crate) const PROCESS_TERMINATE: DWORD = 0x0001;
+    pub(crate) const PROCESS_QUERY_LIMITED_INFORMATION: DWORD = 0x1000;
+    pub(crate) const SYNCHRONIZE: DWORD = 0x00100000;
+    pub(crate) const TIMER_ALL_ACCESS: DWORD = 0x001F0003;
+    pub(crate) const CREATE_NEW_PROCESS_GROUP: DWORD = 0x00000200;
+    pub(crate) const WT_EXECUTEONLYONCE: u32 = 0x00000008;
+    pub(crate) const WM_CLOSE: u32 = 0x0010;
+    pub(crate) const TH32CS_SNAPPROCESS: DWORD = 0x00000002;
+
+    pub(crate) const JobObjectBasicAccountingInformation: JOBOBJECTINFOCLASS = 1;
+    pub(crate) const JobObjectAssociateCompletionPortInformation: JOBOBJECTINFOCLASS = 7;
+    pub(crate) const JOB_OBJECT_MSG_ACTIVE_PROCESS_ZERO: DWORD = 4;
+
+    // A union of an `i64` and its two halves, of which only the whole value is used
+    #[repr(C)]
+    pub(crate) struct LARGE_INTEGER {
+        QuadPart: i64,
+    }
+
+    impl LARGE_INTEGER {
+        // Unsafe as the accessor of the union of `winapi`
+        pub(crate) unsafe fn QuadPart_mut(&mut self) -> &mut i64 {
+            &mut self.QuadPart
+        }
+    }
+
+    #[repr(C)]
+    pub(crate) struct FILETIME {
+        pub(crate) dwLowDateTime: DWORD,
+        pub(crate) dwHighDateTime: DWORD,
+    }
+
+    #[repr(C)]
+    pub(crate) struct PROCESSENTRY32W {
+        pub(crate) dwSize: DWORD,
+        pub(crate) cntUsage: DWORD,
+        pub(crate) th32ProcessID: DWORD,
+        pub(crate) th32DefaultHeapID: ULONG_PTR,
+        pub(crate) th32ModuleID: DWORD,
+        pub(crate) cntThreads: DWORD,
+        pub(crate) th32ParentProcessID: DWORD,
+        pub(crate) pcPriClassBase: i32,
+        pub(crate) dwFlags: DWORD,
+        pub(crate) szExeFile: [u16; 260],
+    }
+
+    #[repr(C)]
+    pub(crate) struct PROCESS_MEMORY_COUNTERS {
+        pub(crate) cb: DWORD,
+        pub(crate) PageFaultCount: DWORD,
+        pub(crate) PeakWorkingSetSize: usize,
+        pub(crate) WorkingSetSize: usize,
+        pub(crate) QuotaPeakPagedPoolUsage: usize,
+        pub(crate) QuotaPagedPoolUsage: usize,
+        pub(crate) QuotaPeakNonPagedPoolUsage: usize,
+        pub(crate) QuotaNonPagedPoolUsage: usize,
+        pub(crate) PagefileUsage: usize,
+        pub(crate) PeakPagefileUsage: usize,
+    }
+
+    #[repr(C)]
+    pub(crate) struct JOBOBJECT_ASSOCIATE_COMPLETION_PORT {
+        pub(crate) CompletionKey: PVOID,
+        pub(crate) CompletionPort: HANDLE,
+    }
+
+    #[repr(C)]
+    pub(crate) struct JOBOBJECT_BASIC_ACCOUNTING_INFORMATION {
+        pub(crate) TotalUserTime: LARGE_INTEGER,
+        pub(crate) TotalKernelTime: LARGE_INTEGER,
+        pub(crate) ThisPeriodTotalUserTime: LARGE_INTEGER,
+        pub(crate) ThisPeriodTotalKernelTime: LARGE_INTEGER,
+        pub(crate) TotalPageFaultCount: DWORD,
+        pub(crate) TotalProcesses: DWORD,
+        pub(crate) ActiveProcesses: DWORD,
+        pub(crate) TotalTerminatedProcesses: DWORD,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub(crate) fn CloseHandle(hObject: HANDLE) -> BOOL;
+        pub(crate) fn OpenProcess(
+            dwDesiredAccess: DWORD,
+            bInheritHandle: BOOL,
+            dwProcessId: DWORD,
+        ) -> HANDLE;
+        pub(crate) fn TerminateProcess(hProcess: HANDLE, uExitCode: u32) -> BOOL;
+        pub(crate) fn GetExitCodeProcess(hProcess: HANDLE, lpExitCode: *mut DWORD) -> BOOL;
+        pub(crate) fn GetProcessTimes(
+            hProcess: HANDLE,
+            lpCreationTime: *mut FILETIME,
+            lpExitTime: *mut FILETIME,
+            lpKernelTime: *mut FILETIME,
+            lpUserTime: *mut FILETIME,
+        ) -> BOOL;
+
+        pub(crate) fn WaitForSingleObject(hHandle: HANDLE, dwMilliseconds: DWORD) -> DWORD;
+        pub(crate) fn WaitForSingleObjectEx(
+            hHandle: HANDLE,
+            dwMilliseconds: DWORD,
+            bAlertable: BOOL,
+        ) -> DWORD;
+        pub(crate) fn WaitForMultipleObjects(
+            nCount: DWORD,
+            lpHandles: *const HANDLE,
+            bWaitAll: BOOL,
+            dwMilliseconds: DWORD,
+        ) -> DWORD;
+        pub(crate) fn WaitForMultipleObjectsEx(
+            nCount: DWORD,
+            lpHandles: *const HANDLE,
+            bWaitAll: BOOL,
+            dwMilliseconds: DWORD,
+            bAlertable: BOOL,
+        ) -> DWORD;
+
+        pub(crate) fn CreateEventW(
+            lpEventAttributes: LPSECURITY_ATTRIBUTES,
+            bManualReset: BOOL,
+            bInitialState: BOOL,
+            lpName: LPCWSTR,
+        ) -> HANDLE;
+        pub(crate) fn SetEvent(hEvent: HANDLE) -> BOOL;
+        pub(crate) fn CreateWaitableTimerExW(
+            lpTimerAttributes: LPSECURITY_ATTRIBUTES,
+            lpTimerName: LPCWSTR,
+            dwFlags: DWORD,
+            dwDesiredAccess: DWORD,
+        ) -> HANDLE;
+        pub(crate) fn SetWaitableTimer(
+            hTimer: HANDLE,
+            lpDueTime: *const LARGE_INTEGER,
+            lPeriod: i32,
+            pfnCompletionRoutine: PTIMERAPCROUTINE,
+            lpArgToCompletionRoutine: LPVOID,
+            fResume: BOOL,
+        ) -> BOOL;
+
+        pub(crate) fn RegisterWaitForSingleObject(
+            phNewWaitObject: *mut HANDLE,
+            hObject: HANDLE,
+            Callback: WAITORTIMERCALLBACK,
+            Context: PVOID,
+            dwMilliseconds: u32,
+            dwFlags: u32,
+        ) -> BOOL;
+        pub(crate) fn UnregisterWait(WaitHandle: HANDLE) -> BOOL;
+        pub(crate) fn UnregisterWaitEx(WaitHandle: HANDLE, CompletionEvent: HANDLE) -> BOOL;
+
+        pub(crate) fn CreateJobObjectW(
+            lpJobAttributes: LPSECURITY_ATTRIBUTES,
+            lpName: LPCWSTR,
+        ) -> HANDLE;
+        pub(crate) fn AssignProcessToJobObject(hJob: HANDLE, hProcess: HANDLE) -> BOOL;
+        pub(crate) fn TerminateJobObject(hJob: HANDLE, uExitCode: u32) -> BOOL;
+        pub(crate) fn QueryInformationJobObject(
+            hJob: HANDLE,
+            JobObjectInformationClass: JOBOBJECTINFOCLASS,
+            lpJobObjectInformation: LPVOID,
+            cbJobObjectInformationLength: DWORD,
+            lpReturnLength: *mut DWORD,
+        ) -> BOOL;
+        pub(crate) fn SetInformationJobObject(
+            hJob: HANDLE,
+            JobObjectInformationClass: JOBOBJECTINFOCLASS,
+            lpJobObjectInformation: LPVOID,
+            cbJobObjectInformationLength: DWORD,
+        ) -> BOOL;
+        pub(crate) fn CreateIoCompletionPort(
+            FileHandle: HANDLE,
+            ExistingCompletionPort: HANDLE,
+            CompletionKey: ULONG_PTR,
+            NumberOfConcurrentThreads: DWORD,
+        ) -> HANDLE;
+        pub(crate) fn GetQueuedCompletionStatus(
+            CompletionPort: HANDLE,
+            lpNumberOfBytesTransferred: *mut DWORD,
+            lpCompletionKey: *mut ULONG_PTR,
+            lpOverlapped: *mut LPOVERLAPPED,
+            dwMilliseconds: DWORD,
+        ) -> BOOL;
+
+        pub(crate) fn CreateToolhelp32Snapshot(dwFlags: DWORD, th32ProcessID: DWORD) -> HANDLE;
+        pub(crate) fn Process32FirstW(hSnapshot: HANDLE, lppe: *mut PROCESSENTRY32W) -> BOOL;
+        pub(crate) fn Process32NextW(hSnapshot: HANDLE, lppe: *mut PROCESSENTRY32W) -> BOOL;
+
+        pub(crate) fn PeekNamedPipe(
+            hNamedPipe: HANDLE,
+            lpBuffer: LPVOID,
+            nBufferSize: DWORD,
+            lpBytesRead: *mut DWORD,
+            lpTotalBytesAvail: *mut DWORD,
+            lpBytesLeftThisMessage: *mut DWORD,
+        ) -> BOOL;
+    }
+
+    #[link(name = "psapi")]
+    extern "system" {
+        pub(crate) fn GetProcessMemoryInfo(
+            hProcess: HANDLE,
+            ppsmemCounters: *mut PROCESS_MEMORY_COUNTERS,
+            cb: DWORD,
+        ) -> BOOL;
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        pub(crate) fn EnumWindows(lpEnumFunc: WNDENUMPROC, lParam: LPARAM) -> BOOL;
+        pub(crate) fn GetWindowThreadProcessId(hWnd: HWND, lpdwProcessId: *mut DWORD) -> DWORD;
+        pub(crate) fn PostMessageW(hWnd: HWND, Msg: u32, wParam: usize, lParam: LPARAM) -> BOOL;
+    }
+}
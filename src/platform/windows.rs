@@ -1,25 +1,413 @@
 use std::io;
-use std::os::windows::io::AsRawHandle;
-use std::process::Child;
+use std::mem;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle, RawHandle};
+use std::process::{Child, ExitStatus};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
 
-use winapi::shared::winerror::WAIT_TIMEOUT;
-use winapi::um::synchapi::WaitForSingleObject;
-use winapi::um::winbase::WAIT_OBJECT_0;
+use super::win32::{
+    CloseHandle, CreateEventW, CreateToolhelp32Snapshot, CreateWaitableTimerExW, EnumWindows,
+    GetWindowThreadProcessId, OpenProcess, PostMessageW, Process32FirstW, Process32NextW, SetEvent,
+    SetWaitableTimer, TerminateProcess, WaitForMultipleObjects, WaitForMultipleObjectsEx,
+    WaitForSingleObjectEx, BOOL, DWORD, FALSE, HANDLE, HWND, INFINITE, INVALID_HANDLE_VALUE,
+    LARGE_INTEGER, LPARAM, MAXIMUM_WAIT_OBJECTS, PROCESSENTRY32W, PROCESS_TERMINATE,
+    TH32CS_SNAPPROCESS, TIMER_ALL_ACCESS, TRUE, WAIT_FAILED, WAIT_IO_COMPLETION, WAIT_OBJECT_0,
+    WAIT_TIMEOUT, WM_CLOSE,
+};
 
-use crate::error::{_generate_default_error, _generate_timeout_error};
+use super::{deadline_after, remaining_until, Backend};
+use crate::error::{_generate_os_error, _generate_timeout_error};
 
-pub(crate) fn _wait_timeout_untraced_ms(child: &mut Child, timeout_ms: u32) -> io::Result<()> {
-    let handle = child.as_raw_handle();
+// Not defined by winapi, available since Windows 10 version 1803
+const CREATE_WAITABLE_TIMER_HIGH_RESOLUTION: DWORD = 0x00000002;
 
-    let winapi_handle: *mut winapi::ctypes::c_void = handle as *mut winapi::ctypes::c_void;
+pub(crate) fn _active_backend() -> Backend {
+    Backend::WindowsWaitObject
+}
+
+// Whether the waits are alertable, set by `set_alertable_waits`
+static ALERTABLE_WAITS: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether the waits are alertable, so that the APCs queued to the waiting thread still run.
+///
+/// By default, the waits are not alertable: the asynchronous procedure calls queued with `QueueUserAPC`
+/// and the completion routines of alertable I/O, such as `ReadFileEx`, are delayed until the waiting
+/// thread enters an alertable state. When enabled, the waits use `WaitForSingleObjectEx` and
+/// `WaitForMultipleObjectsEx` with `bAlertable` set, and the wait is resumed with the time remaining until
+/// the initial timeout each time it is interrupted by `WAIT_IO_COMPLETION`. The setting applies to the
+/// whole process.
+///
+/// This function is only available on Windows.
+///
+/// # Example
+/// ```rust
+/// use child_wait_timeout::{alertable_waits, set_alertable_waits};
+///
+/// set_alertable_waits(true);
+/// assert!(alertable_waits());
+/// # set_alertable_waits(false);
+/// ```
+pub fn set_alertable_waits(alertable: bool) {
+    ALERTABLE_WAITS.store(alertable, Ordering::Relaxed);
+}
+
+/// Returns whether the waits are alertable, `false` by default.
+///
+/// This function is only available on Windows.
+pub fn alertable_waits() -> bool {
+    ALERTABLE_WAITS.load(Ordering::Relaxed)
+}
 
-    let result = unsafe { WaitForSingleObject(winapi_handle, timeout_ms) };
+/// Returns the raw 32-bit exit code of the process, as returned by `GetExitCodeProcess`.
+///
+/// [`ExitStatus::code`] reports it as a signed integer, which turns the `NTSTATUS` of a crashed process into
+/// a negative number. The raw code reads as documented, for instance `0xC0000005` for an access violation or
+/// `0xC00000FD` for a stack overflow: a code whose two highest bits are set is the `NTSTATUS` of an error.
+///
+/// This function is only available on Windows.
+///
+/// # Example
+/// ```rust,no_run
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::{raw_exit_code, ChildWT};
+///
+/// let mut child = Command::new("crashing.exe").spawn()?;
+/// let status = child.wait_timeout(Duration::from_secs(5))?;
+/// if raw_exit_code(&status) >> 30 == 0b11 {
+///     eprintln!("crashed with NTSTATUS {:#010X}", raw_exit_code(&status));
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+pub fn raw_exit_code(status: &ExitStatus) -> u32 {
+    // Always set on Windows, where the exit code is kept as is
+    status.code().unwrap_or_default() as u32
+}
 
-    if result == WAIT_TIMEOUT {
+// The `bAlertable` argument of the waits
+fn alertable() -> BOOL {
+    if alertable_waits() {
+        TRUE
+    } else {
+        FALSE
+    }
+}
+
+pub(crate) fn _wait_timeout_untraced(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    _wait_handle_untraced(child.as_raw_handle() as HANDLE, timeout).map(|()| None)
+}
+
+// Waits for the process to exit, the handle must have the `SYNCHRONIZE` access right
+pub(crate) fn _wait_handle_untraced(handle: HANDLE, timeout: Duration) -> io::Result<()> {
+    // Whole milliseconds are waited on directly, without creating a timer
+    let result = match timeout_ms(timeout) {
+        Some(timeout_ms) => unsafe { WaitForSingleObjectEx(handle, timeout_ms, alertable()) },
+        None => wait_waitable_timer(&[handle], timeout)?,
+    };
+
+    // An APC ran in the thread, the caller waits again with the remaining time as if the timeout had expired
+    if result == WAIT_TIMEOUT || result == WAIT_IO_COMPLETION {
         _generate_timeout_error()
     } else if result == WAIT_OBJECT_0 {
         Ok(())
     } else {
-        _generate_default_error()
+        _generate_os_error()
+    }
+}
+
+pub(crate) fn _wait_any_untraced(children: &[Child], timeout: Duration) -> io::Result<usize> {
+    let handles: Vec<HANDLE> = children
+        .iter()
+        .map(|child| child.as_raw_handle() as HANDLE)
+        .collect();
+
+    // One handle is kept for the waitable timer
+    if handles.len() < MAXIMUM_WAIT_OBJECTS as usize {
+        wait_any_handles(&handles, timeout)
+    } else {
+        wait_any_chunked(&handles, timeout)
+    }
+}
+
+// Waits for one of at most `MAXIMUM_WAIT_OBJECTS - 1` processes, returns its index
+fn wait_any_handles(handles: &[HANDLE], timeout: Duration) -> io::Result<usize> {
+    let result = match timeout_ms(timeout) {
+        Some(timeout_ms) => unsafe {
+            WaitForMultipleObjectsEx(
+                handles.len() as DWORD,
+                handles.as_ptr(),
+                FALSE,
+                timeout_ms,
+                alertable(),
+            )
+        },
+        None => wait_waitable_timer(handles, timeout)?,
+    };
+
+    if result == WAIT_TIMEOUT || result == WAIT_IO_COMPLETION {
+        _generate_timeout_error()
+    } else if result < WAIT_OBJECT_0 + handles.len() as DWORD {
+        Ok((result - WAIT_OBJECT_0) as usize)
+    } else {
+        _generate_os_error()
+    }
+}
+
+// Waits for one of more processes than a single wait can handle. The first ones are waited on by the
+// calling thread along with an event, which is set by helper threads waiting on the others in chunks.
+fn wait_any_chunked(handles: &[HANDLE], timeout: Duration) -> io::Result<usize> {
+    let exited = create_event()?;
+    let cancel = create_event()?;
+    // The index of the process found by a helper thread, or the error of its wait
+    let found: Mutex<Option<io::Result<usize>>> = Mutex::new(None);
+
+    // The calling thread keeps one handle for the event and one for the waitable timer,
+    // the helper threads one for the cancellation event
+    let (first, others) = handles.split_at(MAXIMUM_WAIT_OBJECTS as usize - 2);
+    let chunk_len = MAXIMUM_WAIT_OBJECTS as usize - 1;
+
+    let result = thread::scope(|scope| {
+        for (index, chunk) in others.chunks(chunk_len).enumerate() {
+            let offset = first.len() + index * chunk_len;
+            // Raw handles are not `Send`, they are valid until the scope ends
+            let chunk: Vec<usize> = chunk.iter().map(|&handle| handle as usize).collect();
+            let (exited, cancel, found) = (
+                exited.as_raw_handle() as usize,
+                cancel.as_raw_handle() as usize,
+                &found,
+            );
+            scope.spawn(move || {
+                let mut waited: Vec<HANDLE> =
+                    chunk.iter().map(|&handle| handle as HANDLE).collect();
+                waited.push(cancel as HANDLE);
+                let result = unsafe {
+                    WaitForMultipleObjects(waited.len() as DWORD, waited.as_ptr(), FALSE, INFINITE)
+                };
+
+                let result = if result < WAIT_OBJECT_0 + chunk.len() as DWORD {
+                    Ok(offset + (result - WAIT_OBJECT_0) as usize)
+                } else if result == WAIT_OBJECT_0 + chunk.len() as DWORD {
+                    // The calling thread is done waiting
+                    return;
+                } else {
+                    Err(io::Error::last_os_error())
+                };
+
+                // Only the first process found is reported
+                let mut found = found.lock().unwrap_or_else(|e| e.into_inner());
+                if found.is_none() {
+                    *found = Some(result);
+                }
+                unsafe { SetEvent(exited as HANDLE) };
+            });
+        }
+
+        let mut waited = first.to_vec();
+        waited.push(exited.as_raw_handle() as HANDLE);
+        let result = wait_any_handles(&waited, timeout);
+
+        // The helper threads are joined at the end of the scope
+        unsafe { SetEvent(cancel.as_raw_handle() as HANDLE) };
+        result
+    });
+
+    match result? {
+        index if index < first.len() => Ok(index),
+        _ => found
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+            .expect("the event is only set once the result is stored"),
+    }
+}
+
+// Waits for all the processes to exit, without reaping them. The processes are waited on in chunks
+// of `MAXIMUM_WAIT_OBJECTS`, each one with the time remaining until the deadline.
+pub(crate) fn _wait_all_untraced(children: &[Child], timeout: Duration) -> io::Result<()> {
+    let handles: Vec<HANDLE> = children
+        .iter()
+        .map(|child| child.as_raw_handle() as HANDLE)
+        .collect();
+
+    let deadline = deadline_after(timeout);
+    for chunk in handles.chunks(MAXIMUM_WAIT_OBJECTS as usize) {
+        // A waitable timer cannot be added to a wait for all the handles, the timeout is rounded down
+        // and the caller waits again for the remaining fraction of a millisecond
+        let timeout_ms = remaining_until(deadline)
+            .as_millis()
+            .min((INFINITE - 1) as u128) as DWORD;
+        let result = unsafe {
+            WaitForMultipleObjectsEx(
+                chunk.len() as DWORD,
+                chunk.as_ptr(),
+                TRUE,
+                timeout_ms,
+                alertable(),
+            )
+        };
+
+        if result == WAIT_TIMEOUT || result == WAIT_IO_COMPLETION {
+            return _generate_timeout_error();
+        } else if result >= WAIT_OBJECT_0 + chunk.len() as DWORD {
+            return _generate_os_error();
+        }
+    }
+    Ok(())
+}
+
+// Creates a manual-reset event which is not set
+fn create_event() -> io::Result<OwnedHandle> {
+    let event = unsafe { CreateEventW(ptr::null_mut(), TRUE, FALSE, ptr::null()) };
+    if event.is_null() {
+        return _generate_os_error();
+    }
+    Ok(unsafe { OwnedHandle::from_raw_handle(event as RawHandle) })
+}
+
+// Returns the timeout in milliseconds if it is a whole number of milliseconds below `INFINITE`
+fn timeout_ms(timeout: Duration) -> Option<DWORD> {
+    let timeout_ms = timeout.as_millis();
+    if timeout.subsec_nanos().is_multiple_of(1_000_000) && timeout_ms < INFINITE as u128 {
+        Some(timeout_ms as DWORD)
+    } else {
+        None
+    }
+}
+
+// Waits for one of the processes or for a waitable timer set to the timeout, in units of 100 nanoseconds.
+// Returns the result of `WaitForMultipleObjects` for the processes, `WAIT_TIMEOUT` if the timer expired first.
+fn wait_waitable_timer(processes: &[HANDLE], timeout: Duration) -> io::Result<DWORD> {
+    let mut timer = unsafe {
+        CreateWaitableTimerExW(
+            ptr::null_mut(),
+            ptr::null(),
+            CREATE_WAITABLE_TIMER_HIGH_RESOLUTION,
+            TIMER_ALL_ACCESS,
+        )
+    };
+    if timer.is_null() {
+        // High-resolution timers are not supported by older versions of Windows
+        timer =
+            unsafe { CreateWaitableTimerExW(ptr::null_mut(), ptr::null(), 0, TIMER_ALL_ACCESS) };
+        if timer.is_null() {
+            return _generate_os_error();
+        }
+    }
+
+    // A negative due time is relative to the current time, rounded up to the next 100 nanoseconds
+    let intervals = timeout.as_nanos().div_ceil(100).min(i64::MAX as u128) as i64;
+    let mut due_time: LARGE_INTEGER = unsafe { mem::zeroed() };
+    unsafe { *due_time.QuadPart_mut() = -intervals };
+
+    let set = unsafe { SetWaitableTimer(timer, &due_time, 0, None, ptr::null_mut(), FALSE) };
+
+    // When several handles are signaled, the lowest index is returned so exits take precedence
+    let mut handles = processes.to_vec();
+    handles.push(timer);
+    let result = if set == 0 {
+        WAIT_FAILED
+    } else {
+        unsafe {
+            WaitForMultipleObjectsEx(
+                handles.len() as DWORD,
+                handles.as_ptr(),
+                FALSE,
+                INFINITE,
+                alertable(),
+            )
+        }
+    };
+    // Retrieved before closing the timer, which may overwrite the last error
+    let error = io::Error::last_os_error();
+
+    unsafe { CloseHandle(timer) };
+
+    if result == WAIT_FAILED {
+        Err(error)
+    } else if result == WAIT_OBJECT_0 + processes.len() as DWORD {
+        Ok(WAIT_TIMEOUT)
+    } else {
+        Ok(result)
+    }
+}
+
+// Asks the child to close by posting `WM_CLOSE` to its top-level windows, like `taskkill` without `/F`
+pub(crate) fn terminate(pid: u32) -> io::Result<()> {
+    unsafe extern "system" fn close_window(hwnd: HWND, pid: LPARAM) -> BOOL {
+        let mut owner: DWORD = 0;
+        unsafe { GetWindowThreadProcessId(hwnd, &mut owner) };
+        if owner == pid as DWORD {
+            unsafe { PostMessageW(hwnd, WM_CLOSE, 0, 0) };
+        }
+        TRUE
+    }
+
+    if unsafe { EnumWindows(Some(close_window), pid as LPARAM) } == 0 {
+        return _generate_os_error();
+    }
+    Ok(())
+}
+
+// Kills the child with the given function, then its descendants, found by walking a snapshot
+// of the running processes
+pub(crate) fn kill_tree_with(pid: u32, kill: impl FnOnce() -> io::Result<()>) -> io::Result<()> {
+    let descendants = descendants(pid)?;
+    kill()?;
+
+    // Descendants which have already exited are skipped
+    for pid in descendants {
+        let process = unsafe { OpenProcess(PROCESS_TERMINATE, FALSE, pid) };
+        if !process.is_null() {
+            unsafe {
+                TerminateProcess(process, 1);
+                CloseHandle(process);
+            }
+        }
     }
+    Ok(())
+}
+
+// Returns the pids of the descendants of the process, parents first
+fn descendants(pid: DWORD) -> io::Result<Vec<DWORD>> {
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return _generate_os_error();
+    }
+
+    let mut entry: PROCESSENTRY32W = unsafe { mem::zeroed() };
+    entry.dwSize = mem::size_of::<PROCESSENTRY32W>() as DWORD;
+    let mut processes = Vec::new();
+    let mut found = unsafe { Process32FirstW(snapshot, &mut entry) } != 0;
+    while found {
+        processes.push((entry.th32ProcessID, entry.th32ParentProcessID));
+        found = unsafe { Process32NextW(snapshot, &mut entry) } != 0;
+    }
+
+    unsafe { CloseHandle(snapshot) };
+
+    // Windows does not reparent orphans, so the tree is rebuilt from the parent pids
+    let mut tree = vec![pid];
+    let mut index = 0;
+    while index < tree.len() {
+        let parent = tree[index];
+        for &(process, process_parent) in &processes {
+            if process_parent == parent && !tree.contains(&process) {
+                tree.push(process);
+            }
+        }
+        index += 1;
+    }
+
+    tree.remove(0);
+    Ok(tree)
 }
@@ -0,0 +1,151 @@
+use std::io;
+use std::os::windows::io::{AsHandle, AsRawHandle, OwnedHandle};
+use std::os::windows::process::ExitStatusExt;
+use std::process::{Child, ExitStatus};
+use std::ptr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::win32::{
+    GetExitCodeProcess, RegisterWaitForSingleObject, UnregisterWaitEx, BOOLEAN, DWORD, HANDLE,
+    INFINITE, INVALID_HANDLE_VALUE, PVOID, WT_EXECUTEONLYONCE,
+};
+
+use crate::error::{_generate_os_error, _generate_timeout_error};
+
+type Callback = Box<dyn FnOnce(io::Result<ExitStatus>) + Send>;
+
+// Shared with the thread pool until the wait is unregistered
+struct Context {
+    // Owned by the registration so that the child can be dropped before its exit
+    process: OwnedHandle,
+    callback: Mutex<Option<Callback>>,
+}
+
+/// A callback registered with the system thread pool, called once the child exits or the timeout expires.
+///
+/// The wait is registered with `RegisterWaitForSingleObject`, so no thread is blocked while the child is
+/// running: the thread pool waits on many handles per thread. GUI applications and services can then watch
+/// their children without dedicating a thread to each one, nor blocking their message loop.
+///
+/// Dropping the registration cancels the callback if it has not been called yet. If the callback is
+/// running, the drop waits for it to return, so the registration must not be dropped from the callback.
+///
+/// This type is only available on Windows.
+///
+/// # Example
+/// ```rust,no_run
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+/// use std::sync::mpsc;
+///
+/// use child_wait_timeout::ExitCallback;
+///
+/// let child = Command::new("timeout").args(["/t", "1"]).spawn()?;
+/// let (sender, receiver) = mpsc::channel();
+/// let _registration = ExitCallback::register(&child, Duration::from_secs(5), move |status| {
+///     let _ = sender.send(status);
+/// })?;
+///
+/// // The current thread is free to run its message loop in the meantime
+/// let status = receiver.recv()??;
+/// println!("child exited with {}", status);
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ExitCallback {
+    wait: HANDLE,
+    context: *const Context,
+}
+
+// The wait handle and the context are only used to unregister the wait, from any thread
+unsafe impl Send for ExitCallback {}
+unsafe impl Sync for ExitCallback {}
+
+impl ExitCallback {
+    /// Registers a callback called once the child exits or the timeout expires.
+    ///
+    /// The callback is called once, from a thread of the system thread pool, with the exit status of the
+    /// child, or with an error of kind `ErrorKind::TimedOut` if the timeout expires first. The child is not
+    /// consumed, its exit status can still be retrieved with [`Child::wait`] afterwards. The timeout is
+    /// rounded up to the next millisecond.
+    ///
+    /// The callback should return quickly, so that the other waits of the thread pool are not delayed.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the handle of the child could not be duplicated, or if the wait could not be registered.
+    pub fn register<F>(child: &Child, timeout: Duration, callback: F) -> io::Result<Self>
+    where
+        F: FnOnce(io::Result<ExitStatus>) + Send + 'static,
+    {
+        let process = child.as_handle().try_clone_to_owned()?;
+        let context = Box::into_raw(Box::new(Context {
+            process,
+            callback: Mutex::new(Some(Box::new(callback))),
+        }));
+
+        // Round the timeout up so that the callback is never called before it expires
+        let timeout_ms = timeout
+            .as_nanos()
+            .div_ceil(1_000_000)
+            .min((INFINITE - 1) as u128) as DWORD;
+
+        let mut wait: HANDLE = ptr::null_mut();
+        let registered = unsafe {
+            RegisterWaitForSingleObject(
+                &mut wait,
+                (*context).process.as_raw_handle() as HANDLE,
+                Some(notify),
+                context as PVOID,
+                timeout_ms,
+                WT_EXECUTEONLYONCE,
+            )
+        };
+        if registered == 0 {
+            let error = _generate_os_error();
+            drop(unsafe { Box::from_raw(context) });
+            return error;
+        }
+
+        Ok(ExitCallback { wait, context })
+    }
+}
+
+impl Drop for ExitCallback {
+    fn drop(&mut self) {
+        // Blocks until a running callback returns, the context can then be freed
+        unsafe { UnregisterWaitEx(self.wait, INVALID_HANDLE_VALUE) };
+        drop(unsafe { Box::from_raw(self.context as *mut Context) });
+    }
+}
+
+// Called by the thread pool once the child has exited or the timeout has expired
+unsafe extern "system" fn notify(context: PVOID, timed_out: BOOLEAN) {
+    let context = unsafe { &*(context as *const Context) };
+
+    let callback = context
+        .callback
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take();
+    let Some(callback) = callback else {
+        return;
+    };
+
+    if timed_out != 0 {
+        callback(_generate_timeout_error());
+        return;
+    }
+
+    let mut code: DWORD = 0;
+    if unsafe { GetExitCodeProcess(context.process.as_raw_handle() as HANDLE, &mut code) } == 0 {
+        callback(_generate_os_error());
+    } else {
+        callback(Ok(ExitStatus::from_raw(code)));
+    }
+}
@@ -0,0 +1,203 @@
+use std::io;
+use std::mem;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle, RawHandle};
+use std::process::Child;
+use std::ptr;
+use std::time::Duration;
+
+use super::win32::{
+    AssignProcessToJobObject, CreateIoCompletionPort, CreateJobObjectW, GetQueuedCompletionStatus,
+    JobObjectAssociateCompletionPortInformation, JobObjectBasicAccountingInformation,
+    QueryInformationJobObject, SetInformationJobObject, TerminateJobObject, DWORD, HANDLE,
+    INFINITE, INVALID_HANDLE_VALUE, JOBOBJECT_ASSOCIATE_COMPLETION_PORT,
+    JOBOBJECT_BASIC_ACCOUNTING_INFORMATION, JOB_OBJECT_MSG_ACTIVE_PROCESS_ZERO, LPOVERLAPPED,
+    LPVOID, ULONG_PTR, WAIT_TIMEOUT,
+};
+
+use super::{deadline_after, remaining_until};
+use crate::error::{_generate_os_error, _generate_timeout_error};
+
+/// A Windows Job Object grouping a process tree.
+///
+/// Processes spawned by a process assigned to the job are part of the job too, so waiting on the job
+/// or terminating it applies to the whole tree, even when intermediate processes have exited.
+///
+/// This type is only available on Windows.
+///
+/// # Example
+/// ```rust,no_run
+/// # use std::error::Error;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use std::time::Duration;
+/// use std::process::Command;
+///
+/// use child_wait_timeout::Job;
+///
+/// let job = Job::new()?;
+/// let child = Command::new("cmd").args(["/c", "build.bat"]).spawn()?;
+/// job.assign(&child)?;
+/// job.wait_or_terminate(Duration::from_secs(60))?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Job {
+    job: OwnedHandle,
+    // Receives the notifications of the job, such as the exit of its last process
+    port: OwnedHandle,
+}
+
+impl Job {
+    /// Creates an empty job.
+    pub fn new() -> io::Result<Self> {
+        let job = unsafe { CreateJobObjectW(ptr::null_mut(), ptr::null()) };
+        if job.is_null() {
+            return _generate_os_error();
+        }
+        let job = unsafe { OwnedHandle::from_raw_handle(job as RawHandle) };
+
+        let port = unsafe { CreateIoCompletionPort(INVALID_HANDLE_VALUE, ptr::null_mut(), 0, 1) };
+        if port.is_null() {
+            return _generate_os_error();
+        }
+        let port = unsafe { OwnedHandle::from_raw_handle(port as RawHandle) };
+
+        let mut association = JOBOBJECT_ASSOCIATE_COMPLETION_PORT {
+            CompletionKey: ptr::null_mut(),
+            CompletionPort: port.as_raw_handle() as HANDLE,
+        };
+        let result = unsafe {
+            SetInformationJobObject(
+                job.as_raw_handle() as HANDLE,
+                JobObjectAssociateCompletionPortInformation,
+                &mut association as *mut _ as LPVOID,
+                mem::size_of::<JOBOBJECT_ASSOCIATE_COMPLETION_PORT>() as DWORD,
+            )
+        };
+        if result == 0 {
+            return _generate_os_error();
+        }
+
+        Ok(Job { job, port })
+    }
+
+    /// Assigns the child process to the job.
+    ///
+    /// The processes spawned by the child process before the assignment are not part of the job,
+    /// so the child process should be assigned right after being spawned, or spawned suspended.
+    pub fn assign(&self, child: &Child) -> io::Result<()> {
+        let result =
+            unsafe { AssignProcessToJobObject(self.handle(), child.as_raw_handle() as HANDLE) };
+        if result == 0 {
+            return _generate_os_error();
+        }
+        Ok(())
+    }
+
+    /// Returns the number of processes of the job which are still running.
+    pub fn active_processes(&self) -> io::Result<u32> {
+        let mut info: JOBOBJECT_BASIC_ACCOUNTING_INFORMATION = unsafe { mem::zeroed() };
+        let result = unsafe {
+            QueryInformationJobObject(
+                self.handle(),
+                JobObjectBasicAccountingInformation,
+                &mut info as *mut _ as LPVOID,
+                mem::size_of::<JOBOBJECT_BASIC_ACCOUNTING_INFORMATION>() as DWORD,
+                ptr::null_mut(),
+            )
+        };
+        if result == 0 {
+            return _generate_os_error();
+        }
+        Ok(info.ActiveProcesses)
+    }
+
+    /// Waits for all the processes of the job to exit or until the timeout expires.
+    ///
+    /// The processes are not reaped, the exit status of an assigned child process is still retrieved
+    /// with [`Child::wait`].
+    ///
+    /// # Errors
+    ///
+    /// This function may fail with `ErrorKind::TimedOut` if the specified duration expires before all the processes exit.
+    pub fn wait_timeout(&self, timeout: Duration) -> io::Result<()> {
+        let deadline = deadline_after(timeout);
+
+        loop {
+            // The notification may have been consumed by a previous wait, or be stale if processes
+            // were assigned since, so the number of processes is checked after each one
+            if self.active_processes()? == 0 {
+                return Ok(());
+            }
+
+            let remaining = remaining_until(deadline);
+            if remaining.is_zero() {
+                return _generate_timeout_error();
+            }
+
+            // Round the remaining time up so that the deadline is never missed
+            let timeout_ms = remaining
+                .as_nanos()
+                .div_ceil(1_000_000)
+                .min((INFINITE - 1) as u128) as DWORD;
+
+            let mut message: DWORD = 0;
+            let mut key: ULONG_PTR = 0;
+            let mut overlapped: LPOVERLAPPED = ptr::null_mut();
+            let result = unsafe {
+                GetQueuedCompletionStatus(
+                    self.port.as_raw_handle() as HANDLE,
+                    &mut message,
+                    &mut key,
+                    &mut overlapped,
+                    timeout_ms,
+                )
+            };
+
+            // Other notifications, such as the exit of a single process, are skipped
+            if result == 0 {
+                let error = io::Error::last_os_error();
+                if error.raw_os_error() != Some(WAIT_TIMEOUT as i32) {
+                    return Err(error);
+                }
+            } else if message == JOB_OBJECT_MSG_ACTIVE_PROCESS_ZERO {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Waits for all the processes of the job to exit, and terminates them if the timeout expires.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with `ErrorKind::TimedOut` if the timeout expired, in which case all the processes of the job
+    /// have been terminated. It may also fail if the job could not be terminated.
+    pub fn wait_or_terminate(&self, timeout: Duration) -> io::Result<()> {
+        match self.wait_timeout(timeout) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                self.terminate(1)?;
+                Err(e)
+            }
+            result => result,
+        }
+    }
+
+    /// Terminates all the processes of the job with the given exit code.
+    pub fn terminate(&self, exit_code: u32) -> io::Result<()> {
+        if unsafe { TerminateJobObject(self.handle(), exit_code) } == 0 {
+            return _generate_os_error();
+        }
+        Ok(())
+    }
+
+    fn handle(&self) -> HANDLE {
+        self.job.as_raw_handle() as HANDLE
+    }
+}
+
+impl AsRawHandle for Job {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.job.as_raw_handle()
+    }
+}
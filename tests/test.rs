@@ -2,9 +2,11 @@ extern crate utilities;
 
 #[cfg(test)]
 mod tests {
-    use child_wait_timeout::ChildWT;
-    use std::{io, time::Duration};
-    use utilities;
+    use child_wait_timeout::{ChildWT, WaitError};
+    use std::{
+        io,
+        time::{Duration, Instant},
+    };
 
     #[test]
     fn test_wait_timeout_success() {
@@ -62,4 +64,2903 @@ mod tests {
 
         assert_eq!(result.unwrap(), result2.unwrap());
     }
+
+    #[test]
+    fn test_wait_timeout_repeated_timeouts() {
+        // Spawn a short-lived process
+        let mut child = utilities::sleep_child("1");
+
+        // Poll the process with short timeouts until it exits
+        let mut timeouts = 0;
+        let result = loop {
+            match child.wait_timeout(Duration::from_millis(100)) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => timeouts += 1,
+                result => break result,
+            }
+        };
+
+        // Verify that the process exited successfully after a few timeouts
+        assert!(result.is_ok());
+        assert!(timeouts > 0);
+    }
+
+    #[test]
+    fn test_wait_timeout_exit_code() {
+        // Spawn a process exiting with a specific code
+        let mut child = utilities::exit_child(3);
+
+        // Wait for the process to exit with a timeout
+        let result = child.wait_timeout(Duration::from_secs(5));
+
+        // Verify that the exit code is preserved
+        assert_eq!(result.unwrap().code(), Some(3));
+    }
+
+    #[cfg(all(target_os = "linux", feature = "pidfd"))]
+    #[test]
+    fn test_pidfd_child_repeated_timeouts() {
+        use child_wait_timeout::PidFdChild;
+
+        // Spawn a short-lived process
+        let mut child = PidFdChild::new(utilities::sleep_child("1"));
+
+        // Poll the process with short timeouts until it exits, reusing the same pidfd
+        let mut timeouts = 0;
+        let result = loop {
+            match child.wait_timeout(Duration::from_millis(100)) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => timeouts += 1,
+                result => break result,
+            }
+        };
+
+        // Verify that the process exited successfully after a few timeouts
+        assert!(result.is_ok());
+        assert!(timeouts > 0);
+
+        // Verify that the status is still available once the child is reaped
+        let result2 = child.wait_timeout(Duration::from_secs(5));
+        assert_eq!(result.unwrap(), result2.unwrap());
+    }
+
+    #[cfg(all(target_os = "linux", feature = "pidfd"))]
+    #[test]
+    fn test_pidfd_wait_timeout() {
+        use child_wait_timeout::PidFd;
+
+        // Spawn a process exiting with a specific code
+        let mut child = utilities::exit_child(3);
+        let pidfd = PidFd::from_child(&child).unwrap();
+
+        // Wait for the process to exit through the pidfd
+        let status = pidfd.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        // Verify that the process was left for the child to reap
+        assert_eq!(pidfd.try_wait().unwrap(), Some(status));
+        assert_eq!(child.wait().unwrap(), status);
+
+        // Verify that the exit status of a process reaped elsewhere is reported as lost
+        let result = pidfd.wait_timeout(Duration::from_secs(5));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "pidfd"))]
+    #[test]
+    fn test_pidfd_epoll_wait_deadline() {
+        use child_wait_timeout::{PidFd, PidFdEpoll};
+        use std::time::Instant;
+
+        // Spawn two short-lived processes and a long-running one
+        let mut children = [
+            utilities::sleep_child("0"),
+            utilities::sleep_child("1"),
+            utilities::sleep_child("5"),
+        ];
+
+        let mut epoll = PidFdEpoll::new().unwrap();
+        for (key, child) in children.iter().enumerate() {
+            epoll.add(key, PidFd::from_child(child).unwrap()).unwrap();
+        }
+
+        // Collect the processes exiting before the deadline
+        let deadline = Instant::now() + Duration::from_secs(3);
+        let mut exited = Vec::new();
+        loop {
+            let keys: Vec<usize> = epoll.wait_deadline(deadline).unwrap().collect();
+            if keys.is_empty() {
+                break;
+            }
+            for key in keys {
+                epoll.remove(key).unwrap();
+                exited.push(key);
+            }
+        }
+
+        // Verify that only the short-lived processes exited
+        exited.sort();
+        assert_eq!(exited, [0, 1]);
+        assert_eq!(epoll.len(), 1);
+        assert!(Instant::now() >= deadline);
+
+        children[2].kill().unwrap();
+        for child in children.iter_mut() {
+            child.wait().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_wait_timeout_other_child_exits() {
+        // Spawn a short-lived process and a longer one
+        let mut other = utilities::sleep_child("0");
+        let mut child = utilities::sleep_child("1");
+
+        // Wait for the longer process while the other one exits
+        let result = child.wait_timeout(Duration::from_secs(5));
+
+        // Verify that the exit of the other process did not interrupt the wait
+        assert!(result.is_ok());
+        assert!(other.wait().is_ok());
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        feature = "signal",
+        not(feature = "thread"),
+        not(feature = "pidfd")
+    ))]
+    #[test]
+    fn test_set_notification_signal_invalid() {
+        use child_wait_timeout::{notification_signal, set_notification_signal};
+
+        // SIGCHLD is waited for by default
+        assert_eq!(notification_signal(), 17);
+
+        // Verify that invalid signals and signals which cannot be blocked are rejected
+        for signal in [0, 9, 19, 1000] {
+            let result = set_notification_signal(signal);
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+        assert_eq!(notification_signal(), 17);
+    }
+
+    #[test]
+    fn test_wait_deadline_success() {
+        // Spawn a short-lived process
+        let mut child = utilities::sleep_child("1");
+
+        // Wait for the process to exit before a deadline
+        let result = child.wait_deadline(Instant::now() + Duration::from_secs(5));
+
+        // Verify that the process exited successfully
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_wait_deadline_exceeded() {
+        // Spawn a long-running process
+        let mut child = utilities::sleep_child("3");
+
+        // Wait for the process to exit with a close deadline
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let result = child.wait_deadline(deadline);
+
+        // Verify that the deadline was exceeded
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that a passed deadline does not block
+        let result = child.wait_deadline(deadline);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_wait_timeout_opt_infinite() {
+        // Spawn a short-lived process
+        let mut child = utilities::sleep_child("1");
+
+        // Wait for the process to exit without a timeout
+        let result = child.wait_timeout_opt(None);
+
+        // Verify that the process exited successfully
+        assert!(result.unwrap().success());
+    }
+
+    #[test]
+    fn test_wait_timeout_opt_timeout() {
+        // Spawn a long-running process
+        let mut child = utilities::sleep_child("3");
+
+        // Wait for the process to exit with a shorter timeout
+        let result = child.wait_timeout_opt(Some(Duration::from_secs(1)));
+
+        // Verify that the timeout occurred
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_try_wait_timeout() {
+        // Spawn a process exiting after the first wait
+        let mut child = utilities::sleep_child("2");
+
+        // Verify that the timeout is not an error
+        let result = child.try_wait_timeout(Duration::from_secs(1));
+        assert!(result.unwrap().is_none());
+
+        // Verify that the exit status is returned once the process exits
+        let result = child.try_wait_timeout(Duration::from_secs(5));
+        assert!(result.unwrap().unwrap().success());
+    }
+
+    #[test]
+    fn test_wait_timeout_with_tick() {
+        // Verify that the closure is called while the process is running
+        let mut child = utilities::sleep_child("1");
+        let mut ticks = Vec::new();
+        let result = child.wait_timeout_with_tick(
+            Duration::from_secs(5),
+            Duration::from_millis(200),
+            |elapsed| ticks.push(elapsed),
+        );
+        assert!(result.unwrap().success());
+        assert!(ticks.len() >= 3);
+        assert!(ticks.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // Verify that the timeout is shared by the steps
+        let mut child = utilities::sleep_child("10");
+        let start = Instant::now();
+        let mut ticks = 0;
+        let result = child.wait_timeout_with_tick(
+            Duration::from_millis(500),
+            Duration::from_millis(200),
+            |_| ticks += 1,
+        );
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(ticks >= 2);
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_wait_timeout_timed() {
+        // Verify that the elapsed time is reported along with the exit status
+        let mut child = utilities::sleep_child("1");
+        let timing = child.wait_timeout_timed(Duration::from_secs(5)).unwrap();
+        assert!(timing.status().unwrap().success());
+        assert!(!timing.timed_out());
+        assert!(timing.elapsed() >= Duration::from_millis(500));
+        assert_eq!(
+            timing.remaining(),
+            Duration::from_secs(5) - timing.elapsed()
+        );
+
+        // Verify that the whole timeout is consumed when it expires
+        let mut child = utilities::sleep_child("10");
+        let timing = child
+            .wait_timeout_timed(Duration::from_millis(300))
+            .unwrap();
+        assert!(timing.timed_out());
+        assert!(timing.elapsed() >= Duration::from_millis(300));
+        assert_eq!(timing.remaining(), Duration::ZERO);
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_deadline() {
+        use child_wait_timeout::Deadline;
+
+        // Verify that the waits share the same budget
+        let deadline = Deadline::after(Duration::from_millis(1500));
+        let mut first = utilities::sleep_child("1");
+        let mut second = utilities::sleep_child("10");
+        assert!(deadline.wait(&mut first).unwrap().success());
+        assert!(!deadline.is_expired());
+        let result = deadline.wait_or_kill(&mut second);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(deadline.is_expired());
+        assert_eq!(deadline.remaining(), Duration::ZERO);
+
+        // Verify that the second process was killed and reaped
+        assert!(!second.try_wait().unwrap().unwrap().success());
+    }
+
+    #[test]
+    fn test_wait_error_timeout() {
+        // Spawn a long-running process
+        let mut child = utilities::sleep_child("3");
+
+        // Wait for the process to exit with a shorter timeout
+        let result = child.wait_timeout(Duration::from_secs(1));
+
+        // Verify that the timeout can be matched precisely
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(matches!(WaitError::from(error), WaitError::Timeout));
+
+        // Verify that errors of the backend are returned as is
+        let error: io::Error = WaitError::Backend(io::Error::from_raw_os_error(1)).into();
+        assert_eq!(error.raw_os_error(), Some(1));
+    }
+
+    #[cfg(all(target_os = "linux", feature = "pidfd"))]
+    #[test]
+    fn test_pidfd_os_error() {
+        use child_wait_timeout::PidFd;
+
+        // Spawn a process and reap it so that its pid no longer exists
+        let mut child = utilities::exit_child(0);
+        let pid = child.id();
+        child.wait().unwrap();
+
+        // Verify that the error code of `pidfd_open` is preserved
+        let error = PidFd::from_pid(pid).unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(libc::ESRCH));
+        assert!(matches!(WaitError::from(error), WaitError::NoSuchProcess));
+    }
+
+    #[cfg(all(target_os = "linux", feature = "pidfd"))]
+    #[test]
+    fn test_pidfd_waitid_unsupported() {
+        use child_wait_timeout::{active_backend, Backend, PidFd, PidFdChild};
+        use libc::{
+            sock_filter, sock_fprog, BPF_ABS, BPF_JEQ, BPF_JMP, BPF_K, BPF_LD, BPF_RET, BPF_W,
+        };
+
+        // The fallback lasts for the rest of the process lifetime, so the test runs again alone in a
+        // process of its own
+        if std::env::var_os("CHILD_WAIT_TIMEOUT_WAITID_UNSUPPORTED").is_none() {
+            let status = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", "tests::test_pidfd_waitid_unsupported"])
+                .env("CHILD_WAIT_TIMEOUT_WAITID_UNSUPPORTED", "1")
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        // Open the pidfd of a child before the kernel stops accepting pidfds in `waitid`
+        let mut child = PidFdChild::new(utilities::sleep_child("0.5"));
+        let result = child.wait_timeout(Duration::from_millis(10));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(child.pidfd().is_some());
+        let mut other = utilities::sleep_child("0.5");
+        let pidfd = PidFd::from_child(&other).unwrap();
+
+        // Reject `waitid(P_PIDFD, ...)` with `EINVAL` like Linux 5.3, skipping the test without seccomp
+        let arg0 = if cfg!(target_endian = "little") {
+            16
+        } else {
+            20
+        };
+        let filter = unsafe {
+            [
+                libc::BPF_STMT((BPF_LD | BPF_W | BPF_ABS) as u16, 0),
+                libc::BPF_JUMP(
+                    (BPF_JMP | BPF_JEQ | BPF_K) as u16,
+                    libc::SYS_waitid as u32,
+                    0,
+                    3,
+                ),
+                libc::BPF_STMT((BPF_LD | BPF_W | BPF_ABS) as u16, arg0),
+                libc::BPF_JUMP((BPF_JMP | BPF_JEQ | BPF_K) as u16, libc::P_PIDFD, 0, 1),
+                libc::BPF_STMT(
+                    BPF_RET as u16,
+                    libc::SECCOMP_RET_ERRNO | libc::EINVAL as u32,
+                ),
+                libc::BPF_STMT(BPF_RET as u16, libc::SECCOMP_RET_ALLOW),
+            ]
+        };
+        let program = sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_ptr() as *mut sock_filter,
+        };
+        let installed = unsafe {
+            libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) == 0
+                && libc::syscall(
+                    libc::SYS_seccomp,
+                    libc::SECCOMP_SET_MODE_FILTER,
+                    libc::SECCOMP_FILTER_FLAG_TSYNC,
+                    &program,
+                ) == 0
+        };
+        if !installed {
+            child.as_child_mut().wait().unwrap();
+            other.wait().unwrap();
+            return;
+        }
+
+        // Verify that the wait polling the pidfd reaps the child once it becomes readable
+        let status = child.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert!(status.success());
+
+        // Verify that reading the exit status through a pidfd is reported as unsupported
+        let result = pidfd.wait_timeout(Duration::from_secs(5));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+        assert!(other.wait().unwrap().success());
+
+        // Verify that the next waits fall back to the thread backend
+        assert_eq!(active_backend(), Backend::Thread);
+        let mut child = utilities::sleep_child("0.2");
+        let status = child.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert!(status.success());
+    }
+
+    #[cfg(all(target_os = "linux", feature = "pidfd", not(feature = "uring")))]
+    #[test]
+    fn test_restart_on_interrupt() {
+        use child_wait_timeout::set_restart_on_interrupt;
+        use std::{mem, thread};
+
+        extern "C" fn ignore(_: libc::c_int) {}
+
+        // Install a handler without `SA_RESTART`, so that the signal interrupts the wait
+        let mut action: libc::sigaction = unsafe { mem::zeroed() };
+        action.sa_sigaction = ignore as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        assert_eq!(
+            unsafe { libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut()) },
+            0
+        );
+        let interrupt = |target: libc::pthread_t| {
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(200));
+                unsafe { libc::pthread_kill(target, libc::SIGUSR1) };
+            })
+        };
+
+        // Verify that the interrupted wait is restarted until the child exits
+        let mut child = utilities::sleep_child("1");
+        let sender = interrupt(unsafe { libc::pthread_self() });
+        let result = child.wait_timeout(Duration::from_secs(5));
+        sender.join().unwrap();
+        assert!(result.unwrap().success());
+
+        // Verify that the interruption is surfaced once restarting is disabled
+        set_restart_on_interrupt(false);
+        let mut child = utilities::sleep_child("1");
+        let sender = interrupt(unsafe { libc::pthread_self() });
+        let result = child.wait_timeout(Duration::from_secs(5));
+        sender.join().unwrap();
+        set_restart_on_interrupt(true);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[cfg(all(target_os = "linux", feature = "uring"))]
+    #[test]
+    fn test_uring_wait_without_timeout() {
+        use std::{sync::mpsc, thread};
+
+        // Verify that a wait without timeout returns once the child exits, instead of timing out at once
+        let mut child = utilities::sleep_command("0.2").spawn().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || sender.send(child.wait_timeout(Duration::MAX)));
+        let result = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(result.unwrap().success());
+    }
+
+    #[cfg(all(target_os = "linux", feature = "signal"))]
+    #[test]
+    fn test_signal_wait_on_spawned_thread() {
+        use std::{sync::mpsc, thread};
+
+        use child_wait_timeout::Backend;
+
+        // The notification signal is only blocked in the waiting thread and may be delivered to this one,
+        // verify that the wait still returns once the child exits
+        let backend = if cfg!(feature = "signalfd") {
+            Backend::Signalfd
+        } else {
+            Backend::Signal
+        };
+        let mut child = utilities::sleep_command("0.2").spawn().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || sender.send(child.wait_timeout_with_backend(backend, Duration::MAX)));
+        let result = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(result.unwrap().success());
+    }
+
+    #[cfg(all(target_os = "linux", feature = "signal"))]
+    #[test]
+    fn test_signal_concurrent_waits() {
+        use std::{sync::mpsc, thread};
+
+        use child_wait_timeout::Backend;
+
+        let backend = if cfg!(feature = "signalfd") {
+            Backend::Signalfd
+        } else {
+            Backend::Signal
+        };
+
+        // Each wait may consume the notification of the child of the other one, verify that both
+        // still return once their child exits
+        let (sender, receiver) = mpsc::channel();
+        for duration in ["0.2", "0.4"] {
+            let mut child = utilities::sleep_command(duration).spawn().unwrap();
+            let sender = sender.clone();
+            thread::spawn(move || {
+                sender.send(child.wait_timeout_with_backend(backend, Duration::from_secs(10)))
+            });
+        }
+        for _ in 0..2 {
+            let result = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert!(result.unwrap().success());
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_active_backend() {
+        use child_wait_timeout::{active_backend, Backend};
+
+        // Verify that the backend follows the priority order of the features
+        let backend = active_backend();
+        if cfg!(feature = "uring") {
+            assert!(matches!(
+                backend,
+                Backend::Uring | Backend::Pidfd | Backend::Thread
+            ));
+        } else if cfg!(feature = "pidfd") {
+            assert!(matches!(backend, Backend::Pidfd | Backend::Thread));
+        } else if cfg!(feature = "thread") || !cfg!(feature = "signal") {
+            assert_eq!(backend, Backend::Thread);
+        } else if cfg!(feature = "signalfd") {
+            assert_eq!(backend, Backend::Signalfd);
+        } else {
+            assert_eq!(backend, Backend::Signal);
+        }
+        assert_eq!(backend.to_string(), backend.name());
+    }
+
+    #[cfg(any(target_os = "illumos", target_os = "solaris"))]
+    #[test]
+    fn test_wait_event_ports() {
+        use child_wait_timeout::{active_backend, wait_any_timeout, Backend};
+
+        // Verify that the event ports backend is the default one
+        assert_eq!(active_backend(), Backend::EventPorts);
+
+        // Verify that the exit of the process is reported through the port
+        let mut child = utilities::sleep_child("1");
+        let result = child.wait_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(child
+            .wait_timeout(Duration::from_secs(5))
+            .unwrap()
+            .success());
+
+        // Verify that several processes are waited on through a single port
+        let mut children = [utilities::sleep_child("3"), utilities::sleep_child("1")];
+        let (index, status) = wait_any_timeout(&mut children, Duration::from_secs(5)).unwrap();
+        assert_eq!(index, 1);
+        assert!(status.success());
+        children[0].kill().unwrap();
+        children[0].wait().unwrap();
+    }
+
+    #[cfg(target_os = "fuchsia")]
+    #[test]
+    fn test_wait_fuchsia() {
+        use child_wait_timeout::{active_backend, wait_any_timeout, Backend};
+
+        // Verify that the process handle is waited on by default
+        assert_eq!(active_backend(), Backend::FuchsiaWaitOne);
+
+        let mut child = utilities::sleep_child("1");
+        let result = child.wait_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(child
+            .wait_timeout(Duration::from_secs(5))
+            .unwrap()
+            .success());
+
+        // Verify that several process handles are waited on at once
+        let mut children = [utilities::sleep_child("3"), utilities::sleep_child("1")];
+        let (index, status) = wait_any_timeout(&mut children, Duration::from_secs(5)).unwrap();
+        assert_eq!(index, 1);
+        assert!(status.success());
+        children[0].kill().unwrap();
+        children[0].wait().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_wait_timeout_with_backend() {
+        use child_wait_timeout::Backend;
+
+        // Verify that the thread backend can always be selected
+        let mut child = utilities::sleep_child("1");
+        let result = child.wait_timeout_with_backend(Backend::Thread, Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        let status = child.wait_timeout_with_backend(Backend::Thread, Duration::from_secs(5));
+        assert!(status.unwrap().success());
+
+        // Verify that a backend which is not compiled in is rejected
+        let mut child = utilities::sleep_child("1");
+        let result = child.wait_timeout_with_backend(Backend::Kqueue, Duration::from_secs(5));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+
+        // Verify that the backends of the enabled features can be selected
+        let backend = if cfg!(feature = "pidfd") {
+            Backend::Pidfd
+        } else if cfg!(feature = "signalfd") {
+            Backend::Signalfd
+        } else if cfg!(feature = "signal") {
+            Backend::Signal
+        } else {
+            Backend::Thread
+        };
+        let status = child.wait_timeout_with_backend(backend, Duration::from_secs(5));
+        assert!(status.unwrap().success());
+    }
+
+    #[cfg(feature = "polling")]
+    #[test]
+    fn test_wait_timeout_polling() {
+        use child_wait_timeout::Backend;
+
+        // Verify that the polling backend observes the exit of the process
+        let mut child = utilities::sleep_child("1");
+        let result = child.wait_timeout_with_backend(Backend::Polling, Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        let status = child.wait_timeout_with_backend(Backend::Polling, Duration::from_secs(5));
+        assert!(status.unwrap().success());
+    }
+
+    #[test]
+    fn test_wait_timeout_precise() {
+        // Spawn a long-running process
+        let mut child = utilities::sleep_child("3");
+
+        // Wait for less than a millisecond
+        let start = Instant::now();
+        let result = child.wait_timeout_precise(Duration::from_micros(500));
+
+        // Verify that the timeout is not truncated to zero
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_micros(500));
+
+        // Verify that the full timeout elapses before a timeout is reported
+        let start = Instant::now();
+        let result = child.wait_timeout_precise(Duration::from_micros(1500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_micros(1500));
+    }
+
+    #[test]
+    fn test_wait_timeout_monotonic() {
+        use child_wait_timeout::{wait_any_timeout, wait_pid_timeout};
+
+        let mut children = [utilities::sleep_child("3")];
+        let pid = children[0].id();
+
+        // Verify that no wait reports a timeout before the deadline measured with `Instant`
+        for timeout in [1, 7, 20].map(Duration::from_millis) {
+            let start = Instant::now();
+            let result = children[0].wait_timeout(timeout);
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+            assert!(start.elapsed() >= timeout);
+
+            let start = Instant::now();
+            let result = wait_any_timeout(&mut children, timeout);
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+            assert!(start.elapsed() >= timeout);
+
+            let start = Instant::now();
+            let result = wait_pid_timeout(pid, timeout);
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+            assert!(start.elapsed() >= timeout);
+        }
+
+        children[0].kill().unwrap();
+        children[0].wait().unwrap();
+    }
+
+    #[test]
+    fn test_wait_timeout_zero() {
+        // Spawn a long-running process
+        let mut child = utilities::sleep_child("3");
+
+        // Verify that a zero timeout does not block
+        let start = Instant::now();
+        let result = child.wait_timeout(Duration::ZERO);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // Verify that the exit status is returned once the process has exited
+        let mut child = utilities::exit_child(0);
+        std::thread::sleep(Duration::from_secs(1));
+        let result = child.wait_timeout(Duration::ZERO);
+        assert!(result.unwrap().success());
+    }
+
+    #[test]
+    fn test_wait_or_kill() {
+        // Spawn a long-running process
+        let mut child = utilities::sleep_child("3");
+
+        // Wait for the process to exit with a shorter timeout
+        let result = child.wait_or_kill(Duration::from_secs(1));
+
+        // Verify that the process was killed and reaped
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(!child.try_wait().unwrap().unwrap().success());
+
+        // Verify that a process exiting in time is not killed
+        let mut child = utilities::sleep_child("1");
+        assert!(child
+            .wait_or_kill(Duration::from_secs(5))
+            .unwrap()
+            .success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shutdown_graceful() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // Spawn a long-running process terminating on SIGTERM
+        let mut child = utilities::sleep_child("10");
+
+        // Wait for the process to exit with a shorter timeout
+        let result = child.shutdown(Duration::from_secs(1), Duration::from_secs(5));
+
+        // Verify that the process was terminated by SIGTERM and reaped
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(
+            child.try_wait().unwrap().unwrap().signal(),
+            Some(libc::SIGTERM)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shutdown_killed() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::Command;
+
+        // Spawn a long-running process ignoring SIGTERM
+        let mut child = Command::new("sh")
+            .args(["-c", "trap '' TERM; sleep 10"])
+            .spawn()
+            .unwrap();
+
+        // Wait for the process to exit with a shorter timeout and grace period
+        let result = child.shutdown(Duration::from_secs(1), Duration::from_secs(1));
+
+        // Verify that the process was killed by SIGKILL and reaped
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(
+            child.try_wait().unwrap().unwrap().signal(),
+            Some(libc::SIGKILL)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_with() {
+        use child_wait_timeout::{wait_with, Clock, WaitOptions};
+        use std::os::unix::process::ExitStatusExt;
+
+        // Verify that the process is left running without kill on timeout
+        let options = WaitOptions::new().timeout(Duration::from_millis(500));
+        let mut child = utilities::sleep_child("3");
+        let result = wait_with(&mut child, &options);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(child.try_wait().unwrap().is_none());
+
+        // Verify that the process is terminated with the termination signal during the grace period
+        let options = options
+            .kill_on_timeout(true)
+            .term_signal(libc::SIGINT)
+            .kill_tree(false);
+        let result = wait_with(
+            &mut child,
+            &options.clone().grace_period(Duration::from_secs(5)),
+        );
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(
+            child.try_wait().unwrap().unwrap().signal(),
+            Some(libc::SIGINT)
+        );
+
+        // Verify that the process is killed right away without a grace period
+        let mut child = utilities::sleep_child("3");
+        let result = wait_with(&mut child, &options);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(
+            child.try_wait().unwrap().unwrap().signal(),
+            Some(libc::SIGKILL)
+        );
+
+        // Verify that a process exiting in time is waited for with the system clock
+        let options = WaitOptions::new()
+            .timeout(Duration::from_secs(5))
+            .clock(Clock::System)
+            .kill_on_timeout(true);
+        let mut child = utilities::sleep_child("1");
+        assert!(wait_with(&mut child, &options).unwrap().success());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_wait_or_kill_tree() {
+        use std::io::{BufRead, BufReader};
+        use std::os::unix::process::CommandExt;
+        use std::process::{Command, Stdio};
+
+        // Spawn a process group whose leader runs a grandchild
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 10 & echo $!; wait"])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        let stdout = child.stdout.take().unwrap();
+        BufReader::new(stdout).read_line(&mut line).unwrap();
+        let grandchild = line.trim().to_string();
+
+        // Wait for the process to exit with a shorter timeout
+        let result = child.wait_or_kill(Duration::from_secs(1));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the grandchild was killed, it is either reaped or left as a zombie
+        std::thread::sleep(Duration::from_millis(100));
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", grandchild));
+        assert!(stat.map_or(true, |stat| stat.contains(") Z ")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_group_timeout() {
+        use child_wait_timeout::{wait_group_all_timeout, wait_group_any_timeout};
+        use std::os::unix::process::CommandExt;
+
+        // Spawn a process group with a short-lived and a long-running member
+        // The members are reaped through the process group rather than their `Child`
+        let pgid = utilities::sleep_command("3")
+            .process_group(0)
+            .spawn()
+            .unwrap()
+            .id();
+        let member = utilities::sleep_command("1")
+            .process_group(pgid as i32)
+            .spawn()
+            .unwrap()
+            .id();
+
+        // Verify that the short-lived member is returned first
+        let (pid, status) = wait_group_any_timeout(pgid, Duration::from_secs(2)).unwrap();
+        assert_eq!(pid, member);
+        assert!(status.success());
+
+        // Verify that the timeout expires before the leader exits
+        let mut exited = Vec::new();
+        let result = wait_group_all_timeout(pgid, Duration::from_millis(500), &mut exited);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(exited.is_empty());
+
+        // Verify that all the members are reaped
+        wait_group_all_timeout(pgid, Duration::from_secs(5), &mut exited).unwrap();
+        assert_eq!(exited.len(), 1);
+        assert_eq!(exited[0].0, pgid);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_job_wait_timeout() {
+        use child_wait_timeout::Job;
+
+        // Assign a short-lived and a long-running process to a job
+        let job = Job::new().unwrap();
+        let mut short = utilities::sleep_child("1");
+        let mut long = utilities::sleep_child("5");
+        job.assign(&short).unwrap();
+        job.assign(&long).unwrap();
+
+        // Verify that the job is still running after the short-lived process exits
+        let result = job.wait_timeout(Duration::from_secs(3));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(job.active_processes().unwrap(), 1);
+
+        // Verify that terminating the job on timeout stops all its processes
+        let result = job.wait_or_terminate(Duration::from_secs(1));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(job.active_processes().unwrap(), 0);
+        assert!(short.wait().unwrap().success());
+        assert!(!long.wait().unwrap().success());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_raw_exit_code() {
+        use child_wait_timeout::raw_exit_code;
+
+        // Spawn a process exiting with the NTSTATUS of an access violation
+        let mut child = utilities::exit_child(0xC0000005u32 as i32);
+        let status = child.wait_timeout(Duration::from_secs(5)).unwrap();
+
+        // Verify that the code is reported unsigned
+        assert_eq!(raw_exit_code(&status), 0xC0000005);
+        assert_eq!(status.code(), Some(0xC0000005u32 as i32));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_alertable_waits() {
+        use child_wait_timeout::{alertable_waits, set_alertable_waits};
+
+        set_alertable_waits(true);
+        assert!(alertable_waits());
+
+        // Verify that the alertable waits still time out and report the exit
+        let mut child = utilities::sleep_child("2");
+        let result = child.wait_timeout(Duration::from_millis(500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(child.wait_timeout(Duration::from_secs(5)).is_ok());
+
+        set_alertable_waits(false);
+        assert!(!alertable_waits());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_exit_callback() {
+        use child_wait_timeout::ExitCallback;
+        use std::sync::mpsc;
+
+        // Register callbacks on a short-lived and a long-running process
+        let mut short = utilities::exit_child(3);
+        let mut long = utilities::sleep_child("5");
+        let (sender, receiver) = mpsc::channel();
+        let short_sender = sender.clone();
+        let _short = ExitCallback::register(&short, Duration::from_secs(5), move |status| {
+            short_sender.send(("short", status)).unwrap();
+        })
+        .unwrap();
+        let _long = ExitCallback::register(&long, Duration::from_secs(1), move |status| {
+            sender.send(("long", status)).unwrap();
+        })
+        .unwrap();
+
+        // Verify that the exit status of the short-lived process is reported
+        let (name, status) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(name, "short");
+        assert_eq!(status.unwrap().code(), Some(3));
+        assert_eq!(short.wait().unwrap().code(), Some(3));
+
+        // Verify that the timeout is reported while the long-running process is still running
+        let (name, status) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(name, "long");
+        assert_eq!(status.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(long.try_wait().unwrap().is_none());
+        long.kill().unwrap();
+        long.wait().unwrap();
+    }
+
+    #[test]
+    fn test_wait_any_timeout() {
+        use child_wait_timeout::wait_any_timeout;
+
+        // Spawn a long-running and a short-lived process
+        let mut children = vec![utilities::sleep_child("3"), utilities::sleep_child("1")];
+
+        // Verify that the short-lived process is returned first
+        let (index, status) = wait_any_timeout(&mut children, Duration::from_secs(2)).unwrap();
+        assert_eq!(index, 1);
+        assert!(status.success());
+
+        // Verify that the timeout expires before the long-running process exits
+        children.remove(index).wait().unwrap();
+        let result = wait_any_timeout(&mut children, Duration::from_millis(500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the remaining process is returned once it exits
+        let (index, status) = wait_any_timeout(&mut children, Duration::from_secs(5)).unwrap();
+        assert_eq!(index, 0);
+        assert!(status.success());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_wait_any_timeout_many() {
+        use child_wait_timeout::{wait_all_timeout, wait_any_timeout};
+
+        // Spawn more processes than a single wait can handle, the last one being short-lived
+        let mut children: Vec<_> = (0..100).map(|_| utilities::sleep_child("3")).collect();
+        children.push(utilities::exit_child(0));
+
+        // Verify that the short-lived process is found by a helper thread
+        let (index, status) = wait_any_timeout(&mut children, Duration::from_secs(2)).unwrap();
+        assert_eq!(index, 100);
+        assert!(status.success());
+
+        // Verify that all the processes are waited for together
+        children.remove(index).wait().unwrap();
+        let result = wait_all_timeout(&mut children, Duration::from_millis(500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        let statuses = wait_all_timeout(&mut children, Duration::from_secs(10)).unwrap();
+        assert_eq!(statuses.len(), 100);
+    }
+
+    #[test]
+    fn test_wait_all_timeout() {
+        use child_wait_timeout::wait_all_timeout;
+
+        // Spawn two processes exiting after the first wait
+        let mut children = vec![utilities::sleep_child("2"), utilities::sleep_child("2")];
+
+        // Verify that the deadline is shared by all the children
+        let start = Instant::now();
+        let result = wait_all_timeout(&mut children, Duration::from_secs(1));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_millis(1500));
+
+        // Verify that all the exit statuses are returned
+        let statuses = wait_all_timeout(&mut children, Duration::from_secs(5)).unwrap();
+        assert_eq!(statuses.len(), 2);
+        assert!(statuses.iter().all(|status| status.success()));
+
+        // Verify that a child following the one which timed out is reaped once it has exited
+        let mut children = vec![utilities::sleep_child("10"), utilities::exit_child(0)];
+        let result = wait_all_timeout(&mut children, Duration::from_millis(500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        #[cfg(unix)]
+        assert_eq!(
+            unsafe { libc::kill(children[1].id() as libc::pid_t, 0) },
+            -1
+        );
+        assert!(children[1].try_wait().unwrap().unwrap().success());
+        children[0].kill().unwrap();
+        children[0].wait().unwrap();
+    }
+
+    #[test]
+    fn test_wait_many() {
+        use child_wait_timeout::{wait_many, Deadline};
+
+        let mut children = vec![
+            utilities::sleep_child("10"),
+            utilities::sleep_child("1"),
+            utilities::sleep_child("0"),
+        ];
+        let pids: Vec<u32> = children.iter().map(|child| child.id()).collect();
+
+        // Verify that the exits are reported in order and the running child is returned at the deadline
+        let mut exited = Vec::new();
+        let running = wait_many(
+            &mut children,
+            Deadline::after(Duration::from_secs(3)),
+            |index, status| {
+                assert!(status.success());
+                exited.push(index);
+            },
+        )
+        .unwrap();
+        assert_eq!(exited, [2, 1]);
+        assert_eq!(running, [0]);
+
+        // Verify that the slice kept its order
+        let order: Vec<u32> = children.iter().map(|child| child.id()).collect();
+        assert_eq!(order, pids);
+        children[0].kill().unwrap();
+        children[0].wait().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_pid_timeout() {
+        use child_wait_timeout::wait_pid_timeout;
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+
+        // Spawn a grandchild which is reparented once the shell exits
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 2 & echo $!"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        let stdout = child.stdout.take().unwrap();
+        BufReader::new(stdout).read_line(&mut line).unwrap();
+        let grandchild: u32 = line.trim().parse().unwrap();
+        child.wait().unwrap();
+
+        // Verify that the timeout expires before the grandchild exits
+        let result = wait_pid_timeout(grandchild, Duration::from_millis(500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that its exit is reported although it is not a child
+        wait_pid_timeout(grandchild, Duration::from_secs(5)).unwrap();
+
+        // Verify that a child is not reaped
+        let mut child = utilities::sleep_child("1");
+        wait_pid_timeout(child.id(), Duration::from_secs(5)).unwrap();
+        assert!(child.try_wait().unwrap().unwrap().success());
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_wait_timeout_pid() {
+        use child_wait_timeout::wait_timeout_pid;
+
+        // Verify that the timeout expires before the child exits, and that its exit status is returned
+        let pid = utilities::sleep_child("1").id();
+        let result = wait_timeout_pid(pid, Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(wait_timeout_pid(pid, Duration::from_secs(5))
+            .unwrap()
+            .success());
+
+        let pid = utilities::exit_child(3).id();
+        let status = wait_timeout_pid(pid, Duration::from_secs(5)).unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        // Verify that a process which is not a child is rejected
+        #[cfg(unix)]
+        assert!(wait_timeout_pid(1, Duration::from_millis(100)).is_err());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_wait_pid_timeout_windows() {
+        use child_wait_timeout::wait_pid_timeout;
+
+        // Verify that the timeout expires before the process exits, and that its exit is reported
+        let mut child = utilities::sleep_child("2");
+        let pid = child.id();
+        let result = wait_pid_timeout(pid, Duration::from_millis(500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        wait_pid_timeout(pid, Duration::from_secs(5)).unwrap();
+        assert!(child.wait().unwrap().success());
+
+        // Verify that the pid is not found once the last handle of the process is closed
+        drop(child);
+        let result = wait_pid_timeout(pid, Duration::from_secs(1));
+        assert!(matches!(
+            result.map_err(WaitError::from),
+            Err(WaitError::NoSuchProcess)
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_wait_fd_timeout() {
+        use child_wait_timeout::wait_fd_timeout;
+        use std::os::unix::io::{AsFd, FromRawFd, OwnedFd};
+
+        // Open a pidfd outside of the crate, skipping the test on kernels without `pidfd_open`
+        let mut child = utilities::sleep_child("1");
+        let pidfd = unsafe { libc::syscall(libc::SYS_pidfd_open, child.id(), 0) };
+        if pidfd < 0 {
+            child.kill().unwrap();
+            child.wait().unwrap();
+            return;
+        }
+        let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd as i32) };
+
+        // Verify that the timeout expires before the process exits
+        let result = wait_fd_timeout(pidfd.as_fd(), Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the exit is reported without reaping the child
+        wait_fd_timeout(pidfd.as_fd(), Duration::from_secs(5)).unwrap();
+        assert!(child.try_wait().unwrap().unwrap().success());
+    }
+
+    #[test]
+    fn test_shared_child() {
+        use child_wait_timeout::SharedChild;
+        use std::sync::Arc;
+
+        // Wait for a long-running process from another thread
+        let child = Arc::new(SharedChild::new(utilities::sleep_child("10")));
+        let waiter = {
+            let child = Arc::clone(&child);
+            std::thread::spawn(move || child.wait_timeout(Duration::from_secs(5)))
+        };
+
+        // Verify that the timeout expires before the process is killed
+        let result = child.wait_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that killing it ends the wait of the other thread
+        child.kill().unwrap();
+        let status = waiter.join().unwrap().unwrap();
+        assert!(!status.success());
+
+        // Verify that a reaped child is not killed again
+        assert_eq!(child.try_wait().unwrap(), Some(status));
+        child.kill().unwrap();
+        assert_eq!(child.wait().unwrap(), status);
+    }
+
+    #[test]
+    fn test_exit_report() {
+        use child_wait_timeout::ExitReport;
+
+        // Verify that an exit within the timeout is reported with its code
+        let mut child = utilities::exit_child(3);
+        let report = ExitReport::wait_or_kill(&mut child, Duration::from_secs(5)).unwrap();
+        assert_eq!(report.code(), Some(3));
+        assert!(!report.killed());
+        #[cfg(unix)]
+        assert!(report.usage().is_some());
+
+        // Verify that a child killed on timeout is reported as such
+        let mut child = utilities::sleep_child("10");
+        let report = ExitReport::wait_or_kill(&mut child, Duration::from_millis(100)).unwrap();
+        assert!(report.killed());
+        assert!(!report.success());
+        assert!(report.wall_time() >= Duration::from_millis(100));
+        #[cfg(unix)]
+        assert_eq!(report.signal(), Some(libc::SIGKILL));
+
+        // Verify that the report survives a round trip through JSON
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&report).unwrap();
+            assert_eq!(serde_json::from_str::<ExitReport>(&json).unwrap(), report);
+        }
+    }
+
+    #[test]
+    fn test_timed_child() {
+        use child_wait_timeout::TimedChild;
+
+        // Verify that the waits are bounded by the budget of the child
+        let mut child = TimedChild::new(utilities::sleep_child("10"), Duration::from_millis(300));
+        let result = child.wait_timeout(Duration::from_secs(5));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(child.uptime() >= Duration::from_millis(300));
+        assert_eq!(child.remaining_budget(), Duration::ZERO);
+
+        // Verify that the child is killed once the budget is spent, which ends its uptime
+        let result = child.wait_or_kill();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(child.as_child_mut().try_wait().unwrap().is_some());
+        let uptime = child.uptime();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(child.uptime(), uptime);
+
+        // Verify that a child exiting within its budget is reaped
+        let mut child = TimedChild::new(utilities::exit_child(4), Duration::from_secs(5));
+        assert_eq!(child.wait().unwrap().code(), Some(4));
+        assert!(child.remaining_budget() > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_monitored_child() {
+        use child_wait_timeout::MonitoredChild;
+
+        // Verify that a running child is reported as such, without an exit status
+        let mut child = MonitoredChild::new(utilities::sleep_child("1"));
+        assert!(child.is_running());
+        assert_eq!(child.exit_status(), None);
+        let result = child.wait_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the exit status is kept once the child has been reaped
+        let status = child.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert!(status.success());
+        assert!(!child.is_running());
+        assert_eq!(child.exit_status(), Some(status));
+        assert_eq!(child.wait_timeout(Duration::ZERO).unwrap(), status);
+        child.kill().unwrap();
+
+        // Verify that a child reaped through the inner child is noticed
+        let mut child = MonitoredChild::from(utilities::exit_child(2));
+        let status = child.as_child_mut().wait().unwrap();
+        assert_eq!(child.exit_status(), None);
+        assert!(!child.is_running());
+        assert_eq!(child.exit_status(), Some(status));
+    }
+
+    #[test]
+    fn test_timeout_guard() {
+        use child_wait_timeout::{wait_pid_timeout, TimeoutGuard};
+
+        // Verify that the timeout expires before the process exits
+        let mut guard = TimeoutGuard::new(utilities::sleep_child("10"));
+        let pid = guard.as_child().id();
+        let result = guard.wait_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that dropping the guard kills and reaps the process
+        drop(guard);
+        let result = wait_pid_timeout(pid, Duration::from_secs(1));
+        assert!(matches!(
+            result.map_err(WaitError::from),
+            Err(WaitError::NoSuchProcess)
+        ));
+
+        // Verify that a process taken out of the guard is left running
+        let guard = TimeoutGuard::new(utilities::sleep_child("1"));
+        let mut child = guard.into_inner();
+        assert!(child
+            .wait_timeout(Duration::from_secs(5))
+            .unwrap()
+            .success());
+    }
+
+    #[test]
+    fn test_with_child() {
+        use child_wait_timeout::with_child;
+
+        // Verify that the child is waited for once the closure returns
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let (id, status) = with_child(&mut utilities::exit_command(3), deadline, |child| {
+            child.id()
+        })
+        .unwrap();
+        assert!(id > 0);
+        assert_eq!(status.code(), Some(3));
+
+        // Verify that the child is killed once the deadline passes
+        let deadline = Instant::now() + Duration::from_millis(500);
+        let start = Instant::now();
+        let result = with_child(&mut utilities::sleep_command("10"), deadline, |_| ());
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_child_handle() {
+        use child_wait_timeout::ChildHandle;
+
+        // Wait for a long-running process through a handle moved to another thread
+        let mut child = utilities::sleep_child("10");
+        let handle = ChildHandle::new(&child).unwrap();
+        let waiter = {
+            let handle = handle.clone();
+            std::thread::spawn(move || handle.wait_timeout(Duration::from_secs(5)))
+        };
+
+        // Verify that the timeout expires before the process is killed
+        let result = handle.wait_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that killing it ends the wait without reaping the child
+        handle.kill().unwrap();
+        let status = waiter.join().unwrap().unwrap();
+        assert!(!status.success());
+        assert_eq!(child.wait().unwrap(), status);
+
+        // Verify that the status is kept once the child is reaped
+        assert_eq!(handle.try_wait().unwrap(), Some(status));
+        handle.kill().unwrap();
+    }
+
+    #[test]
+    fn test_wait_set() {
+        use child_wait_timeout::WaitSet;
+
+        // Insert a long-running and a short-lived process
+        let mut set = WaitSet::new();
+        let long = set.insert(utilities::sleep_child("4"));
+        let short = set.insert(utilities::sleep_child("1"));
+        assert_eq!(set.len(), 2);
+
+        // Verify that the exits are received as they happen
+        let (key, _, status) = set.wait_next(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!(key, short);
+        assert!(status.success());
+
+        // Verify that children can be inserted while waiting
+        let late = set.insert(utilities::exit_child(3));
+        let (key, _, status) = set.wait_next(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!(key, late);
+        assert_eq!(status.code(), Some(3));
+
+        let (key, _, _) = set.wait_next(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!(key, long);
+
+        // Verify that an empty set returns `None`
+        assert!(set.wait_next(Duration::from_secs(1)).unwrap().is_none());
+    }
+
+    #[cfg(feature = "reaper")]
+    #[test]
+    fn test_reaper() {
+        use child_wait_timeout::Reaper;
+
+        let reaper = Reaper::global().unwrap();
+        let long = reaper.watch(utilities::sleep_child("2")).unwrap();
+        let exits: Vec<_> = (0..20)
+            .map(|code| reaper.watch(utilities::exit_child(code)).unwrap())
+            .collect();
+
+        // Verify that every exit status is received on its own channel
+        for (code, exit) in exits.iter().enumerate() {
+            let status = exit.wait_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(status.code(), Some(code as i32));
+        }
+
+        // Verify that the timeout is respected
+        let result = long.wait_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(long.try_wait().unwrap().is_none());
+
+        assert!(long.wait_timeout(Duration::from_secs(5)).unwrap().success());
+    }
+
+    #[cfg(feature = "reaper")]
+    #[test]
+    fn test_reaper_exit_notifier() {
+        use child_wait_timeout::Reaper;
+        use std::sync::mpsc::RecvTimeoutError;
+
+        let exits = Reaper::global()
+            .unwrap()
+            .exit_notifier(utilities::sleep_child("1"))
+            .unwrap();
+
+        // Verify that the channel can be waited on with a timeout and receives the exit status once
+        let result = exits.recv_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err(), RecvTimeoutError::Timeout);
+        let status = exits.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(status.success());
+        assert!(exits.recv().is_err());
+    }
+
+    #[cfg(feature = "reaper")]
+    #[test]
+    fn test_reaper_on_exit() {
+        use child_wait_timeout::Reaper;
+        use std::sync::mpsc;
+
+        let reaper = Reaper::global().unwrap();
+        let (sender, receiver) = mpsc::channel();
+
+        // Verify that the callback receives the exit status
+        let exit = sender.clone();
+        reaper
+            .on_exit(
+                utilities::exit_child(4),
+                Duration::from_secs(5),
+                move |status| exit.send(status).unwrap(),
+            )
+            .unwrap();
+        let status = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(status.unwrap().code(), Some(4));
+
+        // Verify that the callback is called once with a timeout error
+        let start = Instant::now();
+        reaper
+            .on_exit(
+                utilities::sleep_child("1"),
+                Duration::from_millis(100),
+                move |status| sender.send(status).unwrap(),
+            )
+            .unwrap();
+        let status = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(status.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_millis(900));
+        assert!(receiver.recv_timeout(Duration::from_secs(2)).is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_wait_timeout() {
+        use child_wait_timeout::AsyncChildWT;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            // Verify that the timeout is respected
+            let mut child = utilities::sleep_child("2");
+            let result = AsyncChildWT::wait_timeout(&mut child, Duration::from_millis(500)).await;
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+            // Verify that the exit is awaited and the child reaped
+            let status = AsyncChildWT::wait_timeout(&mut child, Duration::from_secs(5)).await;
+            assert!(status.unwrap().success());
+            assert!(child.try_wait().unwrap().is_some());
+
+            // Verify that several children can be awaited concurrently on a single thread
+            let mut first = utilities::exit_child(1);
+            let mut second = utilities::exit_child(2);
+            let (first, second) = tokio::join!(
+                AsyncChildWT::wait_timeout(&mut first, Duration::from_secs(5)),
+                AsyncChildWT::wait_timeout(&mut second, Duration::from_secs(5))
+            );
+            assert_eq!(first.unwrap().code(), Some(1));
+            assert_eq!(second.unwrap().code(), Some(2));
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_exit_oneshot() {
+        use child_wait_timeout::AsyncChildWT;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            // Verify that the exit can be raced against a timer without borrowing the child
+            let mut exit = utilities::sleep_child("1").exit_oneshot();
+            let ticked = tokio::select! {
+                _ = &mut exit => false,
+                _ = tokio::time::sleep(Duration::from_millis(100)) => true,
+            };
+            assert!(ticked);
+            assert!(exit.await.unwrap().success());
+
+            // Verify that a Tokio child is reaped by a task of the runtime
+            let child = tokio::process::Command::from(utilities::exit_command(5))
+                .spawn()
+                .unwrap();
+            let status = child.exit_oneshot().await.unwrap();
+            assert_eq!(status.code(), Some(5));
+        });
+    }
+
+    #[cfg(all(feature = "async-io", not(feature = "tokio")))]
+    #[test]
+    fn test_async_io_wait_timeout() {
+        use child_wait_timeout::AsyncChildWT;
+
+        async_io::block_on(async {
+            // Verify that the timeout is respected
+            let mut child = utilities::sleep_child("2");
+            let result = AsyncChildWT::wait_timeout(&mut child, Duration::from_millis(500)).await;
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+            // Verify that the exit is awaited and the child reaped
+            let status = AsyncChildWT::wait_timeout(&mut child, Duration::from_secs(5)).await;
+            assert!(status.unwrap().success());
+            assert!(child.try_wait().unwrap().is_some());
+        });
+    }
+
+    #[test]
+    fn test_exit_stream() {
+        use child_wait_timeout::ExitStream;
+
+        let mut exits = ExitStream::new();
+        let long = exits.insert(utilities::sleep_child("2"));
+        let short = exits.insert(utilities::exit_child(3));
+
+        // Verify that the exits are yielded in the order they happen until the stream is empty
+        let keys: Vec<usize> = exits.iter().map(|exit| exit.unwrap().0).collect();
+        assert_eq!(keys, [short, long]);
+        assert!(exits.is_empty());
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[test]
+    fn test_exit_stream_async() {
+        use child_wait_timeout::ExitStream;
+        use futures_lite::StreamExt;
+
+        let mut exits = ExitStream::new();
+        let long = exits.insert(utilities::sleep_child("2"));
+        let short = exits.insert(utilities::exit_child(3));
+
+        // Verify that the exits are awaited as they happen
+        async_io::block_on(async {
+            let (key, _, status) = exits.next().await.unwrap().unwrap();
+            assert_eq!(key, short);
+            assert_eq!(status.code(), Some(3));
+            let (key, _, status) = exits.next().await.unwrap().unwrap();
+            assert_eq!(key, long);
+            assert!(status.success());
+            assert!(exits.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn test_exit_future() {
+        use futures_lite::future;
+
+        // Verify that the future can be combined with the timer of another runtime
+        let mut child = utilities::sleep_child("2");
+        let timer = async {
+            async_io::Timer::after(Duration::from_millis(500)).await;
+            None
+        };
+        let result =
+            async_io::block_on(future::or(async { Some(child.exit_future().await) }, timer));
+        assert!(result.is_none());
+
+        // Verify that the exit status is returned and the child reaped
+        let status = async_io::block_on(child.exit_future()).unwrap();
+        assert!(status.success());
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_default_methods() {
+        use child_wait_timeout::{Backend, CancelToken, Interrupter};
+        use std::process::ExitStatus;
+
+        // A child only implementing the required method of the trait
+        struct Wrapper(std::process::Child);
+
+        impl ChildWT for Wrapper {
+            fn wait_timeout(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+                self.0.wait_timeout(timeout)
+            }
+        }
+
+        // Verify that the backend is ignored
+        let mut child = Wrapper(utilities::sleep_child("1"));
+        let status = child.wait_timeout_with_backend(Backend::Polling, Duration::from_secs(5));
+        assert!(status.unwrap().success());
+
+        // Verify that the future polls the child
+        let mut child = Wrapper(utilities::sleep_child("1"));
+        let status = async_io::block_on(child.exit_future()).unwrap();
+        assert!(status.success());
+
+        // Verify that the cancellable and interruptible waits are built on the future
+        let token = CancelToken::new();
+        let interrupter = Interrupter::new();
+        let mut child = Wrapper(utilities::sleep_child("1"));
+        let result = child.wait_timeout_cancellable(Duration::from_millis(100), &token);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        let result = child.wait_timeout_interruptible(Duration::from_millis(100), &interrupter);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        let status = child.wait_timeout_interruptible(Duration::from_secs(5), &interrupter);
+        assert!(status.unwrap().success());
+        token.cancel();
+        let result = child.wait_timeout_cancellable(Duration::from_secs(5), &token);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+
+        // Verify that the waits which would kill the child time out, leaving it running
+        let mut child = Wrapper(utilities::sleep_child("10"));
+        let result = child.wait_or_kill(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        let result = child.shutdown(Duration::from_millis(100), Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        let result = child.wait_cpu_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+        assert!(child.0.try_wait().unwrap().is_none());
+        child.0.kill().unwrap();
+        child.0.wait().unwrap();
+
+        // Verify that the output is returned empty
+        let child = Wrapper(utilities::exit_child(3));
+        let output = child
+            .wait_with_output_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert!(output.stdout.is_empty() && output.stderr.is_empty());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_child() {
+        use child_wait_timeout::AsyncChildWT;
+        use tokio::process::Command;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let _guard = runtime.enter();
+
+        // Verify that the blocking wait respects the timeout
+        let mut child = Command::from(utilities::sleep_command("2"))
+            .spawn()
+            .unwrap();
+        let result = ChildWT::wait_timeout(&mut child, Duration::from_millis(500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the blocking wait returns the exit status
+        let status = ChildWT::wait_timeout(&mut child, Duration::from_secs(5)).unwrap();
+        assert!(status.success());
+
+        // Verify that the asynchronous wait returns the exit status
+        let mut child = Command::from(utilities::exit_command(3)).spawn().unwrap();
+        let status = runtime
+            .block_on(AsyncChildWT::wait_timeout(
+                &mut child,
+                Duration::from_secs(5),
+            ))
+            .unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        // Verify that the child is killed when the timeout expires
+        let mut child = Command::from(utilities::sleep_command("10"))
+            .spawn()
+            .unwrap();
+        let result = child.wait_or_kill(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(child.try_wait().unwrap().is_some());
+
+        // Verify that the output is collected from the pipes of the runtime
+        let mut command = Command::from(utilities::exit_command(6));
+        command.stdout(std::process::Stdio::piped());
+        let child = command.spawn().unwrap();
+        let output = child
+            .wait_with_output_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(output.status.code(), Some(6));
+        assert!(output.stdout.is_empty());
+    }
+
+    #[cfg(feature = "mio")]
+    #[test]
+    fn test_mio_source() {
+        use child_wait_timeout::ChildExitSource;
+        use mio::{Events, Interest, Poll, Token};
+
+        let mut child = utilities::sleep_child("1");
+        let mut source = ChildExitSource::new(&child).unwrap();
+        let mut poll = Poll::new().unwrap();
+        poll.registry()
+            .register(&mut source, Token(7), Interest::READABLE)
+            .unwrap();
+
+        // Verify that no event is reported while the child is running
+        let mut events = Events::with_capacity(8);
+        poll.poll(&mut events, Some(Duration::from_millis(200)))
+            .unwrap();
+        assert!(events.is_empty());
+        assert!(source.try_reap(&mut child).unwrap().is_none());
+
+        // Verify that the exit is reported on the token of the source
+        poll.poll(&mut events, Some(Duration::from_secs(5)))
+            .unwrap();
+        assert!(events.iter().any(|event| event.token() == Token(7)));
+        let status = source.try_reap(&mut child).unwrap().unwrap();
+        assert!(status.success());
+
+        poll.registry().deregister(&mut source).unwrap();
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_log_records() {
+        use std::sync::Mutex;
+
+        static RECORDS: Mutex<Vec<(log::Level, String)>> = Mutex::new(Vec::new());
+
+        struct Recorder;
+
+        impl log::Log for Recorder {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                let message = (record.level(), record.args().to_string());
+                RECORDS.lock().unwrap().push(message);
+            }
+
+            fn flush(&self) {}
+        }
+
+        log::set_logger(&Recorder).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+
+        // Verify that the timeout and the kill which follows it are logged with the pid of the child
+        let mut child = utilities::sleep_child("10");
+        let pid = format!("child process {} ", child.id());
+        let result = child.wait_or_kill(Duration::from_millis(200));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Other tests may log concurrently, only the records about this child are kept
+        let records: Vec<_> = RECORDS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, message)| message.contains(&pid))
+            .cloned()
+            .collect();
+        assert!(records
+            .iter()
+            .any(|(level, message)| *level == log::Level::Debug && message.contains("timeout")));
+        assert!(records
+            .iter()
+            .any(|(level, message)| *level == log::Level::Warn && message.contains("killing")));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_metrics() {
+        use metrics::{
+            Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+            SharedString, Unit,
+        };
+        use std::sync::{Arc, Mutex};
+
+        // Records the name of each metric as it is updated
+        #[derive(Default)]
+        struct Updates(Mutex<Vec<String>>);
+
+        struct Handle(String, Arc<Updates>);
+
+        impl CounterFn for Handle {
+            fn increment(&self, _: u64) {
+                self.1 .0.lock().unwrap().push(self.0.clone());
+            }
+
+            fn absolute(&self, _: u64) {}
+        }
+
+        impl HistogramFn for Handle {
+            fn record(&self, _: f64) {
+                self.1 .0.lock().unwrap().push(self.0.clone());
+            }
+        }
+
+        struct TestRecorder(Arc<Updates>);
+
+        impl Recorder for TestRecorder {
+            fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+            fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+                Counter::from_arc(Arc::new(Handle(key.name().into(), self.0.clone())))
+            }
+
+            fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+                Gauge::noop()
+            }
+
+            fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+                Histogram::from_arc(Arc::new(Handle(key.name().into(), self.0.clone())))
+            }
+        }
+
+        // The recorder is local to this thread, so the waits of the other tests are not recorded
+        let updates = Arc::new(Updates::default());
+        metrics::with_local_recorder(&TestRecorder(updates.clone()), || {
+            let mut child = utilities::sleep_child("1");
+            child.wait_timeout(Duration::from_secs(5)).unwrap();
+
+            let mut child = utilities::sleep_child("10");
+            let result = child.wait_or_kill(Duration::from_millis(200));
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        });
+
+        let updates = updates.0.lock().unwrap();
+        let count = |name: &str| updates.iter().filter(|update| *update == name).count();
+        assert_eq!(count("child_wait_timeout_waits_total"), 2);
+        assert_eq!(count("child_wait_timeout_timeouts_total"), 1);
+        assert_eq!(count("child_wait_timeout_kills_total"), 1);
+        assert_eq!(count("child_wait_timeout_wait_duration_seconds"), 2);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_events() {
+        use std::fmt;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        // Records the names of the spans as they are created, and the message of each event with
+        // whether it was emitted within a span
+        #[derive(Default)]
+        struct Records {
+            spans: Mutex<Vec<String>>,
+            events: Mutex<Vec<(String, bool)>>,
+            entered: Mutex<Vec<u64>>,
+            ids: AtomicU64,
+        }
+
+        struct Message(String);
+
+        impl Visit for Message {
+            fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                if field.name() == "message" {
+                    self.0 = format!("{value:?}");
+                }
+            }
+        }
+
+        struct TestSubscriber(Arc<Records>);
+
+        impl Subscriber for TestSubscriber {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                self.0
+                    .spans
+                    .lock()
+                    .unwrap()
+                    .push(span.metadata().name().into());
+                Id::from_u64(self.0.ids.fetch_add(1, Ordering::Relaxed) + 1)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut message = Message(String::new());
+                event.record(&mut message);
+                let in_span = !self.0.entered.lock().unwrap().is_empty();
+                self.0.events.lock().unwrap().push((message.0, in_span));
+            }
+
+            fn enter(&self, span: &Id) {
+                self.0.entered.lock().unwrap().push(span.into_u64());
+            }
+
+            fn exit(&self, _: &Id) {
+                self.0.entered.lock().unwrap().pop();
+            }
+        }
+
+        // The subscriber is local to this thread, so the waits of the other tests are not recorded
+        let records = Arc::new(Records::default());
+        tracing::subscriber::with_default(TestSubscriber(records.clone()), || {
+            let mut child = utilities::sleep_child("1");
+            child.wait_timeout(Duration::from_secs(5)).unwrap();
+
+            let mut child = utilities::sleep_child("10");
+            let result = child.wait_or_kill(Duration::from_millis(200));
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        });
+
+        // Verify that each wait has its span, within which its start and its outcome are recorded
+        let spans = records.spans.lock().unwrap();
+        assert_eq!(
+            spans.iter().filter(|name| *name == "wait_timeout").count(),
+            2
+        );
+        let events = records.events.lock().unwrap();
+        let emitted = |message: &str| {
+            events
+                .iter()
+                .filter(|(event, in_span)| event == message && *in_span)
+                .count()
+        };
+        assert_eq!(emitted("waiting for the child process"), 2);
+        assert_eq!(emitted("child process reaped"), 1);
+        assert_eq!(
+            emitted("timeout expired before the child process exited"),
+            1
+        );
+
+        // Verify that the kill following the timeout is recorded
+        assert!(events
+            .iter()
+            .any(|(event, _)| event == "killing the child process tree after the timeout"));
+    }
+
+    #[test]
+    fn test_wait_timeout_cancellable() {
+        use child_wait_timeout::CancelToken;
+        use std::thread;
+
+        let token = CancelToken::new();
+
+        // Verify that the wait returns the exit status when the token is not cancelled
+        let mut child = utilities::sleep_child("1");
+        let status = child
+            .wait_timeout_cancellable(Duration::from_secs(5), &token)
+            .unwrap();
+        assert!(status.success());
+
+        // Verify that cancelling the token from another thread interrupts the wait
+        let mut child = utilities::sleep_child("10");
+        let canceller = token.clone();
+        let cancel = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            canceller.cancel();
+        });
+        let start = Instant::now();
+        let result = child.wait_timeout_cancellable(Duration::from_secs(5), &token);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert!(start.elapsed() < Duration::from_secs(4));
+        cancel.join().unwrap();
+
+        // Verify that a cancelled token interrupts the following waits right away
+        let result = child.wait_timeout_cancellable(Duration::from_secs(5), &token);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert!(child.try_wait().unwrap().is_none());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_wait_timeout_interruptible() {
+        use child_wait_timeout::Interrupter;
+        use std::thread;
+
+        let interrupter = Interrupter::new();
+
+        // Verify that an interruption before the wait does not affect it
+        interrupter.interrupt();
+        let mut child = utilities::sleep_child("1");
+
+        // Verify that interrupting from another thread wakes the wait
+        let handle = interrupter.clone();
+        let interrupt = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            handle.waker().wake();
+        });
+        let start = Instant::now();
+        let result = child.wait_timeout_interruptible(Duration::from_secs(5), &interrupter);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert!(start.elapsed() < Duration::from_millis(900));
+        interrupt.join().unwrap();
+
+        // Verify that the interrupter can be used again
+        let status = child
+            .wait_timeout_interruptible(Duration::from_secs(5), &interrupter)
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_wait_timeout_cancellable_watcher() {
+        use child_wait_timeout::{Backend, CancelToken, Interrupter};
+
+        // The threads of the crate are counted, so the test runs again alone in a process of its own
+        if std::env::var_os("CHILD_WAIT_TIMEOUT_CANCELLABLE_WATCHER").is_none() {
+            let status = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", "tests::test_wait_timeout_cancellable_watcher"])
+                .env("CHILD_WAIT_TIMEOUT_CANCELLABLE_WATCHER", "1")
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        // Counts the threads of the crate, named `child_wait_timeout` and truncated by the kernel
+        let watcher_threads = || {
+            std::fs::read_dir("/proc/self/task")
+                .unwrap()
+                .filter_map(|task| std::fs::read(task.unwrap().path().join("comm")).ok())
+                .filter(|comm| comm.starts_with(b"child_wait_time"))
+                .count()
+        };
+
+        // Verify that the waits which time out in a loop share a single watcher thread, including the
+        // waits of the thread backend
+        let token = CancelToken::new();
+        let interrupter = Interrupter::new();
+        let mut child = utilities::sleep_child("10");
+        for _ in 0..20 {
+            let result = child.wait_timeout_cancellable(Duration::from_millis(10), &token);
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+            let result = child.wait_timeout_interruptible(Duration::from_millis(10), &interrupter);
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+            let result =
+                child.wait_timeout_with_backend(Backend::Thread, Duration::from_millis(10));
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        }
+        assert_eq!(watcher_threads(), 1);
+
+        // Verify that the shared watcher still reports the exit, then ends
+        child.kill().unwrap();
+        let status = child
+            .wait_timeout_cancellable(Duration::from_secs(5), &token)
+            .unwrap();
+        assert!(!status.success());
+        let start = Instant::now();
+        while watcher_threads() > 0 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_supervisor() {
+        use child_wait_timeout::{RestartPolicy, Service, Supervisor};
+
+        let mut supervisor = Supervisor::new();
+        let failing = supervisor
+            .add(Service::new(utilities::exit_command(1)).max_restarts(2))
+            .unwrap();
+        let hanging = supervisor
+            .add(
+                Service::new(utilities::sleep_command("10"))
+                    .restart(RestartPolicy::Never)
+                    .timeout(Duration::from_millis(500)),
+            )
+            .unwrap();
+
+        // Verify that the failing service is restarted until its limit
+        let mut exits = Vec::new();
+        while let Some(exit) = supervisor.wait_next(Duration::from_secs(5)).unwrap() {
+            exits.push(exit);
+        }
+        let failures: Vec<_> = exits.iter().filter(|exit| exit.key() == failing).collect();
+        assert_eq!(failures.len(), 3);
+        assert!(failures.iter().all(|exit| exit.status().code() == Some(1)));
+        assert_eq!(
+            failures
+                .iter()
+                .map(|exit| exit.restarted())
+                .collect::<Vec<_>>(),
+            [true, true, false]
+        );
+        assert_eq!(supervisor.restarts(failing), Some(2));
+
+        // Verify that the hanging service is killed at its timeout and not restarted
+        let timeouts: Vec<_> = exits.iter().filter(|exit| exit.key() == hanging).collect();
+        assert_eq!(timeouts.len(), 1);
+        assert!(timeouts[0].timed_out() && !timeouts[0].restarted());
+        assert!(!supervisor.is_running(hanging));
+
+        // Verify that the running services are stopped by the shutdown
+        let always = supervisor
+            .add(Service::new(utilities::sleep_command("10")).restart(RestartPolicy::Always))
+            .unwrap();
+        let result = supervisor.wait_next(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        supervisor.shutdown(Duration::from_secs(1)).unwrap();
+        assert!(!supervisor.is_running(always));
+        assert!(supervisor
+            .wait_next(Duration::from_secs(1))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_command_timeout() {
+        use child_wait_timeout::CommandWT;
+
+        // Verify that the exit status is returned before the timeout
+        let status = utilities::exit_command(5)
+            .status_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(status.code(), Some(5));
+
+        // Verify that the command is killed when the timeout expires
+        let start = Instant::now();
+        let result = utilities::sleep_command("10").status_timeout(Duration::from_millis(200));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Verify that the output is collected
+        let output = utilities::exit_command(3)
+            .output_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+
+        #[cfg(unix)]
+        {
+            let output = std::process::Command::new("sh")
+                .args(["-c", "echo out; echo err >&2"])
+                .output_timeout(Duration::from_secs(5))
+                .unwrap();
+            assert_eq!(output.stdout, b"out\n");
+            assert_eq!(output.stderr, b"err\n");
+        }
+
+        let result = utilities::sleep_command("10").output_timeout(Duration::from_millis(200));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_run_with_timeout() {
+        use child_wait_timeout::{run_with_timeout, OutputTimeout, RunTimeoutError};
+
+        // Verify that the output is returned before the timeout
+        let output = run_with_timeout(utilities::exit_command(3), Duration::from_secs(5)).unwrap();
+        assert_eq!(output.status.code(), Some(3));
+
+        // Verify that the expiry is told apart from the other errors, with the output of the killed child
+        let error = run_with_timeout(utilities::sleep_command("10"), Duration::from_millis(200))
+            .unwrap_err();
+        let RunTimeoutError::TimedOut(output) = &error else {
+            panic!("unexpected {:?}", error);
+        };
+        assert!(!output.status.success());
+
+        // Verify that it converts into the error of `output_timeout`
+        let error = io::Error::from(error);
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(error.get_ref().unwrap().is::<OutputTimeout>());
+
+        let command = std::process::Command::new("child_wait_timeout_missing_program");
+        let error = run_with_timeout(command, Duration::from_secs(5)).unwrap_err();
+        assert!(matches!(error, RunTimeoutError::Io(_)));
+        assert!(error.output().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_with_output_timeout() {
+        use child_wait_timeout::OutputTimeout;
+        use std::process::{Command, Stdio};
+
+        // Verify that both streams are collected when the child exits
+        let child = Command::new("sh")
+            .args(["-c", "echo out; echo err >&2; exit 2"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let output = child
+            .wait_with_output_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+
+        // Verify that the partial output is returned when the timeout expires
+        let child = Command::new("sh")
+            .args(["-c", "echo started; exec sleep 10"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let start = Instant::now();
+        let error = child
+            .wait_with_output_timeout(Duration::from_millis(500))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        let output = error
+            .into_inner()
+            .unwrap()
+            .downcast::<OutputTimeout>()
+            .unwrap()
+            .into_output();
+        assert_eq!(output.stdout, b"started\n");
+        assert!(output.stderr.is_empty());
+        assert!(!output.status.success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stream_output_timeout() {
+        use child_wait_timeout::OutputChunk;
+        use std::process::{Command, Stdio};
+
+        // Verify that the chunks of both streams are received before the exit
+        let mut child = Command::new("sh")
+            .args(["-c", "echo out; echo err >&2; exit 3"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let status = child
+            .stream_output_timeout(Duration::from_secs(5), |chunk| match chunk {
+                OutputChunk::Stdout(data) => stdout.extend_from_slice(data),
+                OutputChunk::Stderr(data) => stderr.extend_from_slice(data),
+            })
+            .unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert_eq!(stdout, b"out\n");
+        assert_eq!(stderr, b"err\n");
+
+        // Verify that the output is received as it arrives, and that the child is killed at the timeout
+        let mut child = Command::new("sh")
+            .args(["-c", "echo started; exec sleep 10"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let start = Instant::now();
+        let mut received = None;
+        let result = child.stream_output_timeout(Duration::from_millis(500), |chunk| {
+            assert_eq!(chunk, OutputChunk::Stdout(b"started\n"));
+            received = Some(start.elapsed());
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(received.unwrap() < Duration::from_millis(500));
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_timeout_rusage() {
+        use child_wait_timeout::ChildWTExt;
+        use std::process::Command;
+
+        // Verify that the timeout is respected without reaping the child
+        let mut child = utilities::sleep_child("1");
+        let result = child.wait_timeout_rusage(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        let (status, _) = child.wait_timeout_rusage(Duration::from_secs(5)).unwrap();
+        assert!(status.success());
+
+        // Verify that the CPU time and memory of a busy child are reported
+        let mut child = Command::new("sh")
+            .args([
+                "-c",
+                "i=0; while [ $i -lt 20000 ]; do i=$((i+1)); done; exit 4",
+            ])
+            .spawn()
+            .unwrap();
+        let (status, usage) = child.wait_timeout_rusage(Duration::from_secs(30)).unwrap();
+        assert_eq!(status.code(), Some(4));
+        assert!(usage.user_time() + usage.system_time() > Duration::ZERO);
+        assert!(usage.max_rss() > 0);
+
+        // Verify that the child has been reaped
+        let result = child.wait_timeout_rusage(Duration::from_secs(1));
+        assert_eq!(result.unwrap_err().raw_os_error(), Some(libc::ECHILD));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_timeout_info() {
+        use child_wait_timeout::{ChildWTExt, ExitReason};
+        use std::os::unix::process::ExitStatusExt;
+
+        // Verify that a normal exit is reported with its code
+        let mut child = utilities::exit_child(7);
+        let info = child.wait_timeout_info(Duration::from_secs(5)).unwrap();
+        assert_eq!(info.reason(), ExitReason::Exited(7));
+        assert_eq!(info.pid(), child.id());
+        assert_eq!(info.status().code(), Some(7));
+
+        // Verify that the child is reaped through the standard library, which keeps its status
+        assert_eq!(child.try_wait().unwrap().unwrap().code(), Some(7));
+        let info = child.wait_timeout_info(Duration::from_secs(1)).unwrap();
+        assert_eq!(info.code(), Some(7));
+
+        // Verify that a termination by a signal is reported
+        let mut child = utilities::sleep_child("10");
+        let result = child.wait_timeout_info(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        child.kill().unwrap();
+        let info = child.wait_timeout_info(Duration::from_secs(5)).unwrap();
+        assert_eq!(info.reason(), ExitReason::Killed(libc::SIGKILL));
+        assert_eq!(info.signal(), Some(libc::SIGKILL));
+        assert!(!info.core_dumped());
+        assert_eq!(info.status().signal(), Some(libc::SIGKILL));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn test_wait_cpu_timeout() {
+        use std::process::Command;
+
+        // Verify that a sleeping child is not limited by its CPU time
+        let mut child = utilities::sleep_child("1");
+        let start = Instant::now();
+        let status = child.wait_cpu_timeout(Duration::from_millis(300)).unwrap();
+        assert!(status.success());
+        assert!(start.elapsed() >= Duration::from_millis(500));
+
+        // Verify that a busy child is reported once it has consumed its CPU time
+        let mut child = Command::new("sh")
+            .args(["-c", "while :; do :; done"])
+            .spawn()
+            .unwrap();
+        let result = child.wait_cpu_timeout(Duration::from_millis(300));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn test_wait_with_memory_limit() {
+        use child_wait_timeout::{wait_with, WaitError, WaitOptions};
+
+        // Verify that a child within the limit is waited for
+        let options = WaitOptions::new()
+            .timeout(Duration::from_secs(5))
+            .memory_limit(1 << 30);
+        let mut child = utilities::sleep_child("1");
+        assert!(wait_with(&mut child, &options).unwrap().success());
+
+        // Verify that a child exceeding the limit is killed and reaped
+        let options = WaitOptions::new().memory_limit(1);
+        let mut child = utilities::sleep_child("10");
+        let start = Instant::now();
+        let error = wait_with(&mut child, &options).unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        match WaitError::from(error) {
+            WaitError::MemoryLimitExceeded { limit, resident } => {
+                assert_eq!(limit, 1);
+                assert!(resident > limit);
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn test_run_with_limits() {
+        use child_wait_timeout::{run_with_limits, Limit, Limits};
+        use std::process::Command;
+
+        // Verify that a child within its limits reports its exit status
+        let limits = Limits::new()
+            .wall_time(Duration::from_secs(5))
+            .cpu_time(Duration::from_secs(5))
+            .memory(1 << 30);
+        let report = run_with_limits(Command::new("sleep").arg("1"), &limits).unwrap();
+        assert_eq!(report.tripped(), None);
+        assert!(report.status().success());
+        assert!(report.elapsed() >= Duration::from_millis(500));
+
+        // Verify that each limit is reported once exceeded
+        let limits = Limits::new().wall_time(Duration::from_millis(300));
+        let report = run_with_limits(Command::new("sleep").arg("10"), &limits).unwrap();
+        assert_eq!(report.tripped(), Some(Limit::WallTime));
+        assert!(!report.status().success());
+
+        let limits = Limits::new()
+            .wall_time(Duration::from_secs(5))
+            .cpu_time(Duration::from_millis(300));
+        let mut command = Command::new("sh");
+        command.args(["-c", "while :; do :; done"]);
+        let report = run_with_limits(&mut command, &limits).unwrap();
+        assert_eq!(report.tripped(), Some(Limit::CpuTime));
+        assert!(report.elapsed() < Duration::from_secs(5));
+
+        let limits = Limits::new().wall_time(Duration::from_secs(5)).memory(1);
+        let report = run_with_limits(Command::new("sleep").arg("10"), &limits).unwrap();
+        assert_eq!(report.tripped(), Some(Limit::Memory));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_timeout() {
+        use child_wait_timeout::ReadTimeout;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("sh")
+            .args(["-c", "echo started; exec sleep 10"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdout = child.stdout.take().unwrap();
+        let mut buf = [0; 64];
+
+        // Verify that the available data is read
+        let read = stdout
+            .read_timeout(&mut buf, Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(&buf[..read], b"started\n");
+
+        // Verify that the read times out while the pipe stays open without data
+        let start = Instant::now();
+        let result = stdout.read_timeout(&mut buf, Duration::from_millis(300));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_millis(300));
+
+        // Verify that the end of the pipe is reported once the child is killed
+        child.kill().unwrap();
+        child.wait().unwrap();
+        let read = stdout
+            .read_timeout(&mut buf, Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(read, 0);
+    }
+
+    #[cfg(all(unix, feature = "duct"))]
+    #[test]
+    fn test_duct_handle() {
+        use child_wait_timeout::HandleWT;
+
+        // Verify that a pipeline is waited for and its output collected
+        let handle = duct::cmd!("sh", "-c", "sleep 1; echo done")
+            .pipe(duct::cmd!("cat"))
+            .stdout_capture()
+            .start()
+            .unwrap();
+        let output = handle.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(output.stdout, b"done\n");
+
+        // Verify that the pipeline is killed and reaped once the timeout expires
+        let handle = duct::cmd!("sleep", "10")
+            .pipe(duct::cmd!("cat"))
+            .unchecked()
+            .start()
+            .unwrap();
+        let result = handle.wait_or_kill(Duration::from_millis(300));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(handle.try_wait().unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_group() {
+        use child_wait_timeout::ProcessGroup;
+        use std::process::Command;
+
+        // Verify that the wait covers the background member once the leader has exited
+        let mut group =
+            ProcessGroup::spawn(Command::new("sh").args(["-c", "sleep 10 & exit 3"])).unwrap();
+        let result = group.wait_or_kill(Duration::from_millis(500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the whole group has been killed and the leader reaped, the killed member is only
+        // gone once reaped by its new parent
+        let status = group.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(status.code(), Some(3));
+        let pgid = group.id() as libc::pid_t;
+        assert_eq!(unsafe { libc::kill(-pgid, 0) }, -1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_process_group_subreaper() {
+        use child_wait_timeout::{is_child_subreaper, set_child_subreaper, ProcessGroup};
+        use std::process::Command;
+
+        // Verify that the member orphaned by the subshell is adopted and reaped by the current process
+        let mut group =
+            ProcessGroup::spawn_subreaper(Command::new("sh").args(["-c", "(sleep 10 &); exit 3"]))
+                .unwrap();
+        assert!(is_child_subreaper().unwrap());
+        let result = group.wait_timeout(Duration::from_millis(500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        group.kill().unwrap();
+        let pgid = group.id() as libc::pid_t;
+        assert_eq!(unsafe { libc::kill(-pgid, 0) }, -1);
+        assert_eq!(group.wait_timeout(Duration::ZERO).unwrap().code(), Some(3));
+
+        // The other tests do not expect to adopt the orphans of their children
+        set_child_subreaper(false).unwrap();
+    }
+
+    #[cfg(all(target_os = "linux", feature = "cgroup"))]
+    #[test]
+    fn test_cgroup() {
+        use child_wait_timeout::Cgroup;
+        use std::process::Command;
+
+        // The cgroup hierarchy is not writable in every environment
+        let cgroup = match Cgroup::new() {
+            Ok(cgroup) => cgroup,
+            Err(e) => return eprintln!("skipping the cgroup test: {}", e),
+        };
+
+        // Verify that a descendant which left the process group is still waited for
+        let mut child = cgroup
+            .spawn(Command::new("sh").args(["-c", "setsid sleep 10 & exit 0"]))
+            .unwrap();
+        assert!(child.wait().unwrap().success());
+        assert!(cgroup.is_populated().unwrap());
+        let result = cgroup.wait_or_kill(Duration::from_millis(500));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the killed descendant is gone without being reaped by the current process
+        cgroup.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert!(!cgroup.is_populated().unwrap());
+        let path = cgroup.path().to_path_buf();
+        drop(cgroup);
+        assert!(!path.exists());
+    }
+
+    #[cfg(all(unix, feature = "nix"))]
+    #[test]
+    fn test_nix_interop() {
+        use child_wait_timeout::{wait_group_any_timeout_nix, wait_pid_timeout_nix, ChildWTExt};
+        use nix::sys::signal::Signal;
+        use nix::sys::wait::WaitStatus;
+        use nix::unistd::Pid;
+        use std::os::unix::process::CommandExt;
+
+        // Verify that a group member is reported with its nix pid and status
+        // The leader is reaped through the process group rather than its `Child`
+        let pgid = utilities::sleep_command("1")
+            .process_group(0)
+            .spawn()
+            .unwrap()
+            .id();
+        let pid = Pid::from_raw(pgid as i32);
+        let status = wait_group_any_timeout_nix(pid, Duration::from_secs(5)).unwrap();
+        assert_eq!(status, WaitStatus::Exited(pid, 0));
+
+        // Verify that a negative pid is rejected
+        let result = wait_pid_timeout_nix(Pid::from_raw(-1), Duration::from_secs(1));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        // Verify that a termination by a signal is converted
+        let mut child = utilities::sleep_child("10");
+        child.kill().unwrap();
+        let info = child.wait_timeout_info(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            info.to_wait_status().unwrap(),
+            WaitStatus::Signaled(info.nix_pid(), Signal::SIGKILL, false)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_peek_status_timeout() {
+        use child_wait_timeout::ChildWTExt;
+
+        let mut child = utilities::exit_child(8);
+
+        // Verify that the exit is peeked at without reaping the child
+        let status = child.peek_status_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(status.code(), Some(8));
+        let status = child.peek_status_timeout(Duration::ZERO).unwrap();
+        assert_eq!(status.code(), Some(8));
+        assert_eq!(child.wait().unwrap().code(), Some(8));
+
+        // Verify that the timeout is respected and that a reaped child is reported
+        let mut child = utilities::sleep_child("10");
+        let result = child.peek_status_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        child.kill().unwrap();
+        child.wait().unwrap();
+        let result = child.peek_status_timeout(Duration::ZERO);
+        assert_eq!(result.unwrap_err().raw_os_error(), Some(libc::ECHILD));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_state_timeout() {
+        use child_wait_timeout::{ChildWTExt, WaitState};
+
+        let mut child = utilities::sleep_child("10");
+        let pid = child.id() as libc::pid_t;
+
+        // Verify that no change is reported while the child runs
+        let result = child.wait_state_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the stop and the continuation are reported once each
+        unsafe { libc::kill(pid, libc::SIGSTOP) };
+        let state = child.wait_state_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(state, WaitState::Stopped(libc::SIGSTOP));
+        let result = child.wait_state_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        unsafe { libc::kill(pid, libc::SIGCONT) };
+        let state = child.wait_state_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(state, WaitState::Continued);
+
+        // Verify that the exit is reported and the child reaped
+        child.kill().unwrap();
+        match child.wait_state_timeout(Duration::from_secs(5)).unwrap() {
+            WaitState::Exited(status) => assert_eq!(child.try_wait().unwrap(), Some(status)),
+            state => panic!("unexpected state {:?}", state),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_wait_traced() {
+        use std::os::unix::process::{CommandExt, ExitStatusExt};
+
+        use child_wait_timeout::{ChildWTExt, WaitState};
+
+        let mut command = utilities::sleep_command("10");
+        unsafe {
+            command.pre_exec(|| {
+                let result = libc::ptrace(libc::PTRACE_TRACEME, 0, 0, 0);
+                match result {
+                    -1 => Err(io::Error::last_os_error()),
+                    _ => Ok(()),
+                }
+            });
+        }
+        let mut child = command.spawn().unwrap();
+        let pid = child.id() as libc::pid_t;
+
+        // Verify that the ptrace stop at the exec is not mistaken for an exit
+        let result = child.wait_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the traced wait reports and consumes it
+        let state = child.wait_state_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(state, WaitState::Traced(libc::SIGTRAP));
+        let result = child.wait_timeout(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the exit of the stopped child is still reported
+        unsafe { libc::kill(pid, libc::SIGKILL) };
+        let status = child.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn test_test_util() {
+        use child_wait_timeout::test_util;
+
+        // Verify that the sleepers sleep for fractions of a second on Unix
+        let mut child = test_util::sleep_child(Duration::from_millis(100)).unwrap();
+        assert!(child
+            .wait_timeout(Duration::from_secs(5))
+            .unwrap()
+            .success());
+        let mut child = test_util::sleep_child(Duration::from_secs(10)).unwrap();
+        let result = child.wait_or_kill(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Verify that the exit code is reported
+        let mut child = test_util::exit_child(7).unwrap();
+        let status = child.wait_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(status.code(), Some(7));
+
+        // Verify that the requests to terminate are ignored, leaving only the kill
+        let mut child = test_util::ignore_signals_child(Duration::from_secs(10)).unwrap();
+        let start = Instant::now();
+        let result = child.shutdown(Duration::from_millis(100), Duration::from_millis(200));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            let status = child.try_wait().unwrap().unwrap();
+            assert_eq!(status.signal(), Some(libc::SIGKILL));
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_mock_child() {
+        use child_wait_timeout::{MockChild, MockClock};
+
+        let clock = MockClock::new();
+        let start = Instant::now();
+
+        // Verify that the timeouts expire in virtual time
+        let mut child = MockChild::new(&clock).exit_after(Duration::from_secs(60), 4);
+        let result = child.wait_timeout(Duration::from_secs(10));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(clock.now(), Duration::from_secs(10));
+        assert_eq!(child.try_wait().unwrap(), None);
+
+        // Verify that advancing the clock makes the scheduled exit due
+        clock.advance(Duration::from_secs(50));
+        assert_eq!(child.try_wait().unwrap().unwrap().code(), Some(4));
+
+        // Verify that a child killed after its timeout reports it
+        let mut child = MockChild::new(&clock);
+        let result = child.wait_or_kill(Duration::from_secs(5));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(clock.now(), Duration::from_secs(65));
+        assert!(!child.try_wait().unwrap().unwrap().success());
+
+        // Verify that a child ignoring the request to terminate is killed after the grace period
+        let mut child = MockChild::new(&clock).ignore_terminate();
+        let result = child.shutdown(Duration::from_secs(5), Duration::from_secs(2));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(clock.now(), Duration::from_secs(72));
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            let status = child.try_wait().unwrap().unwrap();
+            assert_eq!(status.signal(), Some(libc::SIGKILL));
+        }
+
+        // Verify that the ticks report the virtual time
+        let mut child = MockChild::new(&clock).exit_after(Duration::from_secs(3), 0);
+        let mut ticks = Vec::new();
+        let status = child
+            .wait_timeout_with_tick(Duration::from_secs(10), Duration::from_secs(1), |elapsed| {
+                ticks.push(elapsed.as_secs())
+            })
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(ticks, [1, 2]);
+
+        // Verify that the exit future moves the clock to the exit
+        let mut child = MockChild::new(&clock).exit_after(Duration::from_secs(5), 0);
+        let status = futures_lite::future::block_on(child.exit_future()).unwrap();
+        assert!(status.success());
+        assert_eq!(clock.now(), Duration::from_secs(80));
+
+        // Verify that a wait which would never return fails
+        let mut child = MockChild::new(&clock);
+        assert!(child.wait().is_err());
+        assert!(futures_lite::future::block_on(child.exit_future()).is_err());
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn test_faulty_child() {
+        use child_wait_timeout::test_util::{Fault, FaultyChild};
+
+        let mut child = FaultyChild::new(utilities::sleep_child("1"));
+        child
+            .inject(Fault::Interrupted)
+            .inject(Fault::OsError(22))
+            .inject(Fault::Error(io::ErrorKind::WouldBlock));
+        assert_eq!(child.pending_faults(), 3);
+
+        // Verify that the faults are injected in order
+        let error = child.wait_timeout(Duration::from_secs(5)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+        #[cfg(unix)]
+        assert_eq!(error.raw_os_error(), Some(libc::EINTR));
+        let error = child.wait_timeout(Duration::from_secs(5)).unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(22));
+        let error = child.wait_timeout(Duration::from_secs(5)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(child.pending_faults(), 0);
+
+        // Verify that the precise wait retries the early timeouts
+        child.inject_n(Fault::TimedOut, 2);
+        let status = child.wait_timeout_precise(Duration::from_secs(5)).unwrap();
+        assert!(status.success());
+        assert_eq!(child.pending_faults(), 0);
+
+        // Verify that a delayed result is returned late
+        let mut child = FaultyChild::new(utilities::exit_child(0));
+        child.inject(Fault::Delay(Duration::from_millis(300)));
+        let start = Instant::now();
+        assert!(child
+            .wait_timeout(Duration::from_secs(5))
+            .unwrap()
+            .success());
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    // The children are reaped by the functions under test rather than through their `Child`
+    #[allow(clippy::zombie_processes)]
+    #[cfg(unix)]
+    #[test]
+    fn test_reap_all_timeout() {
+        use std::os::unix::process::CommandExt;
+
+        use child_wait_timeout::{reap_all_timeout, wait_group_any_timeout};
+
+        // Every child of the process is reaped, including the ones of the other tests, so the test
+        // runs again alone in a process of its own
+        if std::env::var_os("CHILD_WAIT_TIMEOUT_REAP_ALL").is_none() {
+            let status = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", "tests::test_reap_all_timeout"])
+                .env("CHILD_WAIT_TIMEOUT_REAP_ALL", "1")
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        // Counts the threads of the crate, named `child_wait_timeout` and truncated by the kernel
+        #[cfg(target_os = "linux")]
+        let watcher_threads = || {
+            std::fs::read_dir("/proc/self/task")
+                .unwrap()
+                .filter_map(|task| std::fs::read(task.unwrap().path().join("comm")).ok())
+                .filter(|comm| comm.starts_with(b"child_wait_time"))
+                .count()
+        };
+
+        // Verify that the exited children are collected and the running ones waited for
+        utilities::exit_child(0);
+        utilities::exit_child(3);
+        utilities::sleep_child("1");
+        let mut exited = Vec::new();
+        reap_all_timeout(Duration::from_secs(5), &mut exited).unwrap();
+        assert_eq!(exited.len(), 3);
+
+        // Verify that the waits which time out in a loop share a single watcher thread
+        let mut sleeper = utilities::sleep_child("10");
+        for _ in 0..20 {
+            let result = reap_all_timeout(Duration::from_millis(10), &mut exited);
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        }
+        #[cfg(target_os = "linux")]
+        assert_eq!(watcher_threads(), 1);
+        sleeper.kill().unwrap();
+        reap_all_timeout(Duration::from_secs(5), &mut exited).unwrap();
+        assert_eq!(exited.len(), 4);
+
+        // Verify the same for the waits on a process group, whose watcher ends with the group
+        let leader = utilities::sleep_command("10")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        for _ in 0..20 {
+            let result = wait_group_any_timeout(leader.id(), Duration::from_millis(10));
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        }
+        #[cfg(target_os = "linux")]
+        assert!(watcher_threads() <= 2);
+        unsafe { libc::kill(leader.id() as libc::pid_t, libc::SIGKILL) };
+        let (pid, _) = wait_group_any_timeout(leader.id(), Duration::from_secs(5)).unwrap();
+        assert_eq!(pid, leader.id());
+    }
 }
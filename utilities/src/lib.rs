@@ -21,3 +21,25 @@ pub fn sleep_child(time: &str) -> Child {
         .spawn()
         .expect("Failed to start sleep command")
 }
+
+pub fn exit_command(code: i32) -> Command {
+    #[cfg(windows)]
+    {
+        let mut command = Command::new("cmd.exe");
+        command.args(["/C", &format!("exit {}", code)]);
+        command
+    }
+
+    #[cfg(unix)]
+    {
+        let mut command = Command::new("sh");
+        command.args(["-c", &format!("exit {}", code)]);
+        command
+    }
+}
+
+pub fn exit_child(code: i32) -> Child {
+    exit_command(code)
+        .spawn()
+        .expect("Failed to start exit command")
+}